@@ -48,16 +48,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 content: "Step 1: Analyze requirements".to_string(),
                 priority: PlanPriority::Medium,
                 status: PlanStatus::Pending,
+                id: None,
             },
             PlanEntry {
                 content: "Step 2: Implement features".to_string(),
                 priority: PlanPriority::High,
                 status: PlanStatus::InProgress,
+                id: None,
             },
             PlanEntry {
                 content: "Step 3: Test and verify".to_string(),
                 priority: PlanPriority::Medium,
                 status: PlanStatus::Pending,
+                id: None,
             },
         ],
     };
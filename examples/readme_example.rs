@@ -0,0 +1,48 @@
+//! The Quick Start examples from the README
+//!
+//! Compiled with the other examples so the README cannot drift from the
+//! API. Keep this file in sync with the snippets in README.md.
+
+use futures::stream::StreamExt;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions, Message, query};
+
+/// README: Simple Query
+async fn simple_query() -> Result<(), Box<dyn std::error::Error>> {
+    let response = query("What is 2 + 2?").await?;
+    println!("{}", response); // "4"
+    Ok(())
+}
+
+/// README: Client with Options
+async fn client_with_options() -> Result<(), Box<dyn std::error::Error>> {
+    // The stdio connection runs its I/O on local tasks
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let options = IFlowOptions::new()
+                .with_auto_start_process(true)
+                .with_timeout(120.0);
+            let mut client = IFlowClient::new(Some(options));
+            client.connect().await?;
+
+            client.send_message("Hello, iFlow!", None).await?;
+            let mut messages = client.messages();
+            while let Some(message) = messages.next().await {
+                match message {
+                    Message::Assistant { content } => print!("{}", content),
+                    Message::TaskFinish { .. } => break,
+                    _ => {}
+                }
+            }
+
+            client.disconnect().await?;
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    simple_query().await?;
+    client_with_options().await
+}
@@ -4,14 +4,21 @@
 //! between the SDK and iFlow. It handles the JSON-RPC based messaging
 //! and protocol flow.
 
-use crate::error::{IFlowError, Result};
-use crate::types::{IFlowOptions, Message, PermissionMode};
+use crate::error::{ConnectionError, IFlowError, Result};
+use crate::interceptor::{Interceptors, intercept};
+use crate::logger::MessageLogger;
+use crate::types::{
+    ContentBlock, IFlowHooks, IFlowOptions, Message, PermissionCallback, PermissionDecision,
+    PermissionMode, PermissionOption, RetryPolicy, StatisticsCounters, TextContent,
+    ToolCallDecision, ToolCallEvent, ToolCallHook, ToolCallResult,
+};
 use crate::websocket_transport::WebSocketTransport;
 use serde_json::{Value, json};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
-use tokio::time::timeout;
+use tokio::time::{error::Elapsed, timeout};
 use tracing::debug;
 
 /// ACP protocol handler for iFlow communication
@@ -25,16 +32,46 @@ pub struct ACPProtocol {
     initialized: bool,
     /// Whether authentication has been completed
     authenticated: bool,
-    /// Request ID counter
-    request_id: u32,
+    /// ID to try for the next request
+    next_request_id: u32,
+    /// IDs of requests still awaiting a response
+    pending_requests: HashSet<u32>,
+    /// Whether the request ID counter has wrapped around
+    request_ids_wrapped: bool,
+    /// ID of a prompt sent with `start_prompt_blocks` whose response was not awaited yet
+    pending_prompt: Option<u32>,
+    /// Response to the pending prompt, if it arrived while awaiting another request
+    pending_prompt_response: Option<Value>,
     /// Sender for messages to be processed by the client
     message_sender: UnboundedSender<Message>,
     /// Protocol version
     protocol_version: u32,
     /// Permission mode for tool calls
     permission_mode: PermissionMode,
-    /// Configurable timeout in seconds
-    timeout_secs: f64,
+    /// Pre-configured permission decisions by tool name
+    tool_approvals: HashMap<String, PermissionDecision>,
+    /// Timeout in seconds for the `//ready` signal
+    connect_timeout_secs: f64,
+    /// Timeout in seconds for initialize, authenticate and session creation
+    operation_timeout_secs: f64,
+    /// Timeout in seconds for prompt responses
+    prompt_timeout_secs: f64,
+    /// Hooks invoked for received messages
+    hooks: IFlowHooks,
+    /// Hook consulted for tool calls
+    tool_call_hook: Option<Arc<dyn ToolCallHook>>,
+    /// Callback deciding permission requests instead of the permission mode
+    permission_callback: Option<Arc<dyn PermissionCallback>>,
+    /// Logger for received messages
+    logger: Option<MessageLogger>,
+    /// Counters for client statistics
+    statistics: StatisticsCounters,
+    /// Interceptors applied to received messages
+    interceptors: Interceptors,
+    /// Maximum number of notifications sent in one batch
+    max_batch_size: usize,
+    /// Retry policy for initialize, authenticate, session creation and prompts
+    retry_policy: RetryPolicy,
 }
 
 impl ACPProtocol {
@@ -43,7 +80,7 @@ impl ACPProtocol {
     /// # Arguments
     /// * `transport` - WebSocket transport for communication
     /// * `message_sender` - Sender for messages to be processed by the client
-    /// * `timeout_secs` - Timeout in seconds for protocol operations
+    /// * `timeout_secs` - Timeout in seconds for protocol operations, see [`Self::set_timeouts`]
     pub fn new(
         transport: WebSocketTransport,
         message_sender: UnboundedSender<Message>,
@@ -53,11 +90,26 @@ impl ACPProtocol {
             transport,
             initialized: false,
             authenticated: false,
-            request_id: 0,
+            next_request_id: 1,
+            pending_requests: HashSet::new(),
+            request_ids_wrapped: false,
+            pending_prompt: None,
+            pending_prompt_response: None,
             message_sender,
             protocol_version: 1,
             permission_mode: PermissionMode::Auto,
-            timeout_secs,
+            tool_approvals: HashMap::new(),
+            connect_timeout_secs: timeout_secs,
+            operation_timeout_secs: timeout_secs,
+            prompt_timeout_secs: timeout_secs,
+            hooks: IFlowHooks::default(),
+            tool_call_hook: None,
+            permission_callback: None,
+            logger: None,
+            statistics: StatisticsCounters::default(),
+            interceptors: Interceptors::default(),
+            max_batch_size: 10,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
@@ -77,6 +129,14 @@ impl ACPProtocol {
         self.authenticated
     }
 
+    /// Check if the request ID counter has wrapped around
+    ///
+    /// # Returns
+    /// True once the counter went past `u32::MAX` and started over at 0
+    pub fn request_ids_wrapped(&self) -> bool {
+        self.request_ids_wrapped
+    }
+
     /// Set the permission mode for tool calls
     ///
     /// # Arguments
@@ -85,13 +145,225 @@ impl ACPProtocol {
         self.permission_mode = mode;
     }
 
-    /// Generate next request ID
+    /// Set pre-configured permission decisions by tool name
+    ///
+    /// # Arguments
+    /// * `approvals` - The decision for each tool name, consulted before the permission mode
+    pub fn set_tool_approvals(&mut self, approvals: HashMap<String, PermissionDecision>) {
+        self.tool_approvals = approvals;
+    }
+
+    /// Set separate timeouts for the phases of the protocol
+    ///
+    /// # Arguments
+    /// * `connect` - Timeout in seconds for the `//ready` signal
+    /// * `operation` - Timeout in seconds for initialize, authenticate and session creation
+    /// * `prompt` - Timeout in seconds for prompt responses
+    pub fn set_timeouts(&mut self, connect: f64, operation: f64, prompt: f64) {
+        self.connect_timeout_secs = connect;
+        self.operation_timeout_secs = operation;
+        self.prompt_timeout_secs = prompt;
+    }
+
+    /// Set the callback deciding permission requests
+    ///
+    /// # Arguments
+    /// * `callback` - The callback to use, or `None` to rely on the permission mode
+    pub fn set_permission_callback(&mut self, callback: Option<Arc<dyn PermissionCallback>>) {
+        self.permission_callback = callback;
+    }
+
+    /// Set the retry policy for protocol operations
+    ///
+    /// # Arguments
+    /// * `policy` - The policy applied to initialize, authenticate, session creation and prompts
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Wait before retrying a failed operation, if the retry policy allows it
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation, for logging
+    /// * `err` - The error of the failed attempt
+    /// * `attempt` - The number of the failed attempt, starting at 1
     ///
     /// # Returns
-    /// Unique request ID
-    fn next_request_id(&mut self) -> u32 {
-        self.request_id += 1;
-        self.request_id
+    /// True after waiting if the operation should be retried, False otherwise
+    async fn backoff(&self, operation: &str, err: &IFlowError, attempt: u32) -> bool {
+        if !self.retry_policy.should_retry(err, attempt) {
+            return false;
+        }
+        let delay = self.retry_policy.backoff(attempt);
+        tracing::warn!(
+            "{} failed (attempt {}/{}), retrying in {:?}: {}",
+            operation,
+            attempt,
+            self.retry_policy.max_attempts,
+            delay,
+            err
+        );
+        tokio::time::sleep(delay).await;
+        true
+    }
+
+    /// Set the hooks invoked for received messages
+    ///
+    /// # Arguments
+    /// * `hooks` - The hooks to use
+    pub fn set_hooks(&mut self, hooks: IFlowHooks) {
+        self.hooks = hooks;
+    }
+
+    /// Set the hook consulted for tool calls
+    ///
+    /// # Arguments
+    /// * `hook` - The hook to use, or `None` to rely on the permission mode alone
+    pub fn set_tool_call_hook(&mut self, hook: Option<Arc<dyn ToolCallHook>>) {
+        self.tool_call_hook = hook;
+    }
+
+    /// Set the logger for received messages
+    ///
+    /// # Arguments
+    /// * `logger` - The logger to use, or `None` to disable logging
+    pub fn set_logger(&mut self, logger: Option<MessageLogger>) {
+        self.logger = logger;
+    }
+
+    /// Set the counters updated for client statistics
+    ///
+    /// # Arguments
+    /// * `statistics` - The counters shared with the client
+    pub(crate) fn set_statistics(&mut self, statistics: StatisticsCounters) {
+        self.statistics = statistics;
+    }
+
+    /// Set the interceptors applied to received messages
+    ///
+    /// # Arguments
+    /// * `interceptors` - The interceptors shared with the client
+    pub(crate) fn set_interceptors(&mut self, interceptors: Interceptors) {
+        self.interceptors = interceptors;
+    }
+
+    /// Deliver a message to the client, running the `on_message` hook first
+    ///
+    /// # Arguments
+    /// * `msg` - The message to deliver
+    async fn emit(&self, msg: Message) {
+        let Some(msg) = intercept(&self.interceptors, msg) else {
+            return;
+        };
+        self.statistics.record_message(&msg);
+        if let Some(on_message) = &self.hooks.on_message {
+            on_message(&msg);
+        }
+        if let Some(logger) = &self.logger {
+            let _ = logger.log_message(&msg).await;
+        }
+        let _ = self.message_sender.send(msg);
+    }
+
+    /// Pass an error that does not fail the current request to the `on_error` hook
+    ///
+    /// # Arguments
+    /// * `error` - The error to report
+    fn report_error(&self, error: IFlowError) {
+        if let Some(on_error) = &self.hooks.on_error {
+            on_error(&error);
+        }
+    }
+
+    /// Set the maximum number of notifications sent in one batch
+    ///
+    /// # Arguments
+    /// * `size` - The batch size; larger batches are split into chunks of this size
+    pub fn set_max_batch_size(&mut self, size: usize) {
+        self.max_batch_size = size.max(1);
+    }
+
+    /// Set the protocol version requested in `initialize`
+    ///
+    /// # Arguments
+    /// * `version` - The ACP protocol version
+    pub fn set_protocol_version(&mut self, version: u32) {
+        self.protocol_version = version;
+    }
+
+    /// Set the ID of the next request
+    ///
+    /// IDs of requests still awaiting a response are skipped, and IDs wrap
+    /// around to 0 after `u32::MAX`.
+    ///
+    /// # Arguments
+    /// * `id` - The ID to use for the next request
+    #[doc(hidden)]
+    pub fn set_next_request_id(&mut self, id: u32) {
+        self.next_request_id = id;
+    }
+
+    /// Generate next request ID and mark it as pending
+    ///
+    /// Wraps around after `u32::MAX`, skipping IDs still awaiting a response.
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing an ID not used by any pending request
+    /// * `Err(IFlowError::Protocol)` if every ID is pending
+    fn next_request_id(&mut self) -> Result<u32> {
+        if self.pending_requests.len() as u64 > u32::MAX as u64 {
+            return Err(IFlowError::Protocol(
+                "Request ID space exhausted".to_string(),
+            ));
+        }
+
+        loop {
+            let id = self.next_request_id;
+            let (next, wrapped) = id.overflowing_add(1);
+            self.next_request_id = next;
+            if wrapped {
+                self.request_ids_wrapped = true;
+            }
+            if self.pending_requests.insert(id) {
+                return Ok(id);
+            }
+            tracing::debug!("Skipping request ID {} still awaiting a response", id);
+        }
+    }
+
+    /// Send a request with an ID from `next_request_id`
+    ///
+    /// The ID is released if the request could not be sent.
+    async fn send_request_message(&mut self, request_id: u32, request: &Value) -> Result<()> {
+        let result = self.transport.send(request).await;
+        if result.is_err() {
+            self.pending_requests.remove(&request_id);
+        }
+        result
+    }
+
+    /// Wait up to `limit` for the response to a request
+    ///
+    /// The ID is released however the wait ends, so requests that failed or
+    /// timed out do not stay pending.
+    async fn timed_response(
+        &mut self,
+        request_id: u32,
+        limit: Duration,
+    ) -> std::result::Result<Result<Value>, Elapsed> {
+        let response = timeout(limit, self.wait_for_response(request_id)).await;
+        self.pending_requests.remove(&request_id);
+        response
+    }
+
+    /// Clear the pending request answered by `data`, if it is a response
+    fn complete_request(&mut self, data: &Value) {
+        if data.get("method").is_some() {
+            return;
+        }
+        if let Some(id) = data.get("id").and_then(|v| v.as_u64()) {
+            self.pending_requests.remove(&(id as u32));
+        }
     }
 
     /// Initialize the protocol connection
@@ -117,7 +389,7 @@ impl ACPProtocol {
 
         // Wait for //ready signal with timeout and better error handling
         debug!("Waiting for //ready signal...");
-        let ready_timeout = Duration::from_secs_f64(self.timeout_secs);
+        let ready_timeout = Duration::from_secs_f64(self.connect_timeout_secs);
         let start_time = std::time::Instant::now();
 
         loop {
@@ -128,7 +400,7 @@ impl ACPProtocol {
             }
 
             let msg = match timeout(
-                Duration::from_secs_f64(self.timeout_secs.min(10.0)),
+                Duration::from_secs_f64(self.connect_timeout_secs.min(10.0)),
                 self.transport.receive(),
             )
             .await
@@ -167,10 +439,21 @@ impl ACPProtocol {
         // Add a small delay to ensure the server is fully ready
         tokio::time::sleep(Duration::from_millis(100)).await;
 
-        // Send initialize request
-        let request_id = self.next_request_id();
+        let mut attempt = 1;
+        loop {
+            match self.send_initialize(options).await {
+                Err(e) if self.backoff("initialize", &e, attempt).await => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the initialize request and process its response
+    async fn send_initialize(&mut self, options: &IFlowOptions) -> Result<()> {
+        let protocol_version = check_protocol_version(self.protocol_version)?;
+        let request_id = self.next_request_id()?;
         let mut params = json!({
-            "protocolVersion": self.protocol_version,
+            "protocolVersion": protocol_version,
             "clientCapabilities": {
                 "fs": {
                     "readTextFile": true,
@@ -201,37 +484,13 @@ impl ACPProtocol {
             "params": params,
         });
 
-        // Send with retry logic
-        let mut send_attempts = 0;
-        let max_send_attempts = 3;
-
-        while send_attempts < max_send_attempts {
-            match self.transport.send(&request).await {
-                Ok(_) => {
-                    debug!("Sent initialize request (attempt {})", send_attempts + 1);
-                    break;
-                }
-                Err(e) => {
-                    send_attempts += 1;
-                    tracing::warn!(
-                        "Failed to send initialize request (attempt {}): {}",
-                        send_attempts,
-                        e
-                    );
-                    if send_attempts >= max_send_attempts {
-                        return Err(IFlowError::Protocol(format!(
-                            "Failed to send initialize request after {} attempts: {}",
-                            max_send_attempts, e
-                        )));
-                    }
-                    tokio::time::sleep(Duration::from_millis(500)).await;
-                }
-            }
-        }
+        self.send_request_message(request_id, &request).await?;
+        debug!("Sent initialize request");
 
         // Wait for initialize response with timeout
-        let response_timeout = Duration::from_secs_f64(self.timeout_secs);
-        let response = timeout(response_timeout, self.wait_for_response(request_id))
+        let response_timeout = Duration::from_secs_f64(self.operation_timeout_secs);
+        let response = self
+            .timed_response(request_id, response_timeout)
             .await
             .map_err(|_| {
                 IFlowError::Timeout("Timeout waiting for initialize response".to_string())
@@ -249,6 +508,15 @@ impl ACPProtocol {
                 result.get("protocolVersion"),
                 self.authenticated
             );
+            if let Some(version) = result.get("protocolVersion").and_then(|v| v.as_u64())
+                && version < u64::from(self.protocol_version)
+            {
+                tracing::warn!(
+                    "iFlow downgraded the protocol version from {} to {}",
+                    self.protocol_version,
+                    version
+                );
+            }
         } else if let Some(error) = response.get("error") {
             return Err(IFlowError::Protocol(format!(
                 "Initialize failed: {:?}",
@@ -285,7 +553,22 @@ impl ACPProtocol {
             return Ok(());
         }
 
-        let request_id = self.next_request_id();
+        let mut attempt = 1;
+        loop {
+            match self.send_authenticate(method_id, method_info.clone()).await {
+                Err(e) if self.backoff("authenticate", &e, attempt).await => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the authenticate request and process its response
+    async fn send_authenticate(
+        &mut self,
+        method_id: &str,
+        method_info: Option<HashMap<String, String>>,
+    ) -> Result<()> {
+        let request_id = self.next_request_id()?;
         let mut params = json!({
             "methodId": method_id,
         });
@@ -301,12 +584,13 @@ impl ACPProtocol {
             "params": params,
         });
 
-        self.transport.send(&request).await?;
+        self.send_request_message(request_id, &request).await?;
         debug!("Sent authenticate request with method: {}", method_id);
 
         // Wait for authentication response with timeout
-        let response_timeout = Duration::from_secs_f64(self.timeout_secs);
-        let response = timeout(response_timeout, self.wait_for_response(request_id))
+        let response_timeout = Duration::from_secs_f64(self.operation_timeout_secs);
+        let response = self
+            .timed_response(request_id, response_timeout)
             .await
             .map_err(|_| {
                 IFlowError::Timeout("Timeout waiting for authentication response".to_string())
@@ -359,9 +643,7 @@ impl ACPProtocol {
         mcp_servers: Vec<serde_json::Value>,
     ) -> Result<String> {
         if !self.initialized {
-            return Err(IFlowError::Protocol(
-                "Protocol not initialized. Call initialize() first.".to_string(),
-            ));
+            return Err(ConnectionError::NotInitialized.into());
         }
 
         if !self.authenticated {
@@ -370,7 +652,22 @@ impl ACPProtocol {
             ));
         }
 
-        let request_id = self.next_request_id();
+        let mut attempt = 1;
+        loop {
+            match self.send_new_session(cwd, &mcp_servers).await {
+                Err(e) if self.backoff("session/new", &e, attempt).await => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the session/new request and process its response
+    async fn send_new_session(
+        &mut self,
+        cwd: &str,
+        mcp_servers: &[serde_json::Value],
+    ) -> Result<String> {
+        let request_id = self.next_request_id()?;
         let params = json!({
             "cwd": cwd,
             "mcpServers": mcp_servers,
@@ -383,15 +680,16 @@ impl ACPProtocol {
             "params": params,
         });
 
-        self.transport.send(&request).await?;
+        self.send_request_message(request_id, &request).await?;
         debug!(
             "Sent session/new request with cwd: {} and mcpServers: {:?}",
             cwd, mcp_servers
         );
 
         // Wait for response with timeout
-        let response_timeout = Duration::from_secs_f64(self.timeout_secs);
-        let response = timeout(response_timeout, self.wait_for_response(request_id))
+        let response_timeout = Duration::from_secs_f64(self.operation_timeout_secs);
+        let response = self
+            .timed_response(request_id, response_timeout)
             .await
             .map_err(|_| {
                 IFlowError::Timeout("Timeout waiting for session creation response".to_string())
@@ -421,6 +719,65 @@ impl ACPProtocol {
         }
     }
 
+    /// Load an existing session
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID to resume
+    /// * `cwd` - Working directory for the session
+    /// * `mcp_servers` - MCP servers to connect to
+    ///
+    /// # Returns
+    /// * `Ok(())` if iFlow resumed the session
+    /// * `Err(IFlowError)` if the session could not be loaded
+    pub async fn load_session(
+        &mut self,
+        session_id: &str,
+        cwd: &str,
+        mcp_servers: Vec<serde_json::Value>,
+    ) -> Result<()> {
+        if !self.initialized {
+            return Err(ConnectionError::NotInitialized.into());
+        }
+
+        let request_id = self.next_request_id()?;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "session/load",
+            "params": {
+                "sessionId": session_id,
+                "cwd": cwd,
+                "mcpServers": mcp_servers,
+            },
+        });
+
+        self.send_request_message(request_id, &request).await?;
+        debug!("Sent session/load request for session: {}", session_id);
+
+        let response_timeout = Duration::from_secs_f64(self.operation_timeout_secs);
+        let response = self
+            .timed_response(request_id, response_timeout)
+            .await
+            .map_err(|_| {
+                IFlowError::Timeout("Timeout waiting for session load response".to_string())
+            })?
+            .map_err(|e| IFlowError::Protocol(format!("Failed to load session: {}", e)))?;
+
+        if let Some(error) = response.get("error") {
+            Err(IFlowError::Protocol(format!(
+                "session/load failed: {:?}",
+                error
+            )))
+        } else if response.get("result").is_some() {
+            debug!("Loaded session: {}", session_id);
+            Ok(())
+        } else {
+            Err(IFlowError::Protocol(
+                "Invalid session/load response".to_string(),
+            ))
+        }
+    }
+
     /// Send a prompt to the session and wait for response
     ///
     /// # Arguments
@@ -431,29 +788,114 @@ impl ACPProtocol {
     /// * `Ok(u32)` containing the request ID for tracking the message
     /// * `Err(IFlowError)` if sending failed
     pub async fn send_prompt(&mut self, session_id: &str, prompt: &str) -> Result<u32> {
+        self.send_prompt_with_meta(session_id, prompt, None).await
+    }
+
+    /// Send a prompt with request metadata to the session and wait for response
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID
+    /// * `prompt` - The prompt text to send
+    /// * `meta` - Metadata sent as the `_meta` field of the request params
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing the request ID for tracking the message
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_prompt_with_meta(
+        &mut self,
+        session_id: &str,
+        prompt: &str,
+        meta: Option<Value>,
+    ) -> Result<u32> {
+        let blocks = vec![ContentBlock::Text(TextContent {
+            text: prompt.to_string(),
+            annotations: None,
+            meta: None,
+        })];
+        self.send_prompt_blocks(session_id, blocks, meta).await
+    }
+
+    /// Send a prompt made of content blocks to the session and wait for response
+    ///
+    /// Sending the request is retried according to the retry policy, see
+    /// [`Self::set_retry_policy`]. Prompts are not idempotent, so once the
+    /// request went out it is never sent again: a timeout or connection
+    /// error while waiting for the response is returned.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID
+    /// * `blocks` - The content blocks of the prompt
+    /// * `meta` - Metadata sent as the `_meta` field of the request params
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing the request ID for tracking the message
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_prompt_blocks(
+        &mut self,
+        session_id: &str,
+        blocks: Vec<ContentBlock>,
+        meta: Option<Value>,
+    ) -> Result<u32> {
+        // Only the write of the new prompt is retried, not an earlier response
+        self.finish_pending_prompt().await?;
+
+        let mut attempt = 1;
+        let request_id = loop {
+            match self
+                .start_prompt_blocks(session_id, blocks.clone(), meta.clone())
+                .await
+            {
+                Ok(request_id) => break request_id,
+                Err(e) if self.backoff("session/prompt", &e, attempt).await => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        };
+        self.finish_prompt(request_id).await?;
+        Ok(request_id)
+    }
+
+    /// Send a prompt made of content blocks without waiting for its response
+    ///
+    /// The response and the notifications of the prompt are processed by
+    /// [`Self::finish_pending_prompt`], or before the next prompt is sent.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID
+    /// * `blocks` - The content blocks of the prompt
+    /// * `meta` - Metadata sent as the `_meta` field of the request params
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing the request ID for tracking the message
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn start_prompt_blocks(
+        &mut self,
+        session_id: &str,
+        blocks: Vec<ContentBlock>,
+        meta: Option<Value>,
+    ) -> Result<u32> {
         if !self.initialized {
-            return Err(IFlowError::Protocol(
-                "Protocol not initialized. Call initialize() first.".to_string(),
-            ));
+            return Err(ConnectionError::NotInitialized.into());
         }
 
+        // Responses are awaited one at a time, so finish an earlier prompt first
+        self.finish_pending_prompt().await?;
+
         if !self.authenticated {
             return Err(IFlowError::Protocol(
                 "Not authenticated. Call authenticate() first.".to_string(),
             ));
         }
 
-        let request_id = self.next_request_id();
-        // Create prompt as a list of content blocks
-        let prompt_blocks = vec![json!({
-            "type": "text",
-            "text": prompt
-        })];
+        let prompt_blocks = serde_json::to_value(blocks)?;
+        let request_id = self.next_request_id()?;
 
-        let params = json!({
+        let mut params = json!({
             "sessionId": session_id,
             "prompt": prompt_blocks,
         });
+        if let Some(meta) = meta {
+            params["_meta"] = meta;
+        }
 
         let request = json!({
             "jsonrpc": "2.0",
@@ -462,15 +904,49 @@ impl ACPProtocol {
             "params": params,
         });
 
-        self.transport.send(&request).await?;
+        self.send_request_message(request_id, &request).await?;
         debug!("Sent session/prompt");
+        self.pending_prompt = Some(request_id);
 
-        // Wait for response
-        let response_timeout = Duration::from_secs_f64(self.timeout_secs);
-        let response = timeout(response_timeout, self.wait_for_response_with_notifications(request_id))
-            .await
-            .map_err(|_| IFlowError::Timeout("Timeout waiting for prompt response".to_string()))?
-            .map_err(|e| IFlowError::Protocol(format!("Failed to send prompt: {}", e)))?;
+        Ok(request_id)
+    }
+
+    /// Wait for the response to a prompt sent with [`Self::start_prompt_blocks`]
+    ///
+    /// Does nothing if every prompt has been answered.
+    ///
+    /// # Returns
+    /// * `Ok(())` if there was no pending prompt or it finished
+    /// * `Err(IFlowError)` if the prompt failed or timed out
+    pub async fn finish_pending_prompt(&mut self) -> Result<()> {
+        match self.pending_prompt {
+            Some(request_id) => self.finish_prompt(request_id).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Wait for the response to a prompt, processing its notifications
+    async fn finish_prompt(&mut self, request_id: u32) -> Result<()> {
+        self.pending_prompt = None;
+
+        // Wait for response, unless it was read while awaiting another request
+        let response = match self.pending_prompt_response.take() {
+            Some(response) => response,
+            None => {
+                let response_timeout = Duration::from_secs_f64(self.prompt_timeout_secs);
+                let response = timeout(
+                    response_timeout,
+                    self.wait_for_response_with_notifications(request_id),
+                )
+                .await;
+                self.pending_requests.remove(&request_id);
+                response
+                    .map_err(|_| {
+                        IFlowError::Timeout("Timeout waiting for prompt response".to_string())
+                    })?
+                    .map_err(|e| IFlowError::Protocol(format!("Failed to send prompt: {}", e)))?
+            }
+        };
 
         // Check for errors in response
         if let Some(error) = response.get("error") {
@@ -478,12 +954,200 @@ impl ACPProtocol {
         }
 
         // Send task finish message to indicate completion
+        let reason = response
+            .get("result")
+            .and_then(|result| result.get("stopReason"))
+            .and_then(|reason| reason.as_str())
+            .unwrap_or("completed");
         let msg = Message::TaskFinish {
-            reason: Some("completed".to_string()),
+            reason: Some(reason.to_string()),
         };
         let _ = self.message_sender.send(msg);
 
-        Ok(request_id)
+        Ok(())
+    }
+
+    /// Add a prompt to the turn iFlow is working on with `session/inject`
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID
+    /// * `text` - The text to inject
+    ///
+    /// # Returns
+    /// * `Ok(true)` if iFlow accepted the prompt
+    /// * `Ok(false)` if iFlow does not support `session/inject`
+    /// * `Err(IFlowError)` if sending failed or iFlow rejected the prompt
+    pub async fn inject_prompt(&mut self, session_id: &str, text: &str) -> Result<bool> {
+        if !self.initialized {
+            return Err(ConnectionError::NotInitialized.into());
+        }
+
+        let request_id = self.next_request_id()?;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "session/inject",
+            "params": {
+                "sessionId": session_id,
+                "prompt": [{ "type": "text", "text": text }],
+            },
+        });
+
+        self.send_request_message(request_id, &request).await?;
+        debug!("Sent session/inject");
+
+        let response_timeout = Duration::from_secs_f64(self.operation_timeout_secs);
+        let response = self
+            .timed_response(request_id, response_timeout)
+            .await
+            .map_err(|_| {
+                IFlowError::Timeout("Timeout waiting for session/inject response".to_string())
+            })??;
+
+        match response.get("error") {
+            None => Ok(true),
+            // Method not found
+            Some(error) if error.get("code").and_then(|c| c.as_i64()) == Some(-32601) => {
+                debug!("iFlow does not support session/inject");
+                Ok(false)
+            }
+            Some(error) => Err(IFlowError::Protocol(format!(
+                "session/inject failed: {:?}",
+                error
+            ))),
+        }
+    }
+
+    /// Send a request and wait for its result
+    ///
+    /// # Arguments
+    /// * `method` - The method of the request
+    /// * `params` - The parameters of the request
+    ///
+    /// # Returns
+    /// * `Ok(Value)` containing the `result` of the response
+    /// * `Err(IFlowError::Protocol)` if iFlow answered with an error, e.g.
+    ///   because it does not support `method`
+    /// * `Err(IFlowError)` if sending or waiting failed
+    pub async fn send_request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let limit = Duration::from_secs_f64(self.operation_timeout_secs);
+        self.send_request_with_timeout(method, params, limit).await
+    }
+
+    /// Send a request and wait up to `limit` for its result
+    ///
+    /// # Arguments
+    /// * `method` - The method of the request
+    /// * `params` - The parameters of the request
+    /// * `limit` - How long to wait for the response
+    ///
+    /// # Returns
+    /// * `Ok(Value)` containing the `result` of the response
+    /// * `Err(IFlowError::Timeout)` if iFlow did not answer within `limit`
+    /// * `Err(IFlowError)` if sending failed or iFlow returned an error
+    pub async fn send_request_with_timeout(
+        &mut self,
+        method: &str,
+        params: Value,
+        limit: Duration,
+    ) -> Result<Value> {
+        let request_id = self.next_request_id()?;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": method,
+            "params": params,
+        });
+
+        self.send_request_message(request_id, &request).await?;
+        debug!("Sent {} request", method);
+
+        let response = self.timed_response(request_id, limit).await.map_err(|_| {
+            IFlowError::Timeout(format!("Timeout waiting for {} response", method))
+        })??;
+
+        if let Some(error) = response.get("error") {
+            Err(IFlowError::Protocol(format!(
+                "{} failed: {:?}",
+                method, error
+            )))
+        } else {
+            Ok(response.get("result").cloned().unwrap_or(Value::Null))
+        }
+    }
+
+    /// Send a notification, i.e. a request without an ID
+    ///
+    /// # Arguments
+    /// * `method` - The method of the notification
+    /// * `params` - The parameters of the notification
+    ///
+    /// # Returns
+    /// * `Ok(())` if the notification was sent
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_notification(&self, method: &str, params: Value) -> Result<()> {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        });
+        self.transport.send(&notification).await
+    }
+
+    /// Ask iFlow to stop working on the current prompt of a session
+    ///
+    /// Sends the ACP `session/cancel` notification; iFlow answers the
+    /// pending prompt with the `cancelled` stop reason.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session ID
+    ///
+    /// # Returns
+    /// * `Ok(())` if the notification was sent
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn cancel_session(&self, session_id: &str) -> Result<()> {
+        self.send_notification("session/cancel", json!({ "sessionId": session_id }))
+            .await?;
+        debug!("Sent session/cancel for session {}", session_id);
+        Ok(())
+    }
+
+    /// Send several notifications as JSON-RPC batches
+    ///
+    /// Notifications are sent as JSON arrays of at most `max_batch_size`
+    /// entries. If sending a batch fails, its notifications are sent one by
+    /// one instead.
+    ///
+    /// # Arguments
+    /// * `notifications` - The method and parameters of each notification
+    ///
+    /// # Returns
+    /// * `Ok(())` if all notifications were sent
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn batch_send_notifications(
+        &mut self,
+        notifications: Vec<(String, Value)>,
+    ) -> Result<()> {
+        for chunk in notifications.chunks(self.max_batch_size) {
+            let batch: Vec<Value> = chunk
+                .iter()
+                .map(|(method, params)| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": method,
+                        "params": params,
+                    })
+                })
+                .collect();
+
+            if let Err(e) = self.transport.send(&Value::Array(batch)).await {
+                debug!("Batch send failed, sending notifications one by one: {}", e);
+                for (method, params) in chunk {
+                    self.send_notification(method, params.clone()).await?;
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Wait for a response to a specific request
@@ -495,7 +1159,7 @@ impl ACPProtocol {
     /// * `Ok(Value)` containing the response
     /// * `Err(IFlowError)` if waiting failed
     async fn wait_for_response(&mut self, request_id: u32) -> Result<Value> {
-        let timeout_duration = Duration::from_secs_f64(self.timeout_secs);
+        let timeout_duration = Duration::from_secs_f64(self.operation_timeout_secs);
         let start_time = std::time::Instant::now();
 
         loop {
@@ -507,7 +1171,7 @@ impl ACPProtocol {
             }
 
             // Use a shorter timeout for receiving messages to allow for periodic checks
-            let receive_timeout = Duration::from_secs_f64(self.timeout_secs.min(1.0));
+            let receive_timeout = Duration::from_secs_f64(self.operation_timeout_secs.min(1.0));
             let msg = match timeout(receive_timeout, self.transport.receive()).await {
                 Ok(Ok(msg)) => msg,
                 Ok(Err(e)) => {
@@ -535,21 +1199,33 @@ impl ACPProtocol {
                 Ok(data) => data,
                 Err(e) => {
                     tracing::debug!("Failed to parse message as JSON: {}, message: {}", e, msg);
+                    self.report_error(IFlowError::InvalidMessage(format!("{}: {}", e, msg)));
                     continue;
                 }
             };
+            self.complete_request(&data);
 
             // Check if this is the response we're waiting for
             if let Some(id) = data.get("id").and_then(|v| v.as_u64()) {
                 if id == request_id as u64 {
                     return Ok(data);
                 }
+                // Keep the response to a prompt sent without waiting for it
+                if self
+                    .pending_prompt
+                    .is_some_and(|prompt| id == prompt as u64)
+                    && data.get("method").is_none()
+                {
+                    self.pending_prompt_response = Some(data);
+                    continue;
+                }
             }
 
             // If not our response, process as a notification
             if let Err(e) = self.handle_notification(data).await {
                 tracing::warn!("Failed to handle notification: {}", e);
-                // Don't fail the entire wait, just log and continue
+                // Don't fail the entire wait, just report and continue
+                self.report_error(e);
             }
         }
     }
@@ -563,7 +1239,7 @@ impl ACPProtocol {
     /// * `Ok(Value)` containing the response
     /// * `Err(IFlowError)` if waiting failed
     async fn wait_for_response_with_notifications(&mut self, request_id: u32) -> Result<Value> {
-        let timeout_duration = Duration::from_secs_f64(self.timeout_secs);
+        let timeout_duration = Duration::from_secs_f64(self.prompt_timeout_secs);
         let start_time = std::time::Instant::now();
 
         loop {
@@ -575,7 +1251,7 @@ impl ACPProtocol {
             }
 
             // Use a shorter timeout for receiving messages to allow for periodic checks
-            let receive_timeout = Duration::from_secs_f64(self.timeout_secs.min(1.0));
+            let receive_timeout = Duration::from_secs_f64(self.prompt_timeout_secs.min(1.0));
             let msg = match timeout(receive_timeout, self.transport.receive()).await {
                 Ok(Ok(msg)) => msg,
                 Ok(Err(e)) => {
@@ -603,9 +1279,11 @@ impl ACPProtocol {
                 Ok(data) => data,
                 Err(e) => {
                     tracing::debug!("Failed to parse message as JSON: {}, message: {}", e, msg);
+                    self.report_error(IFlowError::InvalidMessage(format!("{}: {}", e, msg)));
                     continue;
                 }
             };
+            self.complete_request(&data);
 
             // Check if this is the response we're waiting for
             if let Some(id) = data.get("id").and_then(|v| v.as_u64()) {
@@ -616,12 +1294,13 @@ impl ACPProtocol {
                         // Process the permission request immediately
                         if let Err(e) = self.handle_client_method(method, data.clone()).await {
                             tracing::warn!("Failed to handle permission request: {}", e);
+                            self.report_error(e);
                         }
                         // Continue waiting for the main response
                         continue;
                     }
                 }
-                
+
                 // If this is the response we're waiting for, return it
                 if id == request_id as u64 {
                     return Ok(data);
@@ -631,7 +1310,8 @@ impl ACPProtocol {
             // If not our response, process as a notification
             if let Err(e) = self.handle_notification(data).await {
                 tracing::warn!("Failed to handle notification: {}", e);
-                // Don't fail the entire wait, just log and continue
+                // Don't fail the entire wait, just report and continue
+                self.report_error(e);
             }
         }
     }
@@ -739,17 +1419,44 @@ impl ACPProtocol {
             tool_type
         );
 
+        let permission_options: Vec<PermissionOption> = options
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|option| {
+                Some(PermissionOption {
+                    option_id: option.get("optionId")?.as_str()?.to_string(),
+                    label: option
+                        .get("name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                })
+            })
+            .collect();
+        let preconfigured = decide_permission(
+            &tool_call.as_object().cloned().unwrap_or_default(),
+            tool_type,
+            &permission_options,
+            &self.tool_approvals,
+            self.tool_call_hook.as_ref(),
+            self.permission_callback.as_ref(),
+        )
+        .await;
+
         // Determine response based on permission_mode
-        let auto_approve = match self.permission_mode {
-            PermissionMode::Auto => {
+        let auto_approve = match (&preconfigured, self.permission_mode) {
+            (Some(PermissionDecision::Approve(_)), _) => true,
+            (Some(PermissionDecision::Deny), _) => false,
+            (None, PermissionMode::Auto) => {
                 // Auto-approve all tool calls
                 true
             }
-            PermissionMode::Manual => {
+            (None, PermissionMode::Manual) => {
                 // Require manual confirmation for all
                 false
             }
-            PermissionMode::Selective => {
+            (None, PermissionMode::Selective) => {
                 // Auto-approve based on tool type
                 // For now, we'll auto-approve read/fetch operations
                 tool_type == "read" || tool_type == "fetch" || tool_type == "list"
@@ -757,7 +1464,20 @@ impl ACPProtocol {
         };
 
         use agent_client_protocol::{RequestPermissionOutcome, RequestPermissionResponse};
-        let permission_response = if auto_approve {
+        self.statistics.record_permission(auto_approve);
+        let permission_response = if let Some(PermissionDecision::Approve(option_id)) =
+            preconfigured
+            && auto_approve
+        {
+            RequestPermissionResponse {
+                outcome: RequestPermissionOutcome::Selected {
+                    option_id: agent_client_protocol::PermissionOptionId(std::sync::Arc::from(
+                        option_id,
+                    )),
+                },
+                meta: None,
+            }
+        } else if auto_approve {
             // Find the appropriate option from the provided options
             let mut selected_option = "proceed_once".to_string();
             if let Some(options_array) = options.as_array() {
@@ -773,14 +1493,18 @@ impl ACPProtocol {
                 }
                 // Fallback to first option's optionId if no specific option found
                 if selected_option == "proceed_once" && !options_array.is_empty() {
-                    if let Some(first_option_id) = options_array[0].get("optionId").and_then(|v| v.as_str()) {
+                    if let Some(first_option_id) =
+                        options_array[0].get("optionId").and_then(|v| v.as_str())
+                    {
                         selected_option = first_option_id.to_string();
                     }
                 }
             }
             RequestPermissionResponse {
                 outcome: RequestPermissionOutcome::Selected {
-                    option_id: agent_client_protocol::PermissionOptionId(std::sync::Arc::from(selected_option)),
+                    option_id: agent_client_protocol::PermissionOptionId(std::sync::Arc::from(
+                        selected_option,
+                    )),
                 },
                 meta: None,
             }
@@ -805,8 +1529,12 @@ impl ACPProtocol {
             RequestPermissionOutcome::Cancelled => "cancelled",
             RequestPermissionOutcome::Selected { option_id } => &*option_id.0,
         };
-        tracing::debug!("Permission request for tool '{}': {}", tool_title, outcome_str);
-        
+        tracing::debug!(
+            "Permission request for tool '{}': {}",
+            tool_title,
+            outcome_str
+        );
+
         Ok(())
     }
 
@@ -826,6 +1554,10 @@ impl ACPProtocol {
         update: &serde_json::Map<String, Value>,
         request_id: Option<u64>,
     ) -> Result<()> {
+        if let Some(on_session_update) = &self.hooks.on_session_update {
+            on_session_update(update_type, update);
+        }
+
         match update_type {
             "agent_message_chunk" => {
                 if let Some(content) = update.get("content") {
@@ -841,7 +1573,7 @@ impl ACPProtocol {
                     };
 
                     let msg = Message::Assistant { content: text };
-                    let _ = self.message_sender.send(msg);
+                    self.emit(msg).await;
                 }
             }
             "user_message_chunk" => {
@@ -858,7 +1590,7 @@ impl ACPProtocol {
                     };
 
                     let msg = Message::User { content: text };
-                    let _ = self.message_sender.send(msg);
+                    self.emit(msg).await;
                 }
             }
             "tool_call" => {
@@ -880,7 +1612,7 @@ impl ACPProtocol {
                         .to_string();
 
                     let msg = Message::ToolCall { id, name, status };
-                    let _ = self.message_sender.send(msg);
+                    self.emit(msg).await;
                 }
             }
             "plan" => {
@@ -913,19 +1645,40 @@ impl ACPProtocol {
                                 _ => super::types::PlanStatus::Pending,
                             };
 
+                            let id = entry
+                                .get("id")
+                                .and_then(|v| v.as_str())
+                                .map(|id| id.to_string());
+
                             Some(super::types::PlanEntry {
                                 content,
                                 priority,
                                 status,
+                                id,
                             })
                         })
                         .collect();
 
                     let msg = Message::Plan { entries };
-                    let _ = self.message_sender.send(msg);
+                    self.emit(msg).await;
                 }
             }
             "tool_call_update" => {
+                if let Some(hook) = &self.tool_call_hook {
+                    let tool_call = update
+                        .get("toolCall")
+                        .and_then(|v| v.as_object())
+                        .unwrap_or(update);
+                    let call = tool_call_event(tool_call);
+                    if matches!(call.status.as_str(), "completed" | "failed") {
+                        let result = ToolCallResult {
+                            status: call.status.clone(),
+                            output: call.output.clone(),
+                        };
+                        hook.after_tool_call(&call, &result);
+                    }
+                }
+
                 // For now, we'll just acknowledge the update if there's a request ID
                 if let Some(id) = request_id {
                     let response = json!({
@@ -975,4 +1728,84 @@ impl ACPProtocol {
     pub fn is_connected(&self) -> bool {
         self.transport.is_connected()
     }
-}
\ No newline at end of file
+}
+
+/// Check that a protocol version fits the 16 bits ACP allows
+///
+/// # Arguments
+/// * `version` - The requested protocol version
+///
+/// # Returns
+/// * `Ok(u16)` with the version
+/// * `Err(IFlowError::Config)` if the version is larger than `u16::MAX`
+pub(crate) fn check_protocol_version(version: u32) -> Result<u16> {
+    u16::try_from(version).map_err(|_| {
+        IFlowError::Config(format!(
+            "protocol_version {} is out of range, ACP versions are at most {}",
+            version,
+            u16::MAX
+        ))
+    })
+}
+
+/// Decide a permission request before the permission mode applies
+///
+/// The tool call hook can veto any tool call, even a pre-approved one.
+/// Otherwise a pre-configured decision for the tool applies, and without
+/// one the permission callback decides.
+///
+/// # Arguments
+/// * `tool_call` - The `toolCall` of the permission request
+/// * `tool_type` - The type of the tool, e.g. `edit`
+/// * `options` - The options iFlow offers for approving the tool call
+/// * `tool_approvals` - Pre-configured decisions by tool name
+/// * `tool_call_hook` - The hook that can veto the tool call
+/// * `permission_callback` - The callback deciding at runtime
+///
+/// # Returns
+/// The decision, or `None` if none of them decided
+pub(crate) async fn decide_permission(
+    tool_call: &serde_json::Map<String, Value>,
+    tool_type: &str,
+    options: &[PermissionOption],
+    tool_approvals: &HashMap<String, PermissionDecision>,
+    tool_call_hook: Option<&Arc<dyn ToolCallHook>>,
+    permission_callback: Option<&Arc<dyn PermissionCallback>>,
+) -> Option<PermissionDecision> {
+    let event = tool_call_event(tool_call);
+    if let Some(hook) = tool_call_hook
+        && let ToolCallDecision::Deny(reason) = hook.before_tool_call(&event)
+    {
+        tracing::debug!("Tool call '{}' denied by hook: {}", event.name, reason);
+        return Some(PermissionDecision::Deny);
+    }
+    if let Some(decision) = tool_approvals.get(&event.name) {
+        return Some(decision.clone());
+    }
+    match permission_callback {
+        Some(callback) => Some(
+            callback
+                .request_permission(&event.name, tool_type, options)
+                .await,
+        ),
+        None => None,
+    }
+}
+
+/// Build a tool call event from the `toolCall` of a permission request or update
+fn tool_call_event(tool_call: &serde_json::Map<String, Value>) -> ToolCallEvent {
+    let field = |key: &str| tool_call.get(key).and_then(|v| v.as_str());
+    ToolCallEvent {
+        id: field("toolCallId")
+            .or(field("id"))
+            .unwrap_or("")
+            .to_string(),
+        name: field("title").unwrap_or("unknown").to_string(),
+        status: field("status").unwrap_or("unknown").to_string(),
+        input: tool_call.get("rawInput").cloned(),
+        output: tool_call
+            .get("rawOutput")
+            .or(tool_call.get("content"))
+            .cloned(),
+    }
+}
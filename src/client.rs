@@ -3,32 +3,39 @@
 //! This module provides the core client functionality for communicating with iFlow
 //! using the [Agent Client Protocol (ACP)](https://github.com/agentclientprotocol/agent-client-protocol) over stdio or WebSocket.
 
-use crate::acp_protocol::ACPProtocol;
-use crate::error::{IFlowError, Result};
+use crate::acp_protocol::{ACPProtocol, check_protocol_version, decide_permission};
+use crate::error::{CancelledBy, ConnectionError, IFlowError, Result};
+use crate::interceptor::{Interceptors, MessageInterceptor, PlanInterceptor, intercept};
 use crate::logger::MessageLogger;
+use crate::plan::{PlanDiff, plan_diff};
+use crate::precheck::ConnectionPrecheck;
 use crate::process_manager::IFlowProcessManager;
 use crate::types::*;
-use crate::websocket_transport::WebSocketTransport;
+use crate::websocket_transport::{WebSocketTransport, jitter_delay};
 use agent_client_protocol::{
     Agent, Client, ClientSideConnection, ContentBlock, SessionId, SessionUpdate,
 };
-use futures::{FutureExt, pin_mut, stream::Stream};
+use futures::{FutureExt, StreamExt, pin_mut, stream::Stream};
 use serde_json;
-use std::path::Path;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
 // ChildStdin import moved to where it's used
 use tokio::sync::{Mutex, mpsc};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
-use tracing::debug;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+use url::Url;
 
 /// Connection type for iFlow client
 enum Connection {
     /// Stdio connection using agent-client-protocol
     Stdio {
-        acp_client: ClientSideConnection,
+        acp_client: Rc<ClientSideConnection>,
         process_manager: Option<IFlowProcessManager>,
         session_id: Option<SessionId>,
         initialized: bool,
@@ -49,23 +56,262 @@ pub struct IFlowClient {
     options: IFlowOptions,
     message_receiver: Arc<Mutex<mpsc::UnboundedReceiver<Message>>>,
     message_sender: mpsc::UnboundedSender<Message>,
+    lookahead: Lookahead,
     connected: Arc<Mutex<bool>>,
     connection: Option<Connection>,
     logger: Option<MessageLogger>,
+    record: Arc<std::sync::Mutex<SessionRecord>>,
+    statistics: StatisticsCounters,
+    interceptors: Interceptors,
+    connected_at: Option<std::time::Instant>,
+    disconnected_at: Option<std::time::Instant>,
+    background_tasks: Vec<tokio::task::AbortHandle>,
+    protocol_observer: Option<Arc<dyn RawProtocolObserver>>,
+    /// Cancelled on disconnect, stopping background tasks and pending waits
+    cancellation: CancellationToken,
+    /// Token passed to `new_with_cancellation`, parent of `cancellation`
+    parent_cancellation: Option<CancellationToken>,
+    /// Stdio prompt sent by `send_message_fire_and_forget` that may still be running
+    detached_prompt: Option<tokio::task::JoinHandle<()>>,
+    /// Corrections held back until running tool calls finish
+    corrections: VecDeque<String>,
+    /// Durations of the last `connect()` and `reconnect()` calls
+    connection_latencies: VecDeque<std::time::Duration>,
+    /// Sessions created with `new_session_in_dir` on the current connection,
+    /// and sessions that were active before switching to another one
+    sessions: Vec<String>,
+    /// Sessions created by this client that have not received `options.system_prompt` yet
+    pending_system_prompts: std::sync::Mutex<HashSet<String>>,
+    /// Limits the prompt rate when `options.rate_limit` is set
+    rate_limiter: Option<Arc<std::sync::Mutex<TokenBucket>>>,
+    /// Callbacks of `subscribe_to_plan`, called by a `PlanInterceptor`
+    plan_subscribers: Option<Arc<std::sync::Mutex<Vec<PlanCallback>>>>,
+    /// Checks run by `connect()` before connecting
+    prechecks: Vec<Box<dyn ConnectionPrecheck>>,
+}
+
+/// Prompt sent by `run_agent_loop` when iFlow stopped before finishing
+const AGENT_LOOP_CONTINUE_PROMPT: &str = "Continue working on the task.";
+
+/// Code of the `Message::Error` delivered when a background prompt fails
+/// (the JSON-RPC internal error code)
+const PROMPT_FAILED_ERROR_CODE: i32 = -32603;
+
+/// How long `IFlowClient::count_tokens` waits for iFlow before estimating
+const COUNT_TOKENS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Messages received in response to a single prompt
+#[derive(Default)]
+struct Turn {
+    response: String,
+    tool_calls: Vec<ToolCallEvent>,
+    plan: Option<Vec<PlanEntry>>,
+    stop_reason: Option<String>,
+    /// Number of assistant chunks
+    chunks: u32,
+    /// Time from the first message to `TaskFinish`
+    duration: std::time::Duration,
+}
+
+/// Activity observed over the lifetime of a client
+#[derive(Default)]
+struct SessionRecord {
+    /// Conversation history (only kept when `options.keep_history` is set)
+    history: Vec<Message>,
+    /// Every tool call message received from iFlow
+    tool_calls: Vec<Message>,
+    /// Number of messages sent and received
+    message_count: u64,
+    /// When the last message was sent or received
+    last_message_at: Option<std::time::SystemTime>,
+    /// Entries of the last plan received from iFlow
+    plan: Option<Vec<PlanEntry>>,
+    /// When the last user message was sent, until the first assistant chunk
+    prompt_sent_at: Option<std::time::Instant>,
+    /// Time from sending a user message to the first assistant chunk
+    prompt_latencies: VecDeque<std::time::Duration>,
+}
+
+impl SessionRecord {
+    /// Record a message received from or sent to iFlow
+    fn observe(&mut self, msg: &Message, keep_history: bool) {
+        self.message_count += 1;
+        self.last_message_at = Some(std::time::SystemTime::now());
+        match msg {
+            Message::ToolCall { .. } => self.tool_calls.push(msg.clone()),
+            Message::Plan { entries } => self.plan = Some(entries.clone()),
+            Message::User { .. } => self.prompt_sent_at = Some(std::time::Instant::now()),
+            Message::Assistant { .. } => {
+                if let Some(sent_at) = self.prompt_sent_at.take() {
+                    record_latency(&mut self.prompt_latencies, sent_at.elapsed());
+                }
+            }
+            _ => {}
+        }
+        if keep_history {
+            self.history.push(msg.clone());
+        }
+    }
+
+    /// IDs of tool calls that have not completed or failed yet
+    fn pending_tool_calls(&self) -> Vec<String> {
+        // Later updates of a tool call replace its earlier status
+        let mut statuses: Vec<(&str, &str)> = Vec::new();
+        for msg in &self.tool_calls {
+            if let Message::ToolCall { id, status, .. } = msg {
+                match statuses.iter_mut().find(|(known, _)| *known == id.as_str()) {
+                    Some(entry) => entry.1 = status,
+                    None => statuses.push((id, status)),
+                }
+            }
+        }
+        statuses
+            .into_iter()
+            .filter(|(_, status)| {
+                !status.eq_ignore_ascii_case("completed") && !status.eq_ignore_ascii_case("failed")
+            })
+            .map(|(id, _)| id.to_string())
+            .collect()
+    }
+}
+
+/// Number of latencies kept by `connection_latency_histogram` and `prompt_latency_histogram`
+const LATENCY_HISTORY_LIMIT: usize = 100;
+
+/// Add a latency to a history, dropping the oldest beyond `LATENCY_HISTORY_LIMIT`
+fn record_latency(history: &mut VecDeque<std::time::Duration>, latency: std::time::Duration) {
+    if history.len() == LATENCY_HISTORY_LIMIT {
+        history.pop_front();
+    }
+    history.push_back(latency);
 }
 
 /// Stream of messages from iFlow
 ///
 /// This stream provides asynchronous access to messages received from iFlow.
 /// It implements the `futures::Stream` trait for easy integration with async code.
+///
+/// With `IFlowOptions::message_rate_limit` set, assistant chunks are
+/// delayed to respect the rate; other messages are delivered immediately.
 pub struct MessageStream {
     receiver: Arc<Mutex<mpsc::UnboundedReceiver<Message>>>,
+    lookahead: Lookahead,
+    rate: Option<MessageRate>,
+    /// Assistant chunk waiting for `delay` to elapse
+    held: Option<Message>,
+    delay: Option<Pin<Box<tokio::time::Sleep>>>,
+    /// Earliest time the next assistant chunk may be delivered
+    next_chunk_at: Option<tokio::time::Instant>,
 }
 
-impl Stream for MessageStream {
-    type Item = Message;
+/// Messages read ahead of the stream, delivered before the channel
+type Lookahead = Arc<std::sync::Mutex<VecDeque<Message>>>;
+
+impl MessageStream {
+    fn new(
+        receiver: Arc<Mutex<mpsc::UnboundedReceiver<Message>>>,
+        lookahead: Lookahead,
+        rate: Option<MessageRate>,
+    ) -> Self {
+        Self {
+            receiver,
+            lookahead,
+            rate,
+            held: None,
+            delay: None,
+            next_chunk_at: None,
+        }
+    }
+
+    /// Deliver `msg`, recording when the next assistant chunk is due
+    fn deliver(&mut self, msg: Message) -> Poll<Option<Message>> {
+        if let (Some(rate), Message::Assistant { .. }) = (self.rate, &msg) {
+            // Intervals past the end of time are capped at 30 years, as tokio does
+            let now = tokio::time::Instant::now();
+            self.next_chunk_at = Some(
+                now.checked_add(rate.interval())
+                    .unwrap_or_else(|| now + std::time::Duration::from_secs(86400 * 365 * 30)),
+            );
+        }
+        Poll::Ready(Some(msg))
+    }
+
+    /// Poll the delay of the held assistant chunk, delivering it once elapsed
+    fn poll_held(&mut self, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        if let Some(delay) = self.delay.as_mut() {
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.delay = None;
+        }
+        match self.held.take() {
+            Some(msg) => self.deliver(msg),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Keep only the messages matching a predicate
+    ///
+    /// Unlike `StreamExt::filter`, the predicate is a plain closure. The
+    /// name differs so it does not shadow `StreamExt::filter`.
+    ///
+    /// # Arguments
+    /// * `predicate` - Called for each message, returning whether to keep it
+    ///
+    /// # Returns
+    /// A `FilteredMessageStream` yielding the matching messages
+    pub fn filter_messages<F>(self, predicate: F) -> FilteredMessageStream
+    where
+        F: FnMut(&Message) -> bool + Send + 'static,
+    {
+        FilteredMessageStream {
+            inner: self,
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Transform each message
+    ///
+    /// Unlike `StreamExt::map`, the result is a named stream type. The name
+    /// differs so it does not shadow `StreamExt::map`.
+    ///
+    /// # Arguments
+    /// * `transform` - Called for each message, returning the item to yield
+    ///
+    /// # Returns
+    /// A `MappedMessageStream` yielding the transformed messages
+    pub fn map_messages<F, T>(self, transform: F) -> MappedMessageStream<T>
+    where
+        F: FnMut(Message) -> T + Send + 'static,
+    {
+        MappedMessageStream {
+            inner: self,
+            transform: Box::new(transform),
+        }
+    }
+
+    /// Keep only the text of assistant chunks
+    ///
+    /// The stream does not end at `Message::TaskFinish`; it ends when the
+    /// message channel closes, so bound it with e.g. `StreamExt::take_until`
+    /// or read it while awaiting the prompt.
+    ///
+    /// # Returns
+    /// A stream of the content of each `Message::Assistant`
+    pub fn assistant_chunks(self) -> impl Stream<Item = String> + Send + Unpin {
+        StreamExt::filter_map(self, |msg| {
+            futures::future::ready(match msg {
+                Message::Assistant { content } => Some(content),
+                _ => None,
+            })
+        })
+    }
+
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        if let Some(msg) = self.lookahead.lock().ok().and_then(|mut l| l.pop_front()) {
+            return Poll::Ready(Some(msg));
+        }
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut receiver = match self.receiver.try_lock() {
             Ok(guard) => guard,
             Err(_) => {
@@ -91,24 +337,167 @@ impl Stream for MessageStream {
     }
 }
 
+impl Stream for MessageStream {
+    type Item = Message;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.held.is_some() {
+            return this.poll_held(cx);
+        }
+
+        let msg = match this.poll_message(cx) {
+            Poll::Ready(Some(msg)) => msg,
+            other => return other,
+        };
+        match this.next_chunk_at {
+            Some(due)
+                if matches!(msg, Message::Assistant { .. })
+                    && tokio::time::Instant::now() < due =>
+            {
+                this.held = Some(msg);
+                this.delay = Some(Box::pin(tokio::time::sleep_until(due)));
+                this.poll_held(cx)
+            }
+            _ => this.deliver(msg),
+        }
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        // Keep a delayed chunk for the next stream rather than losing it
+        if let Some(msg) = self.held.take()
+            && let Ok(mut lookahead) = self.lookahead.lock()
+        {
+            lookahead.push_front(msg);
+        }
+    }
+}
+
+/// Stream of the messages matching a predicate, see [`MessageStream::filter_messages`]
+pub struct FilteredMessageStream {
+    inner: MessageStream,
+    predicate: Box<dyn FnMut(&Message) -> bool + Send>,
+}
+
+impl Stream for FilteredMessageStream {
+    type Item = Message;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(msg)) if !(this.predicate)(&msg) => continue,
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Stream of transformed messages, see [`MessageStream::map_messages`]
+pub struct MappedMessageStream<T> {
+    inner: MessageStream,
+    transform: Box<dyn FnMut(Message) -> T + Send>,
+}
+
+impl<T> Stream for MappedMessageStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.inner)
+            .poll_next(cx)
+            .map(|msg| msg.map(&mut this.transform))
+    }
+}
+
 // Implement the Client trait for handling ACP messages
 struct IFlowClientHandler {
     message_sender: mpsc::UnboundedSender<Message>,
     logger: Option<MessageLogger>,
+    hooks: IFlowHooks,
+    tool_call_hook: Option<Arc<dyn ToolCallHook>>,
+    tool_approvals: HashMap<String, PermissionDecision>,
+    permission_callback: Option<Arc<dyn PermissionCallback>>,
+    statistics: StatisticsCounters,
+    interceptors: Interceptors,
+}
+
+impl IFlowClientHandler {
+    /// Deliver a message to the client's stream, running hooks and logging
+    async fn emit(&self, msg: Message) {
+        let Some(msg) = intercept(&self.interceptors, msg) else {
+            return;
+        };
+        self.statistics.record_message(&msg);
+        if let Some(on_message) = &self.hooks.on_message {
+            on_message(&msg);
+        }
+
+        let _ = self.message_sender.send(msg.clone());
+
+        // Log the message if logger is available
+        if let Some(logger) = &self.logger {
+            let _ = logger.log_message(&msg).await;
+        }
+    }
 }
 
 #[async_trait::async_trait(?Send)]
 impl Client for IFlowClientHandler {
     async fn request_permission(
         &self,
-        _args: agent_client_protocol::RequestPermissionRequest,
+        args: agent_client_protocol::RequestPermissionRequest,
     ) -> anyhow::Result<
         agent_client_protocol::RequestPermissionResponse,
         agent_client_protocol::Error,
     > {
-        // For now, cancel all permissions
+        use agent_client_protocol::{PermissionOptionId, RequestPermissionOutcome};
+
+        let tool_call = serde_json::to_value(&args.tool_call)
+            .ok()
+            .and_then(|tool_call| tool_call.as_object().cloned())
+            .unwrap_or_default();
+        let tool_type = args
+            .tool_call
+            .fields
+            .kind
+            .and_then(|kind| serde_json::to_value(kind).ok())
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string());
+        let options: Vec<PermissionOption> = args
+            .options
+            .iter()
+            .map(|option| PermissionOption {
+                option_id: option.id.0.to_string(),
+                label: option.name.clone(),
+            })
+            .collect();
+
+        // Decided in the same order as over WebSocket; requests that nothing
+        // decided are cancelled rather than left to the permission mode
+        let decision = decide_permission(
+            &tool_call,
+            &tool_type,
+            &options,
+            &self.tool_approvals,
+            self.tool_call_hook.as_ref(),
+            self.permission_callback.as_ref(),
+        )
+        .await
+        .unwrap_or(PermissionDecision::Deny);
+
+        let outcome = match decision {
+            PermissionDecision::Approve(option_id) => RequestPermissionOutcome::Selected {
+                option_id: PermissionOptionId(option_id.into()),
+            },
+            PermissionDecision::Deny => RequestPermissionOutcome::Cancelled,
+        };
+        self.statistics
+            .record_permission(matches!(outcome, RequestPermissionOutcome::Selected { .. }));
         Ok(agent_client_protocol::RequestPermissionResponse {
-            outcome: agent_client_protocol::RequestPermissionOutcome::Cancelled,
+            outcome,
             meta: None,
         })
     }
@@ -177,6 +566,16 @@ impl Client for IFlowClientHandler {
         &self,
         args: agent_client_protocol::SessionNotification,
     ) -> anyhow::Result<(), agent_client_protocol::Error> {
+        if let Some(on_session_update) = &self.hooks.on_session_update
+            && let Ok(serde_json::Value::Object(update)) = serde_json::to_value(&args.update)
+        {
+            let update_type = update
+                .get("sessionUpdate")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            on_session_update(update_type, &update);
+        }
+
         match args.update {
             SessionUpdate::AgentMessageChunk { content } => {
                 let text = match content {
@@ -187,12 +586,7 @@ impl Client for IFlowClientHandler {
                     ContentBlock::Resource(_) => "<resource>".into(),
                 };
                 let msg = Message::Assistant { content: text };
-                let _ = self.message_sender.send(msg.clone());
-
-                // Log the message if logger is available
-                if let Some(logger) = &self.logger {
-                    let _ = logger.log_message(&msg).await;
-                }
+                self.emit(msg).await;
             }
             SessionUpdate::UserMessageChunk { content } => {
                 let text = match content {
@@ -203,12 +597,7 @@ impl Client for IFlowClientHandler {
                     ContentBlock::Resource(_) => "<resource>".into(),
                 };
                 let msg = Message::User { content: text };
-                let _ = self.message_sender.send(msg.clone());
-
-                // Log the message if logger is available
-                if let Some(logger) = &self.logger {
-                    let _ = logger.log_message(&msg).await;
-                }
+                self.emit(msg).await;
             }
             SessionUpdate::ToolCall(tool_call) => {
                 let msg = Message::ToolCall {
@@ -216,20 +605,24 @@ impl Client for IFlowClientHandler {
                     name: tool_call.title.clone(),
                     status: format!("{:?}", tool_call.status),
                 };
-                let _ = self.message_sender.send(msg.clone());
-
-                // Log the message if logger is available
-                if let Some(logger) = &self.logger {
-                    let _ = logger.log_message(&msg).await;
-                }
+                self.emit(msg).await;
             }
             SessionUpdate::Plan(plan) => {
                 let entries = plan
                     .entries
                     .into_iter()
                     .map(|entry| {
+                        // ACP has no entry ID field, so iFlow may send it in `_meta`
+                        let id = entry
+                            .meta
+                            .as_ref()
+                            .and_then(|meta| meta.get("id"))
+                            .and_then(|id| id.as_str())
+                            .map(|id| id.to_string());
+
                         // Convert agent-client-protocol PlanEntry to our PlanEntry
                         super::types::PlanEntry {
+                            id,
                             content: entry.content,
                             priority: match entry.priority {
                                 agent_client_protocol::PlanEntryPriority::High => {
@@ -258,15 +651,30 @@ impl Client for IFlowClientHandler {
                     .collect();
 
                 let msg = Message::Plan { entries };
-                let _ = self.message_sender.send(msg.clone());
-
-                // Log the message if logger is available
-                if let Some(logger) = &self.logger {
-                    let _ = logger.log_message(&msg).await;
+                self.emit(msg).await;
+            }
+            SessionUpdate::ToolCallUpdate(update) => {
+                let finished = match update.fields.status {
+                    Some(agent_client_protocol::ToolCallStatus::Completed) => "completed",
+                    Some(agent_client_protocol::ToolCallStatus::Failed) => "failed",
+                    _ => return Ok(()),
+                };
+                if let Some(hook) = &self.tool_call_hook {
+                    let call = ToolCallEvent {
+                        id: update.id.0.to_string(),
+                        name: update.fields.title.unwrap_or_default(),
+                        status: finished.to_string(),
+                        input: update.fields.raw_input,
+                        output: update.fields.raw_output,
+                    };
+                    let result = ToolCallResult {
+                        status: call.status.clone(),
+                        output: call.output.clone(),
+                    };
+                    hook.after_tool_call(&call, &result);
                 }
             }
             SessionUpdate::AgentThoughtChunk { .. }
-            | SessionUpdate::ToolCallUpdate(_)
             | SessionUpdate::CurrentModeUpdate { .. }
             | SessionUpdate::AvailableCommandsUpdate { .. } => {
                 // Ignore these for now
@@ -290,6 +698,94 @@ impl Client for IFlowClientHandler {
     }
 }
 
+/// Build a user message from a text followed by files to embed
+fn text_with_files(text: &str, files: Option<Vec<&Path>>) -> UserMessage {
+    let mut chunks = vec![UserMessageChunk::Text {
+        content: text.to_string(),
+    }];
+    chunks.extend(
+        files
+            .into_iter()
+            .flatten()
+            .map(|path| UserMessageChunk::Path {
+                path: path.to_path_buf(),
+            }),
+    );
+    UserMessage::new(chunks)
+}
+
+/// The name of an MCP server
+fn mcp_server_name(server: &McpServer) -> &str {
+    match server {
+        McpServer::Http { name, .. }
+        | McpServer::Sse { name, .. }
+        | McpServer::Stdio { name, .. } => name,
+    }
+}
+
+/// MIME types accepted by `IFlowClient::send_image`
+const IMAGE_MIME_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// Detect the MIME type of an image from its file extension
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    file_mime_type(path).filter(|mime_type| IMAGE_MIME_TYPES.contains(mime_type))
+}
+
+/// Format a binary file as a base64-encoded fenced code block
+fn binary_file_block(path: &Path, bytes: &[u8]) -> Result<String> {
+    Ok(format!(
+        "{} (binary, {} bytes, base64-encoded):\n```base64\n{}\n```\n",
+        path.display(),
+        bytes.len(),
+        encode_base64(bytes)?
+    ))
+}
+
+/// Redact `authenticate` requests and their responses from a protocol log
+fn redact_authentication(
+    mut log: Vec<(Direction, serde_json::Value)>,
+) -> Vec<(Direction, serde_json::Value)> {
+    let redacted = serde_json::json!("[redacted]");
+    let mut request_ids = Vec::new();
+    for (direction, json) in &mut log {
+        match direction {
+            Direction::Sent if json["method"] == "authenticate" => {
+                request_ids.push(json["id"].clone());
+                json["params"] = redacted.clone();
+            }
+            Direction::Received
+                if json.get("result").is_some() && request_ids.contains(&json["id"]) =>
+            {
+                json["result"] = redacted.clone();
+            }
+            _ => {}
+        }
+    }
+    log
+}
+
+/// Start the iFlow process, adding its recent stderr to process manager errors
+async fn start_process(
+    pm: &mut IFlowProcessManager,
+    use_websocket: bool,
+) -> Result<Option<String>> {
+    match pm.start(use_websocket).await {
+        Err(IFlowError::ProcessManager(msg)) => {
+            let stderr = pm.recent_stderr(10);
+            if stderr.is_empty() {
+                Err(IFlowError::ProcessManager(msg))
+            } else {
+                Err(IFlowError::ProcessManager(format!(
+                    "{}\nRecent stderr:\n{}",
+                    msg,
+                    stderr.join("\n")
+                )))
+            }
+        }
+        result => result,
+    }
+}
+
 impl IFlowClient {
     /// Create a new iFlow client
     ///
@@ -309,53 +805,379 @@ impl IFlowClient {
             None
         };
 
+        // Invalid rates are reported by `connect`
+        let rate_limiter = options
+            .rate_limit
+            .and_then(|rate| TokenBucket::new(rate).ok())
+            .map(|bucket| Arc::new(std::sync::Mutex::new(bucket)));
+
+        // Debug mode records the raw protocol for `protocol_log()`
+        let protocol_observer = options
+            .debug
+            .then(|| Arc::new(RecordingObserver::new()) as Arc<dyn RawProtocolObserver>);
+
         Self {
             options,
             message_receiver: Arc::new(Mutex::new(receiver)),
             message_sender: sender,
+            lookahead: Arc::new(std::sync::Mutex::new(VecDeque::new())),
             connected: Arc::new(Mutex::new(false)),
             connection: None,
             logger,
+            record: Arc::new(std::sync::Mutex::new(SessionRecord::default())),
+            statistics: StatisticsCounters::default(),
+            interceptors: Interceptors::default(),
+            connected_at: None,
+            disconnected_at: None,
+            background_tasks: Vec::new(),
+            protocol_observer,
+            cancellation: CancellationToken::new(),
+            parent_cancellation: None,
+            detached_prompt: None,
+            corrections: VecDeque::new(),
+            connection_latencies: VecDeque::new(),
+            sessions: Vec::new(),
+            pending_system_prompts: std::sync::Mutex::default(),
+            rate_limiter,
+            plan_subscribers: None,
+            prechecks: Vec::new(),
+        }
+    }
+
+    /// Create a new iFlow client that is cancelled together with `token`
+    ///
+    /// Cancelling `token`, e.g. an application's shutdown token, stops the
+    /// client's background tasks and makes pending sends and response waits
+    /// fail with `IFlowError::Cancelled`. Disconnecting the client does not
+    /// cancel `token`.
+    ///
+    /// # Arguments
+    /// * `options` - Optional configuration options
+    /// * `token` - The token to derive the client's cancellation from
+    ///
+    /// # Returns
+    /// A new IFlowClient instance
+    pub fn new_with_cancellation(options: Option<IFlowOptions>, token: CancellationToken) -> Self {
+        let mut client = Self::new(options);
+        client.cancellation = token.child_token();
+        client.parent_cancellation = Some(token);
+        client
+    }
+
+    /// Create a new iFlow client from a connection string
+    ///
+    /// The scheme of `uri` selects the transport:
+    /// * `stdio://` - talk to a spawned iFlow over stdio
+    /// * `ws://host:port/path?query` - connect over WebSocket
+    /// * `wss://host:port/path?query` - connect over WebSocket with TLS, which
+    ///   requires the `wss` feature
+    ///
+    /// Other settings, including the rest of `extra.websocket` such as the
+    /// reconnect settings, are taken from `extra`.
+    ///
+    /// # Arguments
+    /// * `uri` - The connection string
+    /// * `extra` - Optional configuration to merge the transport into
+    ///
+    /// # Returns
+    /// * `Ok(IFlowClient)` configured for the transport
+    /// * `Err(IFlowError::Connection)` if `uri` is malformed or has an unsupported scheme
+    pub fn new_with_connection_string(uri: &str, extra: Option<IFlowOptions>) -> Result<Self> {
+        let url = Url::parse(uri).map_err(|e| {
+            IFlowError::Connection(format!("Invalid connection string '{}': {}", uri, e))
+        })?;
+        let mut options = extra.unwrap_or_default();
+
+        match url.scheme() {
+            "stdio" => options.websocket = None,
+            "ws" | "wss" => {
+                if url.host_str().is_none_or(str::is_empty) {
+                    return Err(IFlowError::Connection(format!(
+                        "Invalid connection string '{}': missing host",
+                        uri
+                    )));
+                }
+                let websocket = options
+                    .websocket
+                    .get_or_insert_with(WebSocketConfig::default);
+                websocket.url = Some(url.to_string());
+            }
+            scheme => {
+                return Err(IFlowError::Connection(format!(
+                    "Unsupported connection string scheme '{}' in '{}', expected stdio, ws or wss",
+                    scheme, uri
+                )));
+            }
+        }
+
+        Ok(Self::new(Some(options)))
+    }
+
+    /// Get a token that is cancelled when the client disconnects
+    ///
+    /// The token is a child of the client's own token, so cancelling it
+    /// does not affect the client.
+    ///
+    /// # Returns
+    /// A child of the client's cancellation token
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.child_token()
+    }
+
+    /// Set the handler for errors in background work
+    ///
+    /// Errors that do not fail a call into the client, such as a malformed
+    /// message from iFlow or the stdio connection closing, are passed to the
+    /// handler as well as logged. Equivalent to
+    /// [`IFlowOptions::with_error_handler`].
+    ///
+    /// # Arguments
+    /// * `handler` - Called with each error
+    ///
+    /// # Returns
+    /// The client with the handler set
+    pub fn on_error(mut self, handler: Box<dyn Fn(&IFlowError) + Send + Sync + 'static>) -> Self {
+        self.options.hooks.on_error = Some(Arc::from(handler));
+        self
+    }
+
+    /// Set a hook called for every raw `session/update` from iFlow
+    ///
+    /// The hook is called with the update type (e.g. `agent_message_chunk`)
+    /// and the full update object before the update is parsed, including
+    /// update types that produce no message. Messages are still delivered as
+    /// usual. Takes effect immediately on a WebSocket connection; a stdio
+    /// connection picks it up on the next `connect()`.
+    ///
+    /// # Arguments
+    /// * `callback` - Called with the type and fields of each update
+    pub fn on_session_update(&mut self, callback: SessionUpdateHook) {
+        self.options.hooks.on_session_update = Some(callback);
+        self.refresh_protocol_hooks();
+    }
+
+    /// Remove the hook set with [`Self::on_session_update`]
+    pub fn clear_session_update_hook(&mut self) {
+        self.options.hooks.on_session_update = None;
+        self.refresh_protocol_hooks();
+    }
+
+    /// Pass changed hooks on to an established WebSocket connection
+    fn refresh_protocol_hooks(&mut self) {
+        let hooks = self.session_hooks();
+        if let Some(Connection::WebSocket { acp_protocol, .. }) = &mut self.connection {
+            acp_protocol.set_hooks(hooks);
+        }
+    }
+
+    /// Run independent queries concurrently, each with its own options
+    ///
+    /// Every query uses a separate client, as with `query_with_config`, so
+    /// queries can differ in working directory, MCP servers or timeouts.
+    /// The number of queries running at once is capped by the smallest
+    /// `global_concurrency_limit` set in any of the options; without one,
+    /// all queries run at once.
+    ///
+    /// # Arguments
+    /// * `queries` - Prompts paired with the options to run them with
+    ///
+    /// # Returns
+    /// A stream yielding each query's result as it completes
+    pub fn multi_session_query(
+        queries: Vec<(String, IFlowOptions)>,
+    ) -> impl Stream<Item = Result<String>> {
+        let limit = queries
+            .iter()
+            .filter_map(|(_, options)| options.global_concurrency_limit)
+            .min()
+            .unwrap_or(queries.len())
+            .max(1);
+
+        futures::stream::iter(queries)
+            .map(|(prompt, options)| async move {
+                crate::query::query_with_config(&prompt, options).await
+            })
+            .buffer_unordered(limit)
+    }
+
+    /// Limit the rate at which `messages()` delivers assistant chunks
+    ///
+    /// Equivalent to [`IFlowOptions::with_message_rate_limit`]. Applies to
+    /// streams created after this call.
+    ///
+    /// # Arguments
+    /// * `rate` - The maximum chunk rate
+    ///
+    /// # Returns
+    /// The client with the rate limit set
+    pub fn throttle_messages(mut self, rate: MessageRate) -> Self {
+        self.options.message_rate_limit = Some(rate);
+        self
+    }
+
+    /// Limit how many prompts this client sends per second
+    ///
+    /// Equivalent to [`IFlowOptions::with_rate_limit`]. Every `send_message`
+    /// takes a token from a token bucket and waits until one is available.
+    ///
+    /// # Arguments
+    /// * `requests_per_second` - The maximum prompt rate
+    ///
+    /// # Returns
+    /// The client with the rate limit set
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.options.rate_limit = Some(requests_per_second);
+        self.rate_limiter = TokenBucket::new(requests_per_second)
+            .ok()
+            .map(|bucket| Arc::new(std::sync::Mutex::new(bucket)));
+        self
+    }
+
+    /// Wait until the rate limit allows another prompt
+    async fn acquire_rate_limit(&self) {
+        let Some(limiter) = &self.rate_limiter else {
+            return;
+        };
+        let wait = limiter
+            .lock()
+            .map(|mut bucket| bucket.acquire())
+            .unwrap_or_default();
+        if !wait.is_zero() {
+            debug!("Rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
         }
     }
 
+    /// Observe the raw JSON-RPC messages exchanged with iFlow
+    ///
+    /// Only WebSocket connections are observed. Attach a
+    /// `RecordingObserver` to read the messages back with
+    /// [`IFlowClient::protocol_log`].
+    ///
+    /// # Arguments
+    /// * `observer` - Called for every message sent and received
+    ///
+    /// # Returns
+    /// The client with the observer attached
+    pub fn observe_raw_protocol(mut self, observer: Box<dyn RawProtocolObserver>) -> Self {
+        self.protocol_observer = Some(Arc::from(observer));
+        self
+    }
+
+    /// Get the messages recorded by the attached protocol observer
+    ///
+    /// # Returns
+    /// The messages in the order they were sent and received, or an empty
+    /// list if the observer does not record them
+    pub fn protocol_log(&self) -> Vec<(Direction, serde_json::Value)> {
+        self.protocol_observer
+            .as_ref()
+            .and_then(|observer| observer.recorded())
+            .unwrap_or_default()
+    }
+
     /// Connect to iFlow
     ///
     /// Establishes a connection to iFlow, starting the process if auto_start_process is enabled.
     /// This method handles all the necessary setup for communication via stdio or WebSocket.
+    /// Prechecks added with `with_connection_precheck` run first.
     ///
     /// # Returns
     /// * `Ok(())` if the connection was successful
-    /// * `Err(IFlowError)` if the connection failed
+    /// * `Err(IFlowError::Config)` if `rate_limit` or `message_rate_limit` is
+    ///   not a positive, finite rate
+    /// * `Err(IFlowError)` if a precheck or the connection failed
     pub async fn connect(&mut self) -> Result<()> {
         if *self.connected.lock().await {
             tracing::warn!("Already connected to iFlow");
             return Ok(());
         }
 
+        self.options.validate_rate_limits()?;
+
+        let started_at = std::time::Instant::now();
+        for precheck in &self.prechecks {
+            precheck.run(&self.options).await?;
+        }
+
         // Check if we should use WebSocket or stdio
         if self.options.websocket.is_some() {
-            self.connect_websocket().await
+            self.connect_websocket().await?;
         } else {
-            self.connect_stdio().await
+            self.connect_stdio().await?;
         }
-    }
+        self.connected_at = Some(std::time::Instant::now());
+        self.disconnected_at = None;
+        record_latency(&mut self.connection_latencies, started_at.elapsed());
 
-    /// Connect to iFlow via stdio
-    async fn connect_stdio(&mut self) -> Result<()> {
-        debug!("Connecting to iFlow via stdio");
+        if let Some(on_connect) = &self.options.hooks.on_connect {
+            on_connect();
+        }
 
-        // Start iFlow process if auto_start is enabled
-        let mut process_manager = if self.options.process.auto_start {
-            // For stdio mode, we don't need a port
-            let port = self.options.process.start_port.unwrap_or(8090);
-            let mut pm = IFlowProcessManager::new(port, self.options.process.debug);
-            let _url = pm.start(false).await?; // false for stdio
-            debug!("iFlow process started");
-            Some(pm)
-        } else {
-            None
-        };
+        Ok(())
+    }
+
+    /// Connect and set up a session without sending a prompt
+    ///
+    /// Connects if needed, then runs the protocol handshake and creates a
+    /// session, so the first `send_message` goes straight to the prompt. The
+    /// initial message, if configured, is sent and consumed here as well.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the session is ready
+    /// * `Err(IFlowError)` if connecting, initializing or creating the session failed
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use iflow_cli_sdk_rust::IFlowClient;
+    /// # async fn example(client: &mut IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// client.warmup().await?;
+    /// client.send_message("Hello", None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn warmup(&mut self) -> Result<()> {
+        if !*self.connected.lock().await {
+            self.connect().await?;
+        }
+        self.start_session().await
+    }
+
+    /// Build the hooks handed to the protocol handlers
+    ///
+    /// Wraps the user's `on_message` hook so the client can record session activity.
+    fn session_hooks(&self) -> IFlowHooks {
+        let mut hooks = self.options.hooks.clone();
+        let user_hook = hooks.on_message.take();
+        let record = self.record.clone();
+        let keep_history = self.options.keep_history;
+
+        hooks.on_message = Some(Arc::new(move |msg: &Message| {
+            if let Ok(mut record) = record.lock() {
+                record.observe(msg, keep_history);
+            }
+            if let Some(hook) = &user_hook {
+                hook(msg);
+            }
+        }));
+        hooks
+    }
+
+    /// Connect to iFlow via stdio
+    async fn connect_stdio(&mut self) -> Result<()> {
+        debug!("Connecting to iFlow via stdio");
+
+        // Start iFlow process if auto_start is enabled
+        let mut process_manager = if self.options.process.auto_start {
+            // For stdio mode, we don't need a port
+            let port = self.options.process.start_port.unwrap_or(8090);
+            let mut pm = self.process_manager(port);
+            let _url = start_process(&mut pm, false).await?; // false for stdio
+            debug!("iFlow process started");
+            Some(pm)
+        } else {
+            None
+        };
 
         // Get stdin and stdout from the process manager
         let stdin = process_manager
@@ -372,6 +1194,12 @@ impl IFlowClient {
         let handler = IFlowClientHandler {
             message_sender: self.message_sender.clone(),
             logger: self.logger.clone(),
+            hooks: self.session_hooks(),
+            tool_call_hook: self.options.on_tool_call.clone(),
+            tool_approvals: self.options.tool_approvals.clone(),
+            permission_callback: self.options.permission_callback.clone(),
+            statistics: self.statistics.clone(),
+            interceptors: self.interceptors.clone(),
         };
 
         let (conn, handle_io) =
@@ -380,11 +1208,19 @@ impl IFlowClient {
             });
 
         // Handle I/O in the background
-        tokio::task::spawn_local(handle_io);
+        let on_error = self.options.hooks.on_error.clone();
+        tokio::task::spawn_local(async move {
+            if let Err(e) = handle_io.await {
+                tracing::warn!("iFlow stdio connection failed: {}", e);
+                if let Some(on_error) = on_error {
+                    on_error(&IFlowError::Connection(e.to_string()));
+                }
+            }
+        });
 
         // Store the client
         self.connection = Some(Connection::Stdio {
-            acp_client: conn,
+            acp_client: Rc::new(conn),
             process_manager,
             session_id: None,
             initialized: false,
@@ -396,7 +1232,23 @@ impl IFlowClient {
         Ok(())
     }
 
+    /// Create a process manager configured from the options
+    fn process_manager(&self, port: u16) -> IFlowProcessManager {
+        let mut manager = IFlowProcessManager::new(port, self.options.process.debug)
+            .with_stderr_capture(self.options.process.capture_stderr)
+            .with_startup_timeout(self.options.process.startup_timeout)
+            .with_port_scan_range(self.options.process.port_scan_range);
+        if let Some(hook) = &self.options.process.pre_start_hook {
+            manager = manager.with_pre_start_hook(hook.clone());
+        }
+        if let Some(hook) = &self.options.process.post_stop_hook {
+            manager = manager.with_post_stop_hook(hook.clone());
+        }
+        manager
+    }
+
     /// Connect to iFlow via WebSocket
+    #[allow(deprecated)]
     async fn connect_websocket(&mut self) -> Result<()> {
         debug!("Connecting to iFlow via WebSocket");
 
@@ -419,7 +1271,7 @@ impl IFlowClient {
 
                     // Try to connect first to see if iFlow is already running
                     let mut test_transport =
-                        WebSocketTransport::new(url.clone(), self.options.timeout);
+                        WebSocketTransport::new(url.clone(), self.options.connect_timeout);
                     match test_transport.connect().await {
                         Ok(_) => {
                             // Successfully connected to existing iFlow process
@@ -458,13 +1310,13 @@ impl IFlowClient {
                             } else {
                                 // Port is not listening, iFlow is not running, start it
                                 debug!("iFlow not running on port {}, starting process", port);
-                                let mut pm =
-                                    IFlowProcessManager::new(port, self.options.process.debug);
-                                let iflow_url = pm.start(true).await?.ok_or_else(|| {
-                                    IFlowError::Connection(
-                                        "Failed to start iFlow with WebSocket".to_string(),
-                                    )
-                                })?;
+                                let mut pm = self.process_manager(port);
+                                let iflow_url =
+                                    start_process(&mut pm, true).await?.ok_or_else(|| {
+                                        IFlowError::Connection(
+                                            "Failed to start iFlow with WebSocket".to_string(),
+                                        )
+                                    })?;
                                 debug!("Started iFlow process at {}", iflow_url);
 
                                 // Keep the process manager to avoid early handle drop causing child process exit due to stdout/stderr pipe issues
@@ -483,16 +1335,25 @@ impl IFlowClient {
                 // URL is None, auto-generate it by starting iFlow process
                 debug!("iFlow auto-start enabled with auto-generated URL...");
                 let port = self.options.process.start_port.unwrap_or(8090);
-                let mut pm = IFlowProcessManager::new(port, self.options.process.debug);
-                let iflow_url = pm.start(true).await?.ok_or_else(|| {
-                    IFlowError::Connection("Failed to start iFlow with WebSocket".to_string())
-                })?;
-                debug!("Started iFlow process at {}", iflow_url);
+                if let Some(pid) = IFlowProcessManager::find_existing_process(port).await {
+                    // Another client already started iFlow on this port, share it
+                    info!(
+                        "Attaching to existing iFlow process (PID {}) on port {}",
+                        pid, port
+                    );
+                    format!("ws://localhost:{}/acp?peer=iflow", port)
+                } else {
+                    let mut pm = self.process_manager(port);
+                    let iflow_url = start_process(&mut pm, true).await?.ok_or_else(|| {
+                        IFlowError::Connection("Failed to start iFlow with WebSocket".to_string())
+                    })?;
+                    debug!("Started iFlow process at {}", iflow_url);
 
-                // Keep the process manager to avoid early handle drop causing child process exit due to stdout/stderr pipe issues
-                process_manager_to_keep = Some(pm);
+                    // Keep the process manager to avoid early handle drop causing child process exit due to stdout/stderr pipe issues
+                    process_manager_to_keep = Some(pm);
 
-                iflow_url
+                    iflow_url
+                }
             }
         } else {
             // Manual start mode, URL must be provided
@@ -506,7 +1367,16 @@ impl IFlowClient {
         };
 
         // Create WebSocket transport with increased timeout
-        let mut transport = WebSocketTransport::new(final_url.clone(), self.options.timeout);
+        let mut transport =
+            WebSocketTransport::new(final_url.clone(), self.options.connect_timeout)
+                .with_reconnect_settings(
+                    websocket_config.reconnect_attempts,
+                    websocket_config.reconnect_interval,
+                )
+                .with_tls_ca_bundle(websocket_config.tls_ca_bundle.clone())
+                .with_ping_interval(websocket_config.ping_interval)
+                .with_jitter(self.options.jitter)
+                .with_observer(self.protocol_observer.clone());
 
         // Connect to WebSocket with retry logic
         let mut connect_attempts = 0;
@@ -533,11 +1403,10 @@ impl IFlowClient {
                     }
 
                     // Wait before retrying
-                    tracing::debug!(
-                        "Waiting {:?} before retry...",
-                        websocket_config.reconnect_interval
-                    );
-                    tokio::time::sleep(websocket_config.reconnect_interval).await;
+                    let delay =
+                        websocket_config.reconnect_interval + jitter_delay(self.options.jitter);
+                    tracing::debug!("Waiting {:?} before retry...", delay);
+                    tokio::time::sleep(delay).await;
                 }
             }
         }
@@ -545,7 +1414,22 @@ impl IFlowClient {
         // Create ACP protocol handler
         let mut acp_protocol =
             ACPProtocol::new(transport, self.message_sender.clone(), self.options.timeout);
+        acp_protocol.set_timeouts(
+            self.options.connect_timeout,
+            self.options.operation_timeout,
+            self.options.prompt_timeout,
+        );
         acp_protocol.set_permission_mode(self.options.permission_mode);
+        acp_protocol.set_tool_approvals(self.options.tool_approvals.clone());
+        acp_protocol.set_hooks(self.session_hooks());
+        acp_protocol.set_tool_call_hook(self.options.on_tool_call.clone());
+        acp_protocol.set_permission_callback(self.options.permission_callback.clone());
+        acp_protocol.set_max_batch_size(self.options.max_batch_size);
+        acp_protocol.set_retry_policy(self.options.retry_policy.clone());
+        acp_protocol.set_protocol_version(self.options.protocol_version);
+        acp_protocol.set_logger(self.logger.clone());
+        acp_protocol.set_statistics(self.statistics.clone());
+        acp_protocol.set_interceptors(self.interceptors.clone());
 
         // Store the connection (now also holds process_manager)
         self.connection = Some(Connection::WebSocket {
@@ -566,52 +1450,100 @@ impl IFlowClient {
     /// This method initializes the connection, creates a new session, sends the prompt,
     /// and waits for completion before returning.
     ///
+    /// If `options.initial_message` is set and no session exists yet, the
+    /// initial message is sent first and its response is consumed, so it does
+    /// not show up in `messages()`.
+    ///
     /// # Arguments
     /// * `text` - The text message to send to iFlow
-    /// * `_files` - Optional files to send (currently not implemented)
+    /// * `files` - Optional files to embed in the prompt as resources; text
+    ///   files are embedded as text, binary files base64-encoded
     ///
     /// # Returns
     /// * `Ok(())` if the message was sent successfully
-    /// * `Err(IFlowError)` if there was an error
-    pub async fn send_message(&mut self, text: &str, _files: Option<Vec<&Path>>) -> Result<()> {
+    /// * `Err(IFlowError::FileNotFound)` if a file does not exist
+    /// * `Err(IFlowError::FileTooLarge)` if a file exceeds `file_access.max_size`
+    /// * `Err(IFlowError)` if there was another error
+    pub async fn send_message(&mut self, text: &str, files: Option<Vec<&Path>>) -> Result<()> {
+        self.send_message_with_meta(text, files, HashMap::new())
+            .await
+    }
+
+    /// Send a message to iFlow and stream its response as it arrives
+    ///
+    /// Unlike [`IFlowClient::send_message`], this does not wait for the
+    /// response: over stdio the prompt runs on a local task (so this must be
+    /// called within a `LocalSet`) and the returned stream yields the
+    /// messages as iFlow produces them. The task ends the response with a
+    /// `Message::TaskFinish`, or a `Message::Error` if the prompt failed, so
+    /// read the stream up to one of those.
+    ///
+    /// Over WebSocket, session updates are only handled while the protocol
+    /// waits for the prompt response, so the response is read before the
+    /// stream is returned and ends with a `Message::TaskFinish`.
+    ///
+    /// # Arguments
+    /// * `text` - The text message to send to iFlow
+    /// * `files` - Optional files to embed in the prompt
+    ///
+    /// # Returns
+    /// * `Ok(MessageStream)` yielding the messages of the response
+    /// * `Err(IFlowError)` if the prompt could not be sent, or if it failed
+    ///   over WebSocket
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use iflow_cli_sdk_rust::{IFlowClient, Message};
+    /// # async fn example(client: &mut IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// let mut stream = client.send_message_streaming("Explain lifetimes", None).await?;
+    /// while let Some(msg) = stream.next().await {
+    ///     match msg {
+    ///         Message::Assistant { content } => print!("{}", content),
+    ///         Message::TaskFinish { .. } | Message::Error { .. } => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_message_streaming(
+        &mut self,
+        text: &str,
+        files: Option<Vec<&Path>>,
+    ) -> Result<MessageStream> {
         if !*self.connected.lock().await {
             return Err(IFlowError::NotConnected);
         }
+        self.acquire_rate_limit().await;
 
-        let is_websocket = matches!(self.connection, Some(Connection::WebSocket { .. }));
+        self.start_session().await?;
 
-        if is_websocket {
-            // Adapt to the newly added process_manager field
-            if let Some(Connection::WebSocket {
-                mut acp_protocol,
-                mut session_id,
-                process_manager,
-            }) = self.connection.take()
-            {
-                let pm = process_manager;
-                let result = self
-                    .send_message_websocket(&mut acp_protocol, &mut session_id, text)
-                    .await;
-                self.connection = Some(Connection::WebSocket {
-                    acp_protocol,
-                    session_id,
-                    process_manager: pm,
-                });
-                result
-            } else {
-                Err(IFlowError::NotConnected)
-            }
-        } else {
-            // Handle Stdio connection by temporarily taking ownership
-            if let Some(Connection::Stdio {
+        if let Ok(mut record) = self.record.lock() {
+            let msg = Message::User {
+                content: text.to_string(),
+            };
+            record.observe(&msg, self.options.keep_history);
+        }
+
+        let message = self.with_format_instruction(&text_with_files(text, files));
+        let meta = self.prompt_meta(HashMap::new());
+
+        match self.connection.take() {
+            Some(Connection::Stdio {
                 acp_client,
                 process_manager,
                 mut session_id,
                 mut initialized,
-            }) = self.connection.take()
-            {
+            }) => {
                 let result = self
-                    .send_message_stdio(&acp_client, &mut session_id, &mut initialized, text)
+                    .start_prompt_stdio(
+                        &acp_client,
+                        &mut session_id,
+                        &mut initialized,
+                        &message,
+                        meta,
+                    )
                     .await;
                 self.connection = Some(Connection::Stdio {
                     acp_client,
@@ -619,202 +1551,2262 @@ impl IFlowClient {
                     session_id,
                     initialized,
                 });
-                result
-            } else {
-                Err(IFlowError::NotConnected)
+                result?;
             }
+            Some(connection) => {
+                self.connection = Some(connection);
+                self.send_prompt(&message, meta).await?;
+            }
+            None => return Err(IFlowError::NotConnected),
         }
+
+        Ok(self.messages())
     }
 
-    /// Send a message via stdio connection
-    async fn send_message_stdio(
-        &self,
-        client: &ClientSideConnection,
-        session_id: &mut Option<SessionId>,
-        initialized: &mut bool,
-        text: &str,
+    /// Prime the session with a scripted conversation
+    ///
+    /// Each user text is sent to iFlow with `send_message` and iFlow's own
+    /// response is consumed up to its `TaskFinish`. The scripted reply is
+    /// recorded in the history in its place, so nothing is left in
+    /// `messages()` for the next prompt. Intended for tests and evaluations
+    /// only.
+    ///
+    /// # Arguments
+    /// * `turns` - `(user_text, assistant_text)` pairs, in order
+    ///
+    /// # Returns
+    /// * `Ok(())` if every turn was sent
+    /// * `Err(IFlowError)` if sending a message failed
+    #[doc(hidden)]
+    pub async fn send_message_batch_with_context(
+        &mut self,
+        turns: Vec<(String, String)>,
     ) -> Result<()> {
-        tracing::debug!("send_message_stdio called with text: {}", text);
-
-        // Initialize the connection if not already done
-        if !*initialized {
-            tracing::debug!("Initializing connection...");
-            client
-                .initialize(agent_client_protocol::InitializeRequest {
-                    protocol_version: agent_client_protocol::V1,
-                    client_capabilities: agent_client_protocol::ClientCapabilities::default(),
-                    meta: None,
-                })
-                .await
-                .map_err(|e| IFlowError::Connection(format!("Failed to initialize: {}", e)))?;
-
-            *initialized = true;
-            debug!("Initialized stdio connection");
-        }
-
-        // Create a new session if we don't have one
-        if session_id.is_none() {
-            tracing::debug!("Creating new session...");
-            let session_request = agent_client_protocol::NewSessionRequest {
-                mcp_servers: self.options.mcp_servers.clone(),
-                cwd: std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from(".")),
-                meta: None,
-            };
-            tracing::debug!("Session request: {:?}", session_request);
-
-            let session_response = client.new_session(session_request).await.map_err(|e| {
-                tracing::error!("Failed to create session: {}", e);
-                IFlowError::Connection(format!("Failed to create session: {}", e))
-            })?;
-
-            *session_id = Some(session_response.session_id);
-            debug!("Created new session: {:?}", session_id);
-        }
-
-        // Use the existing session
-        let current_session_id = session_id.as_ref().unwrap();
-
-        // Send the prompt and wait for completion
-        tracing::debug!("Sending prompt to session: {:?}", current_session_id);
-        let prompt_response = client
-            .prompt(agent_client_protocol::PromptRequest {
-                session_id: current_session_id.clone(),
-                prompt: vec![agent_client_protocol::ContentBlock::Text(
-                    agent_client_protocol::TextContent {
-                        text: text.to_string(),
-                        annotations: None,
-                        meta: None,
+        for (user_text, assistant_text) in turns {
+            self.send_message(&user_text, None).await?;
+            self.consume_turn("context prompt").await?;
+            if let Ok(mut record) = self.record.lock() {
+                record.observe(
+                    &Message::Assistant {
+                        content: assistant_text,
                     },
-                )],
-                meta: None,
-            })
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to send message: {}", e);
-                IFlowError::Connection(format!("Failed to send message: {}", e))
-            })?;
-
-        tracing::debug!(
-            "Prompt response received, stop reason: {:?}",
-            prompt_response.stop_reason
-        );
-
-        // Send task finish message with the actual stop reason
-        let message = Message::TaskFinish {
-            reason: Some(format!("{:?}", prompt_response.stop_reason)),
-        };
-
-        self.message_sender.send(message).map_err(|e| {
-            tracing::error!("Failed to send task finish message: {}", e);
-            IFlowError::Connection("Message channel closed".to_string())
-        })?;
-
-        debug!("Sent message to iFlow via stdio: {}", text);
+                    self.options.keep_history,
+                );
+            }
+        }
         Ok(())
     }
 
-    /// Send a message via WebSocket connection
-    async fn send_message_websocket(
+    /// Send a message to iFlow with per-message metadata
+    ///
+    /// Like [`IFlowClient::send_message`], but `meta` is merged into
+    /// `options.metadata` for this request only, with entries in `meta`
+    /// taking precedence. The merged metadata is sent as the `_meta` field of
+    /// the `session/prompt` request.
+    ///
+    /// # Arguments
+    /// * `text` - The text message to send to iFlow
+    /// * `files` - Optional files to embed in the prompt
+    /// * `meta` - Metadata for this message, e.g. a request ID for tracing
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError)` if there was an error
+    pub async fn send_message_with_meta(
         &mut self,
-        protocol: &mut ACPProtocol,
-        session_id: &mut Option<String>,
         text: &str,
+        files: Option<Vec<&Path>>,
+        meta: HashMap<String, serde_json::Value>,
     ) -> Result<()> {
-        // Initialize the protocol if not already done
-        if !protocol.is_initialized() {
-            tracing::debug!("Initializing WebSocket protocol...");
-            protocol.initialize(&self.options).await.map_err(|e| {
-                tracing::error!("Failed to initialize protocol: {}", e);
-                e
-            })?;
-
-            // Authenticate if needed
-            if !protocol.is_authenticated() {
-                tracing::debug!("Authenticating...");
-                if let Some(method_id) = &self.options.auth_method_id {
-                    protocol.authenticate(method_id, None).await.map_err(|e| {
-                        tracing::error!("Authentication failed with method {}: {}", method_id, e);
-                        e
-                    })?;
-                } else {
-                    // Try default authentication
-                    protocol.authenticate("iflow", None).await.map_err(|e| {
-                        tracing::error!("Default authentication failed: {}", e);
-                        e
-                    })?;
-                }
-            }
-
-            // Create a new session
-            tracing::debug!("Creating new session...");
-            let current_dir = std::env::current_dir()
-                .unwrap_or_else(|_| std::path::PathBuf::from("."))
-                .to_string_lossy()
-                .to_string();
+        self.send_user_message(text, &text_with_files(text, files), meta)
+            .await
+    }
 
-            // Convert McpServer objects to JSON-compatible format
-            let mcp_servers: Vec<serde_json::Value> = self
-                .options
-                .mcp_servers
-                .iter()
-                .map(|server| {
-                    // Since McpServer is an enum, we need to serialize it directly
-                    // The agent-client-protocol crate handles the serialization
-                    serde_json::json!(server)
-                })
-                .collect();
+    /// Send a structured prompt to iFlow
+    ///
+    /// The prompt is sent as one message whose content blocks are, in order:
+    /// the system instructions, the examples, the context files and the
+    /// user message. See [`StructuredPrompt::to_user_message`].
+    ///
+    /// # Arguments
+    /// * `prompt` - The prompt to send
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError)` if a context file cannot be read or sending failed
+    pub async fn send_structured_prompt(&mut self, prompt: StructuredPrompt) -> Result<()> {
+        let message = prompt.to_user_message();
+        self.send_user_message(&prompt.user, &message, HashMap::new())
+            .await
+    }
 
-            let new_session_id = protocol
-                .create_session(&current_dir, mcp_servers)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Failed to create session: {}", e);
-                    e
-                })?;
-            *session_id = Some(new_session_id);
-            tracing::debug!("Session created successfully");
+    /// Send an image together with a text prompt
+    ///
+    /// The image is sent base64-encoded as an image content block, followed
+    /// by `prompt` as a text block.
+    ///
+    /// # Arguments
+    /// * `image_data` - The raw bytes of the image
+    /// * `mime_type` - One of `image/jpeg`, `image/png`, `image/gif` or `image/webp`
+    /// * `prompt` - The prompt about the image
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError::Validation)` if the MIME type is not supported
+    /// * `Err(IFlowError::FileTooLarge)` if the image exceeds `file_access.max_size`
+    /// * `Err(IFlowError::Config)` if the `file-support` feature is disabled
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_image(
+        &mut self,
+        image_data: &[u8],
+        mime_type: &str,
+        prompt: &str,
+    ) -> Result<()> {
+        if !IMAGE_MIME_TYPES.contains(&mime_type) {
+            return Err(IFlowError::Validation(format!(
+                "Unsupported image type: {} (expected one of {})",
+                mime_type,
+                IMAGE_MIME_TYPES.join(", ")
+            )));
+        }
+        let size = image_data.len() as u64;
+        if size > self.options.file_access.max_size {
+            return Err(IFlowError::FileTooLarge {
+                path: PathBuf::from("<image>"),
+                size,
+                limit: self.options.file_access.max_size,
+            });
         }
 
-        // Make sure we have a session
-        let current_session_id = session_id
-            .as_ref()
-            .ok_or_else(|| IFlowError::Connection("No session available".to_string()))?;
+        let message = UserMessage::new(vec![
+            UserMessageChunk::Image {
+                data: encode_base64(image_data)?,
+                mime_type: mime_type.to_string(),
+            },
+            UserMessageChunk::Text {
+                content: prompt.to_string(),
+            },
+        ]);
+        self.send_user_message(prompt, &message, HashMap::new())
+            .await
+    }
 
-        // Send the prompt and get the request ID
-        tracing::debug!("Sending prompt to session: {}", current_session_id);
-        let _request_id = protocol
-            .send_prompt(current_session_id, text)
+    /// Send an image file together with a text prompt
+    ///
+    /// The MIME type is detected from the file extension. See
+    /// [`IFlowClient::send_image`].
+    ///
+    /// # Arguments
+    /// * `path` - The image file
+    /// * `prompt` - The prompt about the image
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError::Validation)` if the extension is not a supported image type
+    /// * `Err(IFlowError::FileTooLarge)` if the file exceeds `file_access.max_size`
+    /// * `Err(IFlowError)` if the file cannot be read or sending failed
+    pub async fn send_image_file(&mut self, path: &Path, prompt: &str) -> Result<()> {
+        let mime_type = image_mime_type(path).ok_or_else(|| {
+            IFlowError::Validation(format!(
+                "Cannot detect the image type of {}",
+                path.display()
+            ))
+        })?;
+
+        let size = tokio::fs::metadata(path)
             .await
-            .map_err(|e| {
-                tracing::error!("Failed to send prompt: {}", e);
-                e
-            })?;
+            .map_err(|e| IFlowError::from_file_io(e, path))?
+            .len();
+        if size > self.options.file_access.max_size {
+            return Err(IFlowError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit: self.options.file_access.max_size,
+            });
+        }
 
-        debug!("Sent message to iFlow: {}", text);
-        Ok(())
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|e| IFlowError::from_file_io(e, path))?;
+        self.send_image(&data, mime_type, prompt).await
     }
 
-    /// Interrupt the current message generation
+    /// Ask iFlow to explain the last plan it sent
     ///
-    /// Sends an interrupt signal to stop the current message generation.
-    /// This is useful for canceling long-running requests.
+    /// Sends the plan as JSON and waits for the explanation. Neither the
+    /// prompt nor the explanation is added to the conversation history.
     ///
     /// # Returns
-    /// * `Ok(())` if the interrupt was sent successfully
-    /// * `Err(IFlowError)` if there was an error
-    pub async fn interrupt(&self) -> Result<()> {
+    /// * `Ok(String)` containing the explanation
+    /// * `Err(IFlowError::Protocol)` if no plan has been received yet or iFlow
+    ///   reported an error
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn explain_plan(&mut self) -> Result<String> {
+        let (plan, history_len) = self
+            .record
+            .lock()
+            .map(|record| (record.plan.clone(), record.history.len()))
+            .unwrap_or_default();
+        let plan = plan.ok_or_else(|| IFlowError::Protocol("No plan available".to_string()))?;
+
         if !*self.connected.lock().await {
             return Err(IFlowError::NotConnected);
         }
+        self.start_session().await?;
 
-        let message = Message::TaskFinish {
-            reason: Some("interrupted".to_string()),
-        };
+        let prompt = format!(
+            "Please explain this plan in 2-3 sentences: {}",
+            serde_json::to_string(&plan)?
+        );
+        let meta = self.prompt_meta(HashMap::new());
+        self.send_prompt(&UserMessage::new_text(prompt), meta)
+            .await?;
+        let turn = self.collect_turn().await;
 
-        self.message_sender
-            .send(message)
-            .map_err(|_| IFlowError::Connection("Message channel closed".to_string()))?;
-        Ok(())
+        if let Ok(mut record) = self.record.lock() {
+            record.history.truncate(history_len);
+        }
+        Ok(turn?.response)
+    }
+
+    /// Rank documents by their relevance to a query
+    ///
+    /// Asks iFlow for the indices of the most relevant documents as JSON of
+    /// the form `{"indices": [2, 0]}`. Indices that are out of range or
+    /// repeated are dropped.
+    ///
+    /// # Arguments
+    /// * `query` - What to search for
+    /// * `documents` - The documents to rank
+    /// * `top_k` - The maximum number of indices to return
+    ///
+    /// # Returns
+    /// * `Ok(Vec<usize>)` with indices into `documents`, most relevant first
+    /// * `Err(IFlowError::Deserialization)` if the response is not the expected JSON
+    /// * `Err(IFlowError)` if there was any other error
+    pub async fn semantic_search(
+        &mut self,
+        query: &str,
+        documents: Vec<String>,
+        top_k: usize,
+    ) -> Result<Vec<usize>> {
+        #[derive(serde::Deserialize)]
+        struct Ranking {
+            indices: Vec<i64>,
+        }
+
+        if documents.is_empty() || top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut prompt = format!(
+            "Rank the documents below by relevance to the query. Reply only with JSON of the form \
+             {{\"indices\": [2, 0]}} listing the indices of the {} most relevant documents, \
+             most relevant first.\n\nQuery: {}\n\nDocuments:\n",
+            top_k, query
+        );
+        for (index, document) in documents.iter().enumerate() {
+            prompt.push_str(&format!("[{}] {}\n", index, document));
+        }
+
+        self.send_message(&prompt, None).await?;
+        let (response, _) = self.collect_response().await?;
+        let ranking: Ranking =
+            serde_json::from_str(response.trim()).map_err(IFlowError::Deserialization)?;
+
+        let mut indices = Vec::new();
+        for index in ranking.indices {
+            let Ok(index) = usize::try_from(index) else {
+                continue;
+            };
+            if index < documents.len() && !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+        indices.truncate(top_k);
+        Ok(indices)
+    }
+
+    /// Send a user message, creating a session and sending the initial message if needed
+    ///
+    /// # Arguments
+    /// * `text` - The text recorded in the history for this message
+    /// * `message` - The message to send
+    /// * `meta` - Metadata for this message
+    async fn send_user_message(
+        &mut self,
+        text: &str,
+        message: &UserMessage,
+        meta: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        if !*self.connected.lock().await {
+            return Err(IFlowError::NotConnected);
+        }
+        self.acquire_rate_limit().await;
+
+        self.start_session().await?;
+        debug_assert!(
+            !self
+                .assert_protocol_invariants()
+                .iter()
+                .any(|violation| violation.severity == Severity::Error),
+            "ACP protocol invariants violated: {:?}",
+            self.assert_protocol_invariants()
+        );
+
+        if let Ok(mut record) = self.record.lock() {
+            let msg = Message::User {
+                content: text.to_string(),
+            };
+            record.observe(&msg, self.options.keep_history);
+        }
+
+        let message = self.with_format_instruction(message);
+        let started = std::time::Instant::now();
+        let meta = self.prompt_meta(meta);
+        let cancellation = self.cancellation.clone();
+        let result = tokio::select! {
+            result = self.send_prompt(&message, meta) => result,
+            _ = cancellation.cancelled() => Err(IFlowError::Cancelled { by: CancelledBy::User }),
+        };
+        if result.is_ok() {
+            self.statistics.record_response_time(started.elapsed());
+        }
+        result
+    }
+
+    /// Ask for the preferred response format after the user's content
+    fn with_format_instruction(&self, message: &UserMessage) -> UserMessage {
+        let mut message = message.clone();
+        if let Some(instruction) = self.options.preferred_response_format.instruction() {
+            message.chunks.push(UserMessageChunk::Text {
+                content: instruction,
+            });
+        }
+        message
+    }
+
+    /// Send a message to iFlow without waiting for the response
+    ///
+    /// Returns as soon as the `session/prompt` request has been sent, not
+    /// when iFlow has answered it. Useful for messages whose answer does not
+    /// matter, such as extra context.
+    ///
+    /// The messages of the response are not drained: call
+    /// [`IFlowClient::drain_messages`] before the next `send_message`, or they
+    /// pile up and are mixed into the next response. Over WebSocket the
+    /// response is only read from the connection by `drain_messages` or the
+    /// next message sent.
+    ///
+    /// # Arguments
+    /// * `text` - The text message to send to iFlow
+    ///
+    /// # Returns
+    /// * `Ok(())` once the prompt was sent
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_message_fire_and_forget(&mut self, text: &str) -> Result<()> {
+        if !*self.connected.lock().await {
+            return Err(IFlowError::NotConnected);
+        }
+        self.acquire_rate_limit().await;
+
+        self.start_session().await?;
+
+        if let Ok(mut record) = self.record.lock() {
+            let msg = Message::User {
+                content: text.to_string(),
+            };
+            record.observe(&msg, self.options.keep_history);
+        }
+
+        let message =
+            self.with_format_instruction(&UserMessage::new(vec![UserMessageChunk::Text {
+                content: text.to_string(),
+            }]));
+        let meta = self.prompt_meta(HashMap::new());
+
+        match self.connection.take() {
+            Some(Connection::WebSocket {
+                mut acp_protocol,
+                mut session_id,
+                process_manager,
+            }) => {
+                let result = self
+                    .start_prompt_websocket(&mut acp_protocol, &mut session_id, &message, meta)
+                    .await;
+                self.connection = Some(Connection::WebSocket {
+                    acp_protocol,
+                    session_id,
+                    process_manager,
+                });
+                result
+            }
+            Some(Connection::Stdio {
+                acp_client,
+                process_manager,
+                mut session_id,
+                mut initialized,
+            }) => {
+                let result = self
+                    .start_prompt_stdio(
+                        &acp_client,
+                        &mut session_id,
+                        &mut initialized,
+                        &message,
+                        meta,
+                    )
+                    .await;
+                self.connection = Some(Connection::Stdio {
+                    acp_client,
+                    process_manager,
+                    session_id,
+                    initialized,
+                });
+                result
+            }
+            None => Err(IFlowError::NotConnected),
+        }
+    }
+
+    /// Send a prompt via WebSocket, leaving its response to be read later
+    async fn start_prompt_websocket(
+        &self,
+        protocol: &mut ACPProtocol,
+        session_id: &mut Option<String>,
+        message: &UserMessage,
+        meta: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.prepare_session_websocket(protocol, session_id).await?;
+        let current_session_id = session_id.as_ref().ok_or(ConnectionError::NoSession)?;
+        protocol
+            .start_prompt_blocks(
+                current_session_id,
+                self.prompt_blocks(current_session_id, message).await?,
+                meta,
+            )
+            .await?;
+        debug!("Sent message to iFlow without waiting: {:?}", message);
+        Ok(())
+    }
+
+    /// Send a prompt via stdio on a local task that delivers the `TaskFinish`,
+    /// or a `Message::Error` if the prompt fails
+    async fn start_prompt_stdio(
+        &mut self,
+        client: &Rc<ClientSideConnection>,
+        session_id: &mut Option<SessionId>,
+        initialized: &mut bool,
+        message: &UserMessage,
+        meta: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.finish_detached_prompt().await;
+        self.prepare_session_stdio(client, session_id, initialized)
+            .await?;
+        let current_session_id = session_id.clone().ok_or(ConnectionError::NoSession)?;
+        let request = agent_client_protocol::PromptRequest {
+            prompt: self.prompt_blocks(&current_session_id.0, message).await?,
+            session_id: current_session_id,
+            meta,
+        };
+
+        let client = client.clone();
+        let message_sender = self.message_sender.clone();
+        let on_error = self.options.hooks.on_error.clone();
+        self.detached_prompt = Some(tokio::task::spawn_local(async move {
+            match client.prompt(request).await {
+                Ok(response) => {
+                    let _ = message_sender.send(Message::TaskFinish {
+                        reason: Some(format!("{:?}", response.stop_reason)),
+                    });
+                }
+                Err(e) => {
+                    let error = IFlowError::Connection(format!("Failed to send message: {}", e));
+                    tracing::warn!("{}", error);
+                    if let Some(on_error) = on_error {
+                        on_error(&error);
+                    }
+                    let _ = message_sender
+                        .send(Message::error(PROMPT_FAILED_ERROR_CODE, error.to_string()));
+                }
+            }
+        }));
+        debug!("Sent message to iFlow without waiting: {:?}", message);
+        Ok(())
+    }
+
+    /// Wait for the prompt started by `start_prompt_stdio` to finish
+    ///
+    /// Prompts are kept in order, so every stdio send waits for the previous
+    /// fire-and-forget prompt first.
+    async fn finish_detached_prompt(&mut self) {
+        if let Some(prompt) = self.detached_prompt.take() {
+            let _ = prompt.await;
+        }
+    }
+
+    /// Redirect iFlow while it works on a task
+    ///
+    /// Over WebSocket the correction is added to the current turn with
+    /// `session/inject`. If iFlow does not support it, and always over
+    /// stdio, the correction is sent as a new prompt starting with
+    /// [`CORRECTION_MARKER`], without waiting for the response.
+    ///
+    /// Interrupting a running tool may cause unexpected behavior, so while a
+    /// tool call has not completed or failed the correction is buffered. It
+    /// is sent by the next `send_correction` or `flush_corrections` after
+    /// iFlow reported the tool call as finished.
+    ///
+    /// # Arguments
+    /// * `correction` - The correction to send
+    ///
+    /// # Returns
+    /// * `Ok(())` if the correction was sent or buffered
+    /// * `Err(IFlowError)` if sending failed
+    pub async fn send_correction(&mut self, correction: &str) -> Result<()> {
+        if !*self.connected.lock().await {
+            return Err(IFlowError::NotConnected);
+        }
+        self.corrections.push_back(correction.to_string());
+        self.flush_corrections().await?;
+        Ok(())
+    }
+
+    /// Send corrections buffered by `send_correction`
+    ///
+    /// Does nothing while a tool call is still running.
+    ///
+    /// # Returns
+    /// * `Ok(usize)` with the number of corrections sent
+    /// * `Err(IFlowError)` if sending failed; unsent corrections stay buffered
+    pub async fn flush_corrections(&mut self) -> Result<usize> {
+        let running = self
+            .record
+            .lock()
+            .map(|record| record.pending_tool_calls())
+            .unwrap_or_default();
+        if !running.is_empty() {
+            debug!(
+                "Buffering {} correction(s) until tool calls {:?} finish",
+                self.corrections.len(),
+                running
+            );
+            return Ok(0);
+        }
+
+        let mut sent = 0;
+        while let Some(correction) = self.corrections.pop_front() {
+            if let Err(e) = self.deliver_correction(&correction).await {
+                self.corrections.push_front(correction);
+                return Err(e);
+            }
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Number of corrections waiting for running tool calls to finish
+    pub fn buffered_corrections(&self) -> usize {
+        self.corrections.len()
+    }
+
+    /// Send a single correction over the current connection
+    async fn deliver_correction(&mut self, correction: &str) -> Result<()> {
+        self.start_session().await?;
+
+        if let Ok(mut record) = self.record.lock() {
+            let msg = Message::User {
+                content: correction.to_string(),
+            };
+            record.observe(&msg, self.options.keep_history);
+        }
+
+        let message = UserMessage::new(vec![UserMessageChunk::Text {
+            content: format!("{} {}", CORRECTION_MARKER, correction),
+        }]);
+        let meta = self.prompt_meta(HashMap::new());
+
+        match self.connection.take() {
+            Some(Connection::WebSocket {
+                mut acp_protocol,
+                mut session_id,
+                process_manager,
+            }) => {
+                let result = match &session_id {
+                    Some(id) => acp_protocol.inject_prompt(id, correction).await,
+                    None => Err(ConnectionError::NoSession.into()),
+                };
+                let result = match result {
+                    Ok(true) => Ok(()),
+                    Ok(false) => {
+                        self.start_prompt_websocket(
+                            &mut acp_protocol,
+                            &mut session_id,
+                            &message,
+                            meta,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                self.connection = Some(Connection::WebSocket {
+                    acp_protocol,
+                    session_id,
+                    process_manager,
+                });
+                result
+            }
+            Some(Connection::Stdio {
+                acp_client,
+                process_manager,
+                mut session_id,
+                mut initialized,
+            }) => {
+                let result = self
+                    .start_prompt_stdio(
+                        &acp_client,
+                        &mut session_id,
+                        &mut initialized,
+                        &message,
+                        meta,
+                    )
+                    .await;
+                self.connection = Some(Connection::Stdio {
+                    acp_client,
+                    process_manager,
+                    session_id,
+                    initialized,
+                });
+                result
+            }
+            None => Err(IFlowError::NotConnected),
+        }
+    }
+
+    /// Take every message received so far
+    ///
+    /// Waits for a message sent with
+    /// [`IFlowClient::send_message_fire_and_forget`] to be answered, then
+    /// returns all queued messages without blocking for more. Call this
+    /// before sending the next message.
+    ///
+    /// Over stdio a failed fire-and-forget message is reported as a
+    /// `Message::Error` among the returned messages.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Message>)` containing the queued messages, oldest first
+    /// * `Err(IFlowError)` if the WebSocket response to a fire-and-forget
+    ///   message failed
+    pub async fn drain_messages(&mut self) -> Result<Vec<Message>> {
+        self.finish_detached_prompt().await;
+        if let Some(Connection::WebSocket { acp_protocol, .. }) = &mut self.connection {
+            acp_protocol.finish_pending_prompt().await?;
+        }
+
+        let mut messages: Vec<Message> = match self.lookahead.lock() {
+            Ok(mut lookahead) => lookahead.drain(..).collect(),
+            Err(_) => Vec::new(),
+        };
+        let mut receiver = self.message_receiver.lock().await;
+        while let Ok(msg) = receiver.try_recv() {
+            messages.push(msg);
+        }
+        Ok(messages)
+    }
+
+    /// Send a message, recovering from errors between attempts
+    ///
+    /// Failed attempts are passed to [`IFlowClient::recover_from_error`];
+    /// the message is sent again unless recovery aborts.
+    ///
+    /// # Arguments
+    /// * `text` - The text message to send to iFlow
+    /// * `files` - Optional files to embed in the prompt
+    /// * `max_attempts` - Maximum number of times to send the message
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError)` with the last error if all attempts failed or recovery aborted
+    pub async fn send_message_with_recovery(
+        &mut self,
+        text: &str,
+        files: Option<Vec<&Path>>,
+        max_attempts: u32,
+    ) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.send_message(text, files.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            if attempt >= max_attempts {
+                return Err(err);
+            }
+
+            match self.recover_from_error(&err).await? {
+                RecoveryAction::Reconnected | RecoveryAction::RetrySend => {
+                    debug!("Retrying message after error: {}", err);
+                    attempt += 1;
+                }
+                RecoveryAction::Abort(reason) => {
+                    debug!("Not retrying message: {}", reason);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Try to recover from an error returned by the client
+    ///
+    /// * `NotConnected` - connects
+    /// * `Connection` and `Transport` - disconnects and connects again
+    /// * `Timeout` - nothing to do, the message can be sent again
+    /// * anything else - cannot be recovered from
+    ///
+    /// Reconnecting starts a new session.
+    ///
+    /// # Arguments
+    /// * `err` - The error to recover from
+    ///
+    /// # Returns
+    /// * `Ok(RecoveryAction)` describing what the caller should do next
+    /// * `Err(IFlowError)` if reconnecting failed
+    pub async fn recover_from_error(&mut self, err: &IFlowError) -> Result<RecoveryAction> {
+        match err {
+            IFlowError::NotConnected => {
+                self.connect().await?;
+                Ok(RecoveryAction::Reconnected)
+            }
+            IFlowError::Connection(_) | IFlowError::Transport(_) => {
+                if let Err(e) = self.disconnect().await {
+                    debug!("Ignoring error while disconnecting for recovery: {}", e);
+                }
+                self.connect().await?;
+                Ok(RecoveryAction::Reconnected)
+            }
+            IFlowError::Timeout(_) => Ok(RecoveryAction::RetrySend),
+            _ => Ok(RecoveryAction::Abort(err.to_string())),
+        }
+    }
+
+    /// Merge per-message metadata into `options.metadata`
+    ///
+    /// # Returns
+    /// The merged metadata as a JSON object, or `None` if there is none
+    fn prompt_meta(&self, meta: HashMap<String, serde_json::Value>) -> Option<serde_json::Value> {
+        let mut merged: serde_json::Map<String, serde_json::Value> = self
+            .options
+            .metadata
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        merged.extend(meta);
+        (!merged.is_empty()).then_some(serde_json::Value::Object(merged))
+    }
+
+    /// Prepare the session and send the initial message to new sessions
+    async fn start_session(&mut self) -> Result<()> {
+        let new_session = self.session_id().is_none();
+        self.prepare_session().await?;
+
+        if new_session && let Some(initial_message) = self.options.initial_message.clone() {
+            debug!("Sending initial message for new session");
+            let session_meta = self.prompt_meta(HashMap::new());
+            self.send_prompt(&UserMessage::new_text(initial_message), session_meta)
+                .await?;
+            self.consume_turn("initial message").await?;
+        }
+        Ok(())
+    }
+
+    /// Initialize the protocol and create a session on the current connection if needed
+    async fn prepare_session(&mut self) -> Result<()> {
+        match self.connection.take() {
+            Some(Connection::WebSocket {
+                mut acp_protocol,
+                mut session_id,
+                process_manager,
+            }) => {
+                let result = self
+                    .prepare_session_websocket(&mut acp_protocol, &mut session_id)
+                    .await;
+                self.connection = Some(Connection::WebSocket {
+                    acp_protocol,
+                    session_id,
+                    process_manager,
+                });
+                result
+            }
+            Some(Connection::Stdio {
+                acp_client,
+                process_manager,
+                mut session_id,
+                mut initialized,
+            }) => {
+                let result = self
+                    .prepare_session_stdio(&acp_client, &mut session_id, &mut initialized)
+                    .await;
+                self.connection = Some(Connection::Stdio {
+                    acp_client,
+                    process_manager,
+                    session_id,
+                    initialized,
+                });
+                result
+            }
+            None => Err(IFlowError::NotConnected),
+        }
+    }
+
+    /// Send a prompt over the current connection, creating a session if needed
+    async fn send_prompt(
+        &mut self,
+        message: &UserMessage,
+        meta: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let is_websocket = matches!(self.connection, Some(Connection::WebSocket { .. }));
+
+        if is_websocket {
+            // Adapt to the newly added process_manager field
+            if let Some(Connection::WebSocket {
+                mut acp_protocol,
+                mut session_id,
+                process_manager,
+            }) = self.connection.take()
+            {
+                let pm = process_manager;
+                let result = self
+                    .send_message_websocket(&mut acp_protocol, &mut session_id, message, meta)
+                    .await;
+                self.connection = Some(Connection::WebSocket {
+                    acp_protocol,
+                    session_id,
+                    process_manager: pm,
+                });
+                result
+            } else {
+                Err(IFlowError::NotConnected)
+            }
+        } else {
+            // Handle Stdio connection by temporarily taking ownership
+            if let Some(Connection::Stdio {
+                acp_client,
+                process_manager,
+                mut session_id,
+                mut initialized,
+            }) = self.connection.take()
+            {
+                let result = self
+                    .send_message_stdio(
+                        &acp_client,
+                        &mut session_id,
+                        &mut initialized,
+                        message,
+                        meta,
+                    )
+                    .await;
+                self.connection = Some(Connection::Stdio {
+                    acp_client,
+                    process_manager,
+                    session_id,
+                    initialized,
+                });
+                result
+            } else {
+                Err(IFlowError::NotConnected)
+            }
+        }
+    }
+
+    /// Initialize the stdio connection and create a session if needed
+    async fn prepare_session_stdio(
+        &self,
+        client: &ClientSideConnection,
+        session_id: &mut Option<SessionId>,
+        initialized: &mut bool,
+    ) -> Result<()> {
+        self.initialize_stdio(client, initialized).await?;
+
+        // Create a new session if we don't have one
+        if session_id.is_none() {
+            *session_id = Some(self.create_session_stdio(client, &self.options.cwd).await?);
+        }
+        Ok(())
+    }
+
+    /// Initialize the stdio connection if not already done
+    async fn initialize_stdio(
+        &self,
+        client: &ClientSideConnection,
+        initialized: &mut bool,
+    ) -> Result<()> {
+        if !*initialized {
+            tracing::debug!("Initializing connection...");
+            let requested = check_protocol_version(self.options.protocol_version)?;
+            let response = client
+                .initialize(agent_client_protocol::InitializeRequest {
+                    protocol_version: requested.into(),
+                    client_capabilities: agent_client_protocol::ClientCapabilities::default(),
+                    meta: None,
+                })
+                .await
+                .map_err(|e| IFlowError::Connection(format!("Failed to initialize: {}", e)))?;
+            if response.protocol_version < requested.into() {
+                tracing::warn!(
+                    "iFlow downgraded the protocol version from {} to {}",
+                    requested,
+                    response.protocol_version
+                );
+            }
+
+            *initialized = true;
+            debug!("Initialized stdio connection");
+        }
+        Ok(())
+    }
+
+    /// Create a session in `cwd` over stdio
+    async fn create_session_stdio(
+        &self,
+        client: &ClientSideConnection,
+        cwd: &Path,
+    ) -> Result<SessionId> {
+        tracing::debug!("Creating new session...");
+        let session_request = agent_client_protocol::NewSessionRequest {
+            mcp_servers: self.options.mcp_servers.clone(),
+            cwd: cwd.to_path_buf(),
+            meta: None,
+        };
+        tracing::debug!("Session request: {:?}", session_request);
+
+        let session_response = client.new_session(session_request).await.map_err(|e| {
+            tracing::error!("Failed to create session: {}", e);
+            IFlowError::Connection(format!("Failed to create session: {}", e))
+        })?;
+
+        debug!("Created new session: {:?}", session_response.session_id);
+        self.expect_system_prompt(&session_response.session_id.0);
+        Ok(session_response.session_id)
+    }
+
+    /// Initialize the WebSocket protocol and create a session if needed
+    async fn prepare_session_websocket(
+        &self,
+        protocol: &mut ACPProtocol,
+        session_id: &mut Option<String>,
+    ) -> Result<()> {
+        self.initialize_websocket(protocol).await?;
+
+        // Create a new session unless one was attached
+        if session_id.is_none() {
+            let new_session_id = self
+                .create_session_websocket(protocol, &self.options.cwd)
+                .await?;
+            *session_id = Some(new_session_id);
+        }
+        Ok(())
+    }
+
+    /// Initialize and authenticate the WebSocket protocol if not already done
+    async fn initialize_websocket(&self, protocol: &mut ACPProtocol) -> Result<()> {
+        if !protocol.is_initialized() {
+            tracing::debug!("Initializing WebSocket protocol...");
+            protocol.initialize(&self.options).await.map_err(|e| {
+                tracing::error!("Failed to initialize protocol: {}", e);
+                e
+            })?;
+
+            // Authenticate if needed
+            if !protocol.is_authenticated() {
+                tracing::debug!("Authenticating...");
+                if let Some(method_id) = &self.options.auth_method_id {
+                    protocol.authenticate(method_id, None).await.map_err(|e| {
+                        tracing::error!("Authentication failed with method {}: {}", method_id, e);
+                        e
+                    })?;
+                } else {
+                    // Try default authentication
+                    protocol.authenticate("iflow", None).await.map_err(|e| {
+                        tracing::error!("Default authentication failed: {}", e);
+                        e
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a session in `cwd` over WebSocket
+    async fn create_session_websocket(
+        &self,
+        protocol: &mut ACPProtocol,
+        cwd: &Path,
+    ) -> Result<String> {
+        tracing::debug!("Creating new session...");
+        let current_dir = cwd.to_string_lossy().to_string();
+
+        // Convert McpServer objects to JSON-compatible format
+        let mcp_servers: Vec<serde_json::Value> = self
+            .options
+            .mcp_servers
+            .iter()
+            .map(|server| {
+                // Since McpServer is an enum, we need to serialize it directly
+                // The agent-client-protocol crate handles the serialization
+                serde_json::json!(server)
+            })
+            .collect();
+
+        let new_session_id = protocol
+            .create_session(&current_dir, mcp_servers)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to create session: {}", e);
+                e
+            })?;
+        tracing::debug!("Session created successfully");
+        self.expect_system_prompt(&new_session_id);
+        Ok(new_session_id)
+    }
+
+    /// Remember that a new session should receive `options.system_prompt`
+    fn expect_system_prompt(&self, session_id: &str) {
+        if self.options.system_prompt.is_some()
+            && let Ok(mut pending) = self.pending_system_prompts.lock()
+        {
+            pending.insert(session_id.to_string());
+        }
+    }
+
+    /// Build the content blocks of a prompt to `session_id`
+    ///
+    /// The first prompt to a session created by this client starts with
+    /// `options.system_prompt`, if set.
+    async fn prompt_blocks(
+        &self,
+        session_id: &str,
+        message: &UserMessage,
+    ) -> Result<Vec<ContentBlock>> {
+        let mut blocks = message.to_content_blocks(&self.options).await?;
+        let first_prompt = self
+            .pending_system_prompts
+            .lock()
+            .is_ok_and(|mut pending| pending.remove(session_id));
+        if first_prompt && let Some(system_prompt) = &self.options.system_prompt {
+            blocks.insert(
+                0,
+                ContentBlock::Text(TextContent {
+                    text: system_prompt.clone(),
+                    annotations: None,
+                    meta: None,
+                }),
+            );
+        }
+        Ok(blocks)
+    }
+
+    /// Send a message via stdio connection
+    async fn send_message_stdio(
+        &mut self,
+        client: &ClientSideConnection,
+        session_id: &mut Option<SessionId>,
+        initialized: &mut bool,
+        message: &UserMessage,
+        meta: Option<serde_json::Value>,
+    ) -> Result<()> {
+        tracing::debug!("send_message_stdio called with message: {:?}", message);
+        self.finish_detached_prompt().await;
+        self.prepare_session_stdio(client, session_id, initialized)
+            .await?;
+
+        // Use the existing session
+        let current_session_id = session_id.as_ref().unwrap();
+
+        // Send the prompt and wait for completion
+        tracing::debug!("Sending prompt to session: {:?}", current_session_id);
+        let prompt_response = client
+            .prompt(agent_client_protocol::PromptRequest {
+                session_id: current_session_id.clone(),
+                prompt: self.prompt_blocks(&current_session_id.0, message).await?,
+                meta,
+            })
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to send message: {}", e);
+                IFlowError::Connection(format!("Failed to send message: {}", e))
+            })?;
+
+        tracing::debug!(
+            "Prompt response received, stop reason: {:?}",
+            prompt_response.stop_reason
+        );
+
+        // Send task finish message with the actual stop reason
+        let finish = Message::TaskFinish {
+            reason: Some(format!("{:?}", prompt_response.stop_reason)),
+        };
+
+        self.message_sender.send(finish).map_err(|e| {
+            tracing::error!("Failed to send task finish message: {}", e);
+            IFlowError::Connection("Message channel closed".to_string())
+        })?;
+
+        debug!("Sent message to iFlow via stdio: {:?}", message);
+        Ok(())
+    }
+
+    /// Send a message via WebSocket connection
+    async fn send_message_websocket(
+        &mut self,
+        protocol: &mut ACPProtocol,
+        session_id: &mut Option<String>,
+        message: &UserMessage,
+        meta: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.prepare_session_websocket(protocol, session_id).await?;
+
+        // Make sure we have a session
+        let current_session_id = session_id.as_ref().ok_or(ConnectionError::NoSession)?;
+
+        // Send the prompt and get the request ID
+        tracing::debug!("Sending prompt to session: {}", current_session_id);
+        let _request_id = protocol
+            .send_prompt_blocks(
+                current_session_id,
+                self.prompt_blocks(current_session_id, message).await?,
+                meta,
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to send prompt: {}", e);
+                e
+            })?;
+
+        debug!("Sent message to iFlow: {:?}", message);
+        Ok(())
+    }
+
+    /// Load a working directory and a set of files as session context
+    ///
+    /// Changes the working directory (see [`IFlowClient::set_cwd`]), reads
+    /// each file (subject to `file_access.max_size`) and sends them to iFlow
+    /// as a single preamble message. The acknowledgement produced by iFlow is consumed up to and
+    /// including its `TaskFinish`, so it does not show up in `messages()`.
+    ///
+    /// # Arguments
+    /// * `cwd` - The new working directory
+    /// * `files` - The files to provide as context
+    ///
+    /// # Returns
+    /// * `Ok(())` if the context was accepted by iFlow
+    /// * `Err(IFlowError)` if a file could not be read or sending failed
+    pub async fn set_context_window(&mut self, cwd: PathBuf, files: Vec<PathBuf>) -> Result<()> {
+        if cwd != self.options.cwd {
+            self.set_cwd(cwd)?;
+        }
+        let prompt = self.context_window_prompt(&files)?;
+        self.send_message(&prompt, None).await?;
+
+        // Consume the acknowledgement so it does not leak into the user's stream
+        self.consume_turn("context acknowledgement").await
+    }
+
+    /// Discard received messages up to and including the next `TaskFinish`
+    ///
+    /// # Arguments
+    /// * `what` - Description of the awaited response, used in the timeout error
+    async fn consume_turn(&self, what: &str) -> Result<()> {
+        let wait = std::time::Duration::from_secs_f64(self.options.prompt_timeout);
+        let mut receiver = self.message_receiver.lock().await;
+        tokio::time::timeout(wait, async {
+            while let Some(message) = receiver.recv().await {
+                if message.is_task_finish() {
+                    break;
+                }
+            }
+        })
+        .await
+        .map_err(|_| IFlowError::Timeout(format!("Timeout waiting for {}", what)))
+    }
+
+    /// Change the working directory of the iFlow session
+    ///
+    /// ACP has no method to change the working directory of an existing
+    /// session, so the current session is ended instead: the next
+    /// `send_message` creates a new session in `dir`. The conversation of the
+    /// previous session is not carried over. When not connected, only the
+    /// configured working directory is updated.
+    ///
+    /// # Arguments
+    /// * `dir` - The new working directory
+    ///
+    /// # Returns
+    /// * `Ok(())` if the working directory was changed
+    /// * `Err(IFlowError::FileNotFound)` if `dir` is not a directory
+    pub fn set_cwd(&mut self, dir: PathBuf) -> Result<()> {
+        if !dir.is_dir() {
+            return Err(IFlowError::FileNotFound(dir));
+        }
+
+        self.options.cwd = dir;
+        match &mut self.connection {
+            Some(Connection::Stdio { session_id, .. }) => *session_id = None,
+            Some(Connection::WebSocket { session_id, .. }) => *session_id = None,
+            None => {}
+        }
+        debug!(
+            "Working directory changed to {}",
+            self.options.cwd.display()
+        );
+        Ok(())
+    }
+
+    /// Replace the MCP servers available to iFlow
+    ///
+    /// MCP servers are fixed when a session is created, so this closes the
+    /// current session and creates a new one with `servers`. The conversation
+    /// of the closed session is not carried over. When not connected, only
+    /// the options are updated.
+    ///
+    /// # Arguments
+    /// * `servers` - The MCP servers to use
+    ///
+    /// # Returns
+    /// * `Ok(())` if the new session was created
+    /// * `Err(IFlowError)` if closing the session or creating the new one failed
+    pub async fn set_mcp_servers(&mut self, servers: Vec<McpServer>) -> Result<()> {
+        self.options.mcp_servers = servers;
+        if !*self.connected.lock().await {
+            return Ok(());
+        }
+
+        self.close_session().await?;
+        self.start_session().await?;
+        debug!(
+            "Created session {:?} with {} MCP servers",
+            self.session_id(),
+            self.options.mcp_servers.len()
+        );
+        Ok(())
+    }
+
+    /// Add an MCP server, replacing any server with the same name
+    ///
+    /// See [`IFlowClient::set_mcp_servers`].
+    ///
+    /// # Arguments
+    /// * `server` - The MCP server to add
+    pub async fn add_mcp_server(&mut self, server: McpServer) -> Result<()> {
+        let name = mcp_server_name(&server).to_string();
+        let mut servers = self.options.mcp_servers.clone();
+        servers.retain(|existing| mcp_server_name(existing) != name);
+        servers.push(server);
+        self.set_mcp_servers(servers).await
+    }
+
+    /// Remove an MCP server by name
+    ///
+    /// See [`IFlowClient::set_mcp_servers`].
+    ///
+    /// # Arguments
+    /// * `name` - The name of the MCP server to remove
+    ///
+    /// # Returns
+    /// * `Ok(())` if the server was removed
+    /// * `Err(IFlowError::Validation)` if no server has this name; the session is kept
+    pub async fn remove_mcp_server(&mut self, name: &str) -> Result<()> {
+        let mut servers = self.options.mcp_servers.clone();
+        servers.retain(|existing| mcp_server_name(existing) != name);
+        if servers.len() == self.options.mcp_servers.len() {
+            return Err(IFlowError::Validation(format!(
+                "No MCP server named '{}'",
+                name
+            )));
+        }
+        self.set_mcp_servers(servers).await
+    }
+
+    /// Close the current session, if any
+    ///
+    /// Over WebSocket, iFlow is notified with `session/close`. The stdio
+    /// protocol has no such request, so the session is only forgotten.
+    async fn close_session(&mut self) -> Result<()> {
+        match &mut self.connection {
+            Some(Connection::WebSocket {
+                acp_protocol,
+                session_id,
+                ..
+            }) => {
+                if let Some(id) = session_id.take() {
+                    acp_protocol
+                        .send_notification("session/close", serde_json::json!({ "sessionId": id }))
+                        .await?;
+                    debug!("Closed session {}", id);
+                }
+            }
+            Some(Connection::Stdio { session_id, .. }) => *session_id = None,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Send a request about the current session over WebSocket
+    ///
+    /// # Returns
+    /// * `Ok(Some(Value))` containing the result
+    /// * `Ok(None)` if iFlow rejected the request or the connection is not a
+    ///   WebSocket connection
+    /// * `Err(IFlowError)` if the request could not be sent
+    async fn session_request(
+        &mut self,
+        method: &str,
+        mut params: serde_json::Value,
+    ) -> Result<Option<serde_json::Value>> {
+        let Some(Connection::WebSocket {
+            acp_protocol,
+            session_id,
+            ..
+        }) = &mut self.connection
+        else {
+            return Ok(None);
+        };
+
+        if let Some(id) = session_id {
+            params["sessionId"] = serde_json::json!(id);
+        }
+        match acp_protocol.send_request(method, params).await {
+            Ok(result) => Ok(Some(result)),
+            Err(IFlowError::Protocol(e)) => {
+                debug!("iFlow does not support {}: {}", method, e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Count the tokens `text` would use in a prompt
+    ///
+    /// Asks iFlow via `session/count_tokens`, waiting at most two seconds
+    /// regardless of the configured timeouts. If iFlow does not support the request, does
+    /// not answer in time, or the connection is not a WebSocket connection,
+    /// the count is estimated as one token per four bytes.
+    ///
+    /// # Arguments
+    /// * `text` - The text to count
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing the token count, at least 1
+    /// * `Err(IFlowError)` if the request could not be sent
+    pub async fn count_tokens(&mut self, text: &str) -> Result<u32> {
+        let estimate = u32::try_from(text.len() / 4).unwrap_or(u32::MAX).max(1);
+        let Some(Connection::WebSocket {
+            acp_protocol,
+            session_id,
+            ..
+        }) = &mut self.connection
+        else {
+            return Ok(estimate);
+        };
+
+        let mut params = serde_json::json!({ "text": text });
+        if let Some(id) = session_id {
+            params["sessionId"] = serde_json::json!(id);
+        }
+        let result = match acp_protocol
+            .send_request_with_timeout("session/count_tokens", params, COUNT_TOKENS_TIMEOUT)
+            .await
+        {
+            Ok(result) => result,
+            Err(IFlowError::Protocol(e) | IFlowError::Timeout(e)) => {
+                debug!("Estimating tokens, session/count_tokens failed: {}", e);
+                return Ok(estimate);
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(result
+            .get("tokens")
+            .and_then(|tokens| tokens.as_u64())
+            .map(|tokens| u32::try_from(tokens).unwrap_or(u32::MAX))
+            .unwrap_or(estimate))
+    }
+
+    /// Get the number of tokens left in the context window of the session
+    ///
+    /// # Returns
+    /// * `Ok(Some(u32))` containing the remaining tokens
+    /// * `Ok(None)` if iFlow does not report the context window
+    /// * `Err(IFlowError)` if the request could not be sent
+    pub async fn remaining_context_tokens(&mut self) -> Result<Option<u32>> {
+        let result = self
+            .session_request("session/context_window", serde_json::json!({}))
+            .await?;
+        Ok(result
+            .as_ref()
+            .and_then(|result| result.get("remainingTokens"))
+            .and_then(|tokens| tokens.as_u64())
+            .map(|tokens| u32::try_from(tokens).unwrap_or(u32::MAX)))
+    }
+
+    /// Build the preamble prompt used by [`IFlowClient::set_context_window`]
+    ///
+    /// Each file is embedded as a fenced code block preceded by its path.
+    ///
+    /// # Arguments
+    /// * `files` - The files to embed in the prompt
+    ///
+    /// # Returns
+    /// * `Ok(String)` containing the constructed prompt
+    /// * `Err(IFlowError)` if a file is missing or exceeds `file_access.max_size`
+    pub fn context_window_prompt(&self, files: &[PathBuf]) -> Result<String> {
+        let names: Vec<String> = files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        let mut prompt = format!(
+            "I'm providing you with these files for context: [{}]\n",
+            names.join(", ")
+        );
+
+        for (path, name) in files.iter().zip(names.iter()) {
+            let size = std::fs::metadata(path)
+                .map_err(|e| IFlowError::from_file_io(e, path))?
+                .len();
+            if size > self.options.file_access.max_size {
+                return Err(IFlowError::FileTooLarge {
+                    path: path.clone(),
+                    size,
+                    limit: self.options.file_access.max_size,
+                });
+            }
+            let content =
+                std::fs::read_to_string(path).map_err(|e| IFlowError::from_file_io(e, path))?;
+            prompt.push_str(&format!("\n{}:\n```\n{}\n```\n", name, content));
+        }
+
+        Ok(prompt)
+    }
+
+    /// Send a file to iFlow, optionally preceded by a prompt
+    ///
+    /// The file is read subject to `file_access.max_size` and embedded in the
+    /// message as a fenced code block. Files that are not valid UTF-8 text are
+    /// base64-encoded. Unlike the `files` argument of `send_message`, which
+    /// embeds files as resources, the file becomes part of the message text.
+    ///
+    /// # Arguments
+    /// * `path` - The file to send
+    /// * `prompt` - Optional text sent before the file content
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message was sent successfully
+    /// * `Err(IFlowError::FileNotFound)` if the file does not exist
+    /// * `Err(IFlowError::FileTooLarge)` if the file exceeds `file_access.max_size`
+    /// * `Err(IFlowError::Config)` if the file is binary and the `file-support`
+    ///   feature is disabled
+    pub async fn send_file(&mut self, path: &Path, prompt: Option<&str>) -> Result<()> {
+        let size = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| IFlowError::from_file_io(e, path))?
+            .len();
+        if size > self.options.file_access.max_size {
+            return Err(IFlowError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit: self.options.file_access.max_size,
+            });
+        }
+        let bytes = tokio::fs::read(path)
+            .await
+            .map_err(|e| IFlowError::from_file_io(e, path))?;
+
+        let mut text = prompt.map(|p| format!("{}\n\n", p)).unwrap_or_default();
+        match String::from_utf8(bytes) {
+            Ok(content) if !content.contains('\0') => {
+                text.push_str(&format!("{}:\n```\n{}\n```\n", path.display(), content));
+            }
+            Ok(content) => {
+                text.push_str(&binary_file_block(path, content.as_bytes())?);
+            }
+            Err(e) => {
+                text.push_str(&binary_file_block(path, e.as_bytes())?);
+            }
+        }
+
+        self.send_message(&text, None).await
+    }
+
+    /// Run a command in the working directory of the client
+    ///
+    /// The command runs locally, without a shell, in `options.cwd`. It is
+    /// only allowed when file access is enabled and the working directory
+    /// lies within `file_access.allowed_dirs`.
+    ///
+    /// # Arguments
+    /// * `command` - The program to run
+    /// * `args` - The arguments of the program
+    /// * `timeout` - How long the command may run before it is killed
+    ///
+    /// # Returns
+    /// * `Ok(ShellResult)` with the output and exit code of the command
+    /// * `Err(IFlowError::Validation)` if file access is disabled
+    /// * `Err(IFlowError::FilePermissionDenied)` if the working directory is not allowed
+    /// * `Err(IFlowError::Timeout)` if the command did not finish in time
+    /// * `Err(IFlowError::Io)` if the command could not be started
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use iflow_cli_sdk_rust::IFlowClient;
+    /// # async fn example(client: &IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// let result = client
+    ///     .execute_shell("cargo", &["--version"], std::time::Duration::from_secs(10))
+    ///     .await?;
+    /// println!("{} (exit code {})", result.stdout, result.exit_code);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_shell(
+        &self,
+        command: &str,
+        args: &[&str],
+        timeout: std::time::Duration,
+    ) -> Result<ShellResult> {
+        if !self.options.file_access.enabled {
+            return Err(IFlowError::Validation(
+                "Shell commands require file access to be enabled".to_string(),
+            ));
+        }
+        if !self.options.file_access.is_allowed(&self.options.cwd) {
+            return Err(IFlowError::FilePermissionDenied(self.options.cwd.clone()));
+        }
+
+        debug!("Running shell command: {} {:?}", command, args);
+        let started = std::time::Instant::now();
+        let output = tokio::process::Command::new(command)
+            .args(args)
+            .current_dir(&self.options.cwd)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(timeout, output).await.map_err(|_| {
+            IFlowError::Timeout(format!(
+                "Command '{}' timed out after {:?}",
+                command, timeout
+            ))
+        })??;
+
+        Ok(ShellResult {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code().unwrap_or(-1),
+            duration: started.elapsed(),
+        })
+    }
+
+    /// Run a shell script with `sh -c`
+    ///
+    /// See [`IFlowClient::execute_shell`]; the script is killed after
+    /// `options.timeout` seconds.
+    ///
+    /// # Arguments
+    /// * `script` - The script to run
+    ///
+    /// # Returns
+    /// * `Ok(String)` containing the standard output of the script
+    /// * `Err(IFlowError)` if the script could not be run
+    #[allow(deprecated)]
+    pub async fn run_shell(&self, script: &str) -> Result<String> {
+        let timeout = std::time::Duration::from_secs_f64(self.options.timeout);
+        let result = self.execute_shell("sh", &["-c", script], timeout).await?;
+        Ok(result.stdout)
+    }
+
+    /// Get the ID of the current session
+    ///
+    /// # Returns
+    /// `Some(&str)` once a session has been created or attached, `None` otherwise
+    pub fn session_id(&self) -> Option<&str> {
+        match &self.connection {
+            Some(Connection::Stdio { session_id, .. }) => session_id.as_ref().map(|id| &*id.0),
+            Some(Connection::WebSocket { session_id, .. }) => session_id.as_deref(),
+            None => None,
+        }
+    }
+
+    /// Get the ID of the current session, e.g. to persist it across restarts
+    ///
+    /// # Returns
+    /// The same as [`Self::session_id`]
+    pub fn current_session_id(&self) -> Option<&str> {
+        self.session_id()
+    }
+
+    /// Continue a session from an earlier prompt or client
+    ///
+    /// The session is stored on the connection, so the next `send_message`
+    /// prompts it rather than sending `session/new`. Together with
+    /// `current_session_id` this keeps a conversation going across client
+    /// restarts, as long as iFlow still knows the session.
+    ///
+    /// # Arguments
+    /// * `session_id` - The ID of the session to resume
+    ///
+    /// # Returns
+    /// * `Ok(())` if the session was stored
+    /// * `Err(IFlowError::NotConnected)` if the client is not connected
+    pub fn resume_session(&mut self, session_id: SessionId) -> Result<()> {
+        self.attach(session_id.0.to_string())
+    }
+
+    /// Attach to an existing session
+    ///
+    /// The next `send_message` call prompts this session instead of creating
+    /// a new one. The iFlow process must still know the session, so this is
+    /// mainly useful when reconnecting to a long-running iFlow over WebSocket.
+    ///
+    /// # Arguments
+    /// * `session_id` - The ID of the session to attach to
+    ///
+    /// # Returns
+    /// * `Ok(())` if the session was attached
+    /// * `Err(IFlowError::NotConnected)` if the client is not connected
+    pub fn attach(&mut self, session_id: String) -> Result<()> {
+        match &mut self.connection {
+            Some(Connection::Stdio {
+                session_id: current,
+                ..
+            }) => *current = Some(SessionId(session_id.into())),
+            Some(Connection::WebSocket {
+                session_id: current,
+                ..
+            }) => *current = Some(session_id),
+            None => return Err(IFlowError::NotConnected),
+        }
+        debug!("Attached to session: {:?}", self.session_id());
+        Ok(())
+    }
+
+    /// Create a session in another directory without sending a prompt
+    ///
+    /// The session becomes the active session only if there is none yet;
+    /// use `use_session` to switch to it.
+    ///
+    /// # Arguments
+    /// * `dir` - The working directory of the session
+    ///
+    /// # Returns
+    /// * `Ok(String)` containing the ID of the new session
+    /// * `Err(IFlowError::FileNotFound)` if `dir` is not a directory
+    /// * `Err(IFlowError)` if creating the session failed
+    pub async fn new_session_in_dir(&mut self, dir: &Path) -> Result<String> {
+        if !dir.is_dir() {
+            return Err(IFlowError::FileNotFound(dir.to_path_buf()));
+        }
+        if !*self.connected.lock().await {
+            return Err(IFlowError::NotConnected);
+        }
+        self.remember_active_session();
+
+        let new_session_id = match self.connection.take() {
+            Some(Connection::WebSocket {
+                mut acp_protocol,
+                mut session_id,
+                process_manager,
+            }) => {
+                let result = match self.initialize_websocket(&mut acp_protocol).await {
+                    Ok(()) => self.create_session_websocket(&mut acp_protocol, dir).await,
+                    Err(e) => Err(e),
+                };
+                if let Ok(id) = &result {
+                    session_id.get_or_insert_with(|| id.clone());
+                }
+                self.connection = Some(Connection::WebSocket {
+                    acp_protocol,
+                    session_id,
+                    process_manager,
+                });
+                result?
+            }
+            Some(Connection::Stdio {
+                acp_client,
+                process_manager,
+                mut session_id,
+                mut initialized,
+            }) => {
+                let result = match self.initialize_stdio(&acp_client, &mut initialized).await {
+                    Ok(()) => self.create_session_stdio(&acp_client, dir).await,
+                    Err(e) => Err(e),
+                };
+                if let Ok(id) = &result {
+                    session_id.get_or_insert_with(|| id.clone());
+                }
+                self.connection = Some(Connection::Stdio {
+                    acp_client,
+                    process_manager,
+                    session_id,
+                    initialized,
+                });
+                result?.0.to_string()
+            }
+            None => return Err(IFlowError::NotConnected),
+        };
+
+        debug!("Created session {} in {}", new_session_id, dir.display());
+        self.sessions.push(new_session_id.clone());
+        Ok(new_session_id)
+    }
+
+    /// Switch the session used by subsequent messages
+    ///
+    /// # Arguments
+    /// * `session_id` - A session from `list_sessions`
+    ///
+    /// # Returns
+    /// * `Ok(())` if the session is now active
+    /// * `Err(IFlowError::Validation)` if the session is unknown
+    pub fn use_session(&mut self, session_id: &str) -> Result<()> {
+        if !self.list_sessions().iter().any(|id| id == session_id) {
+            return Err(IFlowError::Validation(format!(
+                "Unknown session: {}",
+                session_id
+            )));
+        }
+        self.remember_active_session();
+        self.attach(session_id.to_string())
+    }
+
+    /// Add the active session to `sessions` so switching away keeps it listed
+    ///
+    /// This covers the session created implicitly by `send_message` and
+    /// sessions attached with `attach`.
+    fn remember_active_session(&mut self) {
+        if let Some(active) = self.session_id().map(str::to_string)
+            && !self.sessions.contains(&active)
+        {
+            self.sessions.push(active);
+        }
+    }
+
+    /// List the sessions of the current connection
+    ///
+    /// # Returns
+    /// The sessions created with `new_session_in_dir` or switched away from,
+    /// in order, and the active session
+    pub fn list_sessions(&self) -> Vec<String> {
+        let mut sessions = self.sessions.clone();
+        if let Some(active) = self.session_id()
+            && !sessions.iter().any(|id| id == active)
+        {
+            sessions.push(active.to_string());
+        }
+        sessions
+    }
+
+    /// Get the recorded conversation history
+    ///
+    /// History is only recorded when `IFlowOptions::keep_history` is enabled.
+    ///
+    /// # Returns
+    /// The messages sent to and received from iFlow, in order
+    pub fn history(&self) -> Vec<Message> {
+        self.record
+            .lock()
+            .map(|record| record.history.clone())
+            .unwrap_or_default()
+    }
+
+    /// Export the current session to a JSON file
+    ///
+    /// The file contains the session ID, the conversation history (if kept),
+    /// a non-sensitive snapshot of the options and the tool call log.
+    ///
+    /// # Arguments
+    /// * `path` - Where to write the session file
+    ///
+    /// # Returns
+    /// * `Ok(())` if the file was written
+    /// * `Err(IFlowError)` if serialization or writing failed
+    pub fn export_session(&self, path: &Path) -> Result<()> {
+        let (history, tool_call_log) = self
+            .record
+            .lock()
+            .map(|record| (record.history.clone(), record.tool_calls.clone()))
+            .unwrap_or_default();
+
+        let session = SessionFile {
+            version: SESSION_FILE_VERSION,
+            session_id: self.session_id().map(str::to_string),
+            history,
+            options_snapshot: OptionsSnapshot::from_options(&self.options),
+            tool_call_log,
+        };
+
+        std::fs::write(path, serde_json::to_string_pretty(&session)?)?;
+        debug!("Exported session to {}", path.display());
+        Ok(())
+    }
+
+    /// Create a connected client from a session file
+    ///
+    /// Builds a client from the stored options, connects it and attaches to
+    /// the stored session. Must be called within a `LocalSet` when the stored
+    /// options use stdio.
+    ///
+    /// # Arguments
+    /// * `path` - The session file written by `export_session`
+    ///
+    /// # Returns
+    /// * `Ok(IFlowClient)` connected and attached to the stored session
+    /// * `Err(IFlowError)` if the file is invalid or connecting failed
+    pub async fn import_session(path: &Path) -> Result<IFlowClient> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| IFlowError::from_file_io(e, path))?;
+        let session: SessionFile = serde_json::from_str(&content)?;
+        if session.version != SESSION_FILE_VERSION {
+            return Err(IFlowError::InvalidMessage(format!(
+                "Unsupported session file version: {}",
+                session.version
+            )));
+        }
+
+        let mut client = IFlowClient::new(Some(session.options_snapshot.to_options()));
+        if let Ok(mut record) = client.record.lock() {
+            record.history = session.history;
+            record.tool_calls = session.tool_call_log;
+        }
+
+        client.connect().await?;
+        if let Some(session_id) = session.session_id {
+            client.attach(session_id)?;
+        }
+        Ok(client)
+    }
+
+    /// Save the minimal state needed to resume the current session
+    ///
+    /// # Returns
+    /// * `Ok(SessionCheckpoint)` with the session ID, the time of the last
+    ///   message and the IDs of tool calls still in progress
+    /// * `Err(IFlowError::Validation)` if no session has been created yet
+    pub fn checkpoint(&self) -> Result<SessionCheckpoint> {
+        let session_id = self
+            .session_id()
+            .ok_or_else(|| IFlowError::Validation("No session to checkpoint".to_string()))?
+            .to_string();
+
+        let (last_message_at, pending_tool_calls) = self
+            .record
+            .lock()
+            .map(|record| (record.last_message_at, record.pending_tool_calls()))
+            .unwrap_or_default();
+
+        Ok(SessionCheckpoint {
+            session_id,
+            last_message_at,
+            pending_tool_calls,
+        })
+    }
+
+    /// Resume the session saved in a checkpoint
+    ///
+    /// Connects if needed, attaches to the session and initializes the
+    /// connection so the next prompt goes to the restored session. Must be
+    /// called within a `LocalSet` when using stdio.
+    ///
+    /// # Arguments
+    /// * `checkpoint` - A checkpoint created by [`IFlowClient::checkpoint`]
+    ///
+    /// # Returns
+    /// * `Ok(())` if the session was restored
+    /// * `Err(IFlowError)` if connecting or initializing failed
+    pub async fn restore_from_checkpoint(&mut self, checkpoint: &SessionCheckpoint) -> Result<()> {
+        if !*self.connected.lock().await {
+            self.connect().await?;
+        }
+        self.attach(checkpoint.session_id.clone())?;
+        self.prepare_session().await?;
+
+        if let Ok(mut record) = self.record.lock() {
+            record.last_message_at = record.last_message_at.max(checkpoint.last_message_at);
+        }
+        debug!("Restored session {} from checkpoint", checkpoint.session_id);
+        Ok(())
+    }
+
+    /// Interrupt the current message generation
+    ///
+    /// Asks iFlow to stop generating with [`Self::interrupt_session`], then
+    /// ends the current response locally so that `collect_response` and
+    /// similar calls return `IFlowError::Cancelled` right away.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the interrupt was sent successfully
+    /// * `Err(IFlowError)` if there was an error
+    pub async fn interrupt(&self) -> Result<()> {
+        self.interrupt_session().await?;
+
+        let message = Message::TaskFinish {
+            reason: Some("interrupted".to_string()),
+        };
+
+        self.message_sender
+            .send(message)
+            .map_err(|_| IFlowError::Connection("Message channel closed".to_string()))?;
+        Ok(())
+    }
+
+    /// Ask iFlow to stop working on the prompt of the current session
+    ///
+    /// Sends the ACP `session/cancel` notification, waiting at most the
+    /// operation timeout for it to be sent. Without a session there is
+    /// nothing to stop, and a process that already finished cannot be
+    /// reached, so both cases succeed.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the request was sent or there was nothing to stop
+    /// * `Err(IFlowError::NotConnected)` if the client is not connected
+    /// * `Err(IFlowError::Timeout)` if sending the request timed out
+    pub async fn interrupt_session(&self) -> Result<()> {
+        if !*self.connected.lock().await {
+            return Err(IFlowError::NotConnected);
+        }
+
+        let timeout = std::time::Duration::from_secs_f64(self.options.operation_timeout);
+        let sent = match &self.connection {
+            Some(Connection::WebSocket {
+                acp_protocol,
+                session_id: Some(session_id),
+                ..
+            }) => tokio::time::timeout(timeout, acp_protocol.cancel_session(session_id)).await,
+            Some(Connection::Stdio {
+                acp_client,
+                session_id: Some(session_id),
+                ..
+            }) => {
+                tokio::time::timeout(
+                    timeout,
+                    acp_client
+                        .cancel(agent_client_protocol::CancelNotification {
+                            session_id: session_id.clone(),
+                            meta: None,
+                        })
+                        .map(|result| {
+                            result.map_err(|e| {
+                                IFlowError::Connection(format!("Failed to cancel session: {}", e))
+                            })
+                        }),
+                )
+                .await
+            }
+            _ => return Ok(()),
+        };
+
+        match sent {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => {
+                debug!("iFlow could not be reached to interrupt, ignoring: {}", e);
+                Ok(())
+            }
+            Err(_) => Err(IFlowError::Timeout(
+                "Timeout sending session/cancel".to_string(),
+            )),
+        }
+    }
+
+    /// Get the options this client was configured with
+    ///
+    /// # Returns
+    /// A reference to the client's `IFlowOptions`
+    pub fn options(&self) -> &IFlowOptions {
+        &self.options
+    }
+
+    /// Describe an error in terms an end user can act on
+    ///
+    /// Like [`IFlowError::user_message`], but timeouts say what timed out and
+    /// name the options that control the timeouts.
+    ///
+    /// # Arguments
+    /// * `err` - The error to explain
+    ///
+    /// # Returns
+    /// A human-readable explanation of the error
+    pub fn explain_error(&self, err: &IFlowError) -> String {
+        match err {
+            IFlowError::Timeout(details) => format!(
+                "The operation timed out. Try increasing connect_timeout, operation_timeout or prompt_timeout, or checking iFlow is responsive. Details: {}",
+                details
+            ),
+            _ => err.user_message(),
+        }
+    }
+
+    /// Get session-level metrics of the client
+    ///
+    /// Counters cover every connection made by this client.
+    ///
+    /// # Returns
+    /// A `ClientStatistics` with the current counter values
+    pub fn statistics(&self) -> ClientStatistics {
+        let session_duration = self.connected_at.map(|start| {
+            self.disconnected_at
+                .unwrap_or_else(std::time::Instant::now)
+                .duration_since(start)
+        });
+        self.statistics.statistics(session_duration)
+    }
+
+    /// Get the durations of the last 100 connections
+    ///
+    /// A `connect()` is timed until the transport is established, a
+    /// `reconnect()` until the session is ready again.
+    ///
+    /// # Returns
+    /// The durations in chronological order
+    pub fn connection_latency_histogram(&self) -> Vec<std::time::Duration> {
+        self.connection_latencies.iter().copied().collect()
+    }
+
+    /// Get the prompt latencies of the last 100 prompts
+    ///
+    /// The latency of a prompt is the time from sending the user message to
+    /// the first assistant chunk. Prompts answered without text are left out.
+    ///
+    /// # Returns
+    /// The latencies in chronological order
+    pub fn prompt_latency_histogram(&self) -> Vec<std::time::Duration> {
+        self.record
+            .lock()
+            .map(|record| record.prompt_latencies.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the 95th percentile of `prompt_latency_histogram`
+    ///
+    /// # Returns
+    /// The latency 95% of the recorded prompts did not exceed, or `None` if
+    /// no latency was recorded
+    pub fn p95_prompt_latency(&self) -> Option<std::time::Duration> {
+        let mut latencies = self.prompt_latency_histogram();
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort();
+        // Nearest-rank percentile
+        let rank = (latencies.len() * 95).div_ceil(100);
+        Some(latencies[rank - 1])
+    }
+
+    /// Collect the complete state of the client for a bug report
+    ///
+    /// Extends [`IFlowClient::snapshot`] with the recorded protocol log,
+    /// recent stderr of the iFlow process, statistics and the options.
+    /// Authentication settings are redacted.
+    ///
+    /// # Returns
+    /// A `DebugDump` that can be printed or serialized to JSON
+    pub fn debug_dump(&self) -> DebugDump {
+        let mut protocol_log = redact_authentication(self.protocol_log());
+        protocol_log.drain(..protocol_log.len().saturating_sub(100));
+
+        let process_manager = match &self.connection {
+            Some(Connection::Stdio {
+                process_manager, ..
+            })
+            | Some(Connection::WebSocket {
+                process_manager, ..
+            }) => process_manager.as_ref(),
+            None => None,
+        };
+        let recent_stderr = process_manager
+            .map(|pm| pm.recent_stderr(10))
+            .unwrap_or_default();
+
+        DebugDump {
+            snapshot: self.snapshot(),
+            protocol_log,
+            recent_stderr,
+            statistics: self.statistics(),
+            message_queue_depth: self.message_queue_depth(),
+            options_redacted: self.options.redacted_json(),
+            rust_sdk_version: crate::VERSION,
+        }
+    }
+
+    /// Number of received messages not yet delivered
+    fn message_queue_depth(&self) -> usize {
+        let queued = self
+            .message_receiver
+            .try_lock()
+            .map(|receiver| receiver.len())
+            .unwrap_or(0);
+        let lookahead = self.lookahead.lock().map(|l| l.len()).unwrap_or(0);
+        queued + lookahead
+    }
+
+    /// Check the invariants of the ACP connection
+    ///
+    /// Checks that a session is only used after the protocol was initialized
+    /// and authenticated, that a session exists, that request IDs have not
+    /// wrapped around and that received messages are being consumed. Useful
+    /// for conformance testing; in debug builds, sending a message asserts
+    /// that there are no violations of `Severity::Error`.
+    ///
+    /// # Returns
+    /// The broken invariants, or an empty list if there are none
+    pub fn assert_protocol_invariants(&self) -> Vec<ProtocolViolation> {
+        let mut state = ProtocolState {
+            connected: self
+                .connected
+                .try_lock()
+                .map(|connected| *connected)
+                .unwrap_or(self.connection.is_some()),
+            session_id: self.session_id().map(str::to_string),
+            queued_messages: self.message_queue_depth(),
+            ..ProtocolState::default()
+        };
+        match &self.connection {
+            Some(Connection::Stdio { initialized, .. }) => {
+                state.initialized = *initialized;
+            }
+            Some(Connection::WebSocket { acp_protocol, .. }) => {
+                state.initialized = acp_protocol.is_initialized();
+                state.authenticated = Some(acp_protocol.is_authenticated());
+                state.request_ids_wrapped = acp_protocol.request_ids_wrapped();
+            }
+            None => {}
+        }
+        state.violations()
+    }
+
+    /// Capture the current state of the client
+    ///
+    /// # Returns
+    /// A `SessionSnapshot` whose `Display` output is suitable for bug reports
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let connection_type = match &self.connection {
+            Some(Connection::Stdio { .. }) => Some(ConnectionType::Stdio),
+            Some(Connection::WebSocket { .. }) => Some(ConnectionType::WebSocket),
+            None => None,
+        };
+        let connected = self
+            .connected
+            .try_lock()
+            .map(|connected| *connected)
+            .unwrap_or(connection_type.is_some());
+
+        let (message_count, tool_calls_completed, last_message_at) = self
+            .record
+            .lock()
+            .map(|record| {
+                let completed = record
+                    .tool_calls
+                    .iter()
+                    .filter(|msg| {
+                        matches!(msg, Message::ToolCall { status, .. }
+                            if status.eq_ignore_ascii_case("completed"))
+                    })
+                    .count() as u64;
+                (record.message_count, completed, record.last_message_at)
+            })
+            .unwrap_or_default();
+
+        SessionSnapshot {
+            session_id: self.session_id().map(str::to_string),
+            connection_type,
+            connected,
+            message_count,
+            tool_calls_completed,
+            last_message_at,
+            // iFlow does not report its version over ACP yet
+            iflow_version: None,
+            options_summary: IFlowOptionsSummary::from_options(&self.options),
+        }
+    }
+
+    /// Use the given logger instead of the one configured by `options.logging`
+    ///
+    /// Must be called before `connect()` to take effect.
+    ///
+    /// # Arguments
+    /// * `logger` - The logger to record messages with
+    pub fn with_logger(mut self, logger: MessageLogger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Add a check that `connect()` runs before connecting
+    ///
+    /// Prechecks run in the order they were added; the first one to fail
+    /// makes `connect()` return its error without connecting.
+    ///
+    /// # Arguments
+    /// * `check` - The precheck to add, e.g. `BinaryInPathPrecheck("iflow".into())`
+    pub fn with_connection_precheck(mut self, check: Box<dyn ConnectionPrecheck>) -> Self {
+        self.prechecks.push(check);
+        self
+    }
+
+    /// Add an interceptor for messages received from iFlow
+    ///
+    /// Interceptors run in the order they were added, before hooks, logging
+    /// and the message stream see a message. `TaskFinish` messages are not
+    /// intercepted.
+    ///
+    /// # Arguments
+    /// * `interceptor` - The interceptor to add
+    pub fn with_interceptor(self, interceptor: Box<dyn MessageInterceptor>) -> Self {
+        if let Ok(mut interceptors) = self.interceptors.lock() {
+            interceptors.push(interceptor);
+        }
+        self
+    }
+
+    /// Call `callback` with the full plan whenever iFlow sends a plan
+    ///
+    /// The callback receives the latest plan, not only what changed. It is
+    /// called from the interceptor chain, after the interceptors added so
+    /// far, so it must not block; spawn heavy work onto a separate task.
+    /// Several callbacks can be subscribed at once.
+    ///
+    /// # Arguments
+    /// * `callback` - Called with the entries of every plan
+    pub fn subscribe_to_plan(&mut self, callback: PlanCallback) {
+        let subscribers = match &self.plan_subscribers {
+            Some(subscribers) => subscribers.clone(),
+            None => {
+                let subscribers = Arc::new(std::sync::Mutex::new(Vec::new()));
+                if let Ok(mut interceptors) = self.interceptors.lock() {
+                    interceptors.push(Box::new(PlanInterceptor(subscribers.clone())));
+                }
+                self.plan_subscribers = Some(subscribers.clone());
+                subscribers
+            }
+        };
+        if let Ok(mut subscribers) = subscribers.lock() {
+            subscribers.push(callback);
+        }
+    }
+
+    /// Remove every callback added with `subscribe_to_plan`
+    pub fn unsubscribe_from_plan(&mut self) {
+        if let Some(subscribers) = &self.plan_subscribers
+            && let Ok(mut subscribers) = subscribers.lock()
+        {
+            subscribers.clear();
+        }
+    }
+
+    /// Disable message logging regardless of `options.logging`
+    ///
+    /// Must be called before `connect()` to take effect.
+    pub fn without_logger(mut self) -> Self {
+        self.logger = None;
+        self
+    }
+
+    /// Get the logger recording messages, if any
+    pub fn logger(&self) -> Option<&MessageLogger> {
+        self.logger.as_ref()
     }
 
     /// Receive messages from iFlow
@@ -824,9 +3816,68 @@ impl IFlowClient {
     /// # Returns
     /// A `MessageStream` that implements `futures::Stream`
     pub fn messages(&self) -> MessageStream {
-        MessageStream {
-            receiver: self.message_receiver.clone(),
-        }
+        MessageStream::new(
+            self.message_receiver.clone(),
+            self.lookahead.clone(),
+            self.options.message_rate_limit,
+        )
+    }
+
+    /// Stream the changes of the plan
+    ///
+    /// Compares each `Message::Plan` with the previous plan (initially
+    /// empty) and yields the diff when something changed. Like
+    /// [`IFlowClient::messages`], this consumes the messages it reads, so
+    /// other message types are dropped.
+    ///
+    /// # Returns
+    /// A stream of plan diffs, ending when the message stream ends
+    pub fn diff_plan_updates(&self) -> impl Stream<Item = PlanDiff> {
+        self.watch_plan().map(|(_, diff)| diff)
+    }
+
+    /// Stream the plan together with its changes
+    ///
+    /// See [`IFlowClient::diff_plan_updates`].
+    ///
+    /// # Returns
+    /// A stream of the full current plan and its diff to the previous plan
+    pub fn watch_plan(&self) -> impl Stream<Item = (Vec<PlanEntry>, PlanDiff)> {
+        self.messages()
+            .scan(Vec::new(), |previous: &mut Vec<PlanEntry>, message| {
+                let update = match message {
+                    Message::Plan { entries } => {
+                        let diff = plan_diff(previous, &entries);
+                        *previous = entries.clone();
+                        Some((entries, diff))
+                    }
+                    _ => None,
+                };
+                futures::future::ready(Some(update))
+            })
+            .filter_map(|update| {
+                futures::future::ready(update.filter(|(_, diff)| diff.has_changes()))
+            })
+    }
+
+    /// Receive messages together with their timing and session
+    ///
+    /// Like [`IFlowClient::messages`], but each message is wrapped in a
+    /// `MessageWithMeta` carrying the time it was read, the current session
+    /// ID and its position in the stream, for latency analysis.
+    ///
+    /// # Returns
+    /// A stream of messages with metadata
+    pub fn stream_with_metadata(&self) -> impl Stream<Item = MessageWithMeta> {
+        let session_id = self.session_id().map(str::to_string);
+        self.messages()
+            .enumerate()
+            .map(move |(sequence, message)| MessageWithMeta {
+                message,
+                received_at: std::time::SystemTime::now(),
+                session_id: session_id.clone(),
+                sequence: sequence as u64,
+            })
     }
 
     /// Receive a single message (convenience method)
@@ -838,58 +3889,646 @@ impl IFlowClient {
     /// * `Ok(None)` if the channel is closed
     /// * `Err(IFlowError)` if there was an error
     pub async fn receive_message(&self) -> Result<Option<Message>> {
+        if let Some(msg) = self.lookahead.lock().ok().and_then(|mut l| l.pop_front()) {
+            return Ok(Some(msg));
+        }
         let mut receiver = self.message_receiver.lock().await;
         Ok(receiver.recv().await)
     }
 
+    /// Look at the next message without receiving it
+    ///
+    /// Does not wait: a message that has not arrived yet is not returned.
+    /// The message stays queued for `receive_message` and message streams.
+    ///
+    /// # Returns
+    /// A copy of the next message, or `None` if no message is available
+    pub fn peek_message(&self) -> Option<Message> {
+        self.inspect_next_message(Message::clone)
+    }
+
+    /// Get the type of the next message without receiving it
+    ///
+    /// Like `peek_message`, but does not copy the message content.
+    ///
+    /// # Returns
+    /// The type of the next message, or `None` if no message is available
+    pub fn next_message_type(&self) -> Option<MessageType> {
+        self.inspect_next_message(Message::message_type)
+    }
+
+    /// Apply `inspect` to the next message, reading it into the lookahead if needed
+    fn inspect_next_message<R>(&self, inspect: impl FnOnce(&Message) -> R) -> Option<R> {
+        let mut lookahead = self.lookahead.lock().ok()?;
+        if lookahead.is_empty() {
+            let msg = self.message_receiver.try_lock().ok()?.try_recv().ok()?;
+            lookahead.push_back(msg);
+        }
+        lookahead.front().map(inspect)
+    }
+
+    /// Collect the response to the last message
+    ///
+    /// Reads messages until `TaskFinish`, concatenating assistant chunks and
+    /// collecting tool calls.
+    ///
+    /// # Returns
+    /// * `Ok((String, Vec<ToolCallEvent>))` containing the response text and tool calls
+    /// * `Err(IFlowError::Protocol)` if iFlow reported an error
+    /// * `Err(IFlowError::Cancelled)` if the response was interrupted
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use iflow_cli_sdk_rust::IFlowClient;
+    /// # async fn example(client: &mut IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// client.send_message("List the files in src", None).await?;
+    /// let (response, tools) = client.collect_response().await?;
+    /// println!("{} ({} tool calls)", response, tools.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn collect_response(&self) -> Result<(String, Vec<ToolCallEvent>)> {
+        let turn = self.collect_turn().await?;
+        if let Some(err) = IFlowError::from_stop_reason(turn.stop_reason.as_deref()) {
+            return Err(err);
+        }
+        Ok((turn.response, turn.tool_calls))
+    }
+
+    /// Wait for iFlow to finish its turn and return the response text
+    ///
+    /// Reads messages until `TaskFinish`, concatenating assistant chunks.
+    /// Messages are read from the same queue as [`IFlowClient::messages`],
+    /// so this can be used after a stream has been created.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long to wait for the turn to finish
+    ///
+    /// # Returns
+    /// * `Ok(String)` containing the response text
+    /// * `Err(IFlowError::Protocol)` if iFlow reported an error
+    /// * `Err(IFlowError::Cancelled)` if the response was interrupted
+    /// * `Err(IFlowError::Timeout)` if the turn did not finish within `timeout`
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use iflow_cli_sdk_rust::IFlowClient;
+    /// # use std::time::Duration;
+    /// # async fn example(client: &mut IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// client.send_message("What is 2 + 2?", None).await?;
+    /// let response = client.await_task_finish(Duration::from_secs(60)).await?;
+    /// println!("{}", response);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn await_task_finish(&self, timeout: std::time::Duration) -> Result<String> {
+        let turn = tokio::time::timeout(timeout, self.collect_turn())
+            .await
+            .map_err(|_| {
+                IFlowError::Timeout(format!(
+                    "iFlow did not finish its turn within {:?}",
+                    timeout
+                ))
+            })??;
+        if let Some(err) = IFlowError::from_stop_reason(turn.stop_reason.as_deref()) {
+            return Err(err);
+        }
+        Ok(turn.response)
+    }
+
+    /// Read the rest of the turn and summarize it
+    ///
+    /// Reads messages until `TaskFinish`, like `collect_response`, but also
+    /// keeps the plan, the stop reason and timing. Can be called right after
+    /// `send_message`.
+    ///
+    /// # Returns
+    /// * `Ok(SessionSummary)` describing the turn
+    /// * `Err(IFlowError::Protocol)` if iFlow reported an error
+    /// * `Err(IFlowError::Cancelled)` if the client was cancelled
+    pub async fn drain_and_summarize(&self) -> Result<SessionSummary> {
+        let turn = self.collect_turn().await?;
+        Ok(SessionSummary {
+            full_response: turn.response,
+            tool_calls: turn.tool_calls,
+            final_plan: turn.plan,
+            stop_reason: turn.stop_reason,
+            total_chunks: turn.chunks,
+            total_duration: turn.duration,
+        })
+    }
+
+    /// Send a prompt and stream the response to a writer
+    ///
+    /// Writes each assistant chunk to `writer` as it arrives and flushes the
+    /// writer once iFlow finishes its turn.
+    ///
+    /// # Arguments
+    /// * `prompt` - The prompt to send
+    /// * `writer` - Where to write the response, e.g. stdout or a socket
+    ///
+    /// # Returns
+    /// * `Ok(())` once the response is complete
+    /// * `Err(IFlowError::Protocol)` if iFlow reported an error
+    /// * `Err(IFlowError::Cancelled)` if the response was interrupted
+    /// * `Err(IFlowError::Io)` if writing failed
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use iflow_cli_sdk_rust::IFlowClient;
+    /// # async fn example(client: &mut IFlowClient) -> iflow_cli_sdk_rust::Result<()> {
+    /// let mut stdout = tokio::io::stdout();
+    /// client.stream_to_writer("Tell me a story", &mut stdout).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_to_writer<W>(&mut self, prompt: &str, writer: &mut W) -> Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        self.send_message(prompt, None).await?;
+
+        while let Some(msg) = self.receive_message().await? {
+            match msg {
+                Message::Assistant { content } => writer.write_all(content.as_bytes()).await?,
+                Message::Error { code, message, .. } => {
+                    return Err(IFlowError::Protocol(format!("{} (code {})", message, code)));
+                }
+                Message::TaskFinish { reason } => {
+                    writer.flush().await?;
+                    if let Some(err) = IFlowError::from_stop_reason(reason.as_deref()) {
+                        return Err(err);
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Read messages up to and including the next `TaskFinish`
+    ///
+    /// Fails with `IFlowError::Cancelled` if the client is cancelled first.
+    async fn collect_turn(&self) -> Result<Turn> {
+        tokio::select! {
+            turn = self.read_turn() => turn,
+            _ = self.cancellation.cancelled() => Err(IFlowError::Cancelled { by: CancelledBy::User }),
+        }
+    }
+
+    /// Read messages until the end of the turn
+    async fn read_turn(&self) -> Result<Turn> {
+        let mut turn = Turn::default();
+        let mut first_message_at = None;
+
+        while let Some(msg) = self.receive_message().await? {
+            let started_at = *first_message_at.get_or_insert_with(std::time::Instant::now);
+            match msg {
+                Message::Assistant { content } => {
+                    turn.response.push_str(&content);
+                    turn.chunks += 1;
+                }
+                Message::ToolCall { id, name, status } => turn.tool_calls.push(ToolCallEvent {
+                    id,
+                    name,
+                    status,
+                    input: None,
+                    output: None,
+                }),
+                Message::Plan { entries } => turn.plan = Some(entries),
+                Message::Error { code, message, .. } => {
+                    return Err(IFlowError::Protocol(format!("{} (code {})", message, code)));
+                }
+                Message::TaskFinish { reason } => {
+                    turn.stop_reason = reason;
+                    turn.duration = started_at.elapsed();
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(turn)
+    }
+
+    /// Work on a task until iFlow finishes it or a limit is reached
+    ///
+    /// Sends `task`, collects the response and keeps prompting iFlow to
+    /// continue while it stops before ending its turn (e.g. because it hit a
+    /// token or turn request limit).
+    ///
+    /// # Arguments
+    /// * `task` - The task to work on
+    /// * `max_turns` - The maximum number of prompts to send
+    /// * `options` - Additional limits and behaviour of the loop
+    ///
+    /// # Returns
+    /// * `Ok(AgentResult)` describing the run; `stop_reason` is `"done"` when
+    ///   iFlow finished the task, `"max_turns"`, `"max_tool_calls"` or
+    ///   `"plan_update"` when a limit stopped the loop, or iFlow's own stop reason
+    /// * `Err(IFlowError)` if sending a prompt failed or iFlow reported an error
+    pub async fn run_agent_loop(
+        &mut self,
+        task: &str,
+        max_turns: u32,
+        options: AgentLoopOptions,
+    ) -> Result<AgentResult> {
+        let previous_mode = self.options.permission_mode;
+        if options.auto_approve_permissions {
+            self.set_protocol_permission_mode(PermissionMode::Auto);
+        }
+
+        let result = self.agent_loop(task, max_turns, &options).await;
+
+        if options.auto_approve_permissions {
+            self.set_protocol_permission_mode(previous_mode);
+        }
+        result
+    }
+
+    async fn agent_loop(
+        &mut self,
+        task: &str,
+        max_turns: u32,
+        options: &AgentLoopOptions,
+    ) -> Result<AgentResult> {
+        let mut result = AgentResult {
+            response: String::new(),
+            turns: 0,
+            tool_calls: Vec::new(),
+            final_plan: None,
+            stop_reason: "max_turns".to_string(),
+        };
+
+        let mut prompt = task;
+        while result.turns < max_turns {
+            self.send_message(prompt, None).await?;
+            let turn = self.collect_turn().await?;
+            result.turns += 1;
+            result.response.push_str(&turn.response);
+            result.tool_calls.extend(turn.tool_calls);
+            let plan_updated = turn.plan.is_some();
+            if turn.plan.is_some() {
+                result.final_plan = turn.plan;
+            }
+
+            if result.tool_calls.len() > options.max_tool_calls as usize {
+                result.stop_reason = "max_tool_calls".to_string();
+                break;
+            }
+
+            match turn.stop_reason.as_deref() {
+                // "completed" is reported when iFlow's response carries no stop reason
+                Some("EndTurn" | "end_turn" | "completed") | None => {
+                    result.stop_reason = "done".to_string();
+                    break;
+                }
+                Some(
+                    reason @ ("Refusal" | "refusal" | "Cancelled" | "cancelled" | "interrupted"),
+                ) => {
+                    result.stop_reason = reason.to_string();
+                    break;
+                }
+                Some(_) => {}
+            }
+
+            if options.pause_on_plan_update && plan_updated {
+                result.stop_reason = "plan_update".to_string();
+                break;
+            }
+
+            prompt = AGENT_LOOP_CONTINUE_PROMPT;
+        }
+
+        Ok(result)
+    }
+
+    /// Pre-configure permission decisions by tool name
+    ///
+    /// Permission requests for a tool in `approvals` are answered with its
+    /// decision before the permission mode is consulted; other tools go
+    /// through the permission mode as usual. A tool call hook can still deny
+    /// a pre-approved tool. Takes effect immediately over WebSocket, and
+    /// with the next connection over stdio.
+    ///
+    /// # Arguments
+    /// * `approvals` - The decision for each tool name, e.g. `{"bash": Approve("proceed_once")}`
+    pub fn set_tool_approvals(&mut self, approvals: HashMap<String, PermissionDecision>) {
+        if let Some(Connection::WebSocket { acp_protocol, .. }) = &mut self.connection {
+            acp_protocol.set_tool_approvals(approvals.clone());
+        }
+        self.options.tool_approvals = approvals;
+    }
+
+    /// Change how the WebSocket protocol answers permission requests
+    fn set_protocol_permission_mode(&mut self, mode: PermissionMode) {
+        if let Some(Connection::WebSocket { acp_protocol, .. }) = &mut self.connection {
+            acp_protocol.set_permission_mode(mode);
+        }
+    }
+
+    /// Wait for a tool call whose name starts with the given prefix
+    ///
+    /// Reads messages until a `Message::ToolCall` with a matching name
+    /// (case-insensitive prefix match) arrives. Other messages read along the
+    /// way are kept and delivered by `messages()` and `receive_message()` in
+    /// their original order.
+    ///
+    /// # Arguments
+    /// * `tool_name_prefix` - The prefix the tool name must start with
+    /// * `timeout` - How long to wait for a matching tool call
+    ///
+    /// # Returns
+    /// * `Ok(Message)` containing the matching tool call
+    /// * `Err(IFlowError::Timeout)` if no matching tool call arrived in time
+    /// * `Err(IFlowError::Connection)` if the message channel closed
+    pub async fn wait_for_tool_call(
+        &self,
+        tool_name_prefix: &str,
+        timeout: std::time::Duration,
+    ) -> Result<Message> {
+        let prefix = tool_name_prefix.to_lowercase();
+        let matches = |msg: &Message| match msg {
+            Message::ToolCall { name, .. } => name.to_lowercase().starts_with(&prefix),
+            _ => false,
+        };
+
+        // A matching tool call may already have been read ahead
+        if let Ok(mut lookahead) = self.lookahead.lock()
+            && let Some(pos) = lookahead.iter().position(&matches)
+        {
+            return Ok(lookahead.remove(pos).expect("position is in bounds"));
+        }
+
+        let search = async {
+            let mut receiver = self.message_receiver.lock().await;
+            while let Some(msg) = receiver.recv().await {
+                if matches(&msg) {
+                    return Ok(msg);
+                }
+                if let Ok(mut lookahead) = self.lookahead.lock() {
+                    lookahead.push_back(msg);
+                }
+            }
+            Err(IFlowError::Connection("Message channel closed".to_string()))
+        };
+
+        tokio::time::timeout(timeout, search).await.map_err(|_| {
+            IFlowError::Timeout(format!(
+                "No tool call matching '{}' within {:?}",
+                tool_name_prefix, timeout
+            ))
+        })?
+    }
+
+    /// Spawn a task that runs alongside the client
+    ///
+    /// The task is aborted by `abort_all_background_tasks()` or when the
+    /// client disconnects, so it cannot outlive the connection it works with.
+    ///
+    /// # Arguments
+    /// * `task` - A closure returning the future to run
+    ///
+    /// # Returns
+    /// A handle that aborts just this task
+    pub fn background_task<F, Fut>(&mut self, task: F) -> tokio::task::AbortHandle
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.background_tasks.retain(|handle| !handle.is_finished());
+        let token = self.cancellation.child_token();
+        let handle = tokio::spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = task() => {}
+            }
+        })
+        .abort_handle();
+        self.background_tasks.push(handle.clone());
+        handle
+    }
+
+    /// Abort every task started with `background_task()`
+    pub fn abort_all_background_tasks(&mut self) {
+        for handle in self.background_tasks.drain(..) {
+            handle.abort();
+        }
+    }
+
     /// Disconnect from iFlow
     ///
     /// Cleans up the connection to iFlow and stops the process if it was started by this client.
-    /// This method ensures proper cleanup of resources.
+    /// This method ensures proper cleanup of resources, and flushes the message log.
     ///
     /// # Returns
     /// * `Ok(())` if the disconnection was successful
     /// * `Err(IFlowError)` if there was an error
     pub async fn disconnect(&mut self) -> Result<()> {
-        *self.connected.lock().await = false;
+        if self.connected_at.is_some() && self.disconnected_at.is_none() {
+            self.disconnected_at = Some(std::time::Instant::now());
+        }
 
-        // Take ownership of the connection to ensure proper cleanup
-        if let Some(connection) = self.connection.take() {
-            match connection {
-                Connection::Stdio {
-                    acp_client,
-                    mut process_manager,
-                    session_id: _,
-                    initialized: _,
-                } => {
-                    // Drop the ACP client connection to stop background tasks
-                    drop(acp_client);
-
-                    // Stop the process if we started it
-                    if let Some(mut pm) = process_manager.take() {
-                        pm.stop().await?;
-                    }
+        let result = self.close_connection().await;
 
-                    // Add a small delay to allow background tasks to finish
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                }
-                Connection::WebSocket {
-                    mut acp_protocol,
-                    mut process_manager,
-                    session_id: _,
-                } => {
-                    let _ = acp_protocol.close().await;
-                    // if we started the process, stop it
-                    if let Some(mut pm) = process_manager.take() {
-                        pm.stop().await?;
-                    }
-                }
+        // Make sure every message received so far is on disk
+        if let Some(logger) = &self.logger
+            && let Err(e) = logger.flush().await
+        {
+            debug!("Failed to flush message log: {}", e);
+        }
+
+        if let Some(result) = result {
+            if let Some(on_disconnect) = &self.options.hooks.on_disconnect {
+                let reason = match &result {
+                    Ok(reason) => reason.clone(),
+                    Err(e) => DisconnectReason::Error(e.to_string()),
+                };
+                on_disconnect(reason);
             }
+            result?;
         }
 
         debug!("Disconnected from iFlow");
         Ok(())
     }
+
+    /// Reconnect to iFlow, keeping the message stream open
+    ///
+    /// Closes the current connection, connects again, runs the protocol
+    /// handshake and resumes the previous session with `session/load`. If
+    /// iFlow cannot load the session, a new one is created. Request IDs start
+    /// over on the new connection. The `on_connection_event` hook receives
+    /// `ConnectionEvent::Reconnected` once the session is ready; the
+    /// `on_connect` and `on_disconnect` hooks are not called.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the client is connected with a ready session
+    /// * `Err(IFlowError)` if connecting or preparing the session failed
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let started_at = std::time::Instant::now();
+        let previous_session = self.session_id().map(str::to_string);
+        if let Some(Err(e)) = self.close_connection().await {
+            debug!(
+                "Ignoring error while closing connection for reconnect: {}",
+                e
+            );
+        }
+
+        if self.options.websocket.is_some() {
+            self.connect_websocket().await?;
+        } else {
+            self.connect_stdio().await?;
+        }
+        self.connected_at = Some(std::time::Instant::now());
+        self.disconnected_at = None;
+
+        let resumed = match previous_session {
+            Some(session_id) => self.load_previous_session(session_id).await?,
+            None => false,
+        };
+        if !resumed {
+            self.prepare_session().await?;
+        }
+        record_latency(&mut self.connection_latencies, started_at.elapsed());
+
+        info!("Reconnected to iFlow, session: {:?}", self.session_id());
+        if let Some(on_connection_event) = &self.options.hooks.on_connection_event {
+            on_connection_event(&ConnectionEvent::Reconnected {
+                session_id: self.session_id().map(str::to_string),
+                resumed,
+            });
+        }
+        Ok(())
+    }
+
+    /// Initialize the current connection and load a previous session
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to resume
+    ///
+    /// # Returns
+    /// * `Ok(true)` if the session was loaded
+    /// * `Ok(false)` if iFlow could not load it; the session ID is cleared
+    /// * `Err(IFlowError)` if the protocol handshake failed
+    async fn load_previous_session(&mut self, session_id: String) -> Result<bool> {
+        self.attach(session_id.clone())?;
+        self.prepare_session().await?;
+
+        let cwd = self.options.cwd.clone();
+        let mcp_servers = self.options.mcp_servers.clone();
+        let loaded = match &mut self.connection {
+            Some(Connection::WebSocket { acp_protocol, .. }) => {
+                let servers = mcp_servers.iter().map(|server| serde_json::json!(server));
+                acp_protocol
+                    .load_session(&session_id, &cwd.to_string_lossy(), servers.collect())
+                    .await
+            }
+            Some(Connection::Stdio { acp_client, .. }) => acp_client
+                .load_session(agent_client_protocol::LoadSessionRequest {
+                    mcp_servers,
+                    cwd,
+                    session_id: SessionId(session_id.clone().into()),
+                    meta: None,
+                })
+                .await
+                .map(|_| ())
+                .map_err(|e| IFlowError::Connection(format!("Failed to load session: {}", e))),
+            None => return Err(IFlowError::NotConnected),
+        };
+
+        match loaded {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                debug!("Could not resume session {}: {}", session_id, e);
+                match &mut self.connection {
+                    Some(Connection::WebSocket { session_id, .. }) => *session_id = None,
+                    Some(Connection::Stdio { session_id, .. }) => *session_id = None,
+                    None => {}
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Close the current connection without calling the disconnect hook
+    ///
+    /// Background tasks and detached prompts are stopped, and the process is
+    /// stopped if this client started it. The message channel stays open.
+    ///
+    /// # Returns
+    /// The result of tearing down the connection, or `None` if there was none
+    async fn close_connection(&mut self) -> Option<Result<DisconnectReason>> {
+        self.abort_all_background_tasks();
+        if let Some(prompt) = self.detached_prompt.take() {
+            prompt.abort();
+        }
+        self.cancellation.cancel();
+        self.cancellation = match &self.parent_cancellation {
+            Some(parent) => parent.child_token(),
+            None => CancellationToken::new(),
+        };
+        *self.connected.lock().await = false;
+        self.sessions.clear();
+        if let Ok(mut pending) = self.pending_system_prompts.lock() {
+            pending.clear();
+        }
+
+        // Take ownership of the connection to ensure proper cleanup
+        let result = match self.connection.take()? {
+            Connection::Stdio {
+                acp_client,
+                process_manager,
+                session_id: _,
+                initialized: _,
+            } => {
+                // Drop the ACP client connection to stop background tasks
+                drop(acp_client);
+
+                // Stop the process if we started it
+                let result = Self::stop_process(process_manager).await;
+
+                // Add a small delay to allow background tasks to finish
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                result
+            }
+            Connection::WebSocket {
+                mut acp_protocol,
+                process_manager,
+                session_id: _,
+            } => {
+                let _ = acp_protocol.close().await;
+                // if we started the process, stop it
+                Self::stop_process(process_manager).await
+            }
+        };
+        Some(result)
+    }
+
+    /// Stop a process started by this client
+    ///
+    /// # Returns
+    /// The disconnect reason, which reports whether the process had already exited
+    async fn stop_process(
+        process_manager: Option<IFlowProcessManager>,
+    ) -> Result<DisconnectReason> {
+        let Some(mut pm) = process_manager else {
+            return Ok(DisconnectReason::Graceful);
+        };
+
+        let reason = if pm.has_exited() {
+            DisconnectReason::ProcessExited
+        } else {
+            DisconnectReason::Graceful
+        };
+        pm.stop().await?;
+        Ok(reason)
+    }
 }
 
 impl Drop for IFlowClient {
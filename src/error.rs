@@ -2,11 +2,15 @@
 //!
 //! This module defines all the error types that can occur when using the iFlow SDK.
 
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Main error type for iFlow SDK
 ///
 /// This enum encompasses all possible errors that can occur when using the iFlow SDK.
+/// Variants wrapping another error return it from `std::error::Error::source`,
+/// so the full chain is available to error reporters.
 #[derive(Error, Debug)]
 pub enum IFlowError {
     /// Connection related errors
@@ -33,6 +37,10 @@ pub enum IFlowError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Options that do not match the environment
+    #[error("Configuration error: {0}")]
+    Config(String),
+
     /// Transport related errors
     #[error("Transport error: {0}")]
     Transport(String),
@@ -41,6 +49,10 @@ pub enum IFlowError {
     #[error("JSON parsing error: {0}")]
     JsonParse(#[from] serde_json::Error),
 
+    /// Errors deserializing an assistant response into a typed value
+    #[error("Deserialization error: {0}")]
+    Deserialization(#[source] serde_json::Error),
+
     /// WebSocket related errors (deprecated)
     #[error("WebSocket error: {0}")]
     WebSocket(String),
@@ -49,6 +61,25 @@ pub enum IFlowError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// File exceeds the configured size limit
+    #[error("File too large: {} is {size} bytes, which exceeds the limit of {limit} bytes", path.display())]
+    FileTooLarge {
+        /// The path of the file
+        path: PathBuf,
+        /// The size of the file in bytes
+        size: u64,
+        /// The configured limit in bytes
+        limit: u64,
+    },
+
+    /// File does not exist
+    #[error("File not found: {}", .0.display())]
+    FileNotFound(PathBuf),
+
+    /// File cannot be accessed with the current permissions
+    #[error("Permission denied: {}", .0.display())]
+    FilePermissionDenied(PathBuf),
+
     /// Process manager related errors
     #[error("Process manager error: {0}")]
     ProcessManager(String),
@@ -61,6 +92,22 @@ pub enum IFlowError {
     #[error("Session not found")]
     SessionNotFound,
 
+    /// The operation was stopped before it completed
+    #[error("Operation cancelled by {by}")]
+    Cancelled {
+        /// What stopped the operation
+        by: CancelledBy,
+    },
+
+    /// A batch of prompts stopped at a failed prompt
+    #[error("Batch failed after {} prompts: {source}", completed.len())]
+    BatchFailed {
+        /// The responses to the prompts before the failed one
+        completed: Vec<String>,
+        /// The error of the failed prompt
+        source: Box<IFlowError>,
+    },
+
     /// Invalid message format error
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
@@ -68,6 +115,198 @@ pub enum IFlowError {
     /// Unknown error
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    /// An error from another library, keeping its source chain
+    #[error(transparent)]
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// What stopped a cancelled operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelledBy {
+    /// The user interrupted or cancelled the operation
+    User,
+    /// A deadline set by the caller expired
+    Timeout,
+    /// The process received a termination signal
+    Signal,
+}
+
+impl std::fmt::Display for CancelledBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CancelledBy::User => write!(f, "user"),
+            CancelledBy::Timeout => write!(f, "timeout"),
+            CancelledBy::Signal => write!(f, "signal"),
+        }
+    }
+}
+
+/// Errors in the state of an ACP connection
+///
+/// Converted into the matching `IFlowError` when returned from the public API.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConnectionError {
+    /// The connection failed
+    #[error("{0}")]
+    ConnectionError(String),
+
+    /// The protocol has not been initialized
+    #[error("Protocol not initialized. Call initialize() first.")]
+    NotInitialized,
+
+    /// No session has been created
+    #[error("No session available")]
+    NoSession,
+
+    /// The connection did not respond in time
+    #[error("Connection timed out after {:.1}s", .0.as_secs_f64())]
+    Timeout(Duration),
+}
+
+impl From<ConnectionError> for IFlowError {
+    fn from(error: ConnectionError) -> Self {
+        match error {
+            ConnectionError::ConnectionError(message) => IFlowError::Connection(message),
+            ConnectionError::NotInitialized | ConnectionError::NoSession => {
+                IFlowError::Protocol(error.to_string())
+            }
+            ConnectionError::Timeout(_) => IFlowError::Timeout(error.to_string()),
+        }
+    }
+}
+
+impl IFlowError {
+    /// Wrap an error from another library
+    ///
+    /// # Arguments
+    /// * `error` - The error to wrap
+    ///
+    /// # Returns
+    /// An `IFlowError::Other` whose source chain is that of `error`
+    pub fn other(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        IFlowError::Other(Box::new(error))
+    }
+
+    /// The error for a turn that ended with `stop_reason`, if it was cancelled
+    ///
+    /// Covers both `interrupt()` and iFlow reporting a cancelled prompt.
+    pub(crate) fn from_stop_reason(stop_reason: Option<&str>) -> Option<Self> {
+        match stop_reason? {
+            "interrupted" | "cancelled" | "Cancelled" => Some(IFlowError::Cancelled {
+                by: CancelledBy::User,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether the operation was cancelled
+    ///
+    /// # Returns
+    /// `true` only for `Cancelled`
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, IFlowError::Cancelled { .. })
+    }
+
+    /// Whether this error was caused by accessing a file
+    ///
+    /// # Returns
+    /// `true` for `FileTooLarge`, `FileNotFound` and `FilePermissionDenied`
+    pub fn is_file_error(&self) -> bool {
+        matches!(
+            self,
+            IFlowError::FileTooLarge { .. }
+                | IFlowError::FileNotFound(_)
+                | IFlowError::FilePermissionDenied(_)
+        )
+    }
+
+    /// Describe the error in terms an end user can act on
+    ///
+    /// See also `IFlowClient::explain_error`, which says what timed out and
+    /// names the timeout options for `Timeout` errors.
+    ///
+    /// # Returns
+    /// A human-readable explanation of the error
+    pub fn user_message(&self) -> String {
+        match self {
+            IFlowError::Connection(msg) => format!(
+                "Could not connect to iFlow. Please check that iFlow is running and reachable. Details: {}",
+                msg
+            ),
+            IFlowError::Protocol(msg) => format!(
+                "iFlow sent an unexpected response. Please make sure your iFlow CLI version is supported. Details: {}",
+                msg
+            ),
+            IFlowError::Authentication(msg) => format!(
+                "Authentication with iFlow failed. Please check your authentication settings. Details: {}",
+                msg
+            ),
+            IFlowError::Timeout(_) => {
+                "The operation timed out. Try increasing the timeout or checking iFlow is responsive."
+                    .to_string()
+            }
+            IFlowError::ToolCall(msg) => format!("A tool call failed. Details: {}", msg),
+            IFlowError::Validation(msg) => format!("Invalid input: {}", msg),
+            IFlowError::Config(msg) => format!(
+                "The iFlow options do not match your environment. Please check your configuration. Details: {}",
+                msg
+            ),
+            IFlowError::Transport(msg) | IFlowError::WebSocket(msg) => format!(
+                "The connection to iFlow was interrupted. Please reconnect and try again. Details: {}",
+                msg
+            ),
+            IFlowError::JsonParse(e) => format!("iFlow sent a message that could not be read. Details: {}", e),
+            IFlowError::Deserialization(e) => format!(
+                "The response from iFlow was not in the expected format. Details: {}",
+                e
+            ),
+            IFlowError::Io(e) => format!("An input/output error occurred. Details: {}", e),
+            IFlowError::FileTooLarge { path, size, limit } => format!(
+                "The file {} is too large ({} bytes). Files may be at most {} bytes; raise file_access.max_size to allow larger files.",
+                path.display(),
+                size,
+                limit
+            ),
+            IFlowError::FileNotFound(path) => {
+                format!("The file {} does not exist.", path.display())
+            }
+            IFlowError::FilePermissionDenied(path) => format!(
+                "The file {} cannot be accessed. Please check its permissions.",
+                path.display()
+            ),
+            IFlowError::ProcessManager(msg) => format!(
+                "Failed to start iFlow CLI. Please ensure iflow is installed and in your PATH. Details: {}",
+                msg
+            ),
+            IFlowError::NotConnected => {
+                "Please call connect() before sending messages.".to_string()
+            }
+            IFlowError::SessionNotFound => {
+                "The iFlow session no longer exists. Please start a new session.".to_string()
+            }
+            IFlowError::Cancelled { .. } => "The operation was cancelled.".to_string(),
+            IFlowError::BatchFailed { completed, source } => format!(
+                "{} prompts were answered before one failed. {}",
+                completed.len(),
+                source.user_message()
+            ),
+            IFlowError::InvalidMessage(msg) => format!("Invalid message: {}", msg),
+            IFlowError::Unknown(msg) => format!("An unexpected error occurred. Details: {}", msg),
+            IFlowError::Other(e) => format!("An unexpected error occurred. Details: {}", e),
+        }
+    }
+
+    /// Map an IO error from accessing `path` to the matching file error
+    pub(crate) fn from_file_io(error: std::io::Error, path: &Path) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => IFlowError::FileNotFound(path.to_path_buf()),
+            std::io::ErrorKind::PermissionDenied => {
+                IFlowError::FilePermissionDenied(path.to_path_buf())
+            }
+            _ => IFlowError::Io(error),
+        }
+    }
 }
 
 /// Result type alias for iFlow SDK
@@ -0,0 +1,74 @@
+//! Message interceptors for iFlow
+//!
+//! Interceptors see every message received from iFlow before it reaches the
+//! hooks, the logger and the message stream. They can pass a message
+//! through, transform it or drop it.
+
+use crate::types::{Message, PlanCallback};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Inspects, transforms or drops messages received from iFlow
+pub trait MessageInterceptor: Send {
+    /// Process a message received from iFlow
+    ///
+    /// # Arguments
+    /// * `msg` - The received message
+    ///
+    /// # Returns
+    /// `Some(Message)` to pass the (possibly transformed) message on, `None` to drop it
+    fn intercept(&mut self, msg: Message) -> Option<Message>;
+}
+
+/// Interceptor that logs every message and passes it through
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingInterceptor;
+
+impl MessageInterceptor for LoggingInterceptor {
+    fn intercept(&mut self, msg: Message) -> Option<Message> {
+        debug!("Intercepted message: {:?}", msg);
+        Some(msg)
+    }
+}
+
+/// Interceptor that keeps only the messages matching a predicate
+#[derive(Debug, Clone, Copy)]
+pub struct FilterInterceptor(pub fn(&Message) -> bool);
+
+impl MessageInterceptor for FilterInterceptor {
+    fn intercept(&mut self, msg: Message) -> Option<Message> {
+        (self.0)(&msg).then_some(msg)
+    }
+}
+
+/// Interceptor that passes every plan to the subscribers of `IFlowClient::subscribe_to_plan`
+pub(crate) struct PlanInterceptor(pub(crate) Arc<Mutex<Vec<PlanCallback>>>);
+
+impl MessageInterceptor for PlanInterceptor {
+    fn intercept(&mut self, msg: Message) -> Option<Message> {
+        if let Message::Plan { entries } = &msg {
+            // Call the subscribers without holding the lock, so they may subscribe
+            let subscribers = self.0.lock().map(|s| s.clone()).unwrap_or_default();
+            for subscriber in subscribers {
+                subscriber(entries);
+            }
+        }
+        Some(msg)
+    }
+}
+
+/// Interceptors shared by the client and its protocol handlers
+pub(crate) type Interceptors = Arc<Mutex<Vec<Box<dyn MessageInterceptor>>>>;
+
+/// Run a message through the interceptors in order
+///
+/// # Returns
+/// The resulting message, or `None` if an interceptor dropped it
+pub(crate) fn intercept(interceptors: &Interceptors, msg: Message) -> Option<Message> {
+    let Ok(mut interceptors) = interceptors.lock() else {
+        return Some(msg);
+    };
+    interceptors
+        .iter_mut()
+        .try_fold(msg, |msg, interceptor| interceptor.intercept(msg))
+}
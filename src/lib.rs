@@ -55,21 +55,36 @@
 pub mod acp_protocol;
 pub mod client;
 pub mod error;
+pub mod interceptor;
 pub mod logger;
+pub mod plan;
+pub mod precheck;
 pub mod process_manager;
 pub mod query;
+pub mod repl;
 pub mod types;
 pub mod websocket_transport;
 
 // Re-export main types
 pub use client::IFlowClient;
-pub use error::{IFlowError, Result};
+pub use error::{CancelledBy, IFlowError, Result};
+pub use interceptor::{FilterInterceptor, LoggingInterceptor, MessageInterceptor};
 pub use logger::{LoggerConfig, MessageLogger};
+pub use plan::{PlanDiff, PlanTracker, plan_diff};
+pub use precheck::{
+    BinaryInPathPrecheck, ConnectionPrecheck, DirectoryExistsPrecheck, McpServerBinaryPrecheck,
+};
 pub use process_manager::IFlowProcessManager;
 pub use query::{
-    query, query_stream, query_stream_with_config, query_stream_with_timeout, query_with_config,
-    query_with_timeout,
+    query, query_and_parse, query_and_parse_json, query_and_parse_json_with_config,
+    query_and_parse_with_config, query_and_parse_with_schema,
+    query_and_parse_with_schema_with_config, query_as_json, query_as_json_with_config, query_batch,
+    query_batch_with_config, query_stream, query_stream_jsonl, query_stream_jsonl_with_config,
+    query_stream_parsed, query_stream_parsed_with_config, query_stream_with_config,
+    query_stream_with_timeout, query_stream_with_tools, query_stream_with_tools_with_config,
+    query_to_writer, query_to_writer_with_config, query_with_config, query_with_timeout,
 };
+pub use repl::{ReplOptions, run_repl, run_repl_with_config};
 pub use types::{IFlowOptions, Message};
 
 // Re-export types from agent-client-protocol that we actually use
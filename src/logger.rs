@@ -4,10 +4,13 @@
 //! to files, with support for log rotation based on file size.
 
 use crate::Message;
+use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
 /// Logger configuration
@@ -22,7 +25,10 @@ pub struct LoggerConfig {
     pub enabled: bool,
     /// Maximum log file size (bytes), will rotate when exceeded
     pub max_file_size: u64,
-    /// Number of log files to retain
+    /// Number of log files to retain, including the active one
+    ///
+    /// Rotated files are archived as `<name>.1.log`, `<name>.2.log` and so
+    /// on, newest first.
     pub max_files: u32,
 }
 
@@ -37,14 +43,23 @@ impl Default for LoggerConfig {
     }
 }
 
+/// A single line of the log file
+#[derive(Serialize, Deserialize)]
+struct LogEntry {
+    /// Milliseconds since the Unix epoch
+    timestamp_ms: u64,
+    message: Message,
+}
+
 /// Message logger
 ///
 /// Handles writing iFlow messages to log files with automatic rotation
-/// based on file size limits.
+/// based on file size limits. Each message is written as one JSON line.
 #[derive(Clone)]
 pub struct MessageLogger {
     config: LoggerConfig,
     writer: Arc<Mutex<BufWriter<File>>>,
+    count: Arc<AtomicU64>,
 }
 
 impl MessageLogger {
@@ -64,6 +79,7 @@ impl MessageLogger {
             return Ok(Self {
                 config,
                 writer: Arc::new(Mutex::new(BufWriter::new(File::create("/dev/null")?))),
+                count: Arc::new(AtomicU64::new(0)),
             });
         }
 
@@ -88,13 +104,36 @@ impl MessageLogger {
         Ok(Self {
             config,
             writer: Arc::new(Mutex::new(BufWriter::new(file))),
+            count: Arc::new(AtomicU64::new(0)),
         })
     }
 
+    /// Get the path of an archived log file
+    ///
+    /// # Arguments
+    /// * `config` - The logger configuration
+    /// * `index` - The number of the archive, 1 being the most recent
+    ///
+    /// # Returns
+    /// `<name>.<index>.log` for a log file `<name>.log`
+    fn archive_path(config: &LoggerConfig, index: u32) -> PathBuf {
+        let stem = config
+            .log_file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = match config.log_file.extension() {
+            Some(extension) => format!("{}.{}.{}", stem, index, extension.to_string_lossy()),
+            None => format!("{}.{}", stem, index),
+        };
+        config.log_file.with_file_name(name)
+    }
+
     /// Rotate log files
     ///
     /// Rotates the log files based on the configured retention policy.
-    /// This method manages the renaming and deletion of old log files.
+    /// The active file becomes archive 1, older archives move up by one and
+    /// archives beyond `max_files` are deleted.
     ///
     /// # Arguments
     /// * `config` - The logger configuration containing rotation settings
@@ -112,7 +151,7 @@ impl MessageLogger {
             let old_path = if i == 0 {
                 config.log_file.clone()
             } else {
-                config.log_file.with_extension(format!("log.{}", i))
+                Self::archive_path(config, i)
             };
 
             let new_path = if i + 1 >= config.max_files {
@@ -122,7 +161,7 @@ impl MessageLogger {
                 }
                 continue;
             } else {
-                config.log_file.with_extension(format!("log.{}", i + 1))
+                Self::archive_path(config, i + 1)
             };
 
             if old_path.exists() {
@@ -154,36 +193,124 @@ impl MessageLogger {
 
         writeln!(writer, "{}", log_entry)?;
         writer.flush()?;
+        self.count.fetch_add(1, Ordering::Relaxed);
 
         // Check file size
         if writer.get_ref().metadata()?.len() >= self.config.max_file_size {
-            Self::rotate_log_file(&self.config)?;
-
-            // Reopen the file
-            let file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&self.config.log_file)?;
-            *writer = BufWriter::new(file);
+            self.rotate(&mut writer)?;
         }
 
         Ok(())
     }
 
-    /// Log raw message using Debug format without any processing
+    /// Rotate the log files and reopen the active file
+    fn rotate(&self, writer: &mut BufWriter<File>) -> Result<(), io::Error> {
+        writer.flush()?;
+        Self::rotate_log_file(&self.config)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.log_file)?;
+        *writer = BufWriter::new(file);
+        Ok(())
+    }
+
+    /// Write buffered entries to the log file and sync it to disk
+    ///
+    /// # Returns
+    /// * `Ok(())` if the log file was flushed
+    /// * `Err(io::Error)` if writing or syncing failed
+    pub async fn flush(&self) -> Result<(), io::Error> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let mut writer = self.writer.lock().await;
+        writer.flush()?;
+        writer.get_ref().sync_data()
+    }
+
+    /// Archive the active log file now, regardless of its size
+    ///
+    /// The active file becomes `<name>.1.log` and logging continues in a new
+    /// file, as if `max_file_size` had been reached.
     ///
-    /// Formats a message for logging using the Debug trait.
-    /// This provides a detailed representation of the message structure.
+    /// # Returns
+    /// * `Ok(())` if the log file was archived
+    /// * `Err(io::Error)` if renaming or reopening a file failed
+    pub async fn archive_now(&self) -> Result<(), io::Error> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let mut writer = self.writer.lock().await;
+        self.rotate(&mut writer)
+    }
+
+    /// Format a message as a single JSON line with a timestamp
     ///
     /// # Arguments
     /// * `message` - The message to format
     ///
     /// # Returns
-    /// A formatted string representation of the message
+    /// A JSON string representation of the log entry
     fn format_message(&self, message: &Message) -> String {
-        // Output raw message structure using Debug format
-        // Use alternate format to avoid truncation
-        format!("{:#?}", message)
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let entry = LogEntry {
+            timestamp_ms,
+            message: message.clone(),
+        };
+        serde_json::to_string(&entry).unwrap_or_else(|_| format!("{:?}", message))
+    }
+
+    /// Get the number of messages written since the logger was created or reset
+    pub fn log_count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Truncate the log file and reset the message count
+    ///
+    /// # Returns
+    /// * `Ok(())` if the log was reset
+    /// * `Err(io::Error)` if the log file could not be truncated
+    pub async fn reset(&self) -> Result<(), io::Error> {
+        let mut writer = self.writer.lock().await;
+        if self.config.enabled {
+            writer.flush()?;
+            writer.get_ref().set_len(0)?;
+        }
+        self.count.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Read all entries from the current log file
+    ///
+    /// Lines that are not valid log entries (e.g. from older log formats)
+    /// are skipped. Archived log files are not read.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<(SystemTime, Message)>)` containing the logged messages, oldest first
+    /// * `Err(io::Error)` if the log file could not be read
+    pub async fn read_all(&self) -> Result<Vec<(SystemTime, Message)>, io::Error> {
+        if !self.config.enabled {
+            return Ok(Vec::new());
+        }
+
+        // Hold the writer so no entry is half-written while reading
+        let _writer = self.writer.lock().await;
+        let contents = std::fs::read_to_string(&self.config.log_file)?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+            .map(|entry| {
+                let timestamp = UNIX_EPOCH + Duration::from_millis(entry.timestamp_ms);
+                (timestamp, entry.message)
+            })
+            .collect())
     }
 
     /// Get current log file path
@@ -0,0 +1,129 @@
+//! Plan tracking for iFlow
+//!
+//! iFlow sends the full plan with every `Message::Plan` update. This module
+//! merges successive updates by entry ID, or by content for entries without
+//! one, so callers can follow how each entry progresses, and computes what
+//! changed between two updates.
+
+use crate::types::{PlanEntry, PlanPriority, PlanStatus};
+
+/// Tracks plan entries across successive plan updates
+///
+/// Entries are matched like in [`plan_diff`]: by ID, or by content when the
+/// update has no entry ID. Matched entries are updated in place, other
+/// entries are appended. Entries keep the order in which they were first seen.
+#[derive(Debug, Clone, Default)]
+pub struct PlanTracker {
+    entries: Vec<PlanEntry>,
+}
+
+impl PlanTracker {
+    /// Create an empty plan tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge a plan update into the tracked entries
+    ///
+    /// An entry without an ID keeps the ID of the tracked entry it matches.
+    ///
+    /// # Arguments
+    /// * `entries` - The entries of a `Message::Plan` update
+    pub fn update(&mut self, entries: Vec<PlanEntry>) {
+        for mut entry in entries {
+            let existing = match &entry.id {
+                Some(id) => self.entries.iter_mut().find(|e| e.id.as_ref() == Some(id)),
+                None => self.entries.iter_mut().find(|e| e.content == entry.content),
+            };
+            match existing {
+                Some(existing) => {
+                    if entry.id.is_none() {
+                        entry.id = existing.id.take();
+                    }
+                    *existing = entry;
+                }
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    /// Get a tracked entry by ID
+    ///
+    /// # Arguments
+    /// * `id` - The ID of the entry
+    ///
+    /// # Returns
+    /// `Some(&PlanEntry)` if an entry with that ID was seen, `None` otherwise
+    pub fn get(&self, id: &str) -> Option<&PlanEntry> {
+        self.entries.iter().find(|e| e.id.as_deref() == Some(id))
+    }
+
+    /// Get all tracked entries in the order they were first seen
+    pub fn entries(&self) -> Vec<&PlanEntry> {
+        self.entries.iter().collect()
+    }
+}
+
+/// Changes between two successive plans
+#[derive(Debug, Clone, Default)]
+pub struct PlanDiff {
+    /// Entries that were not in the previous plan
+    pub added: Vec<PlanEntry>,
+    /// Entries of the previous plan that are now completed
+    pub completed: Vec<PlanEntry>,
+    /// Entries of the previous plan whose status changed, with their previous status
+    pub status_changed: Vec<(PlanEntry, PlanStatus)>,
+    /// Entries of the previous plan whose priority changed, with their previous priority
+    pub priority_changed: Vec<(PlanEntry, PlanPriority)>,
+}
+
+impl PlanDiff {
+    /// Check whether the plan changed
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty()
+            || !self.completed.is_empty()
+            || !self.status_changed.is_empty()
+            || !self.priority_changed.is_empty()
+    }
+}
+
+/// Compute the changes from one plan to the next
+///
+/// Entries are matched by ID, or by content when the previous plan has no
+/// entry with the same ID (iFlow does not always send stable IDs).
+///
+/// # Arguments
+/// * `prev` - The previous plan
+/// * `curr` - The current plan
+///
+/// # Returns
+/// The entries added and the status and priority changes of existing entries
+pub fn plan_diff(prev: &[PlanEntry], curr: &[PlanEntry]) -> PlanDiff {
+    let mut diff = PlanDiff::default();
+
+    for entry in curr {
+        let previous = prev
+            .iter()
+            .find(|p| p.id.is_some() && p.id == entry.id)
+            .or_else(|| prev.iter().find(|p| p.content == entry.content));
+
+        let Some(previous) = previous else {
+            diff.added.push(entry.clone());
+            continue;
+        };
+
+        if previous.status != entry.status {
+            if entry.status == PlanStatus::Completed {
+                diff.completed.push(entry.clone());
+            }
+            diff.status_changed
+                .push((entry.clone(), previous.status.clone()));
+        }
+        if previous.priority != entry.priority {
+            diff.priority_changed
+                .push((entry.clone(), previous.priority.clone()));
+        }
+    }
+
+    diff
+}
@@ -0,0 +1,100 @@
+//! Connection prechecks for iFlow
+//!
+//! Prechecks verify the prerequisites of a connection, such as required
+//! binaries and directories, before `IFlowClient::connect()` starts iFlow.
+//! They turn a missing prerequisite into a clear error up front instead of
+//! a failure halfway through the connection.
+
+use crate::error::{IFlowError, Result};
+use crate::types::IFlowOptions;
+use agent_client_protocol::McpServer;
+use std::path::{Path, PathBuf};
+
+/// Verifies a prerequisite of a connection
+#[async_trait::async_trait]
+pub trait ConnectionPrecheck: Send + Sync {
+    /// Run the check
+    ///
+    /// # Arguments
+    /// * `options` - The options the client is about to connect with
+    ///
+    /// # Returns
+    /// * `Ok(())` if the prerequisite is met
+    /// * `Err(IFlowError)` describing what is missing otherwise
+    async fn run(&self, options: &IFlowOptions) -> Result<()>;
+}
+
+/// Precheck that a binary can be found in `PATH`, like `which`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryInPathPrecheck(pub String);
+
+#[async_trait::async_trait]
+impl ConnectionPrecheck for BinaryInPathPrecheck {
+    async fn run(&self, _options: &IFlowOptions) -> Result<()> {
+        if find_in_path(Path::new(&self.0)).is_some() {
+            Ok(())
+        } else {
+            Err(IFlowError::Config(format!(
+                "{} was not found in PATH",
+                self.0
+            )))
+        }
+    }
+}
+
+/// Precheck that a directory exists and can be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryExistsPrecheck(pub PathBuf);
+
+#[async_trait::async_trait]
+impl ConnectionPrecheck for DirectoryExistsPrecheck {
+    async fn run(&self, _options: &IFlowOptions) -> Result<()> {
+        std::fs::read_dir(&self.0).map_err(|e| IFlowError::from_file_io(e, &self.0))?;
+        Ok(())
+    }
+}
+
+/// Precheck that the command of every configured stdio MCP server exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct McpServerBinaryPrecheck;
+
+#[async_trait::async_trait]
+impl ConnectionPrecheck for McpServerBinaryPrecheck {
+    async fn run(&self, options: &IFlowOptions) -> Result<()> {
+        for server in &options.mcp_servers {
+            if let McpServer::Stdio { name, command, .. } = server
+                && find_in_path(command).is_none()
+            {
+                return Err(IFlowError::Config(format!(
+                    "Command {} of MCP server {} was not found",
+                    command.display(),
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a command the way a shell would
+///
+/// Commands containing a path separator are checked as they are; other
+/// commands are looked up in each directory of `PATH`.
+///
+/// # Returns
+/// The path of the binary, or `None` if it was not found
+fn find_in_path(command: &Path) -> Option<PathBuf> {
+    if command.components().count() > 1 {
+        return command.is_file().then(|| command.to_path_buf());
+    }
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(command);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let executable = candidate.with_extension(std::env::consts::EXE_EXTENSION);
+        executable.is_file().then_some(executable)
+    })
+}
@@ -4,11 +4,19 @@
 //! including starting, stopping, and managing stdio communication.
 
 use crate::error::{IFlowError, Result};
+use crate::types::ProcessHook;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Child;
 use tokio::time::sleep;
 
+/// Maximum number of stderr lines kept by the process manager
+pub const STDERR_BUFFER_LINES: usize = 1000;
+
 /// Manages iFlow CLI process lifecycle
 ///
 /// Handles starting and stopping the iFlow CLI process, as well as
@@ -18,6 +26,13 @@ pub struct IFlowProcessManager {
     start_port: u16,
     port: Option<u16>,
     debug: bool,
+    capture_stderr: bool,
+    startup_timeout: Duration,
+    port_scan_range: u16,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+    pre_start_hook: Option<Arc<dyn ProcessHook>>,
+    post_stop_hook: Option<Arc<dyn ProcessHook>>,
+    pid_file: Option<PathBuf>,
 }
 
 impl IFlowProcessManager {
@@ -35,9 +50,115 @@ impl IFlowProcessManager {
             start_port,
             port: None,
             debug,
+            capture_stderr: false,
+            startup_timeout: Duration::from_secs(60),
+            port_scan_range: 100,
+            stderr: Arc::new(Mutex::new(VecDeque::new())),
+            pre_start_hook: None,
+            post_stop_hook: None,
+            pid_file: None,
         }
     }
 
+    /// Set how long to wait for the WebSocket server to start listening
+    ///
+    /// # Arguments
+    /// * `timeout` - The maximum time to wait after spawning the process
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Set how many ports to try when looking for a free port
+    ///
+    /// # Arguments
+    /// * `range` - The number of ports to try, starting at `start_port` (at least 1)
+    pub fn with_port_scan_range(mut self, range: u16) -> Self {
+        self.port_scan_range = range.max(1);
+        self
+    }
+
+    /// Set a hook to run before the process is spawned
+    ///
+    /// # Arguments
+    /// * `hook` - The hook; an error from it aborts `start()`
+    pub fn with_pre_start_hook(mut self, hook: Arc<dyn ProcessHook>) -> Self {
+        self.pre_start_hook = Some(hook);
+        self
+    }
+
+    /// Set a hook to run after the process has exited
+    ///
+    /// # Arguments
+    /// * `hook` - The hook; errors from it are logged and ignored
+    pub fn with_post_stop_hook(mut self, hook: Arc<dyn ProcessHook>) -> Self {
+        self.post_stop_hook = Some(hook);
+        self
+    }
+
+    /// Capture the process's stderr in WebSocket mode
+    ///
+    /// In stdio mode stderr is always captured. In WebSocket mode it is
+    /// inherited from the parent unless capture or debug mode is enabled.
+    ///
+    /// # Arguments
+    /// * `capture` - Whether to capture stderr
+    pub fn with_stderr_capture(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
+    }
+
+    /// Read lines from `reader` into the stderr ring buffer
+    ///
+    /// Spawns a background task that stops when the reader reaches EOF. Only
+    /// the last `STDERR_BUFFER_LINES` lines are kept. In debug mode each line
+    /// is also logged at `DEBUG` level.
+    ///
+    /// # Arguments
+    /// * `reader` - The stream to read, usually the process's stderr
+    pub fn capture_stderr<R>(&self, reader: R)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let buffer = self.stderr.clone();
+        let debug = self.debug;
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if debug {
+                    tracing::debug!("iflow stderr: {}", line);
+                }
+                let Ok(mut buffer) = buffer.lock() else {
+                    break;
+                };
+                if buffer.len() == STDERR_BUFFER_LINES {
+                    buffer.pop_front();
+                }
+                buffer.push_back(line);
+            }
+        });
+    }
+
+    /// Get the most recent lines the process wrote to stderr
+    ///
+    /// # Arguments
+    /// * `n` - The maximum number of lines to return
+    ///
+    /// # Returns
+    /// Up to `n` lines, oldest first
+    pub fn recent_stderr(&self, n: usize) -> Vec<String> {
+        self.stderr
+            .lock()
+            .map(|buffer| {
+                buffer
+                    .iter()
+                    .skip(buffer.len().saturating_sub(n))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Check if a port is available for use
     ///
     /// # Arguments
@@ -67,6 +188,123 @@ impl IFlowProcessManager {
         .is_ok()
     }
 
+    /// Check whether a port can be used by iFlow
+    ///
+    /// A free port is available. A busy port is probed with a WebSocket
+    /// handshake; if the server answers with `101 Switching Protocols` and
+    /// sends iFlow's `//ready` signal within 2 seconds, the port is taken by
+    /// iFlow. Anything else on the port is reported as a conflict.
+    ///
+    /// # Arguments
+    /// * `port` - Port number to check
+    ///
+    /// # Returns
+    /// * `Ok(true)` if the port is free
+    /// * `Ok(false)` if the port is used by iFlow
+    /// * `Err(IFlowError::ProcessManager)` if the port is used by another service
+    pub async fn port_is_available_for_iflow(port: u16) -> Result<bool> {
+        use futures::StreamExt;
+        use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+        if Self::is_port_available(port) {
+            return Ok(true);
+        }
+
+        let url = format!("ws://127.0.0.1:{}/acp?peer=iflow", port);
+        let probe = async {
+            let (mut ws, _) = connect_async(&url)
+                .await
+                .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
+            while let Some(frame) = ws.next().await {
+                match frame {
+                    Ok(Message::Text(text)) if text.trim() == "//ready" => return Ok(()),
+                    Ok(Message::Text(_)) | Ok(Message::Binary(_)) => {
+                        return Err("server did not send the iFlow ready signal".to_string());
+                    }
+                    Ok(_) => {}
+                    Err(e) => return Err(format!("WebSocket error: {}", e)),
+                }
+            }
+            Err("connection closed before the iFlow ready signal".to_string())
+        };
+
+        match tokio::time::timeout(Duration::from_secs(2), probe).await {
+            Ok(Ok(())) => Ok(false),
+            Ok(Err(reason)) => Err(IFlowError::ProcessManager(format!(
+                "Port {} is in use by another service: {}",
+                port, reason
+            ))),
+            Err(_) => Err(IFlowError::ProcessManager(format!(
+                "Port {} is in use by another service: no iFlow ready signal within 2s",
+                port
+            ))),
+        }
+    }
+
+    /// Find the PID of an iFlow process that is listening on a port
+    ///
+    /// The port must answer like iFlow, see
+    /// [`IFlowProcessManager::port_is_available_for_iflow`], so another
+    /// service on the port is never mistaken for iFlow. The listening process
+    /// itself is not resolved, so the first process named `iflow` is returned.
+    ///
+    /// # Arguments
+    /// * `port` - Port number to check
+    ///
+    /// # Returns
+    /// `Some(pid)` if iFlow answers on the port and an iFlow process is running, `None` otherwise
+    pub async fn find_existing_process(port: u16) -> Option<u32> {
+        if !matches!(Self::port_is_available_for_iflow(port).await, Ok(false)) {
+            return None;
+        }
+
+        Self::find_existing_process_in(port, || {
+            let mut system = sysinfo::System::new();
+            system.refresh_processes_specifics(
+                sysinfo::ProcessesToUpdate::All,
+                true,
+                sysinfo::ProcessRefreshKind::nothing(),
+            );
+            system
+                .processes()
+                .iter()
+                .map(|(pid, process)| (pid.as_u32(), process.name().to_string_lossy().into_owned()))
+                .collect()
+        })
+    }
+
+    /// Find the PID of an iFlow process using the given process listing
+    ///
+    /// Unlike [`IFlowProcessManager::find_existing_process`], the port is
+    /// only checked for a listener, not probed for iFlow. `list_processes`
+    /// is only called when the port is busy.
+    ///
+    /// # Arguments
+    /// * `port` - Port number to check
+    /// * `list_processes` - Returns the running processes as `(pid, name)` pairs
+    ///
+    /// # Returns
+    /// `Some(pid)` if the port is busy and an iFlow process is listed, `None` otherwise
+    pub fn find_existing_process_in<F>(port: u16, list_processes: F) -> Option<u32>
+    where
+        F: FnOnce() -> Vec<(u32, String)>,
+    {
+        if !Self::is_port_listening(port) {
+            return None;
+        }
+
+        list_processes()
+            .into_iter()
+            .filter(|(_, name)| {
+                let stem = std::path::Path::new(name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_lowercase());
+                stem.as_deref() == Some("iflow")
+            })
+            .map(|(pid, _)| pid)
+            .min()
+    }
+
     /// Find an available port starting from the given port
     ///
     /// # Arguments
@@ -80,7 +318,9 @@ impl IFlowProcessManager {
     /// Returns an error if no available port is found
     fn find_available_port(start_port: u16, max_attempts: u16) -> Result<u16> {
         for i in 0..max_attempts {
-            let port = start_port + i;
+            let Some(port) = start_port.checked_add(i) else {
+                break;
+            };
             if Self::is_port_available(port) {
                 tracing::debug!("Found available port: {}", port);
                 return Ok(port);
@@ -90,7 +330,7 @@ impl IFlowProcessManager {
         Err(IFlowError::ProcessManager(format!(
             "No available port found in range {}-{}",
             start_port,
-            start_port + max_attempts
+            start_port.saturating_add(max_attempts.saturating_sub(1))
         )))
     }
 
@@ -106,9 +346,13 @@ impl IFlowProcessManager {
             tracing::debug!("Starting iFlow process with experimental ACP and WebSocket support");
 
             // Find an available port
-            let port = Self::find_available_port(self.start_port, 100)?;
+            let port = Self::find_available_port(self.start_port, self.port_scan_range)?;
             self.port = Some(port);
 
+            if let Some(hook) = &self.pre_start_hook {
+                hook.run(port).await?;
+            }
+
             // Start iFlow process with WebSocket support
             let mut cmd = tokio::process::Command::new("iflow");
             cmd.arg("--experimental-acp");
@@ -122,47 +366,48 @@ impl IFlowProcessManager {
 
             // In WebSocket mode, set stdout/stderr to inherit to avoid blocking/exit when pipes are not consumed
             cmd.stdout(Stdio::inherit());
-            cmd.stderr(Stdio::inherit());
+            if self.capture_stderr || self.debug {
+                cmd.stderr(Stdio::piped());
+            } else {
+                cmd.stderr(Stdio::inherit());
+            }
             cmd.stdin(Stdio::null()); // No stdin needed for WebSocket
 
-            let child = cmd
+            let mut child = cmd
                 .spawn()
                 .map_err(|e| IFlowError::ProcessManager(format!("Failed to start iflow: {}", e)))?;
 
+            if let Some(stderr) = child.stderr.take() {
+                self.capture_stderr(stderr);
+            }
             self.process = Some(child);
 
-            // Wait longer for process to start and WebSocket server to be ready
+            // Wait for the WebSocket server to be ready
             tracing::debug!("Waiting for iFlow process to start...");
-            sleep(Duration::from_secs(8)).await;
-
-            // Verify the port is actually listening with more retries and longer timeout
+            let started = tokio::time::Instant::now();
             let mut attempts = 0;
-            let max_attempts = 30; // 30 attempts * 1 second = 30 seconds total
 
-            while attempts < max_attempts {
-                if Self::is_port_listening(port) {
-                    tracing::debug!("iFlow WebSocket server is ready on port {}", port);
-                    break;
+            while !Self::is_port_listening(port) {
+                let elapsed = started.elapsed();
+                if elapsed >= self.startup_timeout {
+                    return Err(IFlowError::ProcessManager(format!(
+                        "iFlow process failed to start WebSocket server on port {} within {:?}",
+                        port, self.startup_timeout
+                    )));
                 }
 
                 attempts += 1;
-                if attempts % 5 == 0 {
+                if attempts % 10 == 0 {
                     tracing::debug!(
-                        "Still waiting for iFlow to be ready... (attempt {}/{})",
-                        attempts,
-                        max_attempts
+                        "Still waiting for iFlow to be ready... ({:?}/{:?})",
+                        elapsed,
+                        self.startup_timeout
                     );
                 }
 
-                sleep(Duration::from_secs(1)).await;
-            }
-
-            if attempts >= max_attempts {
-                return Err(IFlowError::ProcessManager(format!(
-                    "iFlow process failed to start WebSocket server on port {} after {} seconds",
-                    port, max_attempts
-                )));
+                sleep((self.startup_timeout - elapsed).min(Duration::from_millis(500))).await;
             }
+            tracing::debug!("iFlow WebSocket server is ready on port {}", port);
 
             tracing::debug!(
                 "iFlow process started with WebSocket support on port {}",
@@ -174,6 +419,10 @@ impl IFlowProcessManager {
         } else {
             tracing::debug!("Starting iFlow process with experimental ACP and stdio support");
 
+            if let Some(hook) = &self.pre_start_hook {
+                hook.run(0).await?;
+            }
+
             // Start iFlow process with stdio support
             let mut cmd = tokio::process::Command::new("iflow");
             cmd.arg("--experimental-acp");
@@ -189,10 +438,14 @@ impl IFlowProcessManager {
 
             tracing::debug!("Starting iFlow process with command: {:?}", cmd);
 
-            let child = cmd
+            let mut child = cmd
                 .spawn()
                 .map_err(|e| IFlowError::ProcessManager(format!("Failed to start iflow: {}", e)))?;
 
+            // Drain stderr so the process never blocks on a full pipe
+            if let Some(stderr) = child.stderr.take() {
+                self.capture_stderr(stderr);
+            }
             self.process = Some(child);
 
             // Wait for process to start
@@ -248,11 +501,23 @@ impl IFlowProcessManager {
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
             tracing::debug!("iFlow process stopped");
+
+            if let Some(hook) = &self.post_stop_hook
+                && let Err(e) = hook.run(self.port.unwrap_or(0)).await
+            {
+                tracing::warn!("Post-stop hook failed: {}", e);
+            }
         }
 
         // Clear the port when stopping
         self.port = None;
 
+        if let Some(path) = self.pid_file.take()
+            && let Err(e) = std::fs::remove_file(&path)
+        {
+            tracing::warn!("Failed to remove PID file {}: {}", path.display(), e);
+        }
+
         Ok(())
     }
 
@@ -264,6 +529,52 @@ impl IFlowProcessManager {
         self.port
     }
 
+    /// Get the process ID of the iFlow process
+    ///
+    /// # Returns
+    /// The PID, or None if not running
+    pub fn get_pid(&self) -> Option<u32> {
+        self.process.as_ref().and_then(|p| p.id())
+    }
+
+    /// Write the process ID of the iFlow process to a file
+    ///
+    /// The file is removed again by `stop()`.
+    ///
+    /// # Arguments
+    /// * `path` - The file to write the PID to
+    ///
+    /// # Returns
+    /// * `Ok(())` if the PID file was written
+    /// * `Err(IFlowError::ProcessManager)` if the process is not running
+    /// * `Err(IFlowError)` if writing the file failed
+    pub fn write_pid_file(&mut self, path: &Path) -> Result<()> {
+        let pid = self.get_pid().ok_or_else(|| {
+            IFlowError::ProcessManager("iFlow process is not running".to_string())
+        })?;
+        std::fs::write(path, format!("{}\n", pid))
+            .map_err(|e| IFlowError::from_file_io(e, path))?;
+        self.pid_file = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Read a process ID from a file written by `write_pid_file()`
+    ///
+    /// # Arguments
+    /// * `path` - The PID file
+    ///
+    /// # Returns
+    /// * `Ok(u32)` containing the PID
+    /// * `Err(IFlowError::ProcessManager)` if the file does not contain a PID
+    /// * `Err(IFlowError)` if reading the file failed
+    pub fn read_pid_file(path: &Path) -> Result<u32> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| IFlowError::from_file_io(e, path))?;
+        contents.trim().parse().map_err(|e| {
+            IFlowError::ProcessManager(format!("Invalid PID file {}: {}", path.display(), e))
+        })
+    }
+
     /// Check if the iFlow process is running
     ///
     /// # Returns
@@ -272,6 +583,16 @@ impl IFlowProcessManager {
         self.process.is_some()
     }
 
+    /// Check if the iFlow process has exited on its own
+    ///
+    /// # Returns
+    /// `true` if the process was started and has since exited, `false` otherwise
+    pub fn has_exited(&mut self) -> bool {
+        self.process
+            .as_mut()
+            .is_some_and(|p| matches!(p.try_wait(), Ok(Some(_))))
+    }
+
     /// Take ownership of the process's stdin
     ///
     /// Takes ownership of the process's stdin stream for communication.
@@ -1,7 +1,9 @@
-use crate::client::IFlowClient;
-use crate::error::Result;
-use crate::types::{IFlowOptions, Message};
+use crate::client::{IFlowClient, MessageStream};
+use crate::error::{IFlowError, Result};
+use crate::types::{AgentEvent, IFlowOptions, Message, ResponseFormat};
 use futures::stream::StreamExt;
+use serde::de::DeserializeOwned;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -28,6 +30,7 @@ use tokio::time::timeout;
 ///     Ok(())
 /// }
 /// ```
+#[allow(deprecated)]
 pub async fn query(prompt: &str) -> Result<String> {
     let default_timeout = IFlowOptions::default().timeout;
     query_with_timeout(prompt, default_timeout).await
@@ -58,6 +61,7 @@ pub async fn query(prompt: &str) -> Result<String> {
 ///     Ok(())
 /// }
 /// ```
+#[allow(deprecated)]
 pub async fn query_with_config(prompt: &str, options: IFlowOptions) -> Result<String> {
     // Apply timeout to the entire operation
     let timeout_secs = options.timeout;
@@ -75,51 +79,17 @@ pub async fn query_with_config(prompt: &str, options: IFlowOptions) -> Result<St
                 client.connect().await?;
                 tracing::debug!("Connected to iFlow");
 
-                tracing::debug!("Sending message: {}", prompt);
-                client.send_message(prompt, None).await?;
-                tracing::debug!("Message sent");
-
-                let mut response = String::new();
                 let mut message_stream = client.messages();
-
-                // First wait for the send_message to complete by receiving the TaskFinish message
-                // The send_message function sends a TaskFinish message when the prompt is complete
-                let mut prompt_finished = false;
-                while !prompt_finished {
-                    match timeout(
-                        Duration::from_secs_f64(message_timeout_secs),
-                        message_stream.next(),
-                    )
-                    .await
-                    {
-                        Ok(Some(message)) => {
-                            tracing::debug!("Received message: {:?}", message);
-                            match message {
-                                Message::Assistant { content } => {
-                                    response.push_str(&content);
-                                }
-                                Message::TaskFinish { .. } => {
-                                    prompt_finished = true;
-                                }
-                                _ => {}
-                            }
-                        }
-                        Ok(None) => {
-                            // Stream ended
-                            tracing::debug!("Message stream ended");
-                            prompt_finished = true;
-                        }
-                        Err(_) => {
-                            // Timeout on individual message - this is expected during normal operation
-                            // Continue the loop to check if we should still wait
-                            // The outer timeout will catch if we've exceeded the total time
-                        }
-                    }
-                }
-                tracing::debug!("Query completed, response length: {}", response.len());
+                let response = send_and_collect(
+                    &mut client,
+                    &mut message_stream,
+                    prompt,
+                    message_timeout_secs,
+                )
+                .await;
 
                 client.disconnect().await?;
-                Ok(response.trim().to_string())
+                response
             })
             .await
     })
@@ -132,6 +102,184 @@ pub async fn query_with_config(prompt: &str, options: IFlowOptions) -> Result<St
     }
 }
 
+/// Send a prompt and collect the full response
+///
+/// # Arguments
+/// * `client` - A connected client
+/// * `message_stream` - The message stream of `client`
+/// * `prompt` - The prompt to send
+/// * `message_timeout_secs` - How long to wait for each message before checking again
+///
+/// # Returns
+/// * `Ok(String)` containing the trimmed response
+/// * `Err(IFlowError)` if sending failed or the prompt was cancelled
+async fn send_and_collect(
+    client: &mut IFlowClient,
+    message_stream: &mut MessageStream,
+    prompt: &str,
+    message_timeout_secs: f64,
+) -> Result<String> {
+    tracing::debug!("Sending message: {}", prompt);
+    client.send_message(prompt, None).await?;
+    tracing::debug!("Message sent");
+
+    let mut response = String::new();
+    let mut cancelled = None;
+
+    // First wait for the send_message to complete by receiving the TaskFinish message
+    // The send_message function sends a TaskFinish message when the prompt is complete
+    let mut prompt_finished = false;
+    while !prompt_finished {
+        match timeout(
+            Duration::from_secs_f64(message_timeout_secs),
+            message_stream.next(),
+        )
+        .await
+        {
+            Ok(Some(message)) => {
+                tracing::debug!("Received message: {:?}", message);
+                match message {
+                    Message::Assistant { content } => {
+                        response.push_str(&content);
+                    }
+                    Message::TaskFinish { reason } => {
+                        cancelled = IFlowError::from_stop_reason(reason.as_deref());
+                        prompt_finished = true;
+                    }
+                    _ => {}
+                }
+            }
+            Ok(None) => {
+                // Stream ended
+                tracing::debug!("Message stream ended");
+                prompt_finished = true;
+            }
+            Err(_) => {
+                // Timeout on individual message - this is expected during normal operation
+                // Continue the loop to check if we should still wait
+                // The outer timeout will catch if we've exceeded the total time
+            }
+        }
+    }
+    tracing::debug!("Query completed, response length: {}", response.len());
+
+    if let Some(err) = cancelled {
+        return Err(err);
+    }
+    Ok(response.trim().to_string())
+}
+
+/// Send several prompts to one iFlow session
+///
+/// Uses a single connection and session for all prompts, waiting for the
+/// full response to each prompt before sending the next.
+///
+/// # Arguments
+/// * `prompts` - The prompts to send, in order
+///
+/// # Returns
+/// * `Ok(Vec<String>)` containing the response to each prompt
+/// * `Err(IFlowError::BatchFailed)` with the responses received before a prompt failed
+/// * `Err(IFlowError)` if connecting failed
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::query_batch;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responses = query_batch(&["Pick a number", "Double it"]).await?;
+///     println!("{:?}", responses);
+///     Ok(())
+/// }
+/// ```
+pub async fn query_batch(prompts: &[&str]) -> Result<Vec<String>> {
+    query_batch_with_config(prompts, IFlowOptions::default()).await
+}
+
+/// Send several prompts to one iFlow session with custom options
+///
+/// Uses a single connection and session for all prompts, waiting for the
+/// full response to each prompt before sending the next. The timeout of
+/// the options applies to each prompt.
+///
+/// # Arguments
+/// * `prompts` - The prompts to send, in order
+/// * `options` - Configuration options for the connection
+///
+/// # Returns
+/// * `Ok(Vec<String>)` containing the response to each prompt, empty without prompts
+/// * `Err(IFlowError::BatchFailed)` with the responses received before a prompt failed
+/// * `Err(IFlowError)` if connecting failed
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::{query_batch_with_config, IFlowOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let options = IFlowOptions::new().with_timeout(120.0);
+///     let responses = query_batch_with_config(&["Pick a number", "Double it"], options).await?;
+///     println!("{:?}", responses);
+///     Ok(())
+/// }
+/// ```
+#[allow(deprecated)]
+pub async fn query_batch_with_config(
+    prompts: &[&str],
+    options: IFlowOptions,
+) -> Result<Vec<String>> {
+    if prompts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let timeout_secs = options.timeout;
+    let message_timeout_secs = (timeout_secs / 10.0).clamp(0.1, 1.0);
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let mut client = IFlowClient::new(Some(options));
+            client.connect().await?;
+            let mut message_stream = client.messages();
+
+            let mut completed = Vec::with_capacity(prompts.len());
+            for prompt in prompts {
+                let response = timeout(
+                    Duration::from_secs_f64(timeout_secs),
+                    send_and_collect(
+                        &mut client,
+                        &mut message_stream,
+                        prompt,
+                        message_timeout_secs,
+                    ),
+                )
+                .await
+                .unwrap_or_else(|_| Err(IFlowError::Timeout("Operation timed out".to_string())));
+
+                match response {
+                    Ok(response) => completed.push(response),
+                    Err(e) => {
+                        if let Err(disconnect_err) = client.disconnect().await {
+                            tracing::debug!(
+                                "Failed to disconnect after batch error: {}",
+                                disconnect_err
+                            );
+                        }
+                        return Err(IFlowError::BatchFailed {
+                            completed,
+                            source: Box::new(e),
+                        });
+                    }
+                }
+            }
+
+            client.disconnect().await?;
+            Ok(completed)
+        })
+        .await
+}
+
 /// Simple synchronous query to iFlow with custom timeout
 ///
 /// Sends a query to iFlow and waits for a complete response.
@@ -427,3 +575,518 @@ pub async fn query_stream_with_timeout(
         })
         .await
 }
+
+/// Query iFlow and deserialize the JSON response
+///
+/// Sends a query to iFlow, collects all response chunks into a single buffer
+/// and deserializes it into `T`. The prompt should ask iFlow to answer with
+/// JSON only.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::query_stream_parsed;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Answer {
+///     result: i64,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let answer: Answer =
+///         query_stream_parsed(r#"What is 2 + 2? Reply only with {"result": <number>}"#).await?;
+///     println!("{}", answer.result);
+///     Ok(())
+/// }
+/// ```
+pub async fn query_stream_parsed<T: DeserializeOwned>(prompt: &str) -> Result<T> {
+    let response = query(prompt).await?;
+    serde_json::from_str(&response).map_err(IFlowError::Deserialization)
+}
+
+/// Query iFlow with custom options and deserialize the JSON response
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_stream_parsed_with_config<T: DeserializeOwned>(
+    prompt: &str,
+    options: IFlowOptions,
+) -> Result<T> {
+    let response = query_with_config(prompt, options).await?;
+    serde_json::from_str(&response).map_err(IFlowError::Deserialization)
+}
+
+/// Query iFlow and parse the response with `FromStr`
+///
+/// Surrounding whitespace is trimmed before parsing, so this suits simple
+/// answers such as numbers or booleans.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(T)` containing the parsed response
+/// * `Err(IFlowError::Deserialization)` if the response cannot be parsed as `T`
+/// * `Err(IFlowError)` if there was any other error
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::query_and_parse;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let answer: i32 = query_and_parse("What is 2 + 2? Reply only with the number").await?;
+///     println!("{}", answer);
+///     Ok(())
+/// }
+/// ```
+pub async fn query_and_parse<T>(prompt: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let response = query(prompt).await?;
+    parse_response(&response)
+}
+
+/// Query iFlow with custom options and parse the response with `FromStr`
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(T)` containing the parsed response
+/// * `Err(IFlowError::Deserialization)` if the response cannot be parsed as `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_and_parse_with_config<T>(prompt: &str, options: IFlowOptions) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let response = query_with_config(prompt, options).await?;
+    parse_response(&response)
+}
+
+/// Query iFlow and deserialize the JSON response
+///
+/// Equivalent to [`query_stream_parsed`].
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_and_parse_json<T: DeserializeOwned>(prompt: &str) -> Result<T> {
+    query_stream_parsed(prompt).await
+}
+
+/// Query iFlow with custom options and deserialize the JSON response
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_and_parse_json_with_config<T: DeserializeOwned>(
+    prompt: &str,
+    options: IFlowOptions,
+) -> Result<T> {
+    query_stream_parsed_with_config(prompt, options).await
+}
+
+/// Ask iFlow for a JSON response and deserialize it
+///
+/// Like [`query_and_parse_json`], but also sets the preferred response
+/// format to JSON, so iFlow is instructed to reply with a JSON object.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_as_json<T: DeserializeOwned>(prompt: &str) -> Result<T> {
+    let options = IFlowOptions::new().with_process_config(
+        crate::types::ProcessConfig::new()
+            .enable_auto_start()
+            .stdio_mode(),
+    );
+    query_as_json_with_config(prompt, options).await
+}
+
+/// Ask iFlow for a JSON response with custom options and deserialize it
+///
+/// A JSON response format already set in `options`, e.g. with a schema
+/// hint, is kept; any other format is replaced by JSON.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_as_json_with_config<T: DeserializeOwned>(
+    prompt: &str,
+    mut options: IFlowOptions,
+) -> Result<T> {
+    if !matches!(
+        options.preferred_response_format,
+        ResponseFormat::Json { .. }
+    ) {
+        options.preferred_response_format = ResponseFormat::Json { schema_hint: None };
+    }
+    query_and_parse_json_with_config(prompt, options).await
+}
+
+/// Query iFlow for JSON matching a schema and deserialize the response
+///
+/// The schema hint is appended to the prompt to ask iFlow for JSON in the
+/// expected shape.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `schema_hint` - A description of the expected JSON, e.g. a JSON Schema
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_and_parse_with_schema<T: DeserializeOwned>(
+    prompt: &str,
+    schema_hint: &str,
+) -> Result<T> {
+    query_and_parse_json(&schema_prompt(prompt, schema_hint)).await
+}
+
+/// Query iFlow with custom options for JSON matching a schema
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `schema_hint` - A description of the expected JSON, e.g. a JSON Schema
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(T)` containing the deserialized response
+/// * `Err(IFlowError::Deserialization)` if the response is not valid JSON for `T`
+/// * `Err(IFlowError)` if there was any other error
+pub async fn query_and_parse_with_schema_with_config<T: DeserializeOwned>(
+    prompt: &str,
+    schema_hint: &str,
+    options: IFlowOptions,
+) -> Result<T> {
+    query_and_parse_json_with_config(&schema_prompt(prompt, schema_hint), options).await
+}
+
+/// Append the schema instruction used by [`query_and_parse_with_schema`]
+fn schema_prompt(prompt: &str, schema_hint: &str) -> String {
+    format!(
+        "{}\nRespond with valid JSON matching this schema: {}",
+        prompt, schema_hint
+    )
+}
+
+/// Parse a trimmed response with `FromStr`
+fn parse_response<T>(response: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let trimmed = response.trim();
+    trimmed.parse().map_err(|e: T::Err| {
+        IFlowError::Deserialization(serde::de::Error::custom(format!(
+            "cannot parse {:?}: {}",
+            trimmed, e
+        )))
+    })
+}
+
+/// Stream response chunks and tool call events from iFlow
+///
+/// Like [`query_stream`], but yields every event of the response: text
+/// chunks, tool calls and plan updates, ending with `AgentEvent::Done`. The
+/// client is disconnected once the response is complete.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(impl Stream<Item = AgentEvent>)` yielding the events of the response
+/// * `Err(IFlowError)` if the query could not be sent
+pub async fn query_stream_with_tools(
+    prompt: &str,
+) -> Result<impl futures::Stream<Item = AgentEvent>> {
+    let options = IFlowOptions::new().with_process_config(
+        crate::types::ProcessConfig::new()
+            .enable_auto_start()
+            .stdio_mode(),
+    );
+    query_stream_with_tools_with_config(prompt, options).await
+}
+
+/// Stream response chunks and tool call events from iFlow with custom options
+///
+/// See [`query_stream_with_tools`].
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(impl Stream<Item = AgentEvent>)` yielding the events of the response
+/// * `Err(IFlowError)` if the query could not be sent
+pub async fn query_stream_with_tools_with_config(
+    prompt: &str,
+    options: IFlowOptions,
+) -> Result<impl futures::Stream<Item = AgentEvent>> {
+    let setup = async {
+        let mut client = IFlowClient::new(Some(options));
+        client.connect().await?;
+        client.send_message(prompt, None).await?;
+        let messages = client.messages();
+        Ok(Some((client, messages)))
+    };
+
+    local_stream(setup, |state| async move {
+        let (mut client, mut messages) = state?;
+        while let Some(message) = messages.next().await {
+            match AgentEvent::from_message(message) {
+                Some(event @ AgentEvent::Done(_)) => {
+                    let _ = client.disconnect().await;
+                    return Some((event, None));
+                }
+                Some(event) => return Some((event, Some((client, messages)))),
+                None => {}
+            }
+        }
+        let _ = client.disconnect().await;
+        None
+    })
+    .await
+}
+
+/// Build a stream whose client runs on a `LocalSet` owned by the stream
+///
+/// The stdio client spawns its tasks with `spawn_local`, so `setup` and
+/// every `step` run inside the `LocalSet`, which lives as long as the
+/// stream. Callers do not need a `LocalSet` of their own.
+///
+/// # Arguments
+/// * `setup` - Connects the client and produces the initial state
+/// * `step` - Produces the next item and state, like `futures::stream::unfold`
+///
+/// # Returns
+/// * `Ok(impl Stream<Item = T>)` yielding the items produced by `step`
+/// * `Err(IFlowError)` if `setup` failed
+async fn local_stream<S, T, F, Fut>(
+    setup: impl Future<Output = Result<S>>,
+    step: F,
+) -> Result<impl futures::Stream<Item = T>>
+where
+    F: FnMut(S) -> Fut,
+    Fut: Future<Output = Option<(T, S)>>,
+{
+    let local = tokio::task::LocalSet::new();
+    let state = local.run_until(setup).await?;
+
+    Ok(futures::stream::unfold(
+        (local, state, step),
+        |(local, state, mut step)| async move {
+            let (item, state) = local.run_until(step(state)).await?;
+            Some((item, (local, state, step)))
+        },
+    ))
+}
+
+/// Stream a response from iFlow directly to a writer
+///
+/// Connects to iFlow, writes each response chunk to `writer` as it arrives
+/// and disconnects once the response is complete.
+///
+/// Must be called within a `LocalSet`, since the client uses stdio.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `writer` - Where to write the response, e.g. stdout or a socket
+///
+/// # Returns
+/// * `Ok(())` once the response has been written
+/// * `Err(IFlowError)` if there was an error
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::query_to_writer;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let local = tokio::task::LocalSet::new();
+///     local
+///         .run_until(async {
+///             let mut stdout = tokio::io::stdout();
+///             query_to_writer("Tell me a story", &mut stdout).await
+///         })
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn query_to_writer<W>(prompt: &str, writer: &mut W) -> Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let options = IFlowOptions::new().with_process_config(
+        crate::types::ProcessConfig::new()
+            .enable_auto_start()
+            .stdio_mode(),
+    );
+    query_to_writer_with_config(prompt, writer, options).await
+}
+
+/// Stream a response from iFlow directly to a writer with custom options
+///
+/// See [`query_to_writer`]. Must be called within a `LocalSet` when the
+/// options use stdio.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `writer` - Where to write the response
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(())` once the response has been written
+/// * `Err(IFlowError)` if there was an error
+pub async fn query_to_writer_with_config<W>(
+    prompt: &str,
+    writer: &mut W,
+    options: IFlowOptions,
+) -> Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await?;
+    let result = client.stream_to_writer(prompt, writer).await;
+    client.disconnect().await?;
+    result
+}
+
+/// Stream JSON Lines responses from iFlow
+///
+/// Sends a query to iFlow and returns a stream yielding one deserialized
+/// value per line of the response. Lines may span several response chunks;
+/// blank lines are skipped. The client is disconnected once the response
+/// is complete.
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+///
+/// # Returns
+/// * `Ok(impl Stream<Item = Result<T>>)` yielding one item per line,
+///   `Err(IFlowError::Deserialization)` for lines that are not valid JSON for `T`
+/// * `Err(IFlowError)` if the query could not be sent
+pub async fn query_stream_jsonl<T: DeserializeOwned>(
+    prompt: &str,
+) -> Result<impl futures::Stream<Item = Result<T>>> {
+    let options = IFlowOptions::new().with_process_config(
+        crate::types::ProcessConfig::new()
+            .enable_auto_start()
+            .stdio_mode(),
+    );
+    query_stream_jsonl_with_config(prompt, options).await
+}
+
+/// Stream JSON Lines responses from iFlow with custom options
+///
+/// See [`query_stream_jsonl`].
+///
+/// # Arguments
+/// * `prompt` - The query prompt to send to iFlow
+/// * `options` - Configuration options for the query
+///
+/// # Returns
+/// * `Ok(impl Stream<Item = Result<T>>)` yielding one item per line
+/// * `Err(IFlowError)` if the query could not be sent
+pub async fn query_stream_jsonl_with_config<T: DeserializeOwned>(
+    prompt: &str,
+    options: IFlowOptions,
+) -> Result<impl futures::Stream<Item = Result<T>>> {
+    let setup = async {
+        let mut client = IFlowClient::new(Some(options));
+        client.connect().await?;
+        client.send_message(prompt, None).await?;
+        Ok(JsonLines {
+            messages: client.messages(),
+            client: Some(client),
+            buffer: String::new(),
+            finished: false,
+        })
+    };
+
+    local_stream(setup, |mut state| async move {
+        let item = state.next_line().await?;
+        Some((item, state))
+    })
+    .await
+}
+
+/// State for splitting assistant chunks into JSON lines
+struct JsonLines {
+    client: Option<IFlowClient>,
+    messages: MessageStream,
+    buffer: String,
+    finished: bool,
+}
+
+impl JsonLines {
+    /// Deserialize the next non-empty line, or `None` once the response is exhausted
+    async fn next_line<T: DeserializeOwned>(&mut self) -> Option<Result<T>> {
+        loop {
+            if let Some(pos) = self.buffer.find('\n') {
+                let line: String = self.buffer.drain(..=pos).collect();
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return Some(serde_json::from_str(&line).map_err(IFlowError::Deserialization));
+            }
+
+            if self.finished {
+                if self.buffer.trim().is_empty() {
+                    if let Some(mut client) = self.client.take() {
+                        let _ = client.disconnect().await;
+                    }
+                    return None;
+                }
+                let line = std::mem::take(&mut self.buffer);
+                return Some(serde_json::from_str(&line).map_err(IFlowError::Deserialization));
+            }
+
+            match self.messages.next().await {
+                Some(Message::Assistant { content }) => self.buffer.push_str(&content),
+                Some(Message::TaskFinish { .. }) | None => self.finished = true,
+                Some(_) => {}
+            }
+        }
+    }
+}
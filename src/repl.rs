@@ -0,0 +1,283 @@
+//! Interactive read-eval-print loop for iFlow
+//!
+//! The REPL reads prompts line by line, sends each one to iFlow and streams
+//! the response back as it arrives. Lines starting with `/` are commands
+//! handled by the REPL itself.
+
+use crate::client::IFlowClient;
+use crate::error::{IFlowError, Result};
+use crate::types::{IFlowOptions, Message, PlanStatus};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+const HELP: &str = "\
+Commands:
+  /help     Show this help
+  /stats    Show session statistics
+  /history  Show the conversation history
+  /quit     Exit the REPL
+";
+
+/// Options for the interactive REPL
+#[derive(Debug, Clone)]
+pub struct ReplOptions {
+    /// Text written before each input line
+    pub prompt_prefix: String,
+    /// Whether to print tool calls while streaming a response
+    pub show_tool_calls: bool,
+    /// Whether to print plan updates while streaming a response
+    pub show_plan: bool,
+}
+
+impl Default for ReplOptions {
+    fn default() -> Self {
+        Self {
+            prompt_prefix: "> ".to_string(),
+            show_tool_calls: false,
+            show_plan: false,
+        }
+    }
+}
+
+impl ReplOptions {
+    /// Create REPL options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the text written before each input line
+    ///
+    /// # Arguments
+    /// * `prefix` - The prompt prefix
+    pub fn with_prompt_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prompt_prefix = prefix.into();
+        self
+    }
+
+    /// Set whether tool calls are printed
+    ///
+    /// # Arguments
+    /// * `show` - Whether to print tool calls
+    pub fn with_tool_calls(mut self, show: bool) -> Self {
+        self.show_tool_calls = show;
+        self
+    }
+
+    /// Set whether plan updates are printed
+    ///
+    /// # Arguments
+    /// * `show` - Whether to print plan updates
+    pub fn with_plan(mut self, show: bool) -> Self {
+        self.show_plan = show;
+        self
+    }
+}
+
+impl IFlowClient {
+    /// Run an interactive REPL on stdin and stdout
+    ///
+    /// Connects if needed, then sends each input line to iFlow and streams
+    /// the response until end of input or `/quit`.
+    ///
+    /// # Arguments
+    /// * `options` - Options of the REPL
+    ///
+    /// # Returns
+    /// * `Ok(())` when the user quits
+    /// * `Err(IFlowError)` if connecting or reading input failed
+    pub async fn run_repl(&mut self, options: ReplOptions) -> Result<()> {
+        let input = tokio::io::BufReader::new(tokio::io::stdin());
+        let output = tokio::io::stdout();
+        self.run_repl_with_io(options, input, output).await
+    }
+
+    /// Run an interactive REPL on the given input and output
+    ///
+    /// See [`IFlowClient::run_repl`]. Errors from individual prompts are
+    /// printed and the REPL carries on.
+    ///
+    /// # Arguments
+    /// * `options` - Options of the REPL
+    /// * `input` - Where to read input lines from
+    /// * `output` - Where to write prompts and responses to
+    ///
+    /// # Returns
+    /// * `Ok(())` when the input ends or the user quits
+    /// * `Err(IFlowError)` if connecting, reading or writing failed
+    pub async fn run_repl_with_io<R, W>(
+        &mut self,
+        options: ReplOptions,
+        input: R,
+        mut output: W,
+    ) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        if !self.snapshot().connected {
+            self.connect().await?;
+        }
+
+        let mut lines = input.lines();
+        loop {
+            output.write_all(options.prompt_prefix.as_bytes()).await?;
+            output.flush().await?;
+
+            let Some(line) = lines.next_line().await? else {
+                break;
+            };
+            let line = line.trim();
+
+            match line {
+                "" => continue,
+                "/quit" => break,
+                "/help" => output.write_all(HELP.as_bytes()).await?,
+                "/stats" => {
+                    let stats = format!("{:#?}\n", self.statistics());
+                    output.write_all(stats.as_bytes()).await?;
+                }
+                "/history" => {
+                    for message in self.history() {
+                        if let Some(line) = history_line(&message) {
+                            output.write_all(line.as_bytes()).await?;
+                        }
+                    }
+                }
+                _ if line.starts_with('/') => {
+                    let unknown = format!("Unknown command: {} (try /help)\n", line);
+                    output.write_all(unknown.as_bytes()).await?;
+                }
+                prompt => {
+                    if let Err(e) = self
+                        .stream_repl_response(&options, prompt, &mut output)
+                        .await
+                    {
+                        if matches!(e, IFlowError::Io(_)) {
+                            return Err(e);
+                        }
+                        let error = format!("Error: {}\n", self.explain_error(&e));
+                        output.write_all(error.as_bytes()).await?;
+                    }
+                }
+            }
+            output.flush().await?;
+        }
+
+        output.flush().await?;
+        Ok(())
+    }
+
+    /// Send a prompt and stream the response to the REPL output
+    async fn stream_repl_response<W>(
+        &mut self,
+        options: &ReplOptions,
+        prompt: &str,
+        output: &mut W,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.send_message(prompt, None).await?;
+
+        while let Some(message) = self.receive_message().await? {
+            match message {
+                Message::Assistant { content } => {
+                    output.write_all(content.as_bytes()).await?;
+                    output.flush().await?;
+                }
+                Message::ToolCall { name, status, .. } if options.show_tool_calls => {
+                    let line = format!("\n[tool] {} ({})\n", name, status);
+                    output.write_all(line.as_bytes()).await?;
+                }
+                Message::Plan { entries } if options.show_plan => {
+                    let mut plan = String::from("\n[plan]\n");
+                    for entry in entries {
+                        let mark = match entry.status {
+                            PlanStatus::Completed => "x",
+                            PlanStatus::InProgress => "~",
+                            PlanStatus::Pending => " ",
+                        };
+                        plan.push_str(&format!("  [{}] {}\n", mark, entry.content));
+                    }
+                    output.write_all(plan.as_bytes()).await?;
+                }
+                Message::Error { code, message, .. } => {
+                    return Err(IFlowError::Protocol(format!("{} (code {})", message, code)));
+                }
+                Message::TaskFinish { reason } => {
+                    if let Some(err) = IFlowError::from_stop_reason(reason.as_deref()) {
+                        output.write_all(b"\n").await?;
+                        return Err(err);
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        output.write_all(b"\n").await?;
+        Ok(())
+    }
+}
+
+/// Format a history entry for `/history`
+fn history_line(message: &Message) -> Option<String> {
+    match message {
+        Message::User { content } => Some(format!("user: {}\n", content)),
+        Message::Assistant { content } => Some(format!("assistant: {}\n", content)),
+        Message::ToolCall { name, status, .. } => Some(format!("tool: {} ({})\n", name, status)),
+        _ => None,
+    }
+}
+
+/// Run an interactive REPL on stdin and stdout
+///
+/// Creates a client that starts iFlow over stdio and runs
+/// [`IFlowClient::run_repl`] until the user quits. Must be called within a
+/// `LocalSet`, since the client uses stdio.
+///
+/// # Arguments
+/// * `options` - Options of the REPL
+///
+/// # Returns
+/// * `Ok(())` when the user quits
+/// * `Err(IFlowError)` if there was an error
+///
+/// # Example
+/// ```no_run
+/// use iflow_cli_sdk_rust::{ReplOptions, run_repl};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let local = tokio::task::LocalSet::new();
+///     local.run_until(run_repl(ReplOptions::default())).await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn run_repl(options: ReplOptions) -> Result<()> {
+    let iflow_options = IFlowOptions::new().with_process_config(
+        crate::types::ProcessConfig::new()
+            .enable_auto_start()
+            .stdio_mode(),
+    );
+    run_repl_with_config(options, iflow_options).await
+}
+
+/// Run an interactive REPL on stdin and stdout with custom client options
+///
+/// See [`run_repl`]. Must be called within a `LocalSet` when the options
+/// use stdio.
+///
+/// # Arguments
+/// * `options` - Options of the REPL
+/// * `iflow_options` - Configuration options for the client
+///
+/// # Returns
+/// * `Ok(())` when the user quits
+/// * `Err(IFlowError)` if there was an error
+pub async fn run_repl_with_config(options: ReplOptions, iflow_options: IFlowOptions) -> Result<()> {
+    let mut client = IFlowClient::new(Some(iflow_options));
+    client.connect().await?;
+    let result = client.run_repl(options).await;
+    client.disconnect().await?;
+    result
+}
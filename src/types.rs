@@ -5,9 +5,12 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 
+use super::error::IFlowError;
 // Import logger configuration
 use super::logger::LoggerConfig;
 
@@ -40,6 +43,15 @@ impl Default for PermissionMode {
     }
 }
 
+/// Pre-configured answer to permission requests for a tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PermissionDecision {
+    /// Approve the tool call with the given permission option, e.g. `proceed_once`
+    Approve(String),
+    /// Deny the tool call
+    Deny,
+}
+
 /// Tool call status
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ToolCallStatus {
@@ -111,11 +123,14 @@ pub struct PlanEntry {
     /// The status of the plan entry
     #[serde(default)]
     pub status: PlanStatus,
+    /// Stable ID used to match the entry across plan updates, if iFlow sent one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 }
 
 /// User message chunk
 ///
-/// A chunk of a user message, which can be either text or a file path.
+/// A chunk of a user message, which can be text, a file path or an image.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum UserMessageChunk {
@@ -129,6 +144,15 @@ pub enum UserMessageChunk {
         #[serde(rename = "path")]
         path: PathBuf,
     },
+    /// Image content
+    Image {
+        /// Base64-encoded image data
+        #[serde(rename = "data")]
+        data: String,
+        /// MIME type of the image, e.g. `image/png`
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
 }
 
 /// User message
@@ -185,6 +209,291 @@ impl UserMessage {
             chunks,
         }
     }
+    /// Convert the message to ACP content blocks
+    ///
+    /// Text chunks become text blocks. Path chunks are read from disk and
+    /// embedded as resources, subject to `file_access.max_size`.
+    ///
+    /// # Arguments
+    /// * `options` - The options providing the file size limit
+    ///
+    /// # Returns
+    /// * `Ok(Vec<ContentBlock>)` with one block per chunk
+    /// * `Err(IFlowError)` if a file cannot be read or is too large
+    pub async fn to_content_blocks(
+        &self,
+        options: &IFlowOptions,
+    ) -> crate::error::Result<Vec<ContentBlock>> {
+        let mut blocks = Vec::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            let block = match chunk {
+                UserMessageChunk::Text { content } => text_block(content.clone()),
+                UserMessageChunk::Path { path } => {
+                    let size = tokio::fs::metadata(path)
+                        .await
+                        .map_err(|e| IFlowError::from_file_io(e, path))?
+                        .len();
+                    if size > options.file_access.max_size {
+                        return Err(IFlowError::FileTooLarge {
+                            path: path.clone(),
+                            size,
+                            limit: options.file_access.max_size,
+                        });
+                    }
+                    let bytes = tokio::fs::read(path)
+                        .await
+                        .map_err(|e| IFlowError::from_file_io(e, path))?;
+                    file_resource_block(path, bytes)?
+                }
+                UserMessageChunk::Image { data, mime_type } => {
+                    image_block(data.clone(), mime_type.clone())
+                }
+            };
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+}
+
+impl TryFrom<UserMessage> for Vec<ContentBlock> {
+    type Error = IFlowError;
+
+    /// Convert a user message to ACP content blocks, reading files synchronously
+    fn try_from(message: UserMessage) -> crate::error::Result<Self> {
+        message
+            .chunks
+            .into_iter()
+            .map(|chunk| match chunk {
+                UserMessageChunk::Text { content } => Ok(text_block(content)),
+                UserMessageChunk::Path { path } => {
+                    let bytes =
+                        std::fs::read(&path).map_err(|e| IFlowError::from_file_io(e, &path))?;
+                    file_resource_block(&path, bytes)
+                }
+                UserMessageChunk::Image { data, mime_type } => Ok(image_block(data, mime_type)),
+            })
+            .collect()
+    }
+}
+
+fn image_block(data: String, mime_type: String) -> ContentBlock {
+    ContentBlock::Image(ImageContent {
+        annotations: None,
+        data,
+        mime_type,
+        uri: None,
+        meta: None,
+    })
+}
+
+fn text_block(text: String) -> ContentBlock {
+    ContentBlock::Text(TextContent {
+        text,
+        annotations: None,
+        meta: None,
+    })
+}
+
+/// Embed a file as a resource
+///
+/// UTF-8 text is embedded as text, anything else base64-encoded as a blob.
+fn file_resource_block(path: &Path, bytes: Vec<u8>) -> crate::error::Result<ContentBlock> {
+    let uri = std::path::absolute(path)
+        .ok()
+        .and_then(|path| url::Url::from_file_path(path).ok())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| format!("file://{}", path.display()));
+    let mime_type = file_mime_type(path);
+
+    let resource = match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => {
+            agent_client_protocol::EmbeddedResourceResource::TextResourceContents(
+                agent_client_protocol::TextResourceContents {
+                    mime_type: Some(mime_type.unwrap_or("text/plain").to_string()),
+                    text,
+                    uri,
+                    meta: None,
+                },
+            )
+        }
+        Ok(text) => blob_resource(text.as_bytes(), mime_type, uri)?,
+        Err(e) => blob_resource(e.as_bytes(), mime_type, uri)?,
+    };
+
+    Ok(ContentBlock::Resource(
+        agent_client_protocol::EmbeddedResource {
+            resource,
+            annotations: None,
+            meta: None,
+        },
+    ))
+}
+
+fn blob_resource(
+    bytes: &[u8],
+    mime_type: Option<&str>,
+    uri: String,
+) -> crate::error::Result<agent_client_protocol::EmbeddedResourceResource> {
+    Ok(
+        agent_client_protocol::EmbeddedResourceResource::BlobResourceContents(
+            agent_client_protocol::BlobResourceContents {
+                blob: encode_base64(bytes)?,
+                mime_type: Some(mime_type.unwrap_or("application/octet-stream").to_string()),
+                uri,
+                meta: None,
+            },
+        ),
+    )
+}
+
+/// Detect the MIME type of a file from its extension
+///
+/// # Returns
+/// The MIME type, or `None` for unknown extensions
+pub(crate) fn file_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime_type = match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "js" | "mjs" => "text/javascript",
+        "ts" => "text/x-typescript",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "sh" => "application/x-sh",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    Some(mime_type)
+}
+
+/// Encode bytes as standard base64 with padding
+///
+/// # Returns
+/// * `Ok(String)` containing the encoded bytes
+/// * `Err(IFlowError::Config)` if the `file-support` feature is disabled
+#[cfg(feature = "file-support")]
+pub(crate) fn encode_base64(bytes: &[u8]) -> crate::error::Result<String> {
+    use base64::Engine;
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+/// Encode bytes as standard base64 with padding
+///
+/// # Returns
+/// * `Ok(String)` containing the encoded bytes
+/// * `Err(IFlowError::Config)` if the `file-support` feature is disabled
+#[cfg(not(feature = "file-support"))]
+pub(crate) fn encode_base64(_bytes: &[u8]) -> crate::error::Result<String> {
+    Err(IFlowError::Config(
+        "Embedding binary data requires the `file-support` feature".to_string(),
+    ))
+}
+
+/// A prompt assembled from instructions, examples, context files and a user message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StructuredPrompt {
+    /// Instructions on how iFlow should respond
+    pub system: Option<String>,
+    /// The user message
+    pub user: String,
+    /// Files embedded in the prompt as context
+    pub context_files: Vec<PathBuf>,
+    /// Few-shot examples as (user, assistant) pairs
+    pub examples: Vec<(String, String)>,
+}
+
+impl StructuredPrompt {
+    /// Create a builder for a structured prompt
+    pub fn builder() -> StructuredPromptBuilder {
+        StructuredPromptBuilder::default()
+    }
+
+    /// Convert the prompt to a user message
+    ///
+    /// ACP prompts have no roles, so each part becomes its own chunk, in
+    /// order: the system instructions, one chunk per example, one path chunk
+    /// per context file and finally the user message.
+    ///
+    /// # Returns
+    /// A UserMessage with one chunk per part of the prompt
+    pub fn to_user_message(&self) -> UserMessage {
+        let mut chunks = Vec::new();
+        if let Some(system) = &self.system {
+            chunks.push(UserMessageChunk::Text {
+                content: format!("Instructions:\n{}", system),
+            });
+        }
+        for (i, (user, assistant)) in self.examples.iter().enumerate() {
+            chunks.push(UserMessageChunk::Text {
+                content: format!(
+                    "Example {}:\nUser: {}\nAssistant: {}",
+                    i + 1,
+                    user,
+                    assistant
+                ),
+            });
+        }
+        chunks.extend(
+            self.context_files
+                .iter()
+                .map(|path| UserMessageChunk::Path { path: path.clone() }),
+        );
+        chunks.push(UserMessageChunk::Text {
+            content: self.user.clone(),
+        });
+        UserMessage::new(chunks)
+    }
+}
+
+/// Builder for `StructuredPrompt`
+#[derive(Debug, Clone, Default)]
+pub struct StructuredPromptBuilder {
+    prompt: StructuredPrompt,
+}
+
+impl StructuredPromptBuilder {
+    /// Set the system instructions
+    pub fn system(mut self, system: impl Into<String>) -> Self {
+        self.prompt.system = Some(system.into());
+        self
+    }
+
+    /// Set the user message
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.prompt.user = user.into();
+        self
+    }
+
+    /// Add a file to embed as context
+    pub fn context_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.prompt.context_files.push(path.into());
+        self
+    }
+
+    /// Add a few-shot example
+    pub fn example(mut self, user: impl Into<String>, assistant: impl Into<String>) -> Self {
+        self.prompt.examples.push((user.into(), assistant.into()));
+        self
+    }
+
+    /// Build the prompt
+    pub fn build(self) -> StructuredPrompt {
+        self.prompt
+    }
 }
 
 /// Icon for tool calls
@@ -327,6 +636,316 @@ impl ToolCallMessage {
             agent_info: None,
         }
     }
+
+    /// Set the content of the tool call
+    ///
+    /// # Arguments
+    /// * `content` - The content of the tool call
+    pub fn with_content(mut self, content: ToolCallContent) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    /// Add a file location to the tool call
+    ///
+    /// # Arguments
+    /// * `location` - The location to add
+    pub fn with_location(mut self, location: ToolCallLocation) -> Self {
+        self.locations.get_or_insert_with(Vec::new).push(location);
+        self
+    }
+
+    /// Set the confirmation details of the tool call
+    ///
+    /// # Arguments
+    /// * `confirmation` - The confirmation details
+    pub fn with_confirmation(mut self, confirmation: ToolCallConfirmation) -> Self {
+        self.confirmation = Some(confirmation);
+        self
+    }
+
+    /// Set the agent ID of the tool call
+    ///
+    /// # Arguments
+    /// * `agent_id` - The agent ID
+    pub fn with_agent_id(mut self, agent_id: String) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    /// Set the tool name of the tool call
+    ///
+    /// # Arguments
+    /// * `tool_name` - The name of the tool
+    pub fn with_tool_name(mut self, tool_name: String) -> Self {
+        self.tool_name = Some(tool_name);
+        self
+    }
+
+    /// Validate the tool call message
+    ///
+    /// Checks that the ID, label and icon value are non-empty and that
+    /// `execute` confirmations carry a command.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the message is valid
+    /// * `Err(IFlowError::Validation)` describing the first problem found
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.id.is_empty() {
+            return Err(IFlowError::Validation("Tool call id is empty".to_string()));
+        }
+        if self.label.is_empty() {
+            return Err(IFlowError::Validation(
+                "Tool call label is empty".to_string(),
+            ));
+        }
+        if self.icon.value.is_empty() {
+            return Err(IFlowError::Validation(
+                "Tool call icon value is empty".to_string(),
+            ));
+        }
+        if let Some(confirmation) = &self.confirmation
+            && confirmation.confirmation_type == "execute"
+            && confirmation.command.is_none()
+        {
+            return Err(IFlowError::Validation(
+                "Execute confirmation requires a command".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A tool call observed while collecting a response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallEvent {
+    /// The ID of the tool call
+    pub id: String,
+    /// The name of the tool
+    pub name: String,
+    /// The status of the tool call
+    pub status: String,
+    /// The input of the tool call, if reported
+    pub input: Option<serde_json::Value>,
+    /// The output of the tool call, if reported
+    pub output: Option<serde_json::Value>,
+}
+
+/// The result of a shell command run with `IFlowClient::execute_shell`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellResult {
+    /// Standard output of the command
+    pub stdout: String,
+    /// Standard error of the command
+    pub stderr: String,
+    /// Exit code of the command, or -1 if it was terminated by a signal
+    pub exit_code: i32,
+    /// How long the command ran
+    pub duration: Duration,
+}
+
+/// An event in the response to a query, as yielded by `query_stream_with_tools`
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// A chunk of the assistant's response
+    Chunk(String),
+    /// A tool call was started or reported progress
+    ToolCallStarted {
+        /// The ID of the tool call
+        id: String,
+        /// The name of the tool
+        name: String,
+    },
+    /// A tool call completed or failed
+    ToolCallCompleted {
+        /// The ID of the tool call
+        id: String,
+        /// The output of the tool call, if reported
+        output: Option<serde_json::Value>,
+    },
+    /// The plan was updated
+    PlanUpdate(Vec<PlanEntry>),
+    /// The response is complete, with the stop reason
+    Done(String),
+}
+
+impl AgentEvent {
+    /// Map a message received from iFlow to an event
+    ///
+    /// Tool calls with status `completed` or `failed` map to
+    /// `ToolCallCompleted`, other tool calls to `ToolCallStarted`.
+    ///
+    /// # Arguments
+    /// * `msg` - The received message
+    ///
+    /// # Returns
+    /// The matching event, or `None` for user and error messages
+    pub fn from_message(msg: Message) -> Option<Self> {
+        match msg {
+            Message::Assistant { content } => Some(AgentEvent::Chunk(content)),
+            Message::ToolCall { id, name, status } => {
+                let finished = status.eq_ignore_ascii_case("completed")
+                    || status.eq_ignore_ascii_case("failed");
+                Some(if finished {
+                    AgentEvent::ToolCallCompleted { id, output: None }
+                } else {
+                    AgentEvent::ToolCallStarted { id, name }
+                })
+            }
+            Message::Plan { entries } => Some(AgentEvent::PlanUpdate(entries)),
+            Message::TaskFinish { reason } => Some(AgentEvent::Done(
+                reason.unwrap_or_else(|| "completed".to_string()),
+            )),
+            Message::User { .. } | Message::Error { .. } => None,
+        }
+    }
+}
+
+/// Options for `IFlowClient::run_agent_loop`
+#[derive(Debug, Clone)]
+pub struct AgentLoopOptions {
+    /// Approve all permission requests while the loop runs (WebSocket only)
+    pub auto_approve_permissions: bool,
+    /// Stop after a turn that updated the plan, so the caller can review it
+    pub pause_on_plan_update: bool,
+    /// Stop once more than this many tool calls have been made
+    pub max_tool_calls: u32,
+}
+
+impl Default for AgentLoopOptions {
+    fn default() -> Self {
+        Self {
+            auto_approve_permissions: false,
+            pause_on_plan_update: false,
+            max_tool_calls: u32::MAX,
+        }
+    }
+}
+
+/// Outcome of `IFlowClient::run_agent_loop`
+#[derive(Debug, Clone)]
+pub struct AgentResult {
+    /// The assistant's response across all turns
+    pub response: String,
+    /// The number of prompts sent
+    pub turns: u32,
+    /// Every tool call made during the loop
+    pub tool_calls: Vec<ToolCallEvent>,
+    /// The most recent plan, if iFlow sent one
+    pub final_plan: Option<Vec<PlanEntry>>,
+    /// Why the loop stopped
+    pub stop_reason: String,
+}
+
+/// Summary of a turn, returned by `IFlowClient::drain_and_summarize`
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    /// The concatenated assistant text
+    pub full_response: String,
+    /// Every tool call update of the turn
+    pub tool_calls: Vec<ToolCallEvent>,
+    /// The last plan, if iFlow sent one
+    pub final_plan: Option<Vec<PlanEntry>>,
+    /// iFlow's stop reason
+    pub stop_reason: Option<String>,
+    /// The number of assistant chunks
+    pub total_chunks: u32,
+    /// Time from the first message of the turn to `TaskFinish`
+    pub total_duration: Duration,
+}
+
+/// Retry policy for protocol operations over WebSocket
+///
+/// Applied to `initialize`, `authenticate` and session creation. Prompts
+/// are only retried while the `session/prompt` request could not be
+/// written, never after it went out. Only transient errors are retried,
+/// see [`RetryPolicy::is_transient`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Factor by which the delay grows after each retry
+    pub multiplier: f64,
+    /// Upper bound of the delay
+    pub max_delay: Duration,
+    /// Whether to randomize each delay to between half and all of its value
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            jitter: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retrying after a failed attempt, without jitter
+    ///
+    /// # Arguments
+    /// * `attempt` - The number of the failed attempt, starting at 1
+    ///
+    /// # Returns
+    /// `initial_delay * multiplier^(attempt - 1)`, capped at `max_delay`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        if delay.is_finite() && delay < self.max_delay.as_secs_f64() {
+            Duration::from_secs_f64(delay.max(0.0))
+        } else {
+            self.max_delay
+        }
+    }
+
+    /// Delay before retrying after a failed attempt, with jitter if enabled
+    ///
+    /// # Arguments
+    /// * `attempt` - The number of the failed attempt, starting at 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        use rand::Rng;
+
+        let delay = self.delay(attempt);
+        if self.jitter && !delay.is_zero() {
+            rand::rng().random_range(delay / 2..=delay)
+        } else {
+            delay
+        }
+    }
+
+    /// Whether an error is worth retrying
+    ///
+    /// Timeouts, connection and transport errors are transient;
+    /// authentication, protocol and all other errors are not.
+    pub fn is_transient(err: &IFlowError) -> bool {
+        matches!(
+            err,
+            IFlowError::Timeout(_) | IFlowError::Connection(_) | IFlowError::Transport(_)
+        )
+    }
+
+    /// Whether to retry after a failed attempt
+    ///
+    /// # Arguments
+    /// * `err` - The error of the failed attempt
+    /// * `attempt` - The number of the failed attempt, starting at 1
+    pub fn should_retry(&self, err: &IFlowError, attempt: u32) -> bool {
+        attempt < self.max_attempts && Self::is_transient(err)
+    }
 }
 
 /// Configuration for WebSocket connection
@@ -338,6 +957,10 @@ pub struct WebSocketConfig {
     pub reconnect_attempts: u32,
     /// Interval between reconnect attempts
     pub reconnect_interval: Duration,
+    /// PEM file of CA certificates trusted for `wss://` URLs besides the native roots
+    pub tls_ca_bundle: Option<PathBuf>,
+    /// Interval of keepalive pings, or `None` to send no pings
+    pub ping_interval: Option<Duration>,
 }
 
 impl Default for WebSocketConfig {
@@ -346,6 +969,8 @@ impl Default for WebSocketConfig {
             url: Some("ws://localhost:8090/acp?peer=iflow".to_string()),
             reconnect_attempts: 3,
             reconnect_interval: Duration::from_secs(5),
+            tls_ca_bundle: None,
+            ping_interval: None,
         }
     }
 }
@@ -377,6 +1002,7 @@ impl WebSocketConfig {
             url: Some(url),
             reconnect_attempts,
             reconnect_interval,
+            ..Default::default()
         }
     }
 
@@ -389,8 +1015,33 @@ impl WebSocketConfig {
             url: None,
             reconnect_attempts,
             reconnect_interval,
+            ..Default::default()
         }
     }
+
+    /// Trust the CA certificates in a PEM file for `wss://` URLs
+    ///
+    /// Useful for deployments with a private CA or a self-signed certificate.
+    /// `wss://` URLs require the `wss` feature, which is enabled by default.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the PEM file
+    pub fn with_tls_ca_bundle(mut self, path: impl Into<PathBuf>) -> Self {
+        self.tls_ca_bundle = Some(path.into());
+        self
+    }
+
+    /// Send keepalive pings on an otherwise idle connection
+    ///
+    /// Keeps load balancers and NAT devices from dropping long-running
+    /// sessions. See [`WebSocketTransport::with_ping_interval`](crate::websocket_transport::WebSocketTransport::with_ping_interval).
+    ///
+    /// # Arguments
+    /// * `interval` - Time between pings
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = Some(interval);
+        self
+    }
 }
 
 /// Configuration for file access
@@ -417,6 +1068,130 @@ impl Default for FileAccessConfig {
     }
 }
 
+impl FileAccessConfig {
+    /// Check whether a path may be accessed
+    ///
+    /// # Arguments
+    /// * `path` - The path to check
+    ///
+    /// # Returns
+    /// True if file access is enabled and `path` lies within one of
+    /// `allowed_dirs` (or no directories are configured), False otherwise
+    pub fn is_allowed(&self, path: &Path) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Some(allowed_dirs) = &self.allowed_dirs else {
+            return true;
+        };
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        allowed_dirs.iter().any(|dir| {
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            path.starts_with(dir)
+        })
+    }
+}
+
+/// Hook into the lifecycle of an auto-started iFlow process
+///
+/// Useful for deployment scripts, e.g. to set up credentials before iFlow
+/// starts or to clean up temporary directories after it stops.
+#[async_trait::async_trait]
+pub trait ProcessHook: Send + Sync {
+    /// Run the hook
+    ///
+    /// # Arguments
+    /// * `port` - The port of the iFlow process, or 0 in stdio mode
+    async fn run(&self, port: u16) -> crate::error::Result<()>;
+}
+
+impl std::fmt::Debug for dyn ProcessHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProcessHook")
+    }
+}
+
+/// Direction of a JSON-RPC message seen by a `RawProtocolObserver`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent to iFlow
+    Sent,
+    /// Received from iFlow
+    Received,
+}
+
+/// Observer of the raw JSON-RPC messages exchanged with iFlow
+///
+/// Useful for debugging protocol issues and for conformance tests. Only
+/// WebSocket connections are observed; stdio connections are handled by the
+/// `agent-client-protocol` crate and never expose their messages.
+pub trait RawProtocolObserver: Send + Sync {
+    /// Called before a message is sent
+    ///
+    /// # Arguments
+    /// * `json` - The message
+    fn on_send(&self, json: &serde_json::Value);
+
+    /// Called after a message is received
+    ///
+    /// # Arguments
+    /// * `json` - The message
+    fn on_receive(&self, json: &serde_json::Value);
+
+    /// The messages observed so far, if this observer records them
+    ///
+    /// # Returns
+    /// `None` unless the observer keeps a log, as `RecordingObserver` does
+    fn recorded(&self) -> Option<Vec<(Direction, serde_json::Value)>> {
+        None
+    }
+}
+
+impl std::fmt::Debug for dyn RawProtocolObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RawProtocolObserver")
+    }
+}
+
+/// A `RawProtocolObserver` that records every message in order
+#[derive(Debug, Clone, Default)]
+pub struct RecordingObserver {
+    messages: Arc<std::sync::Mutex<Vec<(Direction, serde_json::Value)>>>,
+}
+
+impl RecordingObserver {
+    /// Create an observer with an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared log, for inspecting it after the observer was handed to a client
+    pub fn messages(&self) -> Arc<std::sync::Mutex<Vec<(Direction, serde_json::Value)>>> {
+        self.messages.clone()
+    }
+
+    fn record(&self, direction: Direction, json: &serde_json::Value) {
+        if let Ok(mut messages) = self.messages.lock() {
+            messages.push((direction, json.clone()));
+        }
+    }
+}
+
+impl RawProtocolObserver for RecordingObserver {
+    fn on_send(&self, json: &serde_json::Value) {
+        self.record(Direction::Sent, json);
+    }
+
+    fn on_receive(&self, json: &serde_json::Value) {
+        self.record(Direction::Received, json);
+    }
+
+    fn recorded(&self) -> Option<Vec<(Direction, serde_json::Value)>> {
+        self.messages.lock().ok().map(|messages| messages.clone())
+    }
+}
+
 /// Configuration for process management
 #[derive(Debug, Clone)]
 pub struct ProcessConfig {
@@ -424,8 +1199,18 @@ pub struct ProcessConfig {
     pub auto_start: bool,
     /// Port to start the iFlow process on (only used in auto-start WebSocket mode)
     pub start_port: Option<u16>,
-    /// Whether to start the iFlow process in debug mode
+    /// Whether to start the iFlow process with `--debug` and log its stderr
     pub debug: bool,
+    /// Whether to capture stderr in WebSocket mode (always captured in stdio mode)
+    pub capture_stderr: bool,
+    /// How long to wait for an auto-started iFlow process to accept WebSocket connections
+    pub startup_timeout: Duration,
+    /// Number of ports to try, starting at `start_port`, when looking for a free port
+    pub port_scan_range: u16,
+    /// Hook run before the iFlow process is spawned
+    pub pre_start_hook: Option<Arc<dyn ProcessHook>>,
+    /// Hook run after the iFlow process has exited
+    pub post_stop_hook: Option<Arc<dyn ProcessHook>>,
 }
 
 impl Default for ProcessConfig {
@@ -434,6 +1219,11 @@ impl Default for ProcessConfig {
             auto_start: true,
             start_port: None, // No port needed for stdio mode
             debug: false,
+            capture_stderr: false,
+            startup_timeout: Duration::from_secs(60),
+            port_scan_range: 100,
+            pre_start_hook: None,
+            post_stop_hook: None,
         }
     }
 }
@@ -462,27 +1252,83 @@ impl ProcessConfig {
         self
     }
 
-    /// Disable process auto-start
-    pub fn manual_start(self) -> Self {
-        self.auto_start(false)
+    /// Set whether to start the iFlow process in debug mode
+    ///
+    /// In debug mode iFlow is started with `--debug` and its stderr is
+    /// captured and logged at `DEBUG` level, also in WebSocket mode.
+    ///
+    /// # Arguments
+    /// * `debug` - Whether to enable debug mode
+    pub fn with_debug(self, debug: bool) -> Self {
+        self.debug(debug)
     }
 
-    /// Enable process auto-start
-    pub fn enable_auto_start(self) -> Self {
-        self.auto_start(true)
+    /// Set whether to capture the iFlow process's stderr in WebSocket mode
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
     }
 
-    /// Enable debug mode
-    pub fn enable_debug(self) -> Self {
-        self.debug(true)
+    /// Set how long to wait for an auto-started iFlow process to be ready
+    ///
+    /// This is separate from `IFlowOptions::timeout`, which applies to protocol operations.
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
     }
 
-    /// Configure for stdio mode (no port needed)
-    pub fn stdio_mode(mut self) -> Self {
-        self.start_port = None;
+    /// Set how many ports, starting at `start_port`, to try when looking for a free port
+    ///
+    /// A range of 0 is treated as 1.
+    pub fn with_port_scan_range(mut self, range: u16) -> Self {
+        self.port_scan_range = range.max(1);
         self
     }
-}
+
+    /// Use exactly `port`, failing to start if it is busy
+    pub fn with_preferred_port(mut self, port: u16) -> Self {
+        self.start_port = Some(port);
+        self.port_scan_range = 1;
+        self
+    }
+
+    /// Set a hook to run before the iFlow process is spawned
+    ///
+    /// An error from the hook aborts the start.
+    pub fn with_pre_start_hook(mut self, hook: Arc<dyn ProcessHook>) -> Self {
+        self.pre_start_hook = Some(hook);
+        self
+    }
+
+    /// Set a hook to run after the iFlow process has exited
+    ///
+    /// Errors from the hook are logged and otherwise ignored.
+    pub fn with_post_stop_hook(mut self, hook: Arc<dyn ProcessHook>) -> Self {
+        self.post_stop_hook = Some(hook);
+        self
+    }
+
+    /// Disable process auto-start
+    pub fn manual_start(self) -> Self {
+        self.auto_start(false)
+    }
+
+    /// Enable process auto-start
+    pub fn enable_auto_start(self) -> Self {
+        self.auto_start(true)
+    }
+
+    /// Enable debug mode
+    pub fn enable_debug(self) -> Self {
+        self.debug(true)
+    }
+
+    /// Configure for stdio mode (no port needed)
+    pub fn stdio_mode(mut self) -> Self {
+        self.start_port = None;
+        self
+    }
+}
 
 /// Configuration for logging
 #[derive(Debug, Clone)]
@@ -505,6 +1351,378 @@ impl Default for LoggingConfig {
     }
 }
 
+/// Reason passed to the `on_disconnect` hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client was disconnected by calling `disconnect()`
+    Graceful,
+    /// An error occurred while tearing down the connection
+    Error(String),
+    /// The iFlow process had already exited before the disconnect
+    ProcessExited,
+}
+
+/// Connection lifecycle event passed to the `on_connection_event` hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// `IFlowClient::reconnect()` re-established the connection
+    Reconnected {
+        /// The session used after reconnecting
+        session_id: Option<String>,
+        /// Whether the previous session was resumed rather than replaced
+        resumed: bool,
+    },
+}
+
+/// Callback invoked after a connection is established
+pub type ConnectHook = Arc<dyn Fn() + Send + Sync>;
+/// Callback invoked after a connection is closed
+pub type DisconnectHook = Arc<dyn Fn(DisconnectReason) + Send + Sync>;
+/// Callback invoked for every message received from iFlow
+pub type MessageHook = Arc<dyn Fn(&Message) + Send + Sync>;
+/// Callback invoked for errors that occur outside of a call into the client
+pub type ErrorHook = Arc<dyn Fn(&IFlowError) + Send + Sync>;
+/// Callback invoked for connection lifecycle events
+pub type ConnectionEventHook = Arc<dyn Fn(&ConnectionEvent) + Send + Sync>;
+/// Callback invoked with the latest plan whenever iFlow sends one
+pub type PlanCallback = Arc<dyn Fn(&[PlanEntry]) + Send + Sync>;
+/// Callback invoked with the type and fields of every raw `session/update`
+pub type SessionUpdateHook =
+    Arc<dyn Fn(&str, &serde_json::Map<String, serde_json::Value>) + Send + Sync>;
+
+/// Decision of a [`ToolCallHook`] about a tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolCallDecision {
+    /// Let the permission mode decide
+    Allow,
+    /// Reject the tool call, with the reason
+    Deny(String),
+}
+
+/// The result of a finished tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallResult {
+    /// The final status of the tool call, e.g. `completed` or `failed`
+    pub status: String,
+    /// The output of the tool call, if reported
+    pub output: Option<serde_json::Value>,
+}
+
+/// Inspects tool calls before and after they run
+///
+/// `before_tool_call` is consulted for every permission request before the
+/// permission mode, so it can veto tool calls the mode would approve.
+pub trait ToolCallHook: Send + Sync {
+    /// Decide whether a tool call may run
+    ///
+    /// # Arguments
+    /// * `call` - The tool call iFlow asks permission for
+    ///
+    /// # Returns
+    /// `ToolCallDecision::Deny` to cancel the tool call, `ToolCallDecision::Allow`
+    /// to leave the decision to the permission mode
+    fn before_tool_call(&self, call: &ToolCallEvent) -> ToolCallDecision;
+
+    /// Observe a tool call that completed or failed
+    ///
+    /// # Arguments
+    /// * `call` - The finished tool call
+    /// * `result` - Its result
+    fn after_tool_call(&self, _call: &ToolCallEvent, _result: &ToolCallResult) {}
+}
+
+impl std::fmt::Debug for dyn ToolCallHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ToolCallHook")
+    }
+}
+
+/// A choice offered by iFlow when asking permission for a tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionOption {
+    /// The ID to approve the tool call with, e.g. `proceed_once`
+    pub option_id: String,
+    /// The human-readable label of the option
+    pub label: String,
+}
+
+/// Decides permission requests at runtime, e.g. by asking the user
+///
+/// A callback takes precedence over the permission mode. Before it is
+/// asked, the tool call hook can veto the tool call, and a pre-configured
+/// tool approval answers the request without asking the callback. This
+/// order is the same over WebSocket and stdio.
+#[async_trait::async_trait]
+pub trait PermissionCallback: Send + Sync {
+    /// Decide whether a tool call may run
+    ///
+    /// # Arguments
+    /// * `tool_title` - The title of the tool call
+    /// * `tool_type` - The kind of tool, e.g. `read` or `edit`
+    /// * `options` - The options iFlow offers for approving the tool call
+    ///
+    /// # Returns
+    /// `PermissionDecision::Approve` with one of the option IDs, or `PermissionDecision::Deny`
+    async fn request_permission(
+        &self,
+        tool_title: &str,
+        tool_type: &str,
+        options: &[PermissionOption],
+    ) -> PermissionDecision;
+}
+
+impl std::fmt::Debug for dyn PermissionCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PermissionCallback")
+    }
+}
+
+/// Push-style event hooks for the client lifecycle
+///
+/// Hooks are called synchronously from within the client, so they must not
+/// block. Heavy work should be spawned onto a separate task.
+#[derive(Clone, Default)]
+pub struct IFlowHooks {
+    /// Called at the end of `connect()`
+    pub on_connect: Option<ConnectHook>,
+    /// Called at the end of `disconnect()`
+    pub on_disconnect: Option<DisconnectHook>,
+    /// Called for every message before it is delivered to the message stream
+    pub on_message: Option<MessageHook>,
+    /// Called for errors in background work, e.g. malformed messages from iFlow
+    pub on_error: Option<ErrorHook>,
+    /// Called for every `session/update` before it is parsed into a message
+    pub on_session_update: Option<SessionUpdateHook>,
+    /// Called for connection lifecycle events, e.g. a reconnect
+    pub on_connection_event: Option<ConnectionEventHook>,
+}
+
+impl std::fmt::Debug for IFlowHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IFlowHooks")
+            .field("on_connect", &self.on_connect.is_some())
+            .field("on_disconnect", &self.on_disconnect.is_some())
+            .field("on_message", &self.on_message.is_some())
+            .field("on_error", &self.on_error.is_some())
+            .field("on_session_update", &self.on_session_update.is_some())
+            .field("on_connection_event", &self.on_connection_event.is_some())
+            .finish()
+    }
+}
+
+impl IFlowHooks {
+    /// Create an empty set of hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hook called after a connection is established
+    pub fn on_connect(mut self, hook: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook called after a connection is closed
+    pub fn on_disconnect(
+        mut self,
+        hook: impl Fn(DisconnectReason) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_disconnect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook called for every received message
+    pub fn on_message(mut self, hook: impl Fn(&Message) + Send + Sync + 'static) -> Self {
+        self.on_message = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook called for errors in background work
+    pub fn on_error(mut self, hook: impl Fn(&IFlowError) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook called for every raw session update
+    pub fn on_session_update(
+        mut self,
+        hook: impl Fn(&str, &serde_json::Map<String, serde_json::Value>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_session_update = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook called for connection lifecycle events
+    pub fn on_connection_event(
+        mut self,
+        hook: impl Fn(&ConnectionEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_connection_event = Some(Arc::new(hook));
+        self
+    }
+}
+
+/// Format iFlow is asked to respond in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// No particular format
+    #[default]
+    PlainText,
+    /// A JSON object, optionally matching a schema
+    Json {
+        /// A description of the expected JSON, e.g. a JSON Schema
+        schema_hint: Option<String>,
+    },
+    /// Markdown
+    Markdown,
+}
+
+impl ResponseFormat {
+    /// The instruction added to prompts to request this format
+    ///
+    /// # Returns
+    /// The instruction, or `None` for `PlainText`
+    pub fn instruction(&self) -> Option<String> {
+        match self {
+            ResponseFormat::PlainText => None,
+            ResponseFormat::Json { schema_hint: None } => {
+                Some("Respond with a valid JSON object.".to_string())
+            }
+            ResponseFormat::Json {
+                schema_hint: Some(schema_hint),
+            } => Some(format!(
+                "Respond with valid JSON matching this schema: {}",
+                schema_hint
+            )),
+            ResponseFormat::Markdown => Some("Format your response as Markdown.".to_string()),
+        }
+    }
+}
+
+/// Maximum rate at which assistant chunks are delivered by a message stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageRate {
+    /// Maximum number of `Message::Assistant` chunks per second
+    pub max_chunks_per_second: f64,
+}
+
+impl MessageRate {
+    /// Create a rate limit
+    ///
+    /// # Arguments
+    /// * `max_chunks_per_second` - Maximum number of assistant chunks per second
+    pub fn new(max_chunks_per_second: f64) -> Self {
+        Self {
+            max_chunks_per_second,
+        }
+    }
+
+    /// Check that the rate can be used to space chunks
+    ///
+    /// # Returns
+    /// * `Ok(())` if the rate is positive, finite and its interval fits in a `Duration`
+    /// * `Err(IFlowError::Config)` otherwise
+    pub fn validate(&self) -> crate::error::Result<()> {
+        check_rate("message_rate_limit", self.max_chunks_per_second)
+    }
+
+    /// The minimum time between two assistant chunks
+    ///
+    /// # Returns
+    /// The interval, zero for rates that are not positive, or `Duration::MAX`
+    /// for rates too small to represent
+    pub fn interval(&self) -> Duration {
+        if self.max_chunks_per_second > 0.0 {
+            Duration::try_from_secs_f64(1.0 / self.max_chunks_per_second).unwrap_or(Duration::MAX)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Check that a per-second rate is positive, finite and not so small that
+/// the time between two events overflows a `Duration`
+///
+/// # Arguments
+/// * `name` - The option name used in the error message
+/// * `per_second` - The rate to check
+fn check_rate(name: &str, per_second: f64) -> crate::error::Result<()> {
+    if !per_second.is_finite() || per_second <= 0.0 {
+        return Err(IFlowError::Config(format!(
+            "{} must be a positive, finite rate, got {}",
+            name, per_second
+        )));
+    }
+    if Duration::try_from_secs_f64(1.0 / per_second).is_err() {
+        return Err(IFlowError::Config(format!(
+            "{} of {} per second is too small",
+            name, per_second
+        )));
+    }
+    Ok(())
+}
+
+/// Token bucket limiting how often prompts are sent
+///
+/// Holds at most one token, so prompts are spaced evenly without bursts.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// Available tokens; negative while acquirers are waiting for refills
+    tokens: f64,
+    /// Capacity of the bucket
+    max_tokens: f64,
+    /// When tokens were last added
+    last_refill: std::time::Instant,
+    /// Tokens added per second
+    refill_rate: f64,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    ///
+    /// # Arguments
+    /// * `requests_per_second` - The refill rate
+    ///
+    /// # Returns
+    /// * `Ok(TokenBucket)` for a valid rate
+    /// * `Err(IFlowError::Config)` if the rate is not positive and finite
+    pub(crate) fn new(requests_per_second: f64) -> crate::error::Result<Self> {
+        check_rate("rate_limit", requests_per_second)?;
+        Ok(Self {
+            tokens: 1.0,
+            max_tokens: 1.0,
+            last_refill: std::time::Instant::now(),
+            refill_rate: requests_per_second,
+        })
+    }
+
+    /// Take a token
+    ///
+    /// The token is taken even if it is not available yet, so concurrent
+    /// callers queue up behind each other.
+    ///
+    /// # Returns
+    /// How long to wait before the token may be used
+    pub(crate) fn acquire(&mut self) -> Duration {
+        if self.refill_rate.is_nan() || self.refill_rate <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let now = std::time::Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.max_tokens);
+        self.last_refill = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::try_from_secs_f64(-self.tokens / self.refill_rate).unwrap_or(Duration::MAX)
+        }
+    }
+}
+
 /// Configuration options for iFlow SDK
 ///
 /// This struct contains all the configuration options for the iFlow SDK,
@@ -515,8 +1733,21 @@ pub struct IFlowOptions {
     pub cwd: PathBuf,
     /// MCP servers to connect to
     pub mcp_servers: Vec<McpServer>,
-    /// Request timeout in seconds
+    /// Overall request timeout in seconds
+    ///
+    /// Deprecated in favour of `connect_timeout`, `operation_timeout` and
+    /// `prompt_timeout`; `with_timeout` sets all three. Still used as the
+    /// overall deadline of the `query` functions.
+    #[deprecated(
+        note = "use `connect_timeout`, `operation_timeout` and `prompt_timeout`; `with_timeout` sets all three"
+    )]
     pub timeout: f64,
+    /// Timeout in seconds for the WebSocket connection and the `//ready` signal
+    pub connect_timeout: f64,
+    /// Timeout in seconds for initialize, authenticate and session creation
+    pub operation_timeout: f64,
+    /// Timeout in seconds for the response to a prompt
+    pub prompt_timeout: f64,
     /// Additional metadata to include in requests
     pub metadata: HashMap<String, serde_json::Value>,
     /// File access configuration
@@ -531,14 +1762,50 @@ pub struct IFlowOptions {
     pub websocket: Option<WebSocketConfig>,
     /// Permission mode for tool calls
     pub permission_mode: PermissionMode,
+    /// Lifecycle and message hooks
+    pub hooks: IFlowHooks,
+    /// Hook consulted for tool calls
+    pub on_tool_call: Option<Arc<dyn ToolCallHook>>,
+    /// Callback deciding permission requests instead of the permission mode
+    pub permission_callback: Option<Arc<dyn PermissionCallback>>,
+    /// Whether to keep the conversation history in memory
+    pub keep_history: bool,
+    /// Message sent to each new session before the first user prompt
+    pub initial_message: Option<String>,
+    /// Standing instruction prepended to the first prompt of each new session
+    pub system_prompt: Option<String>,
+    /// Maximum number of notifications sent in one batch
+    pub max_batch_size: usize,
+    /// ACP protocol version requested in `initialize`
+    pub protocol_version: u32,
+    /// Maximum number of connections `IFlowClient::multi_session_query` opens at once
+    pub global_concurrency_limit: Option<usize>,
+    /// Maximum rate at which `messages()` delivers assistant chunks
+    pub message_rate_limit: Option<MessageRate>,
+    /// Maximum number of prompts sent per second by a client
+    pub rate_limit: Option<f64>,
+    /// Retry policy for protocol operations over WebSocket
+    pub retry_policy: RetryPolicy,
+    /// Format iFlow is asked to respond in
+    pub preferred_response_format: ResponseFormat,
+    /// Pre-configured permission decisions by tool name
+    pub tool_approvals: HashMap<String, PermissionDecision>,
+    /// Upper bound of a random delay added before each reconnect attempt
+    pub jitter: Option<Duration>,
+    /// Whether the client records the raw protocol, see `IFlowOptions::with_debug_mode`
+    pub debug: bool,
 }
 
 impl Default for IFlowOptions {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             mcp_servers: Vec::new(),
             timeout: 120.0,
+            connect_timeout: 10.0,
+            operation_timeout: 30.0,
+            prompt_timeout: 120.0,
             metadata: HashMap::new(),
             file_access: FileAccessConfig::default(),
             process: ProcessConfig::default(),
@@ -546,6 +1813,22 @@ impl Default for IFlowOptions {
             logging: LoggingConfig::default(),
             websocket: None,
             permission_mode: PermissionMode::Auto,
+            hooks: IFlowHooks::default(),
+            on_tool_call: None,
+            permission_callback: None,
+            keep_history: false,
+            initial_message: None,
+            system_prompt: None,
+            max_batch_size: 10,
+            protocol_version: crate::PROTOCOL_VERSION,
+            global_concurrency_limit: None,
+            message_rate_limit: None,
+            rate_limit: None,
+            retry_policy: RetryPolicy::default(),
+            preferred_response_format: ResponseFormat::PlainText,
+            tool_approvals: HashMap::new(),
+            jitter: None,
+            debug: false,
         }
     }
 }
@@ -556,6 +1839,165 @@ impl IFlowOptions {
         Self::default()
     }
 
+    /// Create options with every debugging feature enabled
+    ///
+    /// Shorthand for `IFlowOptions::new().with_debug_mode(true)`.
+    pub fn debug_mode() -> Self {
+        Self::new().with_debug_mode(true)
+    }
+
+    /// Enable or disable all debugging features at once
+    ///
+    /// When enabled, messages are logged at `DEBUG` level to
+    /// `./iflow-debug-{timestamp}.log`, the iFlow process is started with
+    /// `--debug` and the client records the raw protocol, available from
+    /// `IFlowClient::protocol_log`. When disabled, these settings are reset
+    /// to their defaults.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to enable debug mode
+    pub fn with_debug_mode(mut self, enabled: bool) -> Self {
+        self.debug = enabled;
+        self.process.debug = enabled;
+        self.logging = if enabled {
+            let timestamp = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            LoggingConfig {
+                enabled: true,
+                level: "DEBUG".to_string(),
+                logger_config: LoggerConfig {
+                    log_file: PathBuf::from(format!("./iflow-debug-{}.log", timestamp)),
+                    ..LoggerConfig::default()
+                },
+            }
+        } else {
+            LoggingConfig::default()
+        };
+        self
+    }
+
+    /// Create options for non-interactive CI pipelines
+    ///
+    /// Tool calls are approved automatically, messages are logged at `INFO`
+    /// level, iFlow's stderr is captured rather than mixed into the job
+    /// output, and timeouts are long enough for slow runners: 300s for
+    /// requests and 600s for prompts. Transient failures are retried up to
+    /// three times, backing off from 5s to at most 60s.
+    ///
+    /// # Example
+    /// ```
+    /// use iflow_cli_sdk_rust::IFlowOptions;
+    /// use iflow_cli_sdk_rust::types::PermissionMode;
+    ///
+    /// let options = IFlowOptions::for_ci_pipeline().with_auth_method_id("iflow".to_string());
+    /// assert_eq!(options.permission_mode, PermissionMode::Auto);
+    /// assert!(options.logging.enabled);
+    /// ```
+    pub fn for_ci_pipeline() -> Self {
+        let mut options = Self::new().with_timeout(300.0).with_prompt_timeout(600.0);
+        options.permission_mode = PermissionMode::Auto;
+        options.process.debug = false;
+        options.process.capture_stderr = true;
+        options.retry_policy = RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+            jitter: false,
+        };
+        options.logging = LoggingConfig {
+            enabled: true,
+            level: "INFO".to_string(),
+            logger_config: LoggerConfig::default(),
+        };
+        options
+    }
+
+    /// Create options for interactive use on a developer machine
+    ///
+    /// Only read, fetch and list tool calls are approved automatically,
+    /// iFlow's stderr goes to the terminal, message logging is off and the
+    /// conversation history is kept for inspection.
+    ///
+    /// # Example
+    /// ```
+    /// use iflow_cli_sdk_rust::IFlowOptions;
+    /// use iflow_cli_sdk_rust::types::PermissionMode;
+    ///
+    /// let options = IFlowOptions::for_local_dev().with_cwd(std::env::temp_dir());
+    /// assert_eq!(options.permission_mode, PermissionMode::Selective);
+    /// assert!(options.keep_history);
+    /// ```
+    pub fn for_local_dev() -> Self {
+        let mut options = Self::new();
+        options.permission_mode = PermissionMode::Selective;
+        options.process.capture_stderr = false;
+        options.logging.enabled = false;
+        options.keep_history = true;
+        options
+    }
+
+    /// Create options from `IFLOW_*` environment variables
+    ///
+    /// Reads `IFLOW_URL` (WebSocket URL of a running iFlow), `IFLOW_TIMEOUT`
+    /// (seconds), `IFLOW_PERMISSION_MODE` (`auto`, `manual` or `selective`)
+    /// and `IFLOW_AUTH_METHOD_ID`. Unset variables keep their defaults;
+    /// invalid values are logged and ignored.
+    pub fn from_env() -> Self {
+        let mut options = Self::default();
+
+        if let Ok(url) = std::env::var("IFLOW_URL") {
+            options = options.with_websocket_config(WebSocketConfig::new(url));
+        }
+        if let Ok(timeout) = std::env::var("IFLOW_TIMEOUT") {
+            match timeout.parse::<f64>() {
+                Ok(timeout) if timeout > 0.0 => options = options.with_timeout(timeout),
+                _ => tracing::warn!("Ignoring invalid IFLOW_TIMEOUT: {}", timeout),
+            }
+        }
+        if let Ok(mode) = std::env::var("IFLOW_PERMISSION_MODE") {
+            match serde_json::from_value(serde_json::Value::String(mode.to_lowercase())) {
+                Ok(mode) => options.permission_mode = mode,
+                Err(_) => tracing::warn!("Ignoring invalid IFLOW_PERMISSION_MODE: {}", mode),
+            }
+        }
+        if let Ok(method_id) = std::env::var("IFLOW_AUTH_METHOD_ID") {
+            options.auth_method_id = Some(method_id);
+        }
+
+        options
+    }
+
+    /// Create options suited to the environment the program runs in
+    ///
+    /// Starts from [`IFlowOptions::from_env`]. When `CI` is `true` or `1`,
+    /// as set by GitHub Actions and GitLab CI, the prompt timeout is raised
+    /// to 300s, tool calls are approved automatically, messages are logged
+    /// as JSON lines and iFlow's stderr is captured instead of written to the
+    /// terminal. Otherwise tool calls use `Selective` permissions unless
+    /// `IFLOW_PERMISSION_MODE` is set.
+    #[allow(deprecated)]
+    pub fn auto_configure() -> Self {
+        let mut options = Self::from_env();
+
+        let ci = std::env::var("CI")
+            .map(|ci| ci.eq_ignore_ascii_case("true") || ci == "1")
+            .unwrap_or(false);
+        if ci {
+            options.prompt_timeout = options.prompt_timeout.max(300.0);
+            options.timeout = options.timeout.max(options.prompt_timeout);
+            options.permission_mode = PermissionMode::Auto;
+            options.logging.enabled = true;
+            options.process.capture_stderr = true;
+        } else if std::env::var("IFLOW_PERMISSION_MODE").is_err() {
+            options.permission_mode = PermissionMode::Selective;
+        }
+
+        options
+    }
+
     /// Set the current working directory
     ///
     /// # Arguments
@@ -567,10 +2009,48 @@ impl IFlowOptions {
 
     /// Set the request timeout
     ///
+    /// Sets the connect, operation and prompt timeouts to the same value.
+    /// Prefer the more specific `with_*_timeout` builders.
+    ///
     /// # Arguments
     /// * `timeout` - Timeout in seconds
+    #[allow(deprecated)]
     pub fn with_timeout(mut self, timeout: f64) -> Self {
         self.timeout = timeout;
+        self.connect_timeout = timeout;
+        self.operation_timeout = timeout;
+        self.prompt_timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for connecting to iFlow
+    ///
+    /// Covers the WebSocket connection and the wait for iFlow's `//ready` signal.
+    ///
+    /// # Arguments
+    /// * `timeout` - Timeout in seconds
+    pub fn with_connect_timeout(mut self, timeout: f64) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for protocol operations
+    ///
+    /// Covers initialize, authenticate and session creation.
+    ///
+    /// # Arguments
+    /// * `timeout` - Timeout in seconds
+    pub fn with_operation_timeout(mut self, timeout: f64) -> Self {
+        self.operation_timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for prompt responses
+    ///
+    /// # Arguments
+    /// * `timeout` - Timeout in seconds
+    pub fn with_prompt_timeout(mut self, timeout: f64) -> Self {
+        self.prompt_timeout = timeout;
         self
     }
 
@@ -614,11 +2094,21 @@ impl IFlowOptions {
     ///
     /// # Arguments
     /// * `auto_start` - Whether to automatically start the iFlow process
-    pub fn with_auto_start(mut self, auto_start: bool) -> Self {
+    pub fn with_auto_start_process(mut self, auto_start: bool) -> Self {
         self.process.auto_start = auto_start;
         self
     }
 
+    /// Set auto start process
+    ///
+    /// Alias of [`IFlowOptions::with_auto_start_process`].
+    ///
+    /// # Arguments
+    /// * `auto_start` - Whether to automatically start the iFlow process
+    pub fn with_auto_start(self, auto_start: bool) -> Self {
+        self.with_auto_start_process(auto_start)
+    }
+
     /// Set authentication method ID
     ///
     /// # Arguments
@@ -654,6 +2144,861 @@ impl IFlowOptions {
         self.permission_mode = mode;
         self
     }
+
+    /// Add a random delay before each reconnect attempt
+    ///
+    /// Spreads out the reconnects of many clients that lost their connection
+    /// at the same time. Each attempt waits an extra `0..jitter`.
+    ///
+    /// # Arguments
+    /// * `jitter` - Upper bound of the random delay
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Set pre-configured permission decisions by tool name
+    ///
+    /// Permission requests for a tool in `approvals` are answered with its
+    /// decision instead of according to the permission mode.
+    ///
+    /// # Arguments
+    /// * `approvals` - The decision for each tool name
+    pub fn with_tool_approvals(mut self, approvals: HashMap<String, PermissionDecision>) -> Self {
+        self.tool_approvals = approvals;
+        self
+    }
+
+    /// Set lifecycle and message hooks
+    ///
+    /// # Arguments
+    /// * `hooks` - The hooks to call on connect, disconnect and message events
+    pub fn with_hooks(mut self, hooks: IFlowHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Set the handler for errors in background work
+    ///
+    /// Shorthand for setting `hooks.on_error`. Errors that the client can
+    /// recover from, such as a malformed message from iFlow, are otherwise
+    /// only logged.
+    ///
+    /// # Arguments
+    /// * `handler` - Called with each error
+    pub fn with_error_handler(
+        mut self,
+        handler: impl Fn(&IFlowError) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_error = Some(Arc::new(handler));
+        self
+    }
+
+    /// Set the hook consulted for tool calls
+    ///
+    /// # Arguments
+    /// * `hook` - The hook that can veto and observe tool calls
+    pub fn with_tool_call_hook(mut self, hook: Arc<dyn ToolCallHook>) -> Self {
+        self.on_tool_call = Some(hook);
+        self
+    }
+
+    /// Decide permission requests at runtime with a callback
+    ///
+    /// The callback takes precedence over `permission_mode`, so e.g. an
+    /// interactive CLI can ask the user about each tool call.
+    ///
+    /// # Arguments
+    /// * `callback` - The callback asked about each permission request
+    pub fn with_permission_callback(mut self, callback: Arc<dyn PermissionCallback>) -> Self {
+        self.permission_callback = Some(callback);
+        self
+    }
+
+    /// Set whether to keep the conversation history in memory
+    ///
+    /// # Arguments
+    /// * `keep_history` - Whether sent and received messages are recorded
+    pub fn with_history(mut self, keep_history: bool) -> Self {
+        self.keep_history = keep_history;
+        self
+    }
+
+    /// Set a message to send to each new session before the first user prompt
+    ///
+    /// The response to the initial message is consumed by the client and
+    /// does not show up in `IFlowClient::messages()`.
+    ///
+    /// # Arguments
+    /// * `message` - The initial message, e.g. instructions or project context
+    pub fn with_initial_message(mut self, message: String) -> Self {
+        self.initial_message = Some(message);
+        self
+    }
+
+    /// Set a standing instruction, e.g. a persona, language or output format
+    ///
+    /// The system prompt is sent as the first content block of the first
+    /// prompt to each session the client creates, including the initial
+    /// message. iFlow keeps it for the rest of the session, so it is sent
+    /// once per session creation: sessions resumed with `resume_session`,
+    /// `attach` or `reconnect` do not receive it again.
+    ///
+    /// # Arguments
+    /// * `system_prompt` - The instruction to prepend
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(system_prompt.into());
+        self
+    }
+
+    /// Set the maximum number of notifications sent in one batch
+    ///
+    /// # Arguments
+    /// * `size` - The batch size, at least 1
+    pub fn with_max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = size.max(1);
+        self
+    }
+
+    /// Limit how many connections `IFlowClient::multi_session_query` opens at once
+    ///
+    /// # Arguments
+    /// * `limit` - The maximum number of concurrent queries, at least 1
+    pub fn with_global_concurrency_limit(mut self, limit: usize) -> Self {
+        self.global_concurrency_limit = Some(limit.max(1));
+        self
+    }
+
+    /// Limit the rate at which `messages()` delivers assistant chunks
+    ///
+    /// Chunks arriving faster are delayed; other messages are never delayed.
+    ///
+    /// # Arguments
+    /// * `rate` - The maximum chunk rate
+    pub fn with_message_rate_limit(mut self, rate: MessageRate) -> Self {
+        self.message_rate_limit = Some(rate);
+        self
+    }
+
+    /// Set the retry policy for protocol operations over WebSocket
+    ///
+    /// # Arguments
+    /// * `policy` - The retry policy, e.g. `RetryPolicy::none()` to never retry
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Limit how many prompts a client sends per second
+    ///
+    /// Prompts sent faster wait until the rate allows them.
+    ///
+    /// # Arguments
+    /// * `requests_per_second` - The maximum prompt rate
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.rate_limit = Some(requests_per_second);
+        self
+    }
+
+    /// Set the format iFlow is asked to respond in
+    ///
+    /// For formats other than `PlainText`, an instruction is added to every
+    /// message sent with `send_message` and related methods.
+    ///
+    /// # Arguments
+    /// * `format` - The preferred response format
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.preferred_response_format = format;
+        self
+    }
+
+    /// Set the ACP protocol version requested from iFlow
+    ///
+    /// iFlow may answer with a lower version, which is logged as a warning.
+    /// ACP versions are 16-bit, so connecting fails with `IFlowError::Config`
+    /// for versions above `u16::MAX`.
+    ///
+    /// # Arguments
+    /// * `version` - The protocol version, `PROTOCOL_VERSION` by default
+    pub fn with_protocol_version(mut self, version: u32) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
+    /// Compare the serializable fields of two sets of options
+    ///
+    /// Hooks, MCP servers, logging, file access and WebSocket settings are
+    /// not compared. Nested fields are named with a `process.` prefix.
+    ///
+    /// # Arguments
+    /// * `other` - The options to compare against
+    ///
+    /// # Returns
+    /// The fields whose value differs, with their value in `self` and `other`
+    pub fn diff(&self, other: &IFlowOptions) -> IFlowOptionsDiff {
+        let changes = self
+            .patch_fields()
+            .into_iter()
+            .zip(other.patch_fields())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((field, old), (_, new))| (field.to_string(), old, new))
+            .collect();
+        IFlowOptionsDiff { changes }
+    }
+
+    /// Apply a diff to a copy of these options
+    ///
+    /// Every changed field is set to its new value; the old value recorded
+    /// in the diff is not checked.
+    ///
+    /// # Arguments
+    /// * `patch` - The changes to apply, usually from `diff`
+    ///
+    /// # Returns
+    /// * `Ok(IFlowOptions)` with the changes applied
+    /// * `Err(IFlowError)` if a field is unknown or a value has the wrong type
+    pub fn apply_patch(&self, patch: &IFlowOptionsDiff) -> crate::error::Result<IFlowOptions> {
+        let mut options = self.clone();
+        for (field, _, new) in &patch.changes {
+            options.set_patch_field(field, new.clone())?;
+        }
+        Ok(options)
+    }
+
+    /// The fields compared by `diff`, in a fixed order
+    #[allow(deprecated)]
+    fn patch_fields(&self) -> Vec<(&'static str, serde_json::Value)> {
+        use serde_json::json;
+        vec![
+            ("cwd", json!(self.cwd)),
+            ("timeout", json!(self.timeout)),
+            ("connect_timeout", json!(self.connect_timeout)),
+            ("operation_timeout", json!(self.operation_timeout)),
+            ("prompt_timeout", json!(self.prompt_timeout)),
+            ("metadata", json!(self.metadata)),
+            ("auth_method_id", json!(self.auth_method_id)),
+            ("permission_mode", json!(self.permission_mode)),
+            ("keep_history", json!(self.keep_history)),
+            ("debug", json!(self.debug)),
+            ("initial_message", json!(self.initial_message)),
+            ("system_prompt", json!(self.system_prompt)),
+            ("max_batch_size", json!(self.max_batch_size)),
+            ("protocol_version", json!(self.protocol_version)),
+            (
+                "global_concurrency_limit",
+                json!(self.global_concurrency_limit),
+            ),
+            ("rate_limit", json!(self.rate_limit)),
+            ("process.auto_start", json!(self.process.auto_start)),
+            ("process.start_port", json!(self.process.start_port)),
+            ("process.debug", json!(self.process.debug)),
+            ("process.capture_stderr", json!(self.process.capture_stderr)),
+        ]
+    }
+
+    /// The fields compared by `diff` as a JSON object, with secrets redacted
+    pub(crate) fn redacted_json(&self) -> serde_json::Value {
+        let fields = self
+            .patch_fields()
+            .into_iter()
+            .map(|(field, value)| match field {
+                "auth_method_id" if !value.is_null() => (field, serde_json::json!("[redacted]")),
+                _ => (field, value),
+            })
+            .map(|(field, value)| (field.to_string(), value))
+            .collect();
+        serde_json::Value::Object(fields)
+    }
+
+    /// Set one of the fields compared by `diff`
+    #[allow(deprecated)]
+    fn set_patch_field(
+        &mut self,
+        field: &str,
+        value: serde_json::Value,
+    ) -> crate::error::Result<()> {
+        use serde_json::from_value;
+        match field {
+            "cwd" => self.cwd = from_value(value)?,
+            "timeout" => self.timeout = from_value(value)?,
+            "connect_timeout" => self.connect_timeout = from_value(value)?,
+            "operation_timeout" => self.operation_timeout = from_value(value)?,
+            "prompt_timeout" => self.prompt_timeout = from_value(value)?,
+            "metadata" => self.metadata = from_value(value)?,
+            "auth_method_id" => self.auth_method_id = from_value(value)?,
+            "permission_mode" => self.permission_mode = from_value(value)?,
+            "keep_history" => self.keep_history = from_value(value)?,
+            "debug" => self.debug = from_value(value)?,
+            "initial_message" => self.initial_message = from_value(value)?,
+            "system_prompt" => self.system_prompt = from_value(value)?,
+            "max_batch_size" => self.max_batch_size = from_value(value)?,
+            "protocol_version" => self.protocol_version = from_value(value)?,
+            "global_concurrency_limit" => self.global_concurrency_limit = from_value(value)?,
+            "rate_limit" => self.rate_limit = from_value(value)?,
+            "process.auto_start" => self.process.auto_start = from_value(value)?,
+            "process.start_port" => self.process.start_port = from_value(value)?,
+            "process.debug" => self.process.debug = from_value(value)?,
+            "process.capture_stderr" => self.process.capture_stderr = from_value(value)?,
+            _ => {
+                return Err(IFlowError::Validation(format!(
+                    "Unknown option in patch: {}",
+                    field
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `rate_limit` and `message_rate_limit` are usable rates
+    ///
+    /// # Returns
+    /// * `Ok(())` if both are unset or valid
+    /// * `Err(IFlowError::Config)` naming the invalid rate
+    pub fn validate_rate_limits(&self) -> crate::error::Result<()> {
+        if let Some(rate) = self.rate_limit {
+            check_rate("rate_limit", rate)?;
+        }
+        if let Some(rate) = &self.message_rate_limit {
+            rate.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Check that the environment matches the options
+    ///
+    /// Runs these checks concurrently:
+    /// * in manual start WebSocket mode, the URL's host and port accept TCP
+    ///   connections within 2 seconds
+    /// * if logging is enabled, the log file's directory exists or can be created
+    /// * every directory in `file_access.allowed_dirs` exists
+    ///
+    /// The rate limits are checked first with `validate_rate_limits`.
+    ///
+    /// # Returns
+    /// * `Ok(())` if all checks passed
+    /// * `Err(IFlowError::Config)` describing the first failed check
+    pub async fn validate_async(&self) -> crate::error::Result<()> {
+        self.validate_rate_limits()?;
+        tokio::try_join!(
+            self.check_websocket_reachable(),
+            self.check_log_dir_writable(),
+            self.check_allowed_dirs_exist(),
+        )?;
+        Ok(())
+    }
+
+    async fn check_websocket_reachable(&self) -> crate::error::Result<()> {
+        // Auto-started iFlow is not expected to be running yet
+        if self.process.auto_start {
+            return Ok(());
+        }
+        let Some(url) = self.websocket.as_ref().and_then(|ws| ws.url.as_ref()) else {
+            return Ok(());
+        };
+
+        let parsed = url::Url::parse(url)
+            .map_err(|e| IFlowError::Config(format!("Invalid WebSocket URL {}: {}", url, e)))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| IFlowError::Config(format!("WebSocket URL {} has no host", url)))?;
+        let port = parsed
+            .port_or_known_default()
+            .ok_or_else(|| IFlowError::Config(format!("WebSocket URL {} has no port", url)))?;
+
+        match tokio::time::timeout(
+            Duration::from_secs(2),
+            tokio::net::TcpStream::connect((host, port)),
+        )
+        .await
+        {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(IFlowError::Config(format!(
+                "WebSocket URL {} is not reachable: {}",
+                url, e
+            ))),
+            Err(_) => Err(IFlowError::Config(format!(
+                "WebSocket URL {} did not accept a connection within 2s",
+                url
+            ))),
+        }
+    }
+
+    async fn check_log_dir_writable(&self) -> crate::error::Result<()> {
+        if !self.logging.enabled {
+            return Ok(());
+        }
+        match self.logging.logger_config.log_file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => {
+                tokio::fs::create_dir_all(dir).await.map_err(|e| {
+                    IFlowError::Config(format!(
+                        "Cannot create log directory {}: {}",
+                        dir.display(),
+                        e
+                    ))
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn check_allowed_dirs_exist(&self) -> crate::error::Result<()> {
+        for dir in self.file_access.allowed_dirs.iter().flatten() {
+            let metadata = tokio::fs::metadata(dir).await.map_err(|e| {
+                IFlowError::Config(format!("Allowed directory {}: {}", dir.display(), e))
+            })?;
+            if !metadata.is_dir() {
+                return Err(IFlowError::Config(format!(
+                    "Allowed directory {} is not a directory",
+                    dir.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Current version of the session file format
+pub const SESSION_FILE_VERSION: u32 = 1;
+
+/// Non-sensitive subset of `IFlowOptions` stored in session files
+///
+/// Authentication settings, metadata and MCP server definitions (which may
+/// carry credentials in their environment) are intentionally left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionsSnapshot {
+    /// Current working directory
+    pub cwd: PathBuf,
+    /// Request timeout in seconds
+    pub timeout: f64,
+    /// Permission mode for tool calls
+    pub permission_mode: PermissionMode,
+    /// Whether the session used a WebSocket connection
+    pub use_websocket: bool,
+    /// WebSocket URL, if one was configured
+    pub websocket_url: Option<String>,
+    /// Whether the iFlow process was started automatically
+    pub auto_start: bool,
+    /// Port the iFlow process was started on
+    pub start_port: Option<u16>,
+    /// Whether the iFlow process was started in debug mode
+    pub debug: bool,
+    /// Whether the conversation history was kept
+    pub keep_history: bool,
+}
+
+impl OptionsSnapshot {
+    /// Capture the non-sensitive fields of `options`
+    #[allow(deprecated)]
+    pub fn from_options(options: &IFlowOptions) -> Self {
+        Self {
+            cwd: options.cwd.clone(),
+            timeout: options.timeout,
+            permission_mode: options.permission_mode,
+            use_websocket: options.websocket.is_some(),
+            websocket_url: options.websocket.as_ref().and_then(|ws| ws.url.clone()),
+            auto_start: options.process.auto_start,
+            start_port: options.process.start_port,
+            debug: options.process.debug,
+            keep_history: options.keep_history,
+        }
+    }
+
+    /// Rebuild options from the snapshot, using defaults for everything else
+    pub fn to_options(&self) -> IFlowOptions {
+        let mut options = IFlowOptions::new()
+            .with_cwd(self.cwd.clone())
+            .with_timeout(self.timeout)
+            .with_permission_mode(self.permission_mode)
+            .with_auto_start(self.auto_start)
+            .with_history(self.keep_history);
+        options.process.start_port = self.start_port;
+        options.process.debug = self.debug;
+        if self.use_websocket {
+            options.websocket = Some(match &self.websocket_url {
+                Some(url) => WebSocketConfig::new(url.clone()),
+                None => WebSocketConfig::auto_start(),
+            });
+        }
+        options
+    }
+}
+
+/// Portable representation of a session, written by `IFlowClient::export_session`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    /// Format version, currently always `SESSION_FILE_VERSION`
+    pub version: u32,
+    /// ACP session ID
+    pub session_id: Option<String>,
+    /// Conversation history (empty unless `keep_history` was enabled)
+    pub history: Vec<Message>,
+    /// Non-sensitive client options
+    pub options_snapshot: OptionsSnapshot,
+    /// Tool call messages received during the session
+    pub tool_call_log: Vec<Message>,
+}
+
+/// Minimal state needed to resume a session, see `IFlowClient::checkpoint`
+///
+/// Unlike `SessionFile`, a checkpoint does not include the conversation history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionCheckpoint {
+    /// ACP session ID
+    pub session_id: String,
+    /// When the last message was sent or received
+    pub last_message_at: Option<SystemTime>,
+    /// IDs of tool calls that had not completed or failed
+    pub pending_tool_calls: Vec<String>,
+}
+
+impl SessionCheckpoint {
+    /// Serialize the checkpoint to JSON
+    pub fn to_json(&self) -> crate::error::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Deserialize a checkpoint from JSON
+    ///
+    /// # Arguments
+    /// * `json` - JSON written by `to_json`
+    pub fn from_json(json: &str) -> crate::error::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Transport used to communicate with iFlow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionType {
+    /// ACP over the stdio of an iFlow child process
+    Stdio,
+    /// ACP over a WebSocket connection
+    WebSocket,
+}
+
+impl std::fmt::Display for ConnectionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionType::Stdio => write!(f, "stdio"),
+            ConnectionType::WebSocket => write!(f, "WebSocket"),
+        }
+    }
+}
+
+/// Non-sensitive summary of `IFlowOptions` for diagnostics
+///
+/// Authentication settings are reported by presence only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IFlowOptionsSummary {
+    /// Current working directory
+    pub cwd: PathBuf,
+    /// Request timeout in seconds
+    pub timeout: f64,
+    /// Permission mode for tool calls
+    pub permission_mode: PermissionMode,
+    /// WebSocket URL, if one was configured
+    pub websocket_url: Option<String>,
+    /// Whether the iFlow process is started automatically
+    pub auto_start: bool,
+    /// Number of configured MCP servers
+    pub mcp_server_count: usize,
+    /// Whether an authentication method ID is configured
+    pub has_auth_method_id: bool,
+    /// Whether message logging is enabled
+    pub logging_enabled: bool,
+    /// Whether the conversation history is kept
+    pub keep_history: bool,
+}
+
+impl IFlowOptionsSummary {
+    /// Summarize `options`
+    #[allow(deprecated)]
+    pub fn from_options(options: &IFlowOptions) -> Self {
+        Self {
+            cwd: options.cwd.clone(),
+            timeout: options.timeout,
+            permission_mode: options.permission_mode,
+            websocket_url: options.websocket.as_ref().and_then(|ws| ws.url.clone()),
+            auto_start: options.process.auto_start,
+            mcp_server_count: options.mcp_servers.len(),
+            has_auth_method_id: options.auth_method_id.is_some(),
+            logging_enabled: options.logging.enabled,
+            keep_history: options.keep_history,
+        }
+    }
+}
+
+/// Changes between two sets of options, see `IFlowOptions::diff`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IFlowOptionsDiff {
+    /// The changed fields, with their old and new value
+    pub changes: Vec<(String, serde_json::Value, serde_json::Value)>,
+}
+
+impl IFlowOptionsDiff {
+    /// Whether no field changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The names of the changed fields
+    pub fn fields(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .map(|(field, _, _)| field.as_str())
+            .collect()
+    }
+}
+
+/// Point-in-time state of an `IFlowClient`, for debugging and support
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// ACP session ID, once a session has been created
+    pub session_id: Option<String>,
+    /// Transport in use, if connected
+    pub connection_type: Option<ConnectionType>,
+    /// Whether the client is connected
+    pub connected: bool,
+    /// Messages sent to and received from iFlow
+    pub message_count: u64,
+    /// Tool calls reported as completed
+    pub tool_calls_completed: u64,
+    /// When the last message was sent or received
+    pub last_message_at: Option<SystemTime>,
+    /// iFlow version, if reported by iFlow
+    pub iflow_version: Option<String>,
+    /// Non-sensitive summary of the client options
+    pub options_summary: IFlowOptionsSummary,
+}
+
+impl std::fmt::Display for SessionSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.connected, self.connection_type) {
+            (true, Some(connection_type)) => writeln!(f, "Connection: {}", connection_type)?,
+            _ => writeln!(f, "Connection: not connected")?,
+        }
+        writeln!(
+            f,
+            "Session: {}",
+            self.session_id.as_deref().unwrap_or("none")
+        )?;
+        writeln!(
+            f,
+            "Messages: {} ({} tool calls completed)",
+            self.message_count, self.tool_calls_completed
+        )?;
+        match self.last_message_at.and_then(|at| at.elapsed().ok()) {
+            Some(elapsed) => writeln!(f, "Last message: {}s ago", elapsed.as_secs())?,
+            None => writeln!(f, "Last message: never")?,
+        }
+        writeln!(
+            f,
+            "iFlow version: {}",
+            self.iflow_version.as_deref().unwrap_or("unknown")
+        )?;
+
+        let options = &self.options_summary;
+        write!(
+            f,
+            "Options: cwd={}, timeout={}s, permission_mode={:?}, auto_start={}, mcp_servers={}, auth_method={}",
+            options.cwd.display(),
+            options.timeout,
+            options.permission_mode,
+            options.auto_start,
+            options.mcp_server_count,
+            if options.has_auth_method_id {
+                "set"
+            } else {
+                "unset"
+            }
+        )
+    }
+}
+
+/// A message with the time and session it was received in
+///
+/// Yielded by `IFlowClient::stream_with_metadata`.
+#[derive(Debug, Clone)]
+pub struct MessageWithMeta {
+    /// The message
+    pub message: Message,
+    /// When the message was read from the client
+    pub received_at: SystemTime,
+    /// The session the client was attached to when the stream was created
+    pub session_id: Option<String>,
+    /// Position of the message in the stream, starting at 0
+    pub sequence: u64,
+}
+
+impl MessageWithMeta {
+    /// Time elapsed between an earlier message and this one
+    ///
+    /// # Arguments
+    /// * `prior` - The earlier message
+    ///
+    /// # Returns
+    /// The latency, or zero if `prior` was received later
+    pub fn latency_since(&self, prior: &MessageWithMeta) -> Duration {
+        self.received_at
+            .duration_since(prior.received_at)
+            .unwrap_or_default()
+    }
+}
+
+/// Complete state of an `IFlowClient` for bug reports, see `IFlowClient::debug_dump`
+///
+/// Authentication settings are redacted.
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugDump {
+    /// Connection and session state
+    pub snapshot: SessionSnapshot,
+    /// The last 100 raw protocol messages, if a `RecordingObserver` is attached
+    pub protocol_log: Vec<(Direction, serde_json::Value)>,
+    /// The last 10 lines the iFlow process wrote to stderr, if it was started by the client
+    pub recent_stderr: Vec<String>,
+    /// Session-level metrics
+    pub statistics: ClientStatistics,
+    /// Messages received but not yet read from the message stream
+    pub message_queue_depth: usize,
+    /// The client options, with authentication settings redacted
+    pub options_redacted: serde_json::Value,
+    /// Version of this SDK
+    pub rust_sdk_version: &'static str,
+}
+
+/// Number of undelivered messages above which the message queue is considered backed up
+pub const MESSAGE_BACKLOG_LIMIT: usize = 1000;
+
+/// Prefix of corrections sent as a new prompt by `IFlowClient::send_correction`
+pub const CORRECTION_MARKER: &str = "[Correction]";
+
+/// How serious a protocol violation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Unusual, but the connection keeps working
+    Warning,
+    /// The connection is in a state the protocol does not allow
+    Error,
+}
+
+/// A broken invariant of the ACP connection, see `IFlowClient::assert_protocol_invariants`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolViolation {
+    /// How serious the violation is
+    pub severity: Severity,
+    /// What is wrong
+    pub description: String,
+}
+
+impl ProtocolViolation {
+    fn new(severity: Severity, description: &str) -> Self {
+        Self {
+            severity,
+            description: description.to_string(),
+        }
+    }
+}
+
+/// Protocol state of a connection, checked by [`ProtocolState::violations`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtocolState {
+    /// Whether the client is connected
+    pub connected: bool,
+    /// Whether the ACP `initialize` handshake has completed
+    pub initialized: bool,
+    /// Whether authentication has completed, or `None` if the connection does not track it
+    pub authenticated: Option<bool>,
+    /// The current session ID
+    pub session_id: Option<String>,
+    /// Whether the request ID counter has wrapped around
+    pub request_ids_wrapped: bool,
+    /// Number of received messages not yet delivered
+    pub queued_messages: usize,
+}
+
+impl ProtocolState {
+    /// Check the invariants of the protocol
+    ///
+    /// # Returns
+    /// The broken invariants, or an empty list if the state is valid
+    pub fn violations(&self) -> Vec<ProtocolViolation> {
+        let mut violations = Vec::new();
+        if !self.connected {
+            return violations;
+        }
+
+        if self.session_id.is_some() && !self.initialized {
+            violations.push(ProtocolViolation::new(
+                Severity::Error,
+                "A session is in use before the protocol was initialized",
+            ));
+        }
+        if self.session_id.is_some() && self.authenticated == Some(false) {
+            violations.push(ProtocolViolation::new(
+                Severity::Error,
+                "A session is in use before authentication completed",
+            ));
+        }
+        if self.session_id.is_none() {
+            violations.push(ProtocolViolation::new(
+                Severity::Warning,
+                "The connection has no session yet",
+            ));
+        }
+        if self.request_ids_wrapped {
+            violations.push(ProtocolViolation::new(
+                Severity::Warning,
+                "The request ID counter has wrapped around",
+            ));
+        }
+        if self.queued_messages >= MESSAGE_BACKLOG_LIMIT {
+            violations.push(ProtocolViolation::new(
+                Severity::Warning,
+                &format!(
+                    "{} received messages have not been consumed",
+                    self.queued_messages
+                ),
+            ));
+        }
+        violations
+    }
+}
+
+impl std::fmt::Display for DebugDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "iFlow Rust SDK {}", self.rust_sdk_version)?;
+        writeln!(f, "{}", self.snapshot)?;
+        writeln!(f, "Queued messages: {}", self.message_queue_depth)?;
+
+        let stats = &self.statistics;
+        writeln!(
+            f,
+            "Tool calls: {} ({} successful, {} failed)",
+            stats.tool_calls_total, stats.tool_calls_successful, stats.tool_calls_failed
+        )?;
+        writeln!(
+            f,
+            "Permission requests: {} approved, {} cancelled",
+            stats.permission_requests_auto_approved, stats.permission_requests_cancelled
+        )?;
+        writeln!(f, "Options: {}", self.options_redacted)?;
+
+        if !self.recent_stderr.is_empty() {
+            writeln!(f, "Recent stderr:")?;
+            for line in &self.recent_stderr {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        write!(f, "Protocol log: {} messages", self.protocol_log.len())?;
+        for (direction, json) in &self.protocol_log {
+            let arrow = match direction {
+                Direction::Sent => "->",
+                Direction::Received => "<-",
+            };
+            write!(f, "\n  {} {}", arrow, json)?;
+        }
+        Ok(())
+    }
 }
 
 /// Error message details
@@ -748,7 +3093,36 @@ pub enum Message {
     },
 }
 
+/// The type of a [`Message`], without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    /// `Message::User`
+    User,
+    /// `Message::Assistant`
+    Assistant,
+    /// `Message::ToolCall`
+    ToolCall,
+    /// `Message::Plan`
+    Plan,
+    /// `Message::TaskFinish`
+    TaskFinish,
+    /// `Message::Error`
+    Error,
+}
+
 impl Message {
+    /// Get the type of this message
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Message::User { .. } => MessageType::User,
+            Message::Assistant { .. } => MessageType::Assistant,
+            Message::ToolCall { .. } => MessageType::ToolCall,
+            Message::Plan { .. } => MessageType::Plan,
+            Message::TaskFinish { .. } => MessageType::TaskFinish,
+            Message::Error { .. } => MessageType::Error,
+        }
+    }
+
     /// Check if this is a task finish message
     pub fn is_task_finish(&self) -> bool {
         matches!(self, Message::TaskFinish { .. })
@@ -809,3 +3183,109 @@ impl Message {
         }
     }
 }
+
+/// Outcome of `IFlowClient::recover_from_error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// The client reconnected; the message should be sent again
+    Reconnected,
+    /// The connection is fine; the message should be sent again
+    RetrySend,
+    /// The error cannot be recovered from
+    Abort(String),
+}
+
+/// Session-level metrics of an `IFlowClient`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClientStatistics {
+    /// Estimated tokens in assistant responses (characters / 4)
+    pub total_tokens_estimated: u64,
+    /// Tool calls reported by iFlow
+    pub tool_calls_total: u64,
+    /// Tool calls reported as completed
+    pub tool_calls_successful: u64,
+    /// Tool calls reported as failed
+    pub tool_calls_failed: u64,
+    /// Permission requests approved automatically
+    pub permission_requests_auto_approved: u64,
+    /// Permission requests cancelled
+    pub permission_requests_cancelled: u64,
+    /// Time since connecting, or the length of the last connection once disconnected
+    pub session_duration: Option<Duration>,
+    /// Average time `send_message` took to complete
+    pub average_response_time: Option<Duration>,
+}
+
+/// Counters behind `ClientStatistics`, shared by the client and its protocol handlers
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatisticsCounters {
+    response_chars: Arc<AtomicU64>,
+    tool_calls_total: Arc<AtomicU64>,
+    tool_calls_successful: Arc<AtomicU64>,
+    tool_calls_failed: Arc<AtomicU64>,
+    permission_requests_auto_approved: Arc<AtomicU64>,
+    permission_requests_cancelled: Arc<AtomicU64>,
+    response_time_micros: Arc<AtomicU64>,
+    responses: Arc<AtomicU64>,
+}
+
+impl StatisticsCounters {
+    /// Count a message received from iFlow
+    pub(crate) fn record_message(&self, msg: &Message) {
+        match msg {
+            Message::Assistant { content } => {
+                self.response_chars
+                    .fetch_add(content.chars().count() as u64, Ordering::Relaxed);
+            }
+            Message::ToolCall { status, .. } => {
+                self.tool_calls_total.fetch_add(1, Ordering::Relaxed);
+                if status.eq_ignore_ascii_case("completed") {
+                    self.tool_calls_successful.fetch_add(1, Ordering::Relaxed);
+                } else if status.eq_ignore_ascii_case("failed") {
+                    self.tool_calls_failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Count the outcome of a permission request
+    pub(crate) fn record_permission(&self, approved: bool) {
+        let counter = if approved {
+            &self.permission_requests_auto_approved
+        } else {
+            &self.permission_requests_cancelled
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count the time taken by a `send_message` call
+    pub(crate) fn record_response_time(&self, elapsed: Duration) {
+        self.response_time_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read the counters
+    pub(crate) fn statistics(&self, session_duration: Option<Duration>) -> ClientStatistics {
+        let responses = self.responses.load(Ordering::Relaxed);
+        let average_response_time = (responses > 0).then(|| {
+            Duration::from_micros(self.response_time_micros.load(Ordering::Relaxed) / responses)
+        });
+
+        ClientStatistics {
+            total_tokens_estimated: self.response_chars.load(Ordering::Relaxed) / 4,
+            tool_calls_total: self.tool_calls_total.load(Ordering::Relaxed),
+            tool_calls_successful: self.tool_calls_successful.load(Ordering::Relaxed),
+            tool_calls_failed: self.tool_calls_failed.load(Ordering::Relaxed),
+            permission_requests_auto_approved: self
+                .permission_requests_auto_approved
+                .load(Ordering::Relaxed),
+            permission_requests_cancelled: self
+                .permission_requests_cancelled
+                .load(Ordering::Relaxed),
+            session_duration,
+            average_response_time,
+        }
+    }
+}
@@ -2,13 +2,31 @@
 //!
 //! This module provides the low-level WebSocket communication layer.
 //! It handles connection management, message sending/receiving, and
-//! basic error handling.
+//! basic error handling. With the `wss` feature, `wss://` URLs are connected
+//! over TLS with rustls.
+//! Frames are read by a background task per connection, which also answers
+//! pings and sends keepalive pings.
 
 use crate::error::{IFlowError, Result};
+use crate::types::RawProtocolObserver;
+use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
+#[cfg(feature = "wss")]
+use rustls_pki_types::CertificateDer;
+#[cfg(feature = "wss")]
+use rustls_pki_types::pem::PemObject;
 use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tokio_tungstenite::{WebSocketStream, connect_async, tungstenite::protocol::Message};
+use tokio::sync::{Mutex, mpsc};
+#[cfg(not(feature = "wss"))]
+use tokio_tungstenite::connect_async_with_config;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+#[cfg(feature = "wss")]
+use tokio_tungstenite::{Connector, connect_async_tls_with_config};
+use tokio_tungstenite::{WebSocketStream, tungstenite::protocol::Message};
 use tracing::debug;
 use url::Url;
 
@@ -21,11 +39,44 @@ pub struct WebSocketTransport {
     /// WebSocket URL to connect to
     url: String,
     /// Active WebSocket connection (if connected)
-    websocket: Option<WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>>,
-    /// Whether the transport is currently connected
-    connected: bool,
+    websocket: Option<ActiveConnection>,
     /// Connection timeout in seconds
     timeout: f64,
+    /// Number of connection attempts made by `reconnect()`
+    reconnect_attempts: u32,
+    /// Delay before the first retry in `reconnect()`, doubled after each failure
+    reconnect_interval: Duration,
+    /// Upper bound of a random delay added before each reconnect attempt
+    jitter: Option<Duration>,
+    /// Number of successful reconnects
+    reconnect_count: u32,
+    /// Observer of every JSON message sent and received
+    observer: Option<Arc<dyn RawProtocolObserver>>,
+    /// PEM file of CA certificates trusted for `wss://` besides the native roots
+    #[cfg_attr(not(feature = "wss"), allow(dead_code))]
+    tls_ca_bundle: Option<PathBuf>,
+    /// Interval of keepalive pings, if any
+    ping_interval: Option<Duration>,
+}
+
+type WsStream = WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+type WsSink = Arc<Mutex<SplitSink<WsStream, Message>>>;
+
+/// An established connection and the task reading its frames
+struct ActiveConnection {
+    /// Write half, shared with the reader task for pings and pongs
+    sink: WsSink,
+    /// Text and binary messages read by the reader task, then its final error
+    incoming: mpsc::UnboundedReceiver<Result<Message>>,
+    /// Cleared by the reader task when the connection is lost
+    connected: Arc<AtomicBool>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
 }
 
 impl WebSocketTransport {
@@ -38,9 +89,115 @@ impl WebSocketTransport {
         Self {
             url,
             websocket: None,
-            connected: false,
             timeout,
+            reconnect_attempts: 3,
+            reconnect_interval: Duration::from_secs(5),
+            jitter: None,
+            reconnect_count: 0,
+            observer: None,
+            tls_ca_bundle: None,
+            ping_interval: None,
+        }
+    }
+
+    /// Set the retry behaviour of `reconnect()`
+    ///
+    /// # Arguments
+    /// * `attempts` - Number of connection attempts
+    /// * `interval` - Delay before the first retry, doubled after each failure
+    pub fn with_reconnect_settings(mut self, attempts: u32, interval: Duration) -> Self {
+        self.reconnect_attempts = attempts;
+        self.reconnect_interval = interval;
+        self
+    }
+
+    /// Add a random delay of `0..jitter` before each attempt in `reconnect()`
+    ///
+    /// # Arguments
+    /// * `jitter` - Upper bound of the random delay, or `None` for no delay
+    pub fn with_jitter(mut self, jitter: Option<Duration>) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set an observer of the JSON messages sent and received
+    ///
+    /// Messages that are not JSON, such as `//ready`, are not observed.
+    ///
+    /// # Arguments
+    /// * `observer` - The observer to notify
+    pub fn with_observer(mut self, observer: Option<Arc<dyn RawProtocolObserver>>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Trust additional CA certificates for `wss://` connections
+    ///
+    /// TLS is used for `wss://` URLs, verifying the server against the
+    /// platform's root certificates and the certificates in the bundle.
+    /// Without the `wss` feature, connecting to a `wss://` URL fails with
+    /// `IFlowError::Config`.
+    ///
+    /// # Arguments
+    /// * `bundle` - Path of a PEM file of CA certificates, or `None` for the native roots only
+    pub fn with_tls_ca_bundle(mut self, bundle: Option<PathBuf>) -> Self {
+        self.tls_ca_bundle = bundle;
+        self
+    }
+
+    /// Send keepalive pings while connected
+    ///
+    /// A ping is sent every `interval`. If the pong to a ping has not
+    /// arrived when the next ping is due, the transport is disconnected
+    /// and `receive()` fails with `IFlowError::Connection("ping timeout")`;
+    /// `reconnect()` can then re-establish the connection.
+    ///
+    /// # Arguments
+    /// * `interval` - Time between pings, or `None` to send no pings
+    pub fn with_ping_interval(mut self, interval: Option<Duration>) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Build the TLS connector for the configured CA bundle
+    ///
+    /// # Returns
+    /// * `Ok(Some(Connector))` trusting the native roots and the bundle
+    /// * `Ok(None)` without a bundle, to use the default connector
+    /// * `Err(IFlowError::Config)` if the bundle cannot be read or has no certificates
+    #[cfg(feature = "wss")]
+    fn tls_connector(&self) -> Result<Option<Connector>> {
+        let Some(path) = &self.tls_ca_bundle else {
+            return Ok(None);
+        };
+        let invalid_bundle = |e: &dyn std::fmt::Display| {
+            IFlowError::Config(format!("Invalid CA bundle {}: {}", path.display(), e))
+        };
+
+        let mut roots = rustls::RootCertStore::empty();
+        let native = rustls_native_certs::load_native_certs();
+        if !native.errors.is_empty() {
+            debug!(
+                "Errors loading native root certificates: {:?}",
+                native.errors
+            );
+        }
+        roots.add_parsable_certificates(native.certs);
+
+        let certs = CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+            .map_err(|e| invalid_bundle(&e))?;
+        if certs.is_empty() {
+            return Err(invalid_bundle(&"no certificates found"));
+        }
+        for cert in certs {
+            roots.add(cert).map_err(|e| invalid_bundle(&e))?;
         }
+
+        let config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Some(Connector::Rustls(Arc::new(config))))
     }
 
     /// Establish WebSocket connection
@@ -49,7 +206,7 @@ impl WebSocketTransport {
     /// * `Ok(())` if the connection was successful
     /// * `Err(IFlowError)` if the connection failed
     pub async fn connect(&mut self) -> Result<()> {
-        if self.connected {
+        if self.is_connected() {
             tracing::warn!("Already connected to {}", self.url);
             return Ok(());
         }
@@ -57,20 +214,54 @@ impl WebSocketTransport {
         debug!("Connecting to {}", self.url);
 
         // Parse URL to validate it
-        let _url = Url::parse(&self.url)
+        let url = Url::parse(&self.url)
+            .map_err(|e| IFlowError::Connection(format!("Invalid URL: {}", e)))?;
+        #[cfg(feature = "wss")]
+        let connector = match url.scheme() {
+            "wss" => self.tls_connector()?,
+            _ => None,
+        };
+        #[cfg(not(feature = "wss"))]
+        if url.scheme() == "wss" {
+            return Err(IFlowError::Config(
+                "Connecting to wss:// URLs requires the `wss` feature".to_string(),
+            ));
+        }
+
+        let request = self
+            .url
+            .as_str()
+            .into_client_request()
             .map_err(|e| IFlowError::Connection(format!("Invalid URL: {}", e)))?;
 
+        #[cfg(feature = "wss")]
+        let handshake = connect_async_tls_with_config(request, None, false, connector);
+        #[cfg(not(feature = "wss"))]
+        let handshake = connect_async_with_config(request, None, false);
+
         // Attempt to connect with timeout
-        let (ws_stream, _) =
-            tokio::time::timeout(Duration::from_secs_f64(self.timeout), connect_async(&self.url))
-                .await
-                .map_err(|_| IFlowError::Timeout("Connection timeout".to_string()))?
-                .map_err(|e| {
-                    IFlowError::Connection(format!("WebSocket connection failed: {}", e))
-                })?;
+        let (ws_stream, _) = tokio::time::timeout(Duration::from_secs_f64(self.timeout), handshake)
+            .await
+            .map_err(|_| IFlowError::Timeout("Connection timeout".to_string()))?
+            .map_err(|e| IFlowError::Connection(format!("WebSocket connection failed: {}", e)))?;
 
-        self.websocket = Some(ws_stream);
-        self.connected = true;
+        let (sink, stream) = ws_stream.split();
+        let sink = Arc::new(Mutex::new(sink));
+        let (sender, incoming) = mpsc::unbounded_channel();
+        let connected = Arc::new(AtomicBool::new(true));
+        let reader = tokio::spawn(read_frames(
+            stream,
+            sink.clone(),
+            sender,
+            self.ping_interval,
+            connected.clone(),
+        ));
+        self.websocket = Some(ActiveConnection {
+            sink,
+            incoming,
+            connected,
+            reader,
+        });
         debug!("Connected to {}", self.url);
 
         Ok(())
@@ -84,25 +275,29 @@ impl WebSocketTransport {
     /// # Returns
     /// * `Ok(())` if the message was sent successfully
     /// * `Err(IFlowError)` if there was an error
-    pub async fn send(&mut self, message: &Value) -> Result<()> {
-        if !self.connected {
+    pub async fn send(&self, message: &Value) -> Result<()> {
+        if !self.is_connected() {
             return Err(IFlowError::NotConnected);
         }
 
-        let ws_stream = self.websocket.as_mut().ok_or(IFlowError::NotConnected)?;
+        let connection = self.websocket.as_ref().ok_or(IFlowError::NotConnected)?;
+        if let Some(observer) = &self.observer {
+            observer.on_send(message);
+        }
 
         // Serialize message to JSON string
         let data = serde_json::to_string(message).map_err(|e| IFlowError::JsonParse(e))?;
 
         // Send the message
-        ws_stream
+        connection
+            .sink
+            .lock()
+            .await
             .send(Message::Text(data.clone().into()))
             .await
             .map_err(|e| IFlowError::Transport(format!("Failed to send message: {}", e)))?;
 
-        tracing::debug!(
-            "Sent message: {}", data
-        );
+        tracing::debug!("Sent message: {}", data);
 
         Ok(())
     }
@@ -115,23 +310,28 @@ impl WebSocketTransport {
     /// # Returns
     /// * `Ok(())` if the message was sent successfully
     /// * `Err(IFlowError)` if there was an error
-    pub async fn send_raw(&mut self, message: &str) -> Result<()> {
-        if !self.connected {
+    pub async fn send_raw(&self, message: &str) -> Result<()> {
+        if !self.is_connected() {
             return Err(IFlowError::NotConnected);
         }
 
-        let ws_stream = self.websocket.as_mut().ok_or(IFlowError::NotConnected)?;
+        let connection = self.websocket.as_ref().ok_or(IFlowError::NotConnected)?;
+        if let Some(observer) = &self.observer
+            && let Ok(json) = serde_json::from_str::<Value>(message)
+        {
+            observer.on_send(&json);
+        }
 
         // Send the message
-        ws_stream
+        connection
+            .sink
+            .lock()
+            .await
             .send(Message::Text(message.to_string().into()))
             .await
             .map_err(|e| IFlowError::Transport(format!("Failed to send message: {}", e)))?;
 
-        tracing::debug!(
-            "Sent raw message: {}",
-            message.to_string()
-        );
+        tracing::debug!("Sent raw message: {}", message.to_string());
 
         Ok(())
     }
@@ -139,34 +339,20 @@ impl WebSocketTransport {
     /// Receive messages from WebSocket
     ///
     /// This method receives a single message from the WebSocket connection.
+    /// Messages read before the connection was lost are still returned.
     ///
     /// # Returns
     /// * `Ok(String)` containing the received message
     /// * `Err(IFlowError)` if there was an error
     pub async fn receive(&mut self) -> Result<String> {
-        if !self.connected {
-            return Err(IFlowError::NotConnected);
-        }
-
-        let ws_stream = self.websocket.as_mut().ok_or(IFlowError::NotConnected)?;
+        let connection = self.websocket.as_mut().ok_or(IFlowError::NotConnected)?;
 
-        // Receive the next message with proper error handling
         loop {
-            let msg = match ws_stream.next().await {
+            let msg = match connection.incoming.recv().await {
                 Some(Ok(msg)) => msg,
-                Some(Err(e)) => {
-                    tracing::error!("WebSocket error: {}", e);
-                    self.connected = false;
-                    return Err(IFlowError::Transport(format!(
-                        "Failed to receive message: {}",
-                        e
-                    )));
-                }
-                None => {
-                    tracing::debug!("WebSocket connection closed");
-                    self.connected = false;
-                    return Err(IFlowError::Connection("Connection closed".to_string()));
-                }
+                Some(Err(e)) => return Err(e),
+                // The reader task has ended and its error was returned
+                None => return Err(IFlowError::NotConnected),
             };
 
             match msg {
@@ -175,69 +361,115 @@ impl WebSocketTransport {
                     let cleaned_text = text.trim_start_matches(|c: char| {
                         !c.is_ascii() || c.is_control() && c != '\n' && c != '\r' && c != '\t'
                     });
-                    tracing::debug!(
-                        "Received message: {}", cleaned_text.to_string()
-                    );
+                    tracing::debug!("Received message: {}", cleaned_text.to_string());
+                    self.observe_received(cleaned_text);
                     return Ok(cleaned_text.to_string());
                 }
                 Message::Binary(data) => {
                     // Convert binary to string if possible
                     match String::from_utf8(data.to_vec()) {
-                        Ok(text) => return Ok(text),
+                        Ok(text) => {
+                            self.observe_received(&text);
+                            return Ok(text);
+                        }
                         Err(_) => {
                             tracing::debug!("Received binary message, ignoring");
                             continue;
                         }
                     }
                 }
-                Message::Ping(data) => {
-                    // Respond to ping with pong
-                    tracing::debug!("Received ping, sending pong");
-                    if let Err(e) = ws_stream.send(Message::Pong(data)).await {
-                        tracing::error!("Failed to send pong: {}", e);
-                        self.connected = false;
-                        return Err(IFlowError::Transport(format!("Failed to send pong: {}", e)));
-                    }
-                    continue;
-                }
-                Message::Pong(_) => {
-                    tracing::debug!("Received pong");
-                    continue;
-                }
-                Message::Close(close_frame) => {
-                    tracing::debug!("Received close frame: {:?}", close_frame);
-                    self.connected = false;
-                    return Err(IFlowError::Connection(
-                        "Connection closed by server".to_string(),
-                    ));
-                }
-                Message::Frame(_) => {
-                    tracing::debug!("Received raw frame, ignoring");
-                    continue;
-                }
+                // Control frames are handled by the reader task
+                _ => continue,
             }
         }
     }
 
+    /// Pass a received message to the observer if it is JSON
+    fn observe_received(&self, text: &str) {
+        if let Some(observer) = &self.observer
+            && let Ok(json) = serde_json::from_str::<Value>(text)
+        {
+            observer.on_receive(&json);
+        }
+    }
+
     /// Close WebSocket connection gracefully
     pub async fn close(&mut self) -> Result<()> {
-        if let Some(mut ws_stream) = self.websocket.take() {
-            ws_stream
-                .close(None)
+        if let Some(connection) = self.websocket.take() {
+            connection
+                .sink
+                .lock()
+                .await
+                .close()
                 .await
                 .map_err(|e| IFlowError::Transport(format!("Error closing WebSocket: {}", e)))?;
             debug!("WebSocket connection closed");
         }
-        self.connected = false;
         Ok(())
     }
 
+    /// Re-establish the connection to the same URL
+    ///
+    /// Drops the current connection, if any, and retries with exponential
+    /// backoff starting at the configured reconnect interval.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the connection was re-established
+    /// * `Err(IFlowError::Connection)` if all attempts failed
+    pub async fn reconnect(&mut self) -> Result<()> {
+        if let Some(connection) = self.websocket.take() {
+            let _ = connection.sink.lock().await.close().await;
+        }
+
+        let mut delay = self.reconnect_interval;
+        let mut last_error = None;
+        for attempt in 1..=self.reconnect_attempts.max(1) {
+            let jitter = jitter_delay(self.jitter);
+            if !jitter.is_zero() {
+                tokio::time::sleep(jitter).await;
+            }
+            debug!("Reconnecting to {} (attempt {})", self.url, attempt);
+            match self.connect().await {
+                Ok(()) => {
+                    self.reconnect_count += 1;
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!("Reconnect attempt {} failed: {}", attempt, e);
+                    last_error = Some(e);
+                }
+            }
+
+            if attempt < self.reconnect_attempts {
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+            }
+        }
+
+        Err(IFlowError::Connection(format!(
+            "Failed to reconnect to {} after {} attempts: {}",
+            self.url,
+            self.reconnect_attempts.max(1),
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        )))
+    }
+
+    /// Get the number of successful reconnects
+    ///
+    /// # Returns
+    /// How many times `reconnect()` re-established the connection
+    pub fn reconnect_count(&self) -> u32 {
+        self.reconnect_count
+    }
+
     /// Check if the WebSocket is connected
     ///
     /// # Returns
     /// True if connected, False otherwise
     pub fn is_connected(&self) -> bool {
-        self.connected
+        self.websocket
+            .as_ref()
+            .is_some_and(|connection| connection.connected.load(Ordering::SeqCst))
     }
 
     /// Get the WebSocket URL
@@ -248,3 +480,91 @@ impl WebSocketTransport {
         &self.url
     }
 }
+
+/// Read the frames of a connection until it is lost
+///
+/// Text and binary messages are forwarded to `incoming`, pings are
+/// answered and, with a `ping_interval`, keepalive pings are sent. The
+/// error ending the connection is forwarded last.
+async fn read_frames(
+    mut stream: SplitStream<WsStream>,
+    sink: WsSink,
+    incoming: mpsc::UnboundedSender<Result<Message>>,
+    ping_interval: Option<Duration>,
+    connected: Arc<AtomicBool>,
+) {
+    let mut pings = ping_interval
+        .map(|interval| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval));
+    let mut awaiting_pong = false;
+
+    let error = loop {
+        let next_ping = async {
+            match pings.as_mut() {
+                Some(pings) => pings.tick().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            frame = stream.next() => match frame {
+                Some(Ok(Message::Ping(data))) => {
+                    // Respond to ping with pong
+                    tracing::debug!("Received ping, sending pong");
+                    if let Err(e) = sink.lock().await.send(Message::Pong(data)).await {
+                        tracing::error!("Failed to send pong: {}", e);
+                        break IFlowError::Transport(format!("Failed to send pong: {}", e));
+                    }
+                }
+                Some(Ok(Message::Pong(_))) => {
+                    tracing::debug!("Received pong");
+                    awaiting_pong = false;
+                }
+                Some(Ok(Message::Close(close_frame))) => {
+                    tracing::debug!("Received close frame: {:?}", close_frame);
+                    break IFlowError::Connection("Connection closed by server".to_string());
+                }
+                Some(Ok(Message::Frame(_))) => {
+                    tracing::debug!("Received raw frame, ignoring");
+                }
+                Some(Ok(msg)) => {
+                    let _ = incoming.send(Ok(msg));
+                }
+                Some(Err(e)) => {
+                    tracing::error!("WebSocket error: {}", e);
+                    break IFlowError::Transport(format!("Failed to receive message: {}", e));
+                }
+                None => {
+                    tracing::debug!("WebSocket connection closed");
+                    break IFlowError::Connection("Connection closed".to_string());
+                }
+            },
+            _ = next_ping => {
+                if awaiting_pong {
+                    tracing::warn!("No pong received within {:?}", ping_interval);
+                    break IFlowError::Connection("ping timeout".to_string());
+                }
+                if let Err(e) = sink.lock().await.send(Message::Ping(Vec::new().into())).await {
+                    tracing::error!("Failed to send ping: {}", e);
+                    break IFlowError::Transport(format!("Failed to send ping: {}", e));
+                }
+                awaiting_pong = true;
+            }
+        }
+    };
+
+    connected.store(false, Ordering::SeqCst);
+    let _ = incoming.send(Err(error));
+}
+
+/// A random delay in `0..jitter`
+///
+/// # Returns
+/// The delay, or zero if there is no jitter
+pub(crate) fn jitter_delay(jitter: Option<Duration>) -> Duration {
+    use rand::Rng;
+
+    match jitter {
+        Some(jitter) if !jitter.is_zero() => rand::rng().random_range(Duration::ZERO..jitter),
+        _ => Duration::ZERO,
+    }
+}
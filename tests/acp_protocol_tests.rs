@@ -0,0 +1,193 @@
+//! Protocol flow tests against a scripted iFlow server
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::acp_protocol::ACPProtocol;
+use iflow_cli_sdk_rust::websocket_transport::WebSocketTransport;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions, Message};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn test_acp_protocol_handshake_and_prompt() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![SessionScript::new("hello", vec![agent_chunk("Hi there")])],
+    )
+    .await;
+
+    let mut transport = WebSocketTransport::new(server.url(), 5.0);
+    transport.connect().await.unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut protocol = ACPProtocol::new(transport, sender, 5.0);
+
+    protocol.initialize(&IFlowOptions::new()).await.unwrap();
+    assert!(protocol.is_initialized());
+    protocol.authenticate("iflow", None).await.unwrap();
+    let session_id = protocol.create_session("/tmp", Vec::new()).await.unwrap();
+    assert_eq!(session_id, "test-session-1");
+
+    protocol.send_prompt(&session_id, "hello").await.unwrap();
+    assert!(matches!(
+        receiver.recv().await,
+        Some(Message::Assistant { content }) if content == "Hi there"
+    ));
+    assert!(matches!(
+        receiver.recv().await,
+        Some(Message::TaskFinish { reason }) if reason.as_deref() == Some("end_turn")
+    ));
+
+    let _ = protocol.close().await;
+}
+
+#[tokio::test]
+async fn test_client_follows_session_script() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new("read", vec![tool_call("call_1", "read_file", "completed")]),
+            SessionScript::new("summarize", vec![agent_chunk("Summary")])
+                .with_stop_reason("max_tokens"),
+        ],
+    )
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.send_message("read the file", None).await.unwrap();
+    let (response, tools) = client.collect_response().await.unwrap();
+    assert!(response.is_empty());
+    assert_eq!(tools.len(), 1);
+
+    client.send_message("summarize it", None).await.unwrap();
+    let (response, tools) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Summary");
+    assert!(tools.is_empty());
+
+    client.send_message("unscripted", None).await.unwrap();
+    let (response, tools) = client.collect_response().await.unwrap();
+    assert!(response.is_empty() && tools.is_empty());
+
+    client.disconnect().await.unwrap();
+    assert_eq!(server.requests_for("session/prompt").len(), 3);
+}
+
+#[tokio::test]
+async fn test_request_ids_wrap_around() {
+    let server = TestIFlowServer::new(0, Vec::new()).await;
+    let mut transport = WebSocketTransport::new(server.url(), 5.0);
+    transport.connect().await.unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    let mut protocol = ACPProtocol::new(transport, sender, 5.0);
+    protocol.set_next_request_id(u32::MAX - 1);
+
+    protocol.initialize(&IFlowOptions::new()).await.unwrap();
+    protocol.authenticate("iflow", None).await.unwrap();
+    let session_id = protocol.create_session("/tmp", Vec::new()).await.unwrap();
+    protocol.send_prompt(&session_id, "hello").await.unwrap();
+
+    // initialize, session/new and session/prompt; authentication is skipped
+    let ids: Vec<u64> = server
+        .requests()
+        .iter()
+        .filter_map(|request| request["id"].as_u64())
+        .collect();
+    assert_eq!(ids, vec![u64::from(u32::MAX - 1), u64::from(u32::MAX), 0]);
+    assert!(protocol.request_ids_wrapped());
+
+    let _ = protocol.close().await;
+}
+
+#[tokio::test]
+async fn test_next_request_id_zero_does_not_wrap() {
+    let server = TestIFlowServer::new(0, Vec::new()).await;
+    let mut transport = WebSocketTransport::new(server.url(), 5.0);
+    transport.connect().await.unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    let mut protocol = ACPProtocol::new(transport, sender, 5.0);
+    protocol.set_next_request_id(0);
+
+    protocol.initialize(&IFlowOptions::new()).await.unwrap();
+
+    assert_eq!(server.requests()[0]["id"], 0);
+    assert!(!protocol.request_ids_wrapped());
+
+    let _ = protocol.close().await;
+}
+
+#[tokio::test]
+async fn test_request_ids_skip_pending_requests() {
+    let server = TestIFlowServer::new(0, Vec::new()).await;
+    let mut transport = WebSocketTransport::new(server.url(), 5.0);
+    transport.connect().await.unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    let mut protocol = ACPProtocol::new(transport, sender, 5.0);
+    protocol.initialize(&IFlowOptions::new()).await.unwrap();
+    protocol.authenticate("iflow", None).await.unwrap();
+    let session_id = protocol.create_session("/tmp", Vec::new()).await.unwrap();
+
+    // The prompt is sent without waiting, so its ID stays pending
+    protocol.set_next_request_id(u32::MAX);
+    let prompt_id = protocol
+        .start_prompt_blocks(&session_id, Vec::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(prompt_id, u32::MAX);
+
+    protocol.set_next_request_id(u32::MAX);
+    protocol.create_session("/tmp", Vec::new()).await.unwrap();
+
+    let ids: Vec<u64> = server
+        .requests()
+        .iter()
+        .filter_map(|request| request["id"].as_u64())
+        .collect();
+    assert_eq!(ids[ids.len() - 2..], [u64::from(u32::MAX), 0]);
+
+    let _ = protocol.close().await;
+}
+
+#[tokio::test]
+async fn test_batch_send_notifications() {
+    let server = TestIFlowServer::new(0, Vec::new()).await;
+    let mut transport = WebSocketTransport::new(server.url(), 5.0);
+    transport.connect().await.unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    let mut protocol = ACPProtocol::new(transport, sender, 5.0);
+    protocol.initialize(&IFlowOptions::new()).await.unwrap();
+
+    let notifications = |count: usize| {
+        (0..count)
+            .map(|i| ("session/cancel".to_string(), json!({ "sessionId": i })))
+            .collect::<Vec<_>>()
+    };
+    protocol
+        .batch_send_notifications(notifications(3))
+        .await
+        .unwrap();
+    protocol.set_max_batch_size(2);
+    protocol
+        .batch_send_notifications(notifications(5))
+        .await
+        .unwrap();
+
+    // The server handles messages in order, so the batches are recorded by now
+    protocol.create_session("/tmp", Vec::new()).await.unwrap();
+
+    let batches: Vec<Vec<serde_json::Value>> = server
+        .requests()
+        .into_iter()
+        .filter_map(|request| request.as_array().cloned())
+        .collect();
+    let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+    assert_eq!(sizes, vec![3, 2, 2, 1]);
+    assert_eq!(
+        batches[0][2],
+        json!({ "jsonrpc": "2.0", "method": "session/cancel", "params": { "sessionId": 2 } })
+    );
+    assert!(batches.iter().flatten().all(|n| n.get("id").is_none()));
+
+    let _ = protocol.close().await;
+}
@@ -0,0 +1,96 @@
+//! Tests for IFlowClient::run_agent_loop
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, plan, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::AgentLoopOptions;
+use serde_json::json;
+
+async fn connected_client(server: &TestIFlowServer) -> IFlowClient {
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+}
+
+#[tokio::test]
+async fn test_agent_loop_continues_until_done() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![
+            tool_call("call_1", "read_file", "completed"),
+            agent_chunk("step "),
+        ],
+        vec!["max_turn_requests", "end_turn"],
+    )
+    .await;
+    let mut client = connected_client(&server).await;
+
+    let result = client
+        .run_agent_loop("Fix the bug", 5, AgentLoopOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.turns, 2);
+    assert_eq!(result.stop_reason, "done");
+    assert_eq!(result.response, "step step ");
+    assert_eq!(result.tool_calls.len(), 2);
+    assert!(result.final_plan.is_none());
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts.len(), 2);
+    assert_eq!(prompts[0]["params"]["prompt"][0]["text"], "Fix the bug");
+    assert_ne!(prompts[1]["params"]["prompt"][0]["text"], "Fix the bug");
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_agent_loop_stops_at_max_turns() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![agent_chunk("partial")],
+        vec!["max_tokens", "max_tokens", "max_tokens"],
+    )
+    .await;
+    let mut client = connected_client(&server).await;
+
+    let result = client
+        .run_agent_loop("Write a novel", 2, AgentLoopOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.turns, 2);
+    assert_eq!(result.stop_reason, "max_turns");
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_agent_loop_limits() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![
+            plan(json!([{ "content": "Step 1", "status": "in_progress" }])),
+            tool_call("call_1", "shell", "completed"),
+        ],
+        vec!["max_tokens"; 4],
+    )
+    .await;
+    let mut client = connected_client(&server).await;
+
+    let pause = AgentLoopOptions {
+        pause_on_plan_update: true,
+        ..Default::default()
+    };
+    let result = client.run_agent_loop("Plan it", 5, pause).await.unwrap();
+    assert_eq!(result.turns, 1);
+    assert_eq!(result.stop_reason, "plan_update");
+    assert_eq!(result.final_plan.unwrap()[0].content, "Step 1");
+
+    let limited = AgentLoopOptions {
+        max_tool_calls: 1,
+        ..Default::default()
+    };
+    let result = client.run_agent_loop("Run it", 5, limited).await.unwrap();
+    assert_eq!(result.turns, 2);
+    assert_eq!(result.stop_reason, "max_tool_calls");
+
+    client.disconnect().await.unwrap();
+}
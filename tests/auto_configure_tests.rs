@@ -0,0 +1,136 @@
+//! Tests for building IFlowOptions from the environment
+
+use iflow_cli_sdk_rust::IFlowOptions;
+use iflow_cli_sdk_rust::types::PermissionMode;
+use serial_test::serial;
+
+const VARS: [&str; 5] = [
+    "CI",
+    "IFLOW_URL",
+    "IFLOW_TIMEOUT",
+    "IFLOW_PERMISSION_MODE",
+    "IFLOW_AUTH_METHOD_ID",
+];
+
+/// Run `f` with exactly the given variables set
+fn with_env(vars: &[(&str, &str)], f: impl FnOnce()) {
+    // SAFETY: the tests in this file run serially and nothing else reads these variables
+    unsafe {
+        for var in VARS {
+            std::env::remove_var(var);
+        }
+        for (var, value) in vars {
+            std::env::set_var(var, value);
+        }
+    }
+    f();
+    unsafe {
+        for var in VARS {
+            std::env::remove_var(var);
+        }
+    }
+}
+
+#[test]
+#[serial]
+#[allow(deprecated)]
+fn test_from_env() {
+    with_env(&[], || {
+        let options = IFlowOptions::from_env();
+        assert!(options.websocket.is_none());
+        assert_eq!(options.timeout, 120.0);
+        assert_eq!(options.permission_mode, PermissionMode::Auto);
+    });
+
+    with_env(
+        &[
+            ("IFLOW_URL", "ws://localhost:8090/acp"),
+            ("IFLOW_TIMEOUT", "45"),
+            ("IFLOW_PERMISSION_MODE", "Manual"),
+            ("IFLOW_AUTH_METHOD_ID", "oauth"),
+        ],
+        || {
+            let options = IFlowOptions::from_env();
+            let url = options.websocket.as_ref().and_then(|ws| ws.url.clone());
+            assert_eq!(url.as_deref(), Some("ws://localhost:8090/acp"));
+            assert_eq!(options.timeout, 45.0);
+            assert_eq!(options.prompt_timeout, 45.0);
+            assert_eq!(options.permission_mode, PermissionMode::Manual);
+            assert_eq!(options.auth_method_id.as_deref(), Some("oauth"));
+        },
+    );
+
+    with_env(
+        &[
+            ("IFLOW_TIMEOUT", "soon"),
+            ("IFLOW_PERMISSION_MODE", "maybe"),
+        ],
+        || {
+            let options = IFlowOptions::from_env();
+            assert_eq!(options.timeout, 120.0);
+            assert_eq!(options.permission_mode, PermissionMode::Auto);
+        },
+    );
+}
+
+#[test]
+#[serial]
+#[allow(deprecated)]
+fn test_auto_configure_ci() {
+    with_env(
+        &[("CI", "true"), ("IFLOW_PERMISSION_MODE", "manual")],
+        || {
+            let options = IFlowOptions::auto_configure();
+            assert_eq!(options.prompt_timeout, 300.0);
+            assert!(options.timeout >= 300.0);
+            assert_eq!(options.permission_mode, PermissionMode::Auto);
+            assert!(options.logging.enabled);
+            assert!(options.process.capture_stderr);
+        },
+    );
+}
+
+#[test]
+#[serial]
+fn test_auto_configure_development() {
+    with_env(&[], || {
+        let options = IFlowOptions::auto_configure();
+        assert_eq!(options.prompt_timeout, 120.0);
+        assert_eq!(options.permission_mode, PermissionMode::Selective);
+        assert!(!options.logging.enabled);
+        assert!(!options.process.capture_stderr);
+    });
+
+    with_env(
+        &[("CI", "false"), ("IFLOW_PERMISSION_MODE", "auto")],
+        || {
+            let options = IFlowOptions::auto_configure();
+            assert_eq!(options.permission_mode, PermissionMode::Auto);
+            assert!(!options.logging.enabled);
+        },
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_ci_pipeline_preset() {
+    let options = IFlowOptions::for_ci_pipeline();
+    assert_eq!(options.permission_mode, PermissionMode::Auto);
+    assert!(!options.process.debug);
+    assert!(options.process.capture_stderr);
+    assert!(options.logging.enabled);
+    assert_eq!(options.logging.level, "INFO");
+    assert_eq!(options.timeout, 300.0);
+    assert_eq!(options.prompt_timeout, 600.0);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_local_dev_preset() {
+    let options = IFlowOptions::for_local_dev();
+    assert_eq!(options.permission_mode, PermissionMode::Selective);
+    assert!(!options.process.capture_stderr);
+    assert!(!options.logging.enabled);
+    assert!(options.keep_history);
+    assert_eq!(options.timeout, IFlowOptions::default().timeout);
+}
@@ -0,0 +1,63 @@
+//! Tests for IFlowClient::await_task_finish
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_await_task_finish() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("2 + 2 "),
+        tool_call("call_1", "calculator", "completed"),
+        agent_chunk("is 4"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.send_message("What is 2 + 2?", None).await.unwrap();
+    let response = client
+        .await_task_finish(Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(response, "2 + 2 is 4");
+
+    // A stream created earlier does not get in the way
+    let _stream = client.messages();
+    client.send_message("again", None).await.unwrap();
+    let response = client
+        .await_task_finish(Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(response, "2 + 2 is 4");
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_await_task_finish_times_out() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    // Nothing was sent, so no turn finishes
+    let err = client
+        .await_task_finish(Duration::from_millis(100))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::Timeout(_)));
+
+    // The next turn is still collected in full
+    client.send_message("hi", None).await.unwrap();
+    let response = client
+        .await_task_finish(Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(response, "Hello");
+
+    client.disconnect().await.unwrap();
+}
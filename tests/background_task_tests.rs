@@ -0,0 +1,71 @@
+//! Tests for IFlowClient::background_task
+
+mod helpers;
+
+use helpers::server::TestIFlowServer;
+use iflow_cli_sdk_rust::IFlowClient;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn counting_task(
+    counter: Arc<AtomicUsize>,
+) -> impl FnOnce() -> futures::future::BoxFuture<'static, ()> {
+    move || {
+        Box::pin(async move {
+            loop {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_background_task_runs_until_aborted() {
+    let mut client = IFlowClient::new(None);
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let handle = client.background_task(counting_task(counter.clone()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(counter.load(Ordering::SeqCst) > 0);
+
+    handle.abort();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let stopped_at = counter.load(Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(counter.load(Ordering::SeqCst), stopped_at);
+}
+
+#[tokio::test]
+async fn test_abort_all_background_tasks() {
+    let mut client = IFlowClient::new(None);
+    let first = Arc::new(AtomicUsize::new(0));
+    let second = Arc::new(AtomicUsize::new(0));
+
+    let first_handle = client.background_task(counting_task(first.clone()));
+    let second_handle = client.background_task(counting_task(second.clone()));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    client.abort_all_background_tasks();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(first_handle.is_finished());
+    assert!(second_handle.is_finished());
+}
+
+#[tokio::test]
+async fn test_disconnect_aborts_background_tasks() {
+    let server = TestIFlowServer::start(vec![]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let handle = client.background_task(counting_task(counter.clone()));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(counter.load(Ordering::SeqCst) > 0);
+
+    client.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(handle.is_finished());
+}
@@ -0,0 +1,60 @@
+//! Tests for IFlowClient::send_message_batch_with_context
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, Message};
+
+#[tokio::test]
+async fn test_prime_three_turns() {
+    let server = TestIFlowServer::start(vec![]).await;
+
+    let options = helpers::websocket_options(server.url()).with_history(true);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let turns = (1..=3)
+        .map(|i| (format!("question {}", i), format!("answer {}", i)))
+        .collect();
+    client.send_message_batch_with_context(turns).await.unwrap();
+
+    let conversation: Vec<Message> = client
+        .history()
+        .into_iter()
+        .filter(|msg| matches!(msg, Message::User { .. } | Message::Assistant { .. }))
+        .collect();
+    assert_eq!(conversation.len(), 6);
+    for (i, pair) in conversation.chunks(2).enumerate() {
+        assert!(
+            matches!(&pair[0], Message::User { content } if *content == format!("question {}", i + 1))
+        );
+        assert!(
+            matches!(&pair[1], Message::Assistant { content } if *content == format!("answer {}", i + 1))
+        );
+    }
+    assert_eq!(server.requests_for("session/prompt").len(), 3);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_next_message_after_priming_gets_its_own_response() {
+    let server = TestIFlowServer::start(vec![agent_chunk("real answer")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+        .send_message_batch_with_context(vec![
+            ("question 1".to_string(), "answer 1".to_string()),
+            ("question 2".to_string(), "answer 2".to_string()),
+        ])
+        .await
+        .unwrap();
+
+    client.send_message("question 3", None).await.unwrap();
+    let (response, _) = client.collect_response().await.unwrap();
+    assert_eq!(response, "real answer");
+    assert!(client.drain_messages().await.unwrap().is_empty());
+
+    client.disconnect().await.unwrap();
+}
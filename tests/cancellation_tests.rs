@@ -0,0 +1,139 @@
+//! Tests for IFlowError::Cancelled
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{CancelledBy, IFlowClient, IFlowError, query_with_config};
+
+#[tokio::test]
+async fn test_interrupt_cancels_collect_response() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.interrupt().await.unwrap();
+    let err = client.collect_response().await.unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::Cancelled {
+            by: CancelledBy::User
+        }
+    ));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_interrupt_while_collecting_response() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    // interrupt only needs a shared reference, so it can run alongside
+    // a call that is waiting for the response
+    let shared = &client;
+    let (collected, interrupted) = tokio::join!(shared.collect_response(), async {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        shared.interrupt().await
+    });
+    interrupted.unwrap();
+    assert!(matches!(
+        collected,
+        Err(IFlowError::Cancelled {
+            by: CancelledBy::User
+        })
+    ));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_interrupt_sends_session_cancel() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    // Without a session there is nothing to cancel
+    client.interrupt_session().await.unwrap();
+    assert!(server.requests_for("session/cancel").is_empty());
+
+    client.send_message("hi", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.interrupt().await.unwrap();
+    // The server handles frames in order, so the next prompt follows the cancel
+    client.send_message("again", None).await.unwrap();
+
+    let cancels = server.requests_for("session/cancel");
+    assert_eq!(cancels.len(), 1);
+    assert_eq!(cancels[0]["params"]["sessionId"], "test-session-1");
+    assert!(cancels[0].get("id").is_none());
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cancelled_stop_reason() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![agent_chunk("Partial")],
+        vec!["cancelled", "cancelled", "cancelled"],
+    )
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.send_message("hi", None).await.unwrap();
+    let err = client.collect_response().await.unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::Cancelled {
+            by: CancelledBy::User
+        }
+    ));
+
+    let mut writer = Vec::new();
+    let err = client
+        .stream_to_writer("again", &mut writer)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::Cancelled {
+            by: CancelledBy::User
+        }
+    ));
+    assert_eq!(writer, b"Partial");
+
+    client.disconnect().await.unwrap();
+
+    let err = query_with_config("hi", helpers::websocket_options(server.url()))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::Cancelled {
+            by: CancelledBy::User
+        }
+    ));
+}
+
+#[test]
+fn test_cancelled_error() {
+    for by in [CancelledBy::User, CancelledBy::Timeout, CancelledBy::Signal] {
+        let err = IFlowError::Cancelled { by };
+        assert!(err.is_cancelled());
+        assert_eq!(err.user_message(), "The operation was cancelled.");
+    }
+
+    let err = IFlowError::Cancelled {
+        by: CancelledBy::Signal,
+    };
+    assert_eq!(err.to_string(), "Operation cancelled by signal");
+    assert!(!IFlowError::Timeout("slow".to_string()).is_cancelled());
+
+    let client = IFlowClient::new(None);
+    assert_eq!(client.explain_error(&err), "The operation was cancelled.");
+}
@@ -0,0 +1,96 @@
+//! Tests for cancelling a client with a CancellationToken
+
+mod helpers;
+
+use helpers::server::TestIFlowServer;
+use iflow_cli_sdk_rust::{CancelledBy, IFlowClient, IFlowError};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+async fn endless_task() {
+    loop {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_parent_token_stops_background_tasks() {
+    let parent = CancellationToken::new();
+    let mut client = IFlowClient::new_with_cancellation(None, parent.clone());
+
+    let handles = [
+        client.background_task(endless_task),
+        client.background_task(endless_task),
+    ];
+    let child = client.cancellation_token();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(handles.iter().all(|handle| !handle.is_finished()));
+
+    parent.cancel();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(handles.iter().all(|handle| handle.is_finished()));
+    assert!(child.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_parent_token_cancels_collect_response() {
+    let server = TestIFlowServer::start(vec![]).await;
+
+    let parent = CancellationToken::new();
+    let mut client = IFlowClient::new_with_cancellation(
+        Some(helpers::websocket_options(server.url())),
+        parent.clone(),
+    );
+    client.connect().await.unwrap();
+
+    let cancel = parent.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+    });
+    let err = client.collect_response().await.unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::Cancelled {
+            by: CancelledBy::User
+        }
+    ));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_disconnect_cancels_client_token_only() {
+    let server = TestIFlowServer::start(vec![]).await;
+
+    let parent = CancellationToken::new();
+    let mut client = IFlowClient::new_with_cancellation(
+        Some(helpers::websocket_options(server.url())),
+        parent.clone(),
+    );
+    client.connect().await.unwrap();
+
+    let token = client.cancellation_token();
+    client.disconnect().await.unwrap();
+    assert!(token.is_cancelled());
+    assert!(!parent.is_cancelled());
+
+    // A new connection gets a fresh token
+    assert!(!client.cancellation_token().is_cancelled());
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cancelling_child_token_leaves_client_running() {
+    let mut client = IFlowClient::new(None);
+    let handle = client.background_task(endless_task);
+
+    client.cancellation_token().cancel();
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(!handle.is_finished());
+
+    client.abort_all_background_tasks();
+}
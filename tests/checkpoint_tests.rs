@@ -0,0 +1,51 @@
+//! Tests for session checkpoints
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::SessionCheckpoint;
+
+#[tokio::test]
+async fn test_checkpoint_and_restore() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "in_progress"),
+        tool_call("call_2", "write_file", "completed"),
+        agent_chunk("Working on it"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Edit the file", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let checkpoint = client.checkpoint().unwrap();
+    assert_eq!(checkpoint.session_id, "test-session-1");
+    assert!(checkpoint.last_message_at.is_some());
+    assert_eq!(checkpoint.pending_tool_calls, vec!["call_1".to_string()]);
+    client.disconnect().await.unwrap();
+
+    let json = checkpoint.to_json().unwrap();
+    assert!(!json.contains("Working on it"));
+    let checkpoint = SessionCheckpoint::from_json(&json).unwrap();
+
+    let mut restored = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    restored.restore_from_checkpoint(&checkpoint).await.unwrap();
+    assert_eq!(restored.session_id(), Some("test-session-1"));
+
+    // The restored session is prompted instead of a new one
+    restored.send_message("Continue", None).await.unwrap();
+    restored.collect_response().await.unwrap();
+    assert_eq!(server.requests_for("session/new").len(), 1);
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts[1]["params"]["sessionId"], "test-session-1");
+
+    restored.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_checkpoint_without_session() {
+    let client = IFlowClient::new(None);
+    assert!(client.checkpoint().is_err());
+}
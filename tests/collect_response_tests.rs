@@ -0,0 +1,36 @@
+//! Tests for IFlowClient::collect_response
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, plan, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_collect_response() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("Hello"),
+        tool_call("call_1", "read_file", "completed"),
+        plan(json!([{ "content": "Answer", "status": "completed" }])),
+        agent_chunk(" world"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.send_message("hi", None).await.unwrap();
+    let (response, tools) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Hello world");
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].id, "call_1");
+    assert_eq!(tools[0].name, "read_file");
+
+    // Each call only consumes the response to one message
+    client.send_message("again", None).await.unwrap();
+    let (response, tools) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Hello world");
+    assert_eq!(tools.len(), 1);
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,91 @@
+//! Tests for creating clients from connection strings
+
+use iflow_cli_sdk_rust::types::WebSocketConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::time::Duration;
+
+fn websocket_url(client: &IFlowClient) -> Option<String> {
+    client.options().websocket.as_ref()?.url.clone()
+}
+
+#[test]
+fn test_stdio_connection_string() {
+    let extra = IFlowOptions::new().with_websocket_config(WebSocketConfig::auto_start());
+    let client = IFlowClient::new_with_connection_string("stdio://", Some(extra)).unwrap();
+    assert!(client.options().websocket.is_none());
+}
+
+#[test]
+fn test_ws_connection_string() {
+    let client =
+        IFlowClient::new_with_connection_string("ws://localhost:8090/acp?peer=iflow", None)
+            .unwrap();
+    assert_eq!(
+        websocket_url(&client).as_deref(),
+        Some("ws://localhost:8090/acp?peer=iflow")
+    );
+}
+
+#[test]
+fn test_wss_connection_string() {
+    let client =
+        IFlowClient::new_with_connection_string("wss://prod.example.com/acp", None).unwrap();
+    assert_eq!(
+        websocket_url(&client).as_deref(),
+        Some("wss://prod.example.com/acp")
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_connection_string_merges_extra_options() {
+    let extra = IFlowOptions::new()
+        .with_timeout(42.0)
+        .with_websocket_config(
+            WebSocketConfig::with_reconnect_settings(
+                "ws://localhost:1/acp".to_string(),
+                7,
+                Duration::from_millis(10),
+            )
+            .with_ping_interval(Duration::from_secs(15)),
+        );
+    let client =
+        IFlowClient::new_with_connection_string("ws://127.0.0.1:9000/acp", Some(extra)).unwrap();
+
+    let options = client.options();
+    assert_eq!(options.timeout, 42.0);
+    let websocket = options.websocket.as_ref().unwrap();
+    assert_eq!(websocket.url.as_deref(), Some("ws://127.0.0.1:9000/acp"));
+    assert_eq!(websocket.reconnect_attempts, 7);
+    assert_eq!(websocket.ping_interval, Some(Duration::from_secs(15)));
+}
+
+#[test]
+fn test_unsupported_scheme() {
+    let err = IFlowClient::new_with_connection_string("http://localhost:8090/acp", None)
+        .err()
+        .unwrap();
+    assert!(
+        matches!(&err, IFlowError::Connection(message) if message.contains("'http'")),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn test_malformed_connection_strings() {
+    for uri in [
+        "",
+        "localhost:8090",
+        "not a uri",
+        "ws://",
+        "wss://:8090/acp",
+    ] {
+        let result = IFlowClient::new_with_connection_string(uri, None);
+        assert!(
+            matches!(result, Err(IFlowError::Connection(_))),
+            "{} was accepted",
+            uri
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Tests for loading session context with IFlowClient::set_context_window
+
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions, types::FileAccessConfig};
+use std::path::PathBuf;
+
+/// Create a uniquely named file in the system temp directory
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("iflow_ctx_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_context_window_prompt_contains_files() {
+    let main_rs = temp_file("main.rs", "fn main() {}");
+    let cargo_toml = temp_file("Cargo.toml", "[package]\nname = \"demo\"");
+
+    let client = IFlowClient::new(None);
+    let prompt = client
+        .context_window_prompt(&[main_rs.clone(), cargo_toml.clone()])
+        .unwrap();
+
+    assert!(prompt.starts_with("I'm providing you with these files for context:"));
+    assert!(prompt.contains(&main_rs.display().to_string()));
+    assert!(prompt.contains("fn main() {}"));
+    assert!(prompt.contains(&cargo_toml.display().to_string()));
+    assert!(prompt.contains("name = \"demo\""));
+}
+
+#[test]
+fn test_context_window_prompt_rejects_large_file() {
+    let big = temp_file("big.txt", &"x".repeat(64));
+
+    let options = IFlowOptions::new().with_file_access_config(FileAccessConfig {
+        max_size: 16,
+        ..Default::default()
+    });
+    let client = IFlowClient::new(Some(options));
+
+    match client.context_window_prompt(&[big]) {
+        Err(IFlowError::FileTooLarge { size, limit, .. }) => assert!(size > limit),
+        other => panic!("Expected file too large error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_context_window_prompt_missing_file() {
+    let client = IFlowClient::new(None);
+    let result = client.context_window_prompt(&[PathBuf::from("/nonexistent/iflow/file.rs")]);
+    assert!(matches!(result, Err(IFlowError::FileNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_set_context_window_updates_cwd() {
+    let file = temp_file("lib.rs", "pub fn demo() {}");
+    let cwd = file.parent().unwrap().to_path_buf();
+
+    let mut client = IFlowClient::new(None);
+    let result = client.set_context_window(cwd.clone(), vec![file]).await;
+
+    // Not connected, so sending fails, but the working directory is updated
+    assert!(matches!(result, Err(IFlowError::NotConnected)));
+    assert_eq!(client.options().cwd, cwd);
+}
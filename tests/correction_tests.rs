@@ -0,0 +1,80 @@
+//! Tests for sending corrections mid-task
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::CORRECTION_MARKER;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_correction_is_buffered_while_tool_runs() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new("Start", vec![tool_call("call_1", "bash", "in_progress")]),
+            SessionScript::new("Continue", vec![tool_call("call_1", "bash", "completed")]),
+        ],
+    )
+    .await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Start", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    client.send_correction("Use the other file").await.unwrap();
+    assert_eq!(client.buffered_corrections(), 1);
+    assert!(server.requests_for("session/inject").is_empty());
+
+    // Nothing is sent while the tool call is still running
+    assert_eq!(client.flush_corrections().await.unwrap(), 0);
+
+    client.send_message("Continue", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    assert_eq!(client.flush_corrections().await.unwrap(), 1);
+    assert_eq!(client.buffered_corrections(), 0);
+
+    // The test server does not support session/inject, so a prompt is sent instead
+    let injects = server.requests_for("session/inject");
+    assert_eq!(injects.len(), 1);
+    assert_eq!(
+        injects[0]["params"]["prompt"][0]["text"],
+        "Use the other file"
+    );
+    client.drain_messages().await.unwrap();
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(
+        prompts[2]["params"]["prompt"][0]["text"],
+        format!("{} Use the other file", CORRECTION_MARKER)
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_correction_injected_during_prompt() {
+    let server = TestIFlowServer::start_with_methods(vec![("session/inject", json!({}))]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    client.send_message_fire_and_forget("Start").await.unwrap();
+    client
+        .send_correction("Stop after the first step")
+        .await
+        .unwrap();
+    assert_eq!(client.buffered_corrections(), 0);
+
+    // The response of the prompt is still delivered
+    let messages = client.drain_messages().await.unwrap();
+    assert!(messages.iter().any(|msg| msg.is_task_finish()));
+    assert_eq!(server.requests_for("session/inject").len(), 1);
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_correction_requires_connection() {
+    let mut client = IFlowClient::new(None);
+    assert!(client.send_correction("Nope").await.is_err());
+}
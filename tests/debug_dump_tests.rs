@@ -0,0 +1,70 @@
+//! Tests for IFlowClient::debug_dump
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::RecordingObserver;
+use iflow_cli_sdk_rust::{IFlowClient, VERSION};
+
+#[tokio::test]
+async fn test_debug_dump_redacts_auth() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_auth_method_id("secret-method".to_string());
+    let mut client =
+        IFlowClient::new(Some(options)).observe_raw_protocol(Box::new(RecordingObserver::new()));
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+
+    let dump = client.debug_dump();
+    assert_eq!(dump.rust_sdk_version, VERSION);
+    assert_eq!(dump.options_redacted["auth_method_id"], "[redacted]");
+    assert_eq!(dump.options_redacted["timeout"], 10.0);
+    assert!(dump.message_queue_depth >= 2);
+    assert!(!dump.protocol_log.is_empty());
+    assert!(dump.recent_stderr.is_empty());
+
+    let json = serde_json::to_string(&dump).unwrap();
+    assert!(!json.contains("secret-method"));
+    assert!(!json.contains("auth_token"));
+
+    let report = dump.to_string();
+    assert!(!report.contains("secret-method"));
+    assert!(report.contains(&format!("iFlow Rust SDK {}", VERSION)));
+    assert!(report.contains("Queued messages:"));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_debug_dump_keeps_last_100_messages() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .observe_raw_protocol(Box::new(RecordingObserver::new()));
+    client.connect().await.unwrap();
+    for _ in 0..40 {
+        client.send_message("hi", None).await.unwrap();
+        client.collect_response().await.unwrap();
+    }
+
+    let log = client.protocol_log();
+    assert!(log.len() > 100);
+    let dump = client.debug_dump();
+    assert_eq!(dump.protocol_log.len(), 100);
+    assert_eq!(dump.protocol_log, log[log.len() - 100..]);
+    assert_eq!(dump.message_queue_depth, 0);
+
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_debug_dump_without_connection() {
+    let client = IFlowClient::new(None);
+    let dump = client.debug_dump();
+    assert!(!dump.snapshot.connected);
+    assert!(dump.protocol_log.is_empty());
+    assert!(dump.options_redacted["auth_method_id"].is_null());
+    assert!(dump.to_string().contains("Protocol log: 0 messages"));
+}
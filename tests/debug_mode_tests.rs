@@ -0,0 +1,56 @@
+//! Tests for the debug mode shorthand
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::Direction;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions};
+
+#[test]
+fn test_debug_mode_sets_all_fields() {
+    let options = IFlowOptions::debug_mode();
+    assert!(options.debug);
+    assert!(options.process.debug);
+    assert!(options.logging.enabled);
+    assert_eq!(options.logging.level, "DEBUG");
+
+    let log_file = options.logging.logger_config.log_file.to_string_lossy();
+    assert!(log_file.starts_with("./iflow-debug-"));
+    assert!(log_file.ends_with(".log"));
+}
+
+#[test]
+fn test_disabling_debug_mode_restores_defaults() {
+    let defaults = IFlowOptions::new();
+    let options = IFlowOptions::debug_mode().with_debug_mode(false);
+    assert!(!options.debug);
+    assert!(!options.process.debug);
+    assert_eq!(options.logging.enabled, defaults.logging.enabled);
+    assert_eq!(options.logging.level, defaults.logging.level);
+    assert_eq!(
+        options.logging.logger_config.log_file,
+        defaults.logging.logger_config.log_file
+    );
+    assert!(options.diff(&defaults).is_empty());
+}
+
+#[tokio::test]
+async fn test_debug_mode_records_protocol() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+    let log_file = std::env::temp_dir().join(format!("iflow_{}.log", uuid::Uuid::new_v4()));
+
+    let mut options = helpers::websocket_options(server.url()).with_debug_mode(true);
+    options.logging.logger_config.log_file = log_file.clone();
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let log = client.protocol_log();
+    assert!(log.iter().any(
+        |(direction, json)| *direction == Direction::Sent && json["method"] == "session/prompt"
+    ));
+    assert!(log_file.exists());
+    let _ = std::fs::remove_file(&log_file);
+}
@@ -0,0 +1,70 @@
+//! Tests for the background error handler
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, raw_frame};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_error_handler_receives_malformed_message() {
+    let server = TestIFlowServer::start(vec![raw_frame("not json"), agent_chunk("Hello")]).await;
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let recorded = errors.clone();
+    let options = helpers::websocket_options(server.url())
+        .with_error_handler(move |e| recorded.lock().unwrap().push(e.to_string()));
+
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    client.send_message("hi", None).await.unwrap();
+    assert_eq!(client.collect_response().await.unwrap().0, "Hello");
+
+    let errors = errors.lock().unwrap().clone();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("not json"));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_on_error() {
+    let server = TestIFlowServer::start(vec![raw_frame("{broken")]).await;
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let recorded = errors.clone();
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url()))).on_error(
+        Box::new(move |e: &IFlowError| {
+            recorded
+                .lock()
+                .unwrap()
+                .push(matches!(e, IFlowError::InvalidMessage(_)))
+        }),
+    );
+    client.connect().await.unwrap();
+
+    client.send_message("hi", None).await.unwrap();
+    assert_eq!(*errors.lock().unwrap(), vec![true]);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_no_error_for_valid_messages() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let errors = Arc::new(Mutex::new(0));
+    let recorded = errors.clone();
+    let options = helpers::websocket_options(server.url())
+        .with_error_handler(move |_| *recorded.lock().unwrap() += 1);
+
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    client.send_message("hi", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    assert_eq!(*errors.lock().unwrap(), 0);
+
+    client.disconnect().await.unwrap();
+}
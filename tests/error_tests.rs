@@ -0,0 +1,180 @@
+//! Tests for IFlowError helpers
+
+use iflow_cli_sdk_rust::error::ConnectionError;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[test]
+fn test_file_errors_are_file_errors() {
+    let errors = [
+        IFlowError::FileTooLarge {
+            path: PathBuf::from("big.bin"),
+            size: 2048,
+            limit: 1024,
+        },
+        IFlowError::FileNotFound(PathBuf::from("missing.txt")),
+        IFlowError::FilePermissionDenied(PathBuf::from("/root/secret")),
+    ];
+
+    for error in &errors {
+        assert!(error.is_file_error(), "{:?} should be a file error", error);
+    }
+}
+
+#[test]
+fn test_other_errors_are_not_file_errors() {
+    assert!(!IFlowError::Connection("refused".to_string()).is_file_error());
+    assert!(!IFlowError::NotConnected.is_file_error());
+    assert!(!IFlowError::Io(std::io::Error::other("disk")).is_file_error());
+}
+
+#[test]
+fn test_file_error_messages() {
+    let error = IFlowError::FileTooLarge {
+        path: PathBuf::from("big.bin"),
+        size: 2048,
+        limit: 1024,
+    };
+    assert_eq!(
+        error.to_string(),
+        "File too large: big.bin is 2048 bytes, which exceeds the limit of 1024 bytes"
+    );
+    assert_eq!(
+        IFlowError::FileNotFound(PathBuf::from("missing.txt")).to_string(),
+        "File not found: missing.txt"
+    );
+}
+
+#[test]
+fn test_connection_error_conversions() {
+    let error: IFlowError = ConnectionError::ConnectionError("refused".to_string()).into();
+    assert!(matches!(error, IFlowError::Connection(ref message) if message == "refused"));
+
+    let error: IFlowError = ConnectionError::NotInitialized.into();
+    assert!(matches!(error, IFlowError::Protocol(_)));
+
+    let error: IFlowError = ConnectionError::NoSession.into();
+    assert!(
+        matches!(error, IFlowError::Protocol(ref message) if message == "No session available")
+    );
+
+    let error: IFlowError = ConnectionError::Timeout(Duration::from_millis(1500)).into();
+    assert!(
+        matches!(error, IFlowError::Timeout(ref message) if message == "Connection timed out after 1.5s")
+    );
+}
+
+#[test]
+fn test_user_messages_are_human_readable() {
+    let errors = [
+        IFlowError::Connection("refused".to_string()),
+        IFlowError::Protocol("bad frame".to_string()),
+        IFlowError::Authentication("denied".to_string()),
+        IFlowError::Timeout("prompt".to_string()),
+        IFlowError::ToolCall("boom".to_string()),
+        IFlowError::Validation("empty".to_string()),
+        IFlowError::Config("missing directory".to_string()),
+        IFlowError::Transport("reset".to_string()),
+        IFlowError::JsonParse(serde_json::from_str::<u8>("x").unwrap_err()),
+        IFlowError::Deserialization(serde_json::from_str::<u8>("x").unwrap_err()),
+        IFlowError::WebSocket("closed".to_string()),
+        IFlowError::Io(std::io::Error::other("disk")),
+        IFlowError::FileTooLarge {
+            path: PathBuf::from("big.bin"),
+            size: 2048,
+            limit: 1024,
+        },
+        IFlowError::FileNotFound(PathBuf::from("missing.txt")),
+        IFlowError::FilePermissionDenied(PathBuf::from("secret.txt")),
+        IFlowError::ProcessManager("spawn failed".to_string()),
+        IFlowError::NotConnected,
+        IFlowError::SessionNotFound,
+        IFlowError::InvalidMessage("bad".to_string()),
+        IFlowError::Unknown("???".to_string()),
+    ];
+
+    for error in &errors {
+        let message = error.user_message();
+        assert!(!message.is_empty(), "{:?} has no user message", error);
+        assert_ne!(message, format!("{:?}", error));
+    }
+    assert_eq!(
+        IFlowError::NotConnected.user_message(),
+        "Please call connect() before sending messages."
+    );
+    assert!(
+        IFlowError::ProcessManager("spawn failed".to_string())
+            .user_message()
+            .ends_with("in your PATH. Details: spawn failed")
+    );
+}
+
+#[test]
+fn test_explain_error_names_timeout_options() {
+    let options = IFlowOptions::new().with_connect_timeout(5.0);
+    let client = IFlowClient::new(Some(options));
+
+    let message = client.explain_error(&IFlowError::Timeout("Connection timeout".to_string()));
+    assert!(message.starts_with("The operation timed out."));
+    assert!(message.contains("connect_timeout"));
+    assert!(message.ends_with("Details: Connection timeout"));
+    // The deprecated overall timeout is not reported as the one that expired
+    assert!(!message.contains("120"));
+    assert_eq!(
+        client.explain_error(&IFlowError::NotConnected),
+        IFlowError::NotConnected.user_message()
+    );
+}
+
+#[test]
+fn test_wrapped_errors_are_sources() {
+    use std::error::Error;
+
+    let io = IFlowError::Io(std::io::Error::other("disk"));
+    assert_eq!(io.source().unwrap().to_string(), "disk");
+
+    let parse_error = serde_json::from_str::<u32>("x").unwrap_err();
+    let deserialization = IFlowError::Deserialization(parse_error);
+    assert!(deserialization.source().is_some());
+
+    let batch = IFlowError::BatchFailed {
+        completed: vec![],
+        source: Box::new(IFlowError::Timeout("slow".to_string())),
+    };
+    assert_eq!(batch.source().unwrap().to_string(), "Timeout error: slow");
+
+    assert!(IFlowError::NotConnected.source().is_none());
+}
+
+#[test]
+fn test_other_keeps_the_source_chain() {
+    use std::error::Error;
+
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+
+    impl std::fmt::Display for Outer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("upload failed")
+        }
+    }
+
+    impl Error for Outer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let error = IFlowError::other(Outer(std::io::Error::other("disk full")));
+    assert_eq!(error.to_string(), "upload failed");
+    assert_eq!(error.source().unwrap().to_string(), "disk full");
+    assert!(error.user_message().ends_with("Details: upload failed"));
+
+    // Boxed errors convert with `?`
+    fn fails() -> Result<(), IFlowError> {
+        let boxed: Box<dyn Error + Send + Sync> = "bad input".into();
+        Err(boxed)?
+    }
+    assert!(matches!(fails(), Err(IFlowError::Other(e)) if e.to_string() == "bad input"));
+}
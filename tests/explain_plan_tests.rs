@@ -0,0 +1,59 @@
+//! Tests for IFlowClient::explain_plan
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk, plan};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_explain_plan() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new(
+                "Refactor",
+                vec![plan(json!([
+                    { "content": "Read the code", "status": "completed" },
+                    { "content": "Write the tests", "priority": "high", "status": "pending" },
+                ]))],
+            ),
+            SessionScript::new(
+                "explain this plan",
+                vec![agent_chunk(
+                    "First the code is read, then tests are written.",
+                )],
+            ),
+        ],
+    )
+    .await;
+
+    let options = helpers::websocket_options(server.url()).with_history(true);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let err = client.explain_plan().await.unwrap_err();
+    assert!(matches!(err, IFlowError::Protocol(msg) if msg == "No plan available"));
+
+    client
+        .send_message("Refactor the parser", None)
+        .await
+        .unwrap();
+    client.collect_response().await.unwrap();
+    let history = client.history();
+
+    let explanation = client.explain_plan().await.unwrap();
+    assert_eq!(
+        explanation,
+        "First the code is read, then tests are written."
+    );
+    assert_eq!(client.history().len(), history.len());
+
+    let prompts = server.requests_for("session/prompt");
+    let prompt = prompts[1]["params"]["prompt"][0]["text"].as_str().unwrap();
+    assert!(prompt.starts_with("Please explain this plan in 2-3 sentences: "));
+    assert!(prompt.contains(r#""content":"Read the code""#));
+    assert!(prompt.contains(r#""content":"Write the tests","priority":"high""#));
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,83 @@
+//! Tests for attaching files to messages
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("iflow_{}_{}", uuid::Uuid::new_v4(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+/// Send `files` with a message and return the content blocks of the prompt
+async fn sent_blocks(files: Vec<&Path>) -> Vec<Value> {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+        .send_message("analyze this", Some(files))
+        .await
+        .unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    prompts[0]["params"]["prompt"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_text_and_binary_attachments() {
+    let notes = temp_file("notes.md", b"# Notes");
+    let report = temp_file("report.pdf", &[0x25, 0x50, 0x44, 0x46, 0x00, 0xff]);
+
+    let blocks = sent_blocks(vec![&notes, &report]).await;
+
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0]["text"], "analyze this");
+
+    let text = &blocks[1]["resource"];
+    assert_eq!(blocks[1]["type"], "resource");
+    assert_eq!(text["text"], "# Notes");
+    assert_eq!(text["mimeType"], "text/markdown");
+    assert!(text["uri"].as_str().unwrap().starts_with("file://"));
+
+    let blob = &blocks[2]["resource"];
+    assert_eq!(blob["blob"], "JVBERgD/");
+    assert_eq!(blob["mimeType"], "application/pdf");
+    assert!(blob.get("text").is_none());
+
+    let _ = std::fs::remove_file(notes);
+    let _ = std::fs::remove_file(report);
+}
+
+#[tokio::test]
+async fn test_unknown_binary_type() {
+    let data = temp_file("data.bin", &[0x00, 0x01]);
+    let blocks = sent_blocks(vec![&data]).await;
+
+    assert_eq!(
+        blocks[1]["resource"]["mimeType"],
+        "application/octet-stream"
+    );
+    assert_eq!(blocks[1]["resource"]["blob"], "AAE=");
+    let _ = std::fs::remove_file(data);
+}
+
+#[tokio::test]
+async fn test_missing_attachment() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    let missing = Path::new("/nonexistent/iflow/report.pdf");
+    let result = client
+        .send_message("analyze this", Some(vec![missing]))
+        .await;
+    assert!(matches!(result, Err(IFlowError::FileNotFound(_))));
+    assert!(server.requests_for("session/prompt").is_empty());
+
+    client.disconnect().await.unwrap();
+}
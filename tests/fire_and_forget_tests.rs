@@ -0,0 +1,73 @@
+//! Tests for sending messages without waiting for the response
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, Message};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_fire_and_forget_then_drain() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Noted"), agent_chunk(".")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+        .send_message_fire_and_forget("Some context")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let messages = client.drain_messages().await.unwrap();
+    let text: String = messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant { content } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(text, "Noted.");
+    assert!(messages.last().unwrap().is_task_finish());
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    // Nothing is left over for the next message
+    assert!(client.drain_messages().await.unwrap().is_empty());
+    client.send_message("hello", None).await.unwrap();
+    let (response, _) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Noted.");
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_next_message_finishes_pending_prompt() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message_fire_and_forget("first").await.unwrap();
+    client.send_message("second", None).await.unwrap();
+
+    // Both responses are queued, in order
+    let finishes = client
+        .drain_messages()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|message| message.is_task_finish())
+        .count();
+    assert_eq!(finishes, 2);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fire_and_forget_requires_connection() {
+    let mut client = IFlowClient::new(None);
+    let err = client
+        .send_message_fire_and_forget("hello")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::NotConnected));
+    assert!(client.drain_messages().await.unwrap().is_empty());
+}
@@ -0,0 +1,33 @@
+//! Shared helpers for integration tests
+//!
+//! Each test crate only uses a subset of these helpers.
+#![allow(dead_code)]
+
+pub mod server;
+pub mod stdio_agent;
+
+use iflow_cli_sdk_rust::types::WebSocketConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions};
+use server::{SessionScript, TestIFlowServer};
+
+/// Options for a client connecting to an already running server at `url`
+pub fn websocket_options(url: String) -> IFlowOptions {
+    IFlowOptions::new()
+        .with_timeout(10.0)
+        .with_auto_start(false)
+        .with_websocket_config(WebSocketConfig::with_reconnect_settings(
+            url,
+            1,
+            std::time::Duration::from_millis(100),
+        ))
+}
+
+/// A client for a server that plays back `script`
+///
+/// The client is not connected yet. The server rejects prompts that are
+/// not in the script; keep it alive for as long as the client is used.
+pub async fn client_with_script(script: Vec<SessionScript>) -> (IFlowClient, TestIFlowServer) {
+    let server = TestIFlowServer::start_scripted(script).await;
+    let client = IFlowClient::new(Some(websocket_options(server.url())));
+    (client, server)
+}
@@ -0,0 +1,418 @@
+//! A minimal in-process iFlow server speaking ACP over WebSocket
+//!
+//! The server answers the handshake requests (`initialize`, `authenticate`,
+//! `session/new`) and replays `session/update` payloads for every
+//! `session/prompt` before replying with an `end_turn` result. The payloads
+//! are either a fixed list sent for every prompt, or scripted per prompt with
+//! [`SessionScript`]. Other requests get a method-not-found error. Every
+//! JSON-RPC message received from the client is recorded for assertions.
+//!
+//! A scripted exchange reads top to bottom:
+//!
+//! ```ignore
+//! let (mut client, server) = helpers::client_with_script(vec![
+//!     SessionScript::expect_prompt("hello")
+//!         .reply_chunks(vec!["Hello", " world"])
+//!         .reply_task_finish(),
+//! ])
+//! .await;
+//! ```
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+/// Updates sent in response to a prompt containing the given text
+#[derive(Debug, Clone)]
+pub struct SessionScript {
+    /// Text the prompt must contain for this script to be used
+    pub prompt: String,
+    /// The `update` objects sent as `session/update` notifications
+    pub updates: Vec<Value>,
+    /// The `stopReason` of the prompt response
+    pub stop_reason: String,
+    /// Whether to leave the prompt unanswered, as if iFlow hung
+    pub hang: bool,
+}
+
+impl SessionScript {
+    /// Script the updates sent for prompts containing `prompt`
+    pub fn new(prompt: &str, updates: Vec<Value>) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            updates,
+            stop_reason: "end_turn".to_string(),
+            hang: false,
+        }
+    }
+
+    /// Set the `stopReason` of the prompt response
+    pub fn with_stop_reason(mut self, stop_reason: &str) -> Self {
+        self.stop_reason = stop_reason.to_string();
+        self
+    }
+
+    /// Script the reply to prompts containing `prompt`
+    ///
+    /// The reply is built up with the `reply_*` methods.
+    pub fn expect_prompt(prompt: &str) -> Self {
+        Self::new(prompt, Vec::new())
+    }
+
+    /// Reply with an `agent_message_chunk` update for each chunk
+    pub fn reply_chunks(mut self, chunks: Vec<&str>) -> Self {
+        self.updates.extend(chunks.into_iter().map(agent_chunk));
+        self
+    }
+
+    /// Reply with an arbitrary update
+    pub fn reply_update(mut self, update: Value) -> Self {
+        self.updates.push(update);
+        self
+    }
+
+    /// End the turn normally, with an `end_turn` stop reason
+    pub fn reply_task_finish(self) -> Self {
+        self.with_stop_reason("end_turn")
+    }
+
+    /// Send the updates but never answer the prompt
+    pub fn reply_never(mut self) -> Self {
+        self.hang = true;
+        self
+    }
+}
+
+/// Responses of the server to `session/prompt` requests
+#[derive(Clone)]
+struct Responses {
+    updates: Vec<Value>,
+    stop_reasons: Vec<String>,
+    scripts: Vec<SessionScript>,
+    methods: Vec<(String, Value)>,
+    /// Reject prompts that match no script instead of answering them
+    strict: bool,
+}
+
+/// Test double for an iFlow process running with `--experimental-acp --port`
+pub struct TestIFlowServer {
+    port: u16,
+    requests: Arc<Mutex<Vec<Value>>>,
+    handle: JoinHandle<()>,
+}
+
+impl TestIFlowServer {
+    /// Start a server on a random local port
+    ///
+    /// # Arguments
+    /// * `updates` - The `update` objects sent as `session/update` notifications for each prompt
+    pub async fn start(updates: Vec<Value>) -> Self {
+        Self::start_with_stop_reasons(updates, Vec::new()).await
+    }
+
+    /// Start a server that answers successive prompts with the given stop reasons
+    ///
+    /// Prompts beyond the end of `stop_reasons` are answered with `end_turn`.
+    ///
+    /// # Arguments
+    /// * `updates` - The `update` objects sent as `session/update` notifications for each prompt
+    /// * `stop_reasons` - The `stopReason` of each prompt response, in order
+    pub async fn start_with_stop_reasons(updates: Vec<Value>, stop_reasons: Vec<&str>) -> Self {
+        let responses = Responses {
+            updates,
+            stop_reasons: stop_reasons.into_iter().map(String::from).collect(),
+            scripts: Vec::new(),
+            methods: Vec::new(),
+            strict: false,
+        };
+        Self::listen(0, responses).await
+    }
+
+    /// Start a server that answers additional methods with fixed results
+    ///
+    /// # Arguments
+    /// * `methods` - The method names and the `result` to answer them with,
+    ///   or [`no_reply`] to never answer them
+    pub async fn start_with_methods(methods: Vec<(&str, Value)>) -> Self {
+        let responses = Responses {
+            updates: Vec::new(),
+            stop_reasons: Vec::new(),
+            scripts: Vec::new(),
+            methods: methods
+                .into_iter()
+                .map(|(method, result)| (method.to_string(), result))
+                .collect(),
+            strict: false,
+        };
+        Self::listen(0, responses).await
+    }
+
+    /// Start a server that answers prompts according to a script
+    ///
+    /// Each prompt is answered with the first script whose `prompt` it
+    /// contains. Prompts matching no script get no updates.
+    ///
+    /// # Arguments
+    /// * `port` - The port to listen on, or 0 for a random port
+    /// * `script` - The scripted responses
+    pub async fn new(port: u16, script: Vec<SessionScript>) -> Self {
+        let responses = Responses {
+            updates: Vec::new(),
+            stop_reasons: Vec::new(),
+            scripts: script,
+            methods: Vec::new(),
+            strict: false,
+        };
+        Self::listen(port, responses).await
+    }
+
+    /// Start a server that only accepts the scripted prompts
+    ///
+    /// Prompts matching no script are rejected with an error naming the
+    /// prompt, so a test fails at the request it did not expect.
+    ///
+    /// # Arguments
+    /// * `script` - The scripted responses
+    pub async fn start_scripted(script: Vec<SessionScript>) -> Self {
+        let responses = Responses {
+            updates: Vec::new(),
+            stop_reasons: Vec::new(),
+            scripts: script,
+            methods: Vec::new(),
+            strict: true,
+        };
+        Self::listen(0, responses).await
+    }
+
+    async fn listen(port: u16, responses: Responses) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                let responses = responses.clone();
+                tokio::spawn(async move {
+                    if let Ok(ws) = accept_async(stream).await {
+                        serve_connection(ws, responses, recorded).await;
+                    }
+                });
+            }
+        });
+
+        Self {
+            port,
+            requests,
+            handle,
+        }
+    }
+
+    /// The port the server is listening on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The WebSocket URL clients should connect to
+    pub fn url(&self) -> String {
+        format!("ws://127.0.0.1:{}/acp?peer=iflow", self.port)
+    }
+
+    /// All JSON-RPC messages received so far
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The outcome the client answered the permission request `id` with
+    ///
+    /// # Panics
+    /// If the client has not answered the request
+    pub fn permission_outcome(&self, id: u64) -> Value {
+        self.requests()
+            .into_iter()
+            .find(|message| message["id"] == id && message.get("method").is_none())
+            .map(|message| message["result"]["outcome"].clone())
+            .unwrap()
+    }
+
+    /// All received requests with the given method
+    pub fn requests_for(&self, method: &str) -> Vec<Value> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request["method"] == method)
+            .collect()
+    }
+}
+
+impl Drop for TestIFlowServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve_connection(
+    ws: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
+    responses: Responses,
+    recorded: Arc<Mutex<Vec<Value>>>,
+) {
+    let (mut sink, mut stream) = ws.split();
+    if sink.send(Message::Text("//ready".into())).await.is_err() {
+        return;
+    }
+
+    let mut session_count = 0;
+    let mut prompt_count = 0;
+    while let Some(Ok(frame)) = stream.next().await {
+        let Message::Text(text) = frame else {
+            continue;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        recorded.lock().unwrap().push(request.clone());
+
+        // Responses to our own requests (e.g. permission replies) carry no method
+        let Some(method) = request["method"].as_str() else {
+            continue;
+        };
+        let id = request["id"].clone();
+
+        let result = match method {
+            "initialize" => json!({ "protocolVersion": 1, "isAuthenticated": true }),
+            "authenticate" => json!({ "methodId": request["params"]["methodId"] }),
+            "session/new" => {
+                session_count += 1;
+                json!({ "sessionId": format!("test-session-{}", session_count) })
+            }
+            "session/prompt" => {
+                let session_id = request["params"]["sessionId"].clone();
+                let text = request["params"]["prompt"][0]["text"]
+                    .as_str()
+                    .unwrap_or("");
+                let script = responses
+                    .scripts
+                    .iter()
+                    .find(|script| text.contains(&script.prompt));
+                if script.is_none() && responses.strict {
+                    let response = json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32600, "message": format!("Unexpected prompt: {}", text) },
+                    });
+                    let _ = sink.send(Message::Text(response.to_string().into())).await;
+                    continue;
+                }
+                let (updates, stop_reason) = match script {
+                    Some(script) => (&script.updates, script.stop_reason.as_str()),
+                    None => (
+                        &responses.updates,
+                        responses
+                            .stop_reasons
+                            .get(prompt_count)
+                            .map(String::as_str)
+                            .unwrap_or("end_turn"),
+                    ),
+                };
+                for update in updates {
+                    // Raw frames are sent verbatim
+                    if let Some(frame) = update.as_str() {
+                        let _ = sink.send(Message::Text(frame.into())).await;
+                        continue;
+                    }
+                    // Complete JSON-RPC messages (e.g. permission requests) are sent as-is
+                    let notification = if update.get("jsonrpc").is_some() {
+                        update.clone()
+                    } else {
+                        json!({
+                            "jsonrpc": "2.0",
+                            "method": "session/update",
+                            "params": { "sessionId": session_id, "update": update },
+                        })
+                    };
+                    let _ = sink
+                        .send(Message::Text(notification.to_string().into()))
+                        .await;
+                }
+                prompt_count += 1;
+                if script.is_some_and(|script| script.hang) {
+                    continue;
+                }
+                json!({ "stopReason": stop_reason })
+            }
+            _ => match responses.methods.iter().find(|(name, _)| name == method) {
+                Some((_, result)) if *result == no_reply() => continue,
+                Some((_, result)) => result.clone(),
+                None => {
+                    if !id.is_null() {
+                        let response = json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": -32601, "message": "Method not found" },
+                        });
+                        let _ = sink.send(Message::Text(response.to_string().into())).await;
+                    }
+                    continue;
+                }
+            },
+        };
+
+        if !id.is_null() {
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            let _ = sink.send(Message::Text(response.to_string().into())).await;
+        }
+    }
+}
+
+/// Build an `agent_message_chunk` update
+pub fn agent_chunk(text: &str) -> Value {
+    json!({
+        "sessionUpdate": "agent_message_chunk",
+        "content": { "type": "text", "text": text },
+    })
+}
+
+/// A method result that makes the server leave the request unanswered
+pub fn no_reply() -> Value {
+    json!({ "noReply": true })
+}
+
+/// Build a frame that is sent verbatim in place of an update
+pub fn raw_frame(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+/// Build a `tool_call` update
+pub fn tool_call(id: &str, title: &str, status: &str) -> Value {
+    json!({
+        "sessionUpdate": "tool_call",
+        "toolCall": { "id": id, "title": title, "status": status },
+    })
+}
+
+/// Build a `plan` update
+pub fn plan(entries: Value) -> Value {
+    json!({ "sessionUpdate": "plan", "entries": entries })
+}
+
+/// Build a `session/request_permission` request, sent in place of an update
+///
+/// The tool call has the ID `call_<id>` and offers the options
+/// `proceed_always` and `proceed_once`.
+pub fn permission_request(id: u64, title: &str, tool_type: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "session/request_permission",
+        "params": {
+            "toolCall": { "toolCallId": format!("call_{}", id), "title": title, "type": tool_type },
+            "options": [
+                { "optionId": "proceed_always", "name": "Always allow" },
+                { "optionId": "proceed_once", "name": "Allow once" },
+            ],
+        },
+    })
+}
@@ -0,0 +1,81 @@
+//! A minimal ACP agent for testing the stdio transport
+//!
+//! The agent is a shell script installed as `iflow` in a temporary
+//! directory that is put first on `PATH`, so an auto-started stdio client
+//! talks to it instead of the real iFlow CLI.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Reply text of every prompt that is answered
+///
+/// The client handles session updates on their own tasks, so the agent
+/// pauses between the reply and the prompt response to keep them in order.
+pub const REPLY: &str = "Hello from stdio";
+
+/// Prompts containing this text are rejected with a JSON-RPC error
+pub const FAILING_PROMPT: &str = "please fail";
+
+/// Prompts of the form `"{PERMISSION_PROMPT} <tool>"` make the agent ask
+/// permission to run `<tool>`; it replies with the selected option ID, or
+/// `cancelled`
+pub const PERMISSION_PROMPT: &str = "run tool";
+
+const SCRIPT: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+    id=$(printf '%s\n' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+    case "$line" in
+        *'"method":"initialize"'*)
+            printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":1}}\n' "$id" ;;
+        *'"method":"session/new"'*)
+            printf '{"jsonrpc":"2.0","id":%s,"result":{"sessionId":"stdio-session"}}\n' "$id" ;;
+        *'"method":"session/prompt"'*'FAILING_PROMPT'*)
+            printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32603,"message":"Prompt failed"}}\n' "$id" ;;
+        *'"method":"session/prompt"'*'PERMISSION_PROMPT '*)
+            tool=$(printf '%s\n' "$line" | sed -n 's/.*PERMISSION_PROMPT \([a-z_]*\).*/\1/p')
+            printf '{"jsonrpc":"2.0","id":900,"method":"session/request_permission","params":{"sessionId":"stdio-session","toolCall":{"toolCallId":"call_1","title":"%s","kind":"execute"},"options":[{"optionId":"proceed_once","name":"Allow once","kind":"allow_once"}]}}\n' "$tool"
+            IFS= read -r reply
+            outcome=$(printf '%s\n' "$reply" | sed -n 's/.*"optionId":"\([a-z_]*\)".*/\1/p')
+            printf '{"jsonrpc":"2.0","method":"session/update","params":{"sessionId":"stdio-session","update":{"sessionUpdate":"agent_message_chunk","content":{"type":"text","text":"%s"}}}}\n' "${outcome:-cancelled}"
+            sleep 0.2
+            printf '{"jsonrpc":"2.0","id":%s,"result":{"stopReason":"end_turn"}}\n' "$id" ;;
+        *'"method":"session/prompt"'*)
+            printf '{"jsonrpc":"2.0","method":"session/update","params":{"sessionId":"stdio-session","update":{"sessionUpdate":"agent_message_chunk","content":{"type":"text","text":"REPLY"}}}}\n'
+            sleep 0.2
+            printf '{"jsonrpc":"2.0","id":%s,"result":{"stopReason":"end_turn"}}\n' "$id" ;;
+    esac
+done
+"#;
+
+/// Install the agent as `iflow` on `PATH`
+///
+/// The agent is installed once per test crate; later calls return the same
+/// directory.
+pub fn install() -> PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("iflow_stdio_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("iflow");
+        std::fs::write(
+            &script,
+            SCRIPT
+                .replace("FAILING_PROMPT", FAILING_PROMPT)
+                .replace("PERMISSION_PROMPT", PERMISSION_PROMPT)
+                .replace("REPLY", REPLY),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let mut paths = vec![dir.clone()];
+        paths.extend(std::env::split_paths(&path));
+        // SAFETY: tests using the agent only read PATH when spawning it,
+        // after this has returned
+        unsafe { std::env::set_var("PATH", std::env::join_paths(paths).unwrap()) };
+        dir
+    })
+    .clone()
+}
@@ -0,0 +1,58 @@
+//! Tests for IFlowClient lifecycle and message hooks
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{DisconnectReason, IFlowHooks};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_hooks_called_in_order() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello"), agent_chunk(" world")]).await;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (on_connect, on_message, on_disconnect) = (events.clone(), events.clone(), events.clone());
+    let hooks = IFlowHooks::new()
+        .on_connect(move || on_connect.lock().unwrap().push("connect".to_string()))
+        .on_message(move |msg| {
+            let text = msg.get_text().unwrap_or_default();
+            on_message.lock().unwrap().push(format!("message:{}", text));
+        })
+        .on_disconnect(move |reason| {
+            assert_eq!(reason, DisconnectReason::Graceful);
+            on_disconnect.lock().unwrap().push("disconnect".to_string());
+        });
+
+    let options = helpers::websocket_options(server.url()).with_hooks(hooks);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec!["connect", "message:Hello", "message: world", "disconnect"]
+    );
+}
+
+#[tokio::test]
+async fn test_hooks_not_called_without_connection() {
+    let called = Arc::new(Mutex::new(false));
+    let flag = called.clone();
+    let hooks = IFlowHooks::new().on_disconnect(move |_| *flag.lock().unwrap() = true);
+
+    let options = iflow_cli_sdk_rust::IFlowOptions::new().with_hooks(hooks);
+    let mut client = IFlowClient::new(Some(options));
+    client.disconnect().await.unwrap();
+
+    assert!(!*called.lock().unwrap());
+}
+
+#[test]
+fn test_hooks_debug_reports_presence() {
+    let hooks = IFlowHooks::new().on_connect(|| {});
+    let debug = format!("{:?}", hooks);
+    assert!(debug.contains("on_connect: true"));
+    assert!(debug.contains("on_message: false"));
+}
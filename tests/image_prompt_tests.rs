@@ -0,0 +1,82 @@
+//! Tests for sending images to iFlow
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::FileAccessConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+
+#[tokio::test]
+async fn test_send_image_content_blocks() {
+    let server = TestIFlowServer::start(vec![agent_chunk("A red pixel")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+        .send_image(b"\x89PNG", "image/png", "What is in this image?")
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("iflow_{}_image.JPG", uuid::Uuid::new_v4()));
+    std::fs::write(&path, b"Man").unwrap();
+    client
+        .send_image_file(&path, "And this one?")
+        .await
+        .unwrap();
+    client.disconnect().await.unwrap();
+    let _ = std::fs::remove_file(path);
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(
+        prompts[0]["params"]["prompt"],
+        serde_json::json!([
+            { "type": "image", "data": "iVBORw==", "mimeType": "image/png" },
+            { "type": "text", "text": "What is in this image?" },
+        ])
+    );
+    let blocks = prompts[1]["params"]["prompt"].as_array().unwrap();
+    assert_eq!(blocks[0]["data"], "TWFu");
+    assert_eq!(blocks[0]["mimeType"], "image/jpeg");
+    assert_eq!(blocks[1]["text"], "And this one?");
+}
+
+#[tokio::test]
+async fn test_send_image_validation() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_file_access_config(FileAccessConfig {
+            max_size: 4,
+            ..Default::default()
+        });
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let err = client
+        .send_image(b"GIF8", "image/bmp", "What is this?")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::Validation(_)));
+
+    let err = client
+        .send_image(b"GIF89a", "image/gif", "What is this?")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        IFlowError::FileTooLarge {
+            size: 6,
+            limit: 4,
+            ..
+        }
+    ));
+
+    let err = client
+        .send_image_file(std::path::Path::new("diagram.svg"), "What is this?")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::Validation(_)));
+
+    client.disconnect().await.unwrap();
+    assert!(server.requests_for("session/prompt").is_empty());
+}
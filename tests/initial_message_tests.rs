@@ -0,0 +1,61 @@
+//! Tests for IFlowOptions::initial_message
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, Message};
+
+#[tokio::test]
+async fn test_initial_message_sent_before_first_prompt() {
+    let server = TestIFlowServer::start(vec![agent_chunk("reply")]).await;
+    let options = helpers::websocket_options(server.url())
+        .with_initial_message("You are a Rust expert.".to_string());
+
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client
+        .send_message("Explain lifetimes", None)
+        .await
+        .unwrap();
+    client.send_message("And borrowing?", None).await.unwrap();
+
+    let prompts: Vec<_> = server
+        .requests_for("session/prompt")
+        .iter()
+        .map(|p| {
+            p["params"]["prompt"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(
+        prompts,
+        vec![
+            "You are a Rust expert.",
+            "Explain lifetimes",
+            "And borrowing?"
+        ]
+    );
+
+    // Only the responses to the user's prompts reach the stream
+    let mut stream = client.messages();
+    let mut replies = 0;
+    let mut finishes = 0;
+    while finishes < 2 {
+        match stream.next().await.unwrap() {
+            Message::Assistant { .. } => replies += 1,
+            Message::TaskFinish { .. } => finishes += 1,
+            _ => {}
+        }
+    }
+    assert_eq!(replies, 2);
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(100), stream.next())
+            .await
+            .is_err()
+    );
+
+    client.disconnect().await.unwrap();
+}
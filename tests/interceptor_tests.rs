@@ -0,0 +1,109 @@
+//! Tests for message interceptors
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::{
+    FilterInterceptor, IFlowClient, LoggingInterceptor, Message, MessageInterceptor,
+};
+
+/// Upper-cases assistant text
+struct Shout;
+
+impl MessageInterceptor for Shout {
+    fn intercept(&mut self, msg: Message) -> Option<Message> {
+        match msg {
+            Message::Assistant { content } => Some(Message::Assistant {
+                content: content.to_uppercase(),
+            }),
+            other => Some(other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_interceptors_drop_and_transform_messages() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("hello"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .with_interceptor(Box::new(LoggingInterceptor))
+        .with_interceptor(Box::new(FilterInterceptor(|msg| {
+            !matches!(msg, Message::ToolCall { .. })
+        })))
+        .with_interceptor(Box::new(Shout));
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+
+    let (response, tool_calls) = client.collect_response().await.unwrap();
+    assert_eq!(response, "HELLO");
+    assert!(tool_calls.is_empty());
+    assert_eq!(client.statistics().tool_calls_total, 0);
+
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_filter_interceptor() {
+    let mut filter = FilterInterceptor(|msg| !msg.is_task_finish());
+    let kept = filter.intercept(Message::Assistant {
+        content: "text".to_string(),
+    });
+    assert!(kept.is_some());
+    assert!(
+        filter
+            .intercept(Message::TaskFinish { reason: None })
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_plan_subscription() {
+    use helpers::server::plan;
+    use iflow_cli_sdk_rust::types::PlanEntry;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    let server = TestIFlowServer::start(vec![
+        plan(json!([{ "content": "Write tests", "status": "in_progress" }])),
+        plan(json!([
+            { "content": "Write tests", "status": "completed" },
+            { "content": "Fix bugs", "status": "pending" },
+        ])),
+    ])
+    .await;
+
+    let plans: Arc<Mutex<Vec<Vec<PlanEntry>>>> = Arc::default();
+    let count = Arc::new(Mutex::new(0));
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    let seen = plans.clone();
+    client.subscribe_to_plan(Arc::new(move |entries| {
+        seen.lock().unwrap().push(entries.to_vec())
+    }));
+    let counted = count.clone();
+    client.subscribe_to_plan(Arc::new(move |_| *counted.lock().unwrap() += 1));
+    client.connect().await.unwrap();
+    client.send_message("Plan it", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    {
+        let plans = plans.lock().unwrap();
+        assert_eq!(plans.len(), 2);
+        // Each callback receives the full plan
+        assert_eq!(plans[1].len(), 2);
+        assert_eq!(plans[1][0].content, "Write tests");
+        assert_eq!(plans[1][1].content, "Fix bugs");
+    }
+    assert_eq!(*count.lock().unwrap(), 2);
+
+    client.unsubscribe_from_plan();
+    client.send_message("Again", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    assert_eq!(plans.lock().unwrap().len(), 2);
+    assert_eq!(*count.lock().unwrap(), 2);
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,40 @@
+//! Tests for connection and prompt latency tracking
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+
+#[tokio::test]
+async fn test_connection_latency_per_connect() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    assert!(client.connection_latency_histogram().is_empty());
+
+    for _ in 0..5 {
+        client.connect().await.unwrap();
+        client.disconnect().await.unwrap();
+    }
+
+    assert_eq!(client.connection_latency_histogram().len(), 5);
+}
+
+#[tokio::test]
+async fn test_prompt_latency() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello"), agent_chunk(" there")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    assert_eq!(client.p95_prompt_latency(), None);
+
+    for _ in 0..3 {
+        client.send_message("Hi", None).await.unwrap();
+        client.collect_response().await.unwrap();
+    }
+
+    // Only the first chunk of each response is timed
+    let latencies = client.prompt_latency_histogram();
+    assert_eq!(latencies.len(), 3);
+    assert_eq!(client.p95_prompt_latency(), latencies.iter().max().copied());
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,145 @@
+//! Tests for MessageLogger and injecting loggers into IFlowClient
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, LoggerConfig, Message, MessageLogger};
+use std::path::PathBuf;
+
+fn temp_logger() -> (MessageLogger, PathBuf) {
+    let log_file = std::env::temp_dir().join(format!("iflow_{}.log", uuid::Uuid::new_v4()));
+    let logger = MessageLogger::new(LoggerConfig {
+        log_file: log_file.clone(),
+        ..Default::default()
+    })
+    .unwrap();
+    (logger, log_file)
+}
+
+#[tokio::test]
+async fn test_with_logger_records_messages() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello from iFlow")]).await;
+    let (logger, log_file) = temp_logger();
+
+    let mut client =
+        IFlowClient::new(Some(helpers::websocket_options(server.url()))).with_logger(logger);
+    assert_eq!(client.logger().unwrap().log_file_path(), log_file);
+
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let logger = client.logger().unwrap();
+    assert_eq!(logger.log_count(), 1);
+    let entries = logger.read_all().await.unwrap();
+    assert!(
+        matches!(&entries[0].1, Message::Assistant { content } if content == "Hello from iFlow")
+    );
+    let _ = std::fs::remove_file(log_file);
+}
+
+#[test]
+fn test_without_logger() {
+    let (logger, log_file) = temp_logger();
+    let client = IFlowClient::new(None).with_logger(logger).without_logger();
+    assert!(client.logger().is_none());
+    let _ = std::fs::remove_file(log_file);
+}
+
+#[tokio::test]
+async fn test_log_count_read_all_and_reset() {
+    let (logger, log_file) = temp_logger();
+
+    for i in 0..3 {
+        let msg = Message::Assistant {
+            content: format!("chunk {}", i),
+        };
+        logger.log_message(&msg).await.unwrap();
+    }
+    assert_eq!(logger.log_count(), 3);
+
+    let entries = logger.read_all().await.unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[2].1.get_text(), Some("chunk 2"));
+    assert!(entries[0].0 <= entries[2].0);
+
+    logger.reset().await.unwrap();
+    assert_eq!(logger.log_count(), 0);
+    assert_eq!(std::fs::metadata(&log_file).unwrap().len(), 0);
+    assert!(logger.read_all().await.unwrap().is_empty());
+
+    // Logging continues at the start of the truncated file
+    logger
+        .log_message(&Message::error(1, "boom".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(logger.log_count(), 1);
+    assert_eq!(logger.read_all().await.unwrap().len(), 1);
+
+    let _ = std::fs::remove_file(log_file);
+}
+
+fn tiny_logger(max_files: u32) -> (MessageLogger, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("iflow_rotation_{}", uuid::Uuid::new_v4()));
+    let logger = MessageLogger::new(LoggerConfig {
+        log_file: dir.join("messages.log"),
+        max_file_size: 100,
+        max_files,
+        ..Default::default()
+    })
+    .unwrap();
+    (logger, dir)
+}
+
+/// A message whose log entry is a little over 50 bytes
+fn chunk(i: usize) -> Message {
+    Message::Assistant {
+        content: format!("chunk {}", i),
+    }
+}
+
+#[tokio::test]
+async fn test_rotation_at_max_file_size() {
+    let (logger, dir) = tiny_logger(3);
+    let archive = |n: u32| dir.join(format!("messages.{}.log", n));
+
+    // The first entry stays below 100 bytes, the second crosses it
+    logger.log_message(&chunk(0)).await.unwrap();
+    assert!(!archive(1).exists());
+    logger.log_message(&chunk(1)).await.unwrap();
+    assert!(archive(1).exists());
+    assert_eq!(
+        std::fs::metadata(dir.join("messages.log")).unwrap().len(),
+        0
+    );
+
+    // Two more rotations shift the first archive to .2, then delete it
+    for i in 2..6 {
+        logger.log_message(&chunk(i)).await.unwrap();
+    }
+    let archived = std::fs::read_to_string(archive(2)).unwrap();
+    assert!(archived.contains("chunk 2") && archived.contains("chunk 3"));
+    let archived = std::fs::read_to_string(archive(1)).unwrap();
+    assert!(archived.contains("chunk 4") && archived.contains("chunk 5"));
+    assert!(!archive(3).exists());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_archive_now_and_flush() {
+    let (logger, dir) = tiny_logger(5);
+
+    logger.log_message(&chunk(0)).await.unwrap();
+    logger.archive_now().await.unwrap();
+    let archived = std::fs::read_to_string(dir.join("messages.1.log")).unwrap();
+    assert!(archived.contains("chunk 0"));
+
+    logger.log_message(&chunk(1)).await.unwrap();
+    logger.flush().await.unwrap();
+    let entries = logger.read_all().await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(matches!(&entries[0].1, Message::Assistant { content } if content == "chunk 1"));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,71 @@
+//! Tests for changing MCP servers on a connected client
+
+mod helpers;
+
+use helpers::server::TestIFlowServer;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, McpServer};
+
+fn stdio_server(name: &str) -> McpServer {
+    McpServer::Stdio {
+        name: name.to_string(),
+        command: "mcp-server".into(),
+        args: Vec::new(),
+        env: Vec::new(),
+    }
+}
+
+fn server_names(request: &serde_json::Value) -> Vec<String> {
+    request["params"]["mcpServers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|server| server["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_set_mcp_servers_recreates_session() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+    let options =
+        helpers::websocket_options(server.url()).with_mcp_servers(vec![stdio_server("filesystem")]);
+    let mut client = IFlowClient::new(Some(options));
+    client.warmup().await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-1"));
+
+    client.add_mcp_server(stdio_server("github")).await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-2"));
+    assert_eq!(client.options().mcp_servers.len(), 2);
+
+    client.remove_mcp_server("filesystem").await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-3"));
+
+    let sessions = server.requests_for("session/new");
+    assert_eq!(server_names(&sessions[0]), vec!["filesystem"]);
+    assert_eq!(server_names(&sessions[1]), vec!["filesystem", "github"]);
+    assert_eq!(server_names(&sessions[2]), vec!["github"]);
+
+    let closed: Vec<_> = server
+        .requests_for("session/close")
+        .iter()
+        .map(|request| request["params"]["sessionId"].clone())
+        .collect();
+    assert_eq!(closed, vec!["test-session-1", "test-session-2"]);
+
+    // Removing an unknown server keeps the session
+    let result = client.remove_mcp_server("unknown").await;
+    assert!(matches!(result, Err(IFlowError::Validation(_))));
+    assert_eq!(client.session_id(), Some("test-session-3"));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_set_mcp_servers_when_disconnected() {
+    let mut client = IFlowClient::new(None);
+    client
+        .set_mcp_servers(vec![stdio_server("filesystem")])
+        .await
+        .unwrap();
+    assert_eq!(client.options().mcp_servers.len(), 1);
+    assert!(client.session_id().is_none());
+}
@@ -194,6 +194,7 @@ mod tests {
 
     /// Test IFlowOptions with MCP servers and other configurations
     #[test]
+    #[allow(deprecated)]
     fn test_iflow_options_comprehensive_mcp() {
         let mcp_servers = vec![McpServer::Stdio {
             name: "filesystem".to_string(),
@@ -0,0 +1,49 @@
+//! Tests for IFlowClient::stream_with_metadata
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::MessageWithMeta;
+use iflow_cli_sdk_rust::{IFlowClient, Message};
+use std::time::{Duration, SystemTime};
+
+#[tokio::test]
+async fn test_stream_with_metadata() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+
+    let messages: Vec<MessageWithMeta> = client.stream_with_metadata().take(2).collect().await;
+
+    assert_eq!(messages[0].sequence, 0);
+    assert_eq!(messages[1].sequence, 1);
+    assert!(matches!(messages[0].message, Message::Assistant { .. }));
+    assert!(matches!(messages[1].message, Message::TaskFinish { .. }));
+    assert!(messages[0].received_at <= messages[1].received_at);
+    for message in &messages {
+        assert_eq!(message.session_id.as_deref(), Some("test-session-1"));
+    }
+
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_latency_since() {
+    let at = SystemTime::now();
+    let message = |sequence, received_at| MessageWithMeta {
+        message: Message::Assistant {
+            content: String::new(),
+        },
+        received_at,
+        session_id: None,
+        sequence,
+    };
+    let first = message(0, at);
+    let second = message(1, at + Duration::from_millis(250));
+
+    assert_eq!(second.latency_since(&first), Duration::from_millis(250));
+    assert_eq!(first.latency_since(&second), Duration::ZERO);
+}
@@ -0,0 +1,52 @@
+//! Tests for per-message metadata
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use serde_json::json;
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn test_send_message_with_meta_merges_session_metadata() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let session_meta = HashMap::from([
+        ("userId".to_string(), json!("user-1")),
+        ("requestId".to_string(), json!("session")),
+    ]);
+    let options = helpers::websocket_options(server.url()).with_metadata(session_meta);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let meta = HashMap::from([("requestId".to_string(), json!("req-42"))]);
+    client
+        .send_message_with_meta("hello", None, meta)
+        .await
+        .unwrap();
+    client.collect_response().await.unwrap();
+    client.send_message("again", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(
+        prompts[0]["params"]["_meta"],
+        json!({ "userId": "user-1", "requestId": "req-42" })
+    );
+    assert_eq!(
+        prompts[1]["params"]["_meta"],
+        json!({ "userId": "user-1", "requestId": "session" })
+    );
+}
+
+#[tokio::test]
+async fn test_send_message_without_metadata_omits_meta() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("hello", None).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert!(prompts[0]["params"].get("_meta").is_none());
+}
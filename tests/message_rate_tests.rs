@@ -0,0 +1,132 @@
+//! Tests for throttling assistant chunks
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::types::MessageRate;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions, Message};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_chunks_respect_rate() {
+    let chunks = 5;
+    let rate = 20.0;
+    let server =
+        TestIFlowServer::start((0..chunks).map(|i| agent_chunk(&i.to_string())).collect()).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .throttle_messages(MessageRate::new(rate));
+    client.connect().await.unwrap();
+    client.send_message("count", None).await.unwrap();
+
+    let mut stream = client.messages();
+    let mut received = Vec::new();
+    let started = Instant::now();
+    while let Some(message) = stream.next().await {
+        match message {
+            Message::Assistant { content } => received.push(content),
+            Message::TaskFinish { .. } => break,
+            _ => {}
+        }
+    }
+    let elapsed = started.elapsed();
+
+    assert_eq!(received, vec!["0", "1", "2", "3", "4"]);
+    assert!(elapsed >= Duration::from_secs_f64((chunks - 1) as f64 / rate));
+
+    drop(stream);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_other_messages_are_not_delayed() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("a"),
+        agent_chunk("b"),
+        tool_call("call-1", "tool", "completed"),
+    ])
+    .await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_message_rate_limit(MessageRate::new(2.0));
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("go", None).await.unwrap();
+
+    let mut stream = client.messages();
+    let first = stream.next().await.unwrap();
+    assert!(matches!(first, Message::Assistant { .. }));
+
+    // The chunk after the first is delayed, but the tool call and task
+    // finish queued behind it follow immediately
+    let started = Instant::now();
+    assert!(matches!(
+        stream.next().await,
+        Some(Message::Assistant { .. })
+    ));
+    let chunk_delay = started.elapsed();
+    assert!(chunk_delay >= Duration::from_millis(400));
+
+    let started = Instant::now();
+    assert!(matches!(
+        stream.next().await,
+        Some(Message::ToolCall { .. })
+    ));
+    assert!(matches!(
+        stream.next().await,
+        Some(Message::TaskFinish { .. })
+    ));
+    assert!(started.elapsed() < Duration::from_millis(100));
+
+    drop(stream);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dropped_stream_keeps_delayed_chunk() {
+    let server = TestIFlowServer::start(vec![agent_chunk("a"), agent_chunk("b")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .throttle_messages(MessageRate::new(1.0));
+    client.connect().await.unwrap();
+    client.send_message("go", None).await.unwrap();
+
+    let mut stream = client.messages();
+    assert!(matches!(
+        stream.next().await,
+        Some(Message::Assistant { .. })
+    ));
+    let second = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+    assert!(second.is_err());
+    drop(stream);
+
+    let mut stream = client.messages();
+    assert!(matches!(
+        stream.next().await,
+        Some(Message::Assistant { content }) if content == "b"
+    ));
+
+    drop(stream);
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_tiny_rate_interval_does_not_panic() {
+    assert_eq!(MessageRate::new(1e-300).interval(), Duration::MAX);
+    assert_eq!(MessageRate::new(f64::NAN).interval(), Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_invalid_message_rate_is_rejected_on_connect() {
+    for rate in [0.0, f64::NAN, 1e-300] {
+        assert!(matches!(
+            MessageRate::new(rate).validate(),
+            Err(IFlowError::Config(_))
+        ));
+        let options = IFlowOptions::new().with_message_rate_limit(MessageRate::new(rate));
+        let mut client = IFlowClient::new(Some(options));
+        assert!(matches!(client.connect().await, Err(IFlowError::Config(_))));
+    }
+    assert!(MessageRate::new(20.0).validate().is_ok());
+}
@@ -0,0 +1,75 @@
+//! Tests for the filter and map combinators of the message stream
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::types::MessageType;
+use iflow_cli_sdk_rust::{IFlowClient, Message};
+
+async fn connected_client(server: &TestIFlowServer) -> IFlowClient {
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Hi", None).await.unwrap();
+    client
+}
+
+fn updates() -> Vec<serde_json::Value> {
+    vec![
+        agent_chunk("Hello"),
+        tool_call("call_1", "bash", "pending"),
+        agent_chunk(" world"),
+    ]
+}
+
+#[tokio::test]
+async fn test_filter_keeps_matching_messages() {
+    let server = TestIFlowServer::start(updates()).await;
+    let mut client = connected_client(&server).await;
+
+    let messages: Vec<_> = client
+        .messages()
+        .filter_messages(|msg| !matches!(msg, Message::Assistant { .. }))
+        .take(2)
+        .collect()
+        .await;
+    assert!(matches!(messages[0], Message::ToolCall { .. }));
+    assert!(matches!(messages[1], Message::TaskFinish { .. }));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_map_transforms_messages() {
+    let server = TestIFlowServer::start(updates()).await;
+    let mut client = connected_client(&server).await;
+
+    let types: Vec<_> = client
+        .messages()
+        .map_messages(|msg| msg.message_type())
+        .take(4)
+        .collect()
+        .await;
+    assert_eq!(
+        types,
+        vec![
+            MessageType::Assistant,
+            MessageType::ToolCall,
+            MessageType::Assistant,
+            MessageType::TaskFinish
+        ]
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_assistant_chunks_yields_text() {
+    let server = TestIFlowServer::start(updates()).await;
+    let mut client = connected_client(&server).await;
+
+    let text: String = client.messages().assistant_chunks().take(2).collect().await;
+    assert_eq!(text, "Hello world");
+
+    client.disconnect().await.unwrap();
+}
@@ -14,6 +14,7 @@ async fn test_plan_message_comprehensive() {
         content: "Implement user authentication".to_string(),
         priority: PlanPriority::High,
         status: PlanStatus::InProgress,
+        id: None,
     };
 
     assert_eq!(entry.content, "Implement user authentication");
@@ -42,11 +43,13 @@ async fn test_plan_message_comprehensive() {
             content: "Task 1".to_string(),
             priority: PlanPriority::High,
             status: PlanStatus::Pending,
+            id: None,
         },
         PlanEntry {
             content: "Task 2".to_string(),
             priority: PlanPriority::Medium,
             status: PlanStatus::InProgress,
+            id: None,
         },
     ];
 
@@ -0,0 +1,86 @@
+//! Tests for IFlowClient::multi_session_query
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn test_queries_use_their_own_options() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new("first", vec![agent_chunk("one")]),
+            SessionScript::new("second", vec![agent_chunk("two")]),
+        ],
+    )
+    .await;
+
+    let queries = vec![
+        (
+            "first".to_string(),
+            helpers::websocket_options(server.url()).with_cwd(PathBuf::from("/tmp/first")),
+        ),
+        (
+            "second".to_string(),
+            helpers::websocket_options(server.url()).with_cwd(PathBuf::from("/tmp/second")),
+        ),
+    ];
+
+    let mut results: Vec<String> = IFlowClient::multi_session_query(queries)
+        .map(|result| result.unwrap())
+        .collect()
+        .await;
+    results.sort();
+    assert_eq!(results, vec!["one", "two"]);
+
+    let mut cwds: Vec<String> = server
+        .requests_for("session/new")
+        .iter()
+        .map(|request| request["params"]["cwd"].as_str().unwrap().to_string())
+        .collect();
+    cwds.sort();
+    assert_eq!(cwds, vec!["/tmp/first", "/tmp/second"]);
+}
+
+#[tokio::test]
+async fn test_concurrency_limit() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+
+    let queries = (0..3)
+        .map(|i| {
+            (
+                format!("query {}", i),
+                helpers::websocket_options(server.url()).with_global_concurrency_limit(1),
+            )
+        })
+        .collect();
+
+    let results: Vec<_> = IFlowClient::multi_session_query(queries).collect().await;
+    assert_eq!(results.len(), 3);
+    assert!(
+        results
+            .iter()
+            .all(|result| result.as_deref().ok() == Some("ok"))
+    );
+    assert_eq!(server.requests_for("session/prompt").len(), 3);
+}
+
+#[tokio::test]
+async fn test_failed_query_does_not_stop_others() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+
+    let queries = vec![
+        ("good".to_string(), helpers::websocket_options(server.url())),
+        (
+            "bad".to_string(),
+            helpers::websocket_options("ws://127.0.0.1:1/acp".to_string()),
+        ),
+    ];
+
+    let results: Vec<_> = IFlowClient::multi_session_query(queries).collect().await;
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+    assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+}
@@ -0,0 +1,104 @@
+//! Tests for managing several sessions on one connection
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("iflow_{}_{}", name, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_switch_between_sessions() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let frontend = temp_dir("frontend");
+    let backend = temp_dir("backend");
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    let first = client.new_session_in_dir(&frontend).await.unwrap();
+    let second = client.new_session_in_dir(&backend).await.unwrap();
+    assert_ne!(first, second);
+    assert!(server.requests_for("session/prompt").is_empty());
+
+    // The first session became active because there was none
+    assert_eq!(client.session_id(), Some(first.as_str()));
+    assert_eq!(client.list_sessions(), vec![first.clone(), second.clone()]);
+
+    let sessions = server.requests_for("session/new");
+    assert_eq!(
+        sessions[0]["params"]["cwd"],
+        frontend.to_string_lossy().as_ref()
+    );
+    assert_eq!(
+        sessions[1]["params"]["cwd"],
+        backend.to_string_lossy().as_ref()
+    );
+
+    client.use_session(&second).unwrap();
+    client.send_message("In the backend", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.use_session(&first).unwrap();
+    client.send_message("In the frontend", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts[0]["params"]["sessionId"], second.as_str());
+    assert_eq!(prompts[1]["params"]["sessionId"], first.as_str());
+    // No session was created for the prompts
+    assert_eq!(server.requests_for("session/new").len(), 2);
+
+    client.disconnect().await.unwrap();
+    assert!(client.list_sessions().is_empty());
+    let _ = std::fs::remove_dir(frontend);
+    let _ = std::fs::remove_dir(backend);
+}
+
+#[tokio::test]
+async fn test_switch_back_to_implicit_session() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let backend = temp_dir("backend");
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    let implicit = client.session_id().unwrap().to_string();
+
+    let other = client.new_session_in_dir(&backend).await.unwrap();
+    client.use_session(&other).unwrap();
+    assert_eq!(
+        client.list_sessions(),
+        vec![implicit.clone(), other.clone()]
+    );
+
+    client.use_session(&implicit).unwrap();
+    client.send_message("Back again", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts[1]["params"]["sessionId"], implicit.as_str());
+    assert_eq!(client.list_sessions(), vec![implicit, other]);
+
+    client.disconnect().await.unwrap();
+    let _ = std::fs::remove_dir(backend);
+}
+
+#[tokio::test]
+async fn test_use_unknown_session() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    let result = client.use_session("missing");
+    assert!(matches!(result, Err(IFlowError::Validation(_))));
+    let result = client
+        .new_session_in_dir(std::path::Path::new("/nonexistent/iflow/dir"))
+        .await;
+    assert!(matches!(result, Err(IFlowError::FileNotFound(_))));
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,91 @@
+//! Tests for IFlowOptions::diff and apply_patch
+
+use iflow_cli_sdk_rust::types::{IFlowOptionsDiff, PermissionMode};
+use iflow_cli_sdk_rust::{IFlowError, IFlowOptions};
+use serde_json::json;
+use std::path::PathBuf;
+
+fn base() -> IFlowOptions {
+    IFlowOptions::new().with_cwd(PathBuf::from("/tmp/base"))
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_diff_lists_changed_fields() {
+    let old = base()
+        .with_timeout(60.0)
+        .with_permission_mode(PermissionMode::Auto);
+    let mut new = old.clone().with_permission_mode(PermissionMode::Manual);
+    new.timeout = 300.0;
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.fields(), vec!["timeout", "permission_mode"]);
+    assert_eq!(
+        diff.changes[0],
+        ("timeout".to_string(), json!(60.0), json!(300.0))
+    );
+    assert_eq!(
+        diff.changes[1],
+        (
+            "permission_mode".to_string(),
+            json!("auto"),
+            json!("manual")
+        )
+    );
+}
+
+#[test]
+fn test_diff_of_identical_options_is_empty() {
+    let options = base().with_history(true);
+    assert!(options.diff(&options.clone()).is_empty());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_apply_patch_roundtrip() {
+    let old = base();
+    let mut new = base()
+        .with_timeout(30.0)
+        .with_permission_mode(PermissionMode::Selective)
+        .with_history(true)
+        .with_initial_message("Be brief".to_string())
+        .with_global_concurrency_limit(2);
+    new.process.start_port = Some(9000);
+    new.metadata.insert("team".to_string(), json!("ops"));
+
+    let patched = old.apply_patch(&old.diff(&new)).unwrap();
+    assert!(patched.diff(&new).is_empty());
+    assert_eq!(patched.timeout, 30.0);
+    assert_eq!(patched.process.start_port, Some(9000));
+    assert_eq!(patched.metadata["team"], "ops");
+}
+
+#[test]
+fn test_diff_survives_serialization() {
+    let old = base();
+    let new = base().with_timeout(30.0).with_history(true);
+    let diff = old.diff(&new);
+
+    let json = serde_json::to_string(&diff).unwrap();
+    let restored: IFlowOptionsDiff = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, diff);
+    assert!(old.apply_patch(&restored).unwrap().diff(&new).is_empty());
+}
+
+#[test]
+fn test_apply_patch_rejects_invalid_patches() {
+    let options = base();
+
+    let unknown = IFlowOptionsDiff {
+        changes: vec![("color".to_string(), json!(null), json!("blue"))],
+    };
+    assert!(matches!(
+        options.apply_patch(&unknown),
+        Err(IFlowError::Validation(_))
+    ));
+
+    let wrong_type = IFlowOptionsDiff {
+        changes: vec![("timeout".to_string(), json!(120.0), json!("fast"))],
+    };
+    assert!(options.apply_patch(&wrong_type).is_err());
+}
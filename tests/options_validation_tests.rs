@@ -0,0 +1,42 @@
+//! Tests for IFlowOptions::validate_async
+
+use iflow_cli_sdk_rust::types::{FileAccessConfig, WebSocketConfig};
+use iflow_cli_sdk_rust::{IFlowError, IFlowOptions};
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn test_validate_async_default_options() {
+    assert!(IFlowOptions::new().validate_async().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_validate_async_rejects_missing_allowed_dir() {
+    let options = IFlowOptions::new().with_file_access_config(FileAccessConfig {
+        allowed_dirs: Some(vec![
+            std::env::temp_dir(),
+            PathBuf::from("/nonexistent/iflow/allowed"),
+        ]),
+        ..Default::default()
+    });
+
+    let result = options.validate_async().await;
+    assert!(
+        matches!(result, Err(IFlowError::Config(msg)) if msg.contains("/nonexistent/iflow/allowed"))
+    );
+}
+
+#[tokio::test]
+async fn test_validate_async_checks_websocket_reachability() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let url = format!("ws://127.0.0.1:{}/acp", port);
+
+    let options = IFlowOptions::new()
+        .with_websocket_config(WebSocketConfig::new(url.clone()))
+        .with_auto_start(false);
+    assert!(options.validate_async().await.is_ok());
+
+    drop(listener);
+    let result = options.validate_async().await;
+    assert!(matches!(result, Err(IFlowError::Config(_))));
+}
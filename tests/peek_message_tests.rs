@@ -0,0 +1,46 @@
+//! Tests for peeking at the next message
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::types::MessageType;
+use iflow_cli_sdk_rust::{IFlowClient, Message};
+
+#[tokio::test]
+async fn test_peek_does_not_consume_message() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("Hello"),
+        tool_call("call_1", "bash", "pending"),
+    ])
+    .await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Hi", None).await.unwrap();
+
+    let peeked = client.peek_message();
+    assert!(matches!(peeked, Some(Message::Assistant { ref content }) if content == "Hello"));
+    // Peeking again returns the same message
+    assert!(matches!(
+        client.peek_message(),
+        Some(Message::Assistant { .. })
+    ));
+    assert_eq!(client.next_message_type(), Some(MessageType::Assistant));
+
+    let received = client.receive_message().await.unwrap();
+    assert!(matches!(received, Some(Message::Assistant { ref content }) if content == "Hello"));
+
+    assert_eq!(client.next_message_type(), Some(MessageType::ToolCall));
+    let (text, tool_calls) = client.collect_response().await.unwrap();
+    assert_eq!(text, "");
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(client.peek_message().map(|msg| msg.message_type()), None);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_peek_without_messages() {
+    let client = IFlowClient::new(None);
+    assert!(client.peek_message().is_none());
+    assert_eq!(client.next_message_type(), None);
+}
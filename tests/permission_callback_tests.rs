@@ -0,0 +1,210 @@
+//! Tests for runtime permission decisions through a permission callback
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, permission_request};
+use iflow_cli_sdk_rust::types::{
+    PermissionCallback, PermissionDecision, PermissionMode, PermissionOption, ToolCallDecision,
+    ToolCallEvent, ToolCallHook,
+};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// Approves `bash` once and denies everything else, recording each request
+#[derive(Default)]
+struct BashOnly {
+    seen: Mutex<Vec<(String, String, Vec<PermissionOption>)>>,
+}
+
+#[async_trait::async_trait]
+impl PermissionCallback for BashOnly {
+    async fn request_permission(
+        &self,
+        tool_title: &str,
+        tool_type: &str,
+        options: &[PermissionOption],
+    ) -> PermissionDecision {
+        self.seen.lock().unwrap().push((
+            tool_title.to_string(),
+            tool_type.to_string(),
+            options.to_vec(),
+        ));
+        if tool_title == "bash" {
+            PermissionDecision::Approve("proceed_once".to_string())
+        } else {
+            PermissionDecision::Deny
+        }
+    }
+}
+
+async fn run(server: &TestIFlowServer, mode: PermissionMode, callback: Arc<BashOnly>) {
+    let options = helpers::websocket_options(server.url())
+        .with_permission_mode(mode)
+        .with_permission_callback(callback);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("Run it", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+    // Let the server record the permission responses
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+}
+
+#[tokio::test]
+async fn test_callback_overrides_permission_mode() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "bash", "execute"),
+        permission_request(101, "rm", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    run(
+        &server,
+        PermissionMode::Manual,
+        Arc::new(BashOnly::default()),
+    )
+    .await;
+    assert_eq!(
+        server.permission_outcome(100),
+        json!({ "outcome": "selected", "optionId": "proceed_once" })
+    );
+    assert_eq!(
+        server.permission_outcome(101),
+        json!({ "outcome": "cancelled" })
+    );
+
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "rm", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+    run(&server, PermissionMode::Auto, Arc::new(BashOnly::default())).await;
+    assert_eq!(
+        server.permission_outcome(100),
+        json!({ "outcome": "cancelled" })
+    );
+}
+
+#[tokio::test]
+async fn test_callback_receives_request_details() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "bash", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+    let callback = Arc::new(BashOnly::default());
+
+    run(&server, PermissionMode::Manual, callback.clone()).await;
+    let seen = callback.seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    let (title, tool_type, options) = &seen[0];
+    assert_eq!(title, "bash");
+    assert_eq!(tool_type, "execute");
+    assert_eq!(
+        options,
+        &vec![
+            PermissionOption {
+                option_id: "proceed_always".to_string(),
+                label: "Always allow".to_string(),
+            },
+            PermissionOption {
+                option_id: "proceed_once".to_string(),
+                label: "Allow once".to_string(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_tool_approvals_take_precedence_over_callback() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "rm", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+    let callback = Arc::new(BashOnly::default());
+
+    let options = helpers::websocket_options(server.url())
+        .with_permission_callback(callback.clone())
+        .with_tool_approvals(std::collections::HashMap::from([(
+            "rm".to_string(),
+            PermissionDecision::Approve("proceed_once".to_string()),
+        )]));
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("Clean up", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(
+        server.permission_outcome(100),
+        json!({ "outcome": "selected", "optionId": "proceed_once" })
+    );
+    assert!(callback.seen.lock().unwrap().is_empty());
+}
+
+/// Denies `write_file`
+struct DenyWrites;
+
+impl ToolCallHook for DenyWrites {
+    fn before_tool_call(&self, call: &ToolCallEvent) -> ToolCallDecision {
+        if call.name == "write_file" {
+            ToolCallDecision::Deny("writes are not allowed".to_string())
+        } else {
+            ToolCallDecision::Allow
+        }
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stdio_decides_permissions_in_the_same_order() {
+    use helpers::stdio_agent;
+
+    stdio_agent::install();
+    let callback = Arc::new(BashOnly::default());
+    let options = IFlowOptions::new()
+        .with_timeout(10.0)
+        .with_permission_callback(callback.clone())
+        .with_tool_call_hook(Arc::new(DenyWrites))
+        .with_tool_approvals(std::collections::HashMap::from([
+            (
+                "rm".to_string(),
+                PermissionDecision::Approve("proceed_once".to_string()),
+            ),
+            (
+                "write_file".to_string(),
+                PermissionDecision::Approve("proceed_once".to_string()),
+            ),
+        ]));
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let mut client = IFlowClient::new(Some(options));
+            client.connect().await.unwrap();
+            let mut outcome = async |tool: &str| {
+                let prompt = format!("{} {}", stdio_agent::PERMISSION_PROMPT, tool);
+                client.send_message(&prompt, None).await.unwrap();
+                client.collect_response().await.unwrap().0
+            };
+
+            // The approval answers without asking the callback
+            assert_eq!(outcome("rm").await, "proceed_once");
+            // The hook vetoes even a pre-approved tool
+            assert_eq!(outcome("write_file").await, "cancelled");
+            // Anything else is up to the callback
+            assert_eq!(outcome("bash").await, "proceed_once");
+            assert_eq!(outcome("ls").await, "cancelled");
+
+            client.disconnect().await.unwrap();
+        })
+        .await;
+
+    let seen = callback.seen.lock().unwrap();
+    let titles: Vec<&str> = seen.iter().map(|(title, _, _)| title.as_str()).collect();
+    assert_eq!(titles, vec!["bash", "ls"]);
+}
@@ -8,6 +8,7 @@ async fn test_plan_message_structure() {
         content: "Test plan entry".to_string(),
         priority: PlanPriority::High,
         status: PlanStatus::InProgress,
+        id: None,
     };
 
     // Verify the entry has the expected values
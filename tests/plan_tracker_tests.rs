@@ -0,0 +1,200 @@
+//! Tests for PlanTracker and plan entry IDs
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{SessionScript, TestIFlowServer, plan};
+use iflow_cli_sdk_rust::types::{PlanEntry, PlanPriority, PlanStatus};
+use iflow_cli_sdk_rust::{IFlowClient, Message, PlanTracker, plan_diff};
+use serde_json::json;
+
+fn entry(id: &str, content: &str, status: PlanStatus) -> PlanEntry {
+    PlanEntry {
+        content: content.to_string(),
+        priority: PlanPriority::Medium,
+        status,
+        id: Some(id.to_string()),
+    }
+}
+
+#[test]
+fn test_update_applies_status_change_by_id() {
+    let mut tracker = PlanTracker::new();
+    tracker.update(vec![
+        entry("a", "Write code", PlanStatus::InProgress),
+        entry("b", "Write tests", PlanStatus::Pending),
+    ]);
+    tracker.update(vec![
+        entry("b", "Write tests", PlanStatus::InProgress),
+        entry("a", "Write code", PlanStatus::Completed),
+    ]);
+
+    assert_eq!(tracker.get("a").unwrap().status, PlanStatus::Completed);
+    assert_eq!(tracker.get("b").unwrap().status, PlanStatus::InProgress);
+
+    // Entries keep the order in which they were first seen
+    let contents: Vec<_> = tracker
+        .entries()
+        .iter()
+        .map(|e| e.content.as_str())
+        .collect();
+    assert_eq!(contents, vec!["Write code", "Write tests"]);
+}
+
+#[test]
+fn test_update_adds_new_and_unidentified_entries() {
+    let mut tracker = PlanTracker::new();
+    tracker.update(vec![entry("a", "First", PlanStatus::Pending)]);
+    tracker.update(vec![
+        entry("c", "Second", PlanStatus::Pending),
+        PlanEntry {
+            content: "Third".to_string(),
+            ..Default::default()
+        },
+    ]);
+
+    let entries = tracker.entries();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[2].id.is_none());
+}
+
+#[test]
+fn test_update_without_ids_merges_by_content() {
+    let plan = |status: PlanStatus| {
+        vec![
+            PlanEntry {
+                content: "Write code".to_string(),
+                status: status.clone(),
+                ..Default::default()
+            },
+            PlanEntry {
+                content: "Write tests".to_string(),
+                ..Default::default()
+            },
+        ]
+    };
+
+    let mut tracker = PlanTracker::new();
+    tracker.update(plan(PlanStatus::InProgress));
+    tracker.update(plan(PlanStatus::Completed));
+
+    let entries = tracker.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].status, PlanStatus::Completed);
+    assert!(entries.iter().all(|e| e.id.is_none()));
+}
+
+#[tokio::test]
+async fn test_plan_entry_ids_parsed_when_sent() {
+    let server = TestIFlowServer::start(vec![plan(json!([
+        { "id": "step-1", "content": "Analyze", "priority": "high", "status": "completed" },
+        { "content": "Implement", "priority": "medium", "status": "pending" },
+    ]))])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("plan it", None).await.unwrap();
+
+    let mut messages = client.messages();
+    let entries = loop {
+        if let Some(Message::Plan { entries }) = messages.next().await {
+            break entries;
+        }
+    };
+    client.disconnect().await.unwrap();
+
+    assert_eq!(entries[0].id.as_deref(), Some("step-1"));
+    assert_eq!(entries[1].id, None);
+}
+
+#[test]
+fn test_plan_diff_matches_by_id_then_content() {
+    let prev = vec![
+        entry("a", "Write code", PlanStatus::InProgress),
+        PlanEntry {
+            content: "Write docs".to_string(),
+            ..Default::default()
+        },
+    ];
+    let mut docs = PlanEntry {
+        content: "Write docs".to_string(),
+        priority: PlanPriority::High,
+        ..Default::default()
+    };
+    docs.id = Some("generated".to_string());
+    let curr = vec![
+        entry("a", "Write the code", PlanStatus::Completed),
+        docs,
+        entry("c", "Release", PlanStatus::Pending),
+    ];
+
+    let diff = plan_diff(&prev, &curr);
+    assert!(diff.has_changes());
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].content, "Release");
+    assert_eq!(diff.completed.len(), 1);
+    assert_eq!(diff.completed[0].id.as_deref(), Some("a"));
+    assert_eq!(diff.status_changed.len(), 1);
+    assert_eq!(diff.status_changed[0].1, PlanStatus::InProgress);
+    assert_eq!(diff.priority_changed.len(), 1);
+    assert_eq!(diff.priority_changed[0].0.content, "Write docs");
+    assert_eq!(diff.priority_changed[0].1, PlanPriority::Medium);
+
+    assert!(!plan_diff(&curr, &curr).has_changes());
+}
+
+#[tokio::test]
+async fn test_diff_plan_updates_stream() {
+    let first = plan(json!([
+        { "id": "a", "content": "Analyze", "priority": "medium", "status": "pending" },
+        { "id": "b", "content": "Implement", "priority": "medium", "status": "pending" },
+    ]));
+    let second = plan(json!([
+        { "id": "a", "content": "Analyze", "priority": "medium", "status": "in_progress" },
+        { "id": "b", "content": "Implement", "priority": "medium", "status": "pending" },
+    ]));
+    let third = plan(json!([
+        { "id": "a", "content": "Analyze", "priority": "medium", "status": "completed" },
+        { "id": "b", "content": "Implement", "priority": "high", "status": "pending" },
+        { "id": "c", "content": "Test", "priority": "low", "status": "pending" },
+    ]));
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new("one", vec![first]),
+            // The repeated plan has no changes and is not yielded
+            SessionScript::new("two", vec![second.clone(), second]),
+            SessionScript::new("three", vec![third]),
+        ],
+    )
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    for prompt in ["one", "two", "three"] {
+        client.send_message(prompt, None).await.unwrap();
+    }
+
+    let updates: Vec<_> = client.watch_plan().take(3).collect().await;
+    client.disconnect().await.unwrap();
+
+    let (plan, diff) = &updates[0];
+    assert_eq!(plan.len(), 2);
+    assert_eq!(diff.added.len(), 2);
+    assert!(diff.status_changed.is_empty());
+
+    let (_, diff) = &updates[1];
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.status_changed.len(), 1);
+    assert_eq!(diff.status_changed[0].0.status, PlanStatus::InProgress);
+    assert!(diff.completed.is_empty());
+
+    let (plan, diff) = &updates[2];
+    assert_eq!(plan.len(), 3);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.completed.len(), 1);
+    assert_eq!(diff.completed[0].content, "Analyze");
+    assert_eq!(diff.priority_changed.len(), 1);
+    assert_eq!(diff.priority_changed[0].1, PlanPriority::Medium);
+}
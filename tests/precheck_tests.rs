@@ -0,0 +1,113 @@
+//! Tests for connection prechecks
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{
+    BinaryInPathPrecheck, ConnectionPrecheck, DirectoryExistsPrecheck, IFlowClient, IFlowError,
+    IFlowOptions, McpServer, McpServerBinaryPrecheck, Result,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Precheck that counts its runs and fails if `fail` is set
+struct CountingPrecheck {
+    runs: Arc<AtomicUsize>,
+    fail: bool,
+}
+
+#[async_trait::async_trait]
+impl ConnectionPrecheck for CountingPrecheck {
+    async fn run(&self, _options: &IFlowOptions) -> Result<()> {
+        self.runs.fetch_add(1, Ordering::SeqCst);
+        if self.fail {
+            Err(IFlowError::Config("IFLOW_API_KEY is not set".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn stdio_server(command: &str) -> McpServer {
+    McpServer::Stdio {
+        name: "files".to_string(),
+        command: PathBuf::from(command),
+        args: Vec::new(),
+        env: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_failing_precheck_stops_connect() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+    let first = Arc::new(AtomicUsize::new(0));
+    let second = Arc::new(AtomicUsize::new(0));
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .with_connection_precheck(Box::new(CountingPrecheck {
+            runs: first.clone(),
+            fail: true,
+        }))
+        .with_connection_precheck(Box::new(CountingPrecheck {
+            runs: second.clone(),
+            fail: false,
+        }));
+
+    let err = client.connect().await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Configuration error: IFLOW_API_KEY is not set"
+    );
+    assert_eq!(first.load(Ordering::SeqCst), 1);
+    assert_eq!(second.load(Ordering::SeqCst), 0);
+    assert!(!client.snapshot().connected);
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_passing_prechecks_connect() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .with_connection_precheck(Box::new(CountingPrecheck {
+            runs: runs.clone(),
+            fail: false,
+        }))
+        .with_connection_precheck(Box::new(DirectoryExistsPrecheck(std::env::temp_dir())));
+    client.connect().await.unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_built_in_prechecks() {
+    let options = IFlowOptions::new();
+
+    BinaryInPathPrecheck("sh".to_string())
+        .run(&options)
+        .await
+        .unwrap();
+    let err = BinaryInPathPrecheck("no-such-binary-for-iflow".to_string())
+        .run(&options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::Config(_)));
+
+    let missing = std::env::temp_dir().join(format!("iflow_{}", uuid::Uuid::new_v4()));
+    let err = DirectoryExistsPrecheck(missing.clone())
+        .run(&options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::FileNotFound(path) if path == missing));
+
+    let options = IFlowOptions::new().with_mcp_servers(vec![stdio_server("sh")]);
+    McpServerBinaryPrecheck.run(&options).await.unwrap();
+    let options = IFlowOptions::new().with_mcp_servers(vec![stdio_server("/no/such/mcp-server")]);
+    let err = McpServerBinaryPrecheck.run(&options).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Configuration error: Command /no/such/mcp-server of MCP server files was not found"
+    );
+}
@@ -4,10 +4,14 @@
 //! in various scenarios including stdio and websocket modes,
 //! and both manual and auto-start configurations.
 
+mod helpers;
+
 #[cfg(test)]
 mod tests {
     use iflow_cli_sdk_rust::error::IFlowError;
     use iflow_cli_sdk_rust::process_manager::IFlowProcessManager;
+    use iflow_cli_sdk_rust::types::{ProcessConfig, ProcessHook};
+    use std::sync::Arc;
     use std::time::Duration;
     use tokio::time::sleep;
 
@@ -154,4 +158,397 @@ mod tests {
         // Give a small delay to allow cleanup
         sleep(Duration::from_millis(100)).await;
     }
+
+    /// Test that stderr lines are captured into the ring buffer
+    #[tokio::test]
+    async fn test_recent_stderr_captures_lines() {
+        use tokio::io::AsyncWriteExt;
+
+        let pm = IFlowProcessManager::new(8090, false);
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        pm.capture_stderr(reader);
+
+        writer.write_all(b"first\nsecond\nthird\n").await.unwrap();
+        drop(writer);
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(pm.recent_stderr(2), vec!["second", "third"]);
+        assert_eq!(pm.recent_stderr(10).len(), 3);
+    }
+
+    /// Test that stderr lines are logged in debug mode
+    #[tokio::test]
+    async fn test_debug_mode_logs_stderr() {
+        use std::io::Write;
+        use tokio::io::AsyncWriteExt;
+
+        #[derive(Clone, Default)]
+        struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl Write for LogBuffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let pm = IFlowProcessManager::new(8090, ProcessConfig::new().with_debug(true).debug);
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        pm.capture_stderr(reader);
+
+        writer.write_all(b"loading config\n").await.unwrap();
+        drop(writer);
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(pm.recent_stderr(1), vec!["loading config"]);
+        let logs = String::from_utf8_lossy(&logs.0.lock().unwrap()).into_owned();
+        assert!(logs.contains("iflow stderr: loading config"));
+    }
+
+    /// Test that the stderr buffer only keeps the most recent lines
+    #[tokio::test]
+    async fn test_recent_stderr_ring_buffer_limit() {
+        use iflow_cli_sdk_rust::process_manager::STDERR_BUFFER_LINES;
+        use tokio::io::AsyncWriteExt;
+
+        let pm = IFlowProcessManager::new(8090, false);
+        let (mut writer, reader) = tokio::io::duplex(64 * 1024);
+        pm.capture_stderr(reader);
+
+        for i in 0..STDERR_BUFFER_LINES + 5 {
+            writer
+                .write_all(format!("line {}\n", i).as_bytes())
+                .await
+                .unwrap();
+        }
+        drop(writer);
+        sleep(Duration::from_millis(100)).await;
+
+        let lines = pm.recent_stderr(usize::MAX);
+        assert_eq!(lines.len(), STDERR_BUFFER_LINES);
+        assert_eq!(lines[0], "line 5");
+    }
+
+    /// Test that a short startup timeout fails fast instead of waiting for the protocol timeout
+    #[tokio::test]
+    async fn test_startup_timeout_fails_fast() {
+        let mut pm =
+            IFlowProcessManager::new(8096, false).with_startup_timeout(Duration::from_millis(1));
+
+        let started = std::time::Instant::now();
+        let result = pm.start(true).await;
+        let _ = pm.stop().await;
+
+        assert!(matches!(result, Err(IFlowError::ProcessManager(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Test that an iFlow process is only reported when the port is busy
+    #[test]
+    fn test_find_existing_process() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let processes = || {
+            vec![
+                (100, "node".to_string()),
+                (200, "iflow".to_string()),
+                (300, "iflow-helper".to_string()),
+            ]
+        };
+
+        assert_eq!(
+            IFlowProcessManager::find_existing_process_in(port, processes),
+            Some(200)
+        );
+        assert_eq!(
+            IFlowProcessManager::find_existing_process_in(port, || vec![(100, "node".to_string())]),
+            None
+        );
+
+        drop(listener);
+        assert_eq!(
+            IFlowProcessManager::find_existing_process_in(port, || -> Vec<(u32, String)> {
+                panic!("processes must not be listed when the port is free")
+            }),
+            None
+        );
+    }
+
+    /// Test that a port held by another service is not attributed to a running iFlow
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_find_existing_process_ignores_other_service() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A process named `iflow` that is not listening anywhere
+        let agent_dir = super::helpers::stdio_agent::install();
+        let mut agent = tokio::process::Command::new(agent_dir.join("iflow"))
+            .stdin(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let agent_pid = agent.id().unwrap();
+        let mut system = sysinfo::System::new();
+        system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+        let agent_name = system
+            .process(sysinfo::Pid::from_u32(agent_pid))
+            .map(|process| process.name().to_string_lossy().into_owned());
+        assert_eq!(agent_name.as_deref(), Some("iflow"));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let found = IFlowProcessManager::find_existing_process(port).await;
+        server.abort();
+        let _ = agent.kill().await;
+
+        assert_eq!(found, None);
+    }
+
+    /// Test that a scan range of 1 fails on a busy port without trying others
+    #[tokio::test]
+    async fn test_port_scan_range_of_one_on_busy_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let mut pm = IFlowProcessManager::new(port, false).with_port_scan_range(1);
+        let result = pm.start(true).await;
+
+        match result {
+            Err(IFlowError::ProcessManager(msg)) => {
+                assert_eq!(
+                    msg,
+                    format!("No available port found in range {}-{}", port, port)
+                );
+            }
+            other => panic!("expected ProcessManager error, got {:?}", other),
+        }
+        assert!(pm.port().is_none());
+    }
+
+    /// Test the port range builders of ProcessConfig
+    #[test]
+    fn test_process_config_port_range() {
+        assert_eq!(ProcessConfig::new().port_scan_range, 100);
+        assert_eq!(
+            ProcessConfig::new().with_port_scan_range(0).port_scan_range,
+            1
+        );
+
+        let config = ProcessConfig::new().with_preferred_port(9000);
+        assert_eq!(config.start_port, Some(9000));
+        assert_eq!(config.port_scan_range, 1);
+    }
+
+    /// Test that a free port is available for iFlow
+    #[tokio::test]
+    async fn test_port_is_available_for_iflow_when_free() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        assert!(matches!(
+            IFlowProcessManager::port_is_available_for_iflow(port).await,
+            Ok(true)
+        ));
+    }
+
+    /// Test that a port used by iFlow is reported as taken
+    #[tokio::test]
+    async fn test_port_is_available_for_iflow_when_used_by_iflow() {
+        let server = super::helpers::server::TestIFlowServer::start(Vec::new()).await;
+
+        assert!(matches!(
+            IFlowProcessManager::port_is_available_for_iflow(server.port()).await,
+            Ok(false)
+        ));
+    }
+
+    /// Test that a port used by another service is reported as a conflict
+    #[tokio::test]
+    async fn test_port_is_available_for_iflow_when_used_by_other_service() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let result = IFlowProcessManager::port_is_available_for_iflow(port).await;
+        server.abort();
+
+        match result {
+            Err(IFlowError::ProcessManager(msg)) => {
+                assert!(msg.contains(&format!("Port {} is in use by another service", port)));
+            }
+            other => panic!("expected ProcessManager error, got {:?}", other),
+        }
+    }
+
+    /// Hook that appends `<name> <port>` to a file
+    struct FileHook {
+        name: &'static str,
+        path: std::path::PathBuf,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl ProcessHook for FileHook {
+        async fn run(&self, port: u16) -> iflow_cli_sdk_rust::Result<()> {
+            let mut log = std::fs::read_to_string(&self.path).unwrap_or_default();
+            log.push_str(&format!("{} {}\n", self.name, port));
+            std::fs::write(&self.path, log)?;
+            if self.fail {
+                return Err(IFlowError::ProcessManager(format!("{} failed", self.name)));
+            }
+            Ok(())
+        }
+    }
+
+    fn hook_log(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "iflow-process-hook-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Test that a failing pre-start hook runs first and aborts the start
+    #[tokio::test]
+    async fn test_pre_start_hook_runs_before_spawn() {
+        let path = hook_log("pre-start");
+        let hook = Arc::new(FileHook {
+            name: "pre_start",
+            path: path.clone(),
+            fail: true,
+        });
+        let mut pm = IFlowProcessManager::new(8090, false).with_pre_start_hook(hook);
+
+        match pm.start(false).await {
+            Err(IFlowError::ProcessManager(msg)) => assert_eq!(msg, "pre_start failed"),
+            other => panic!("expected the hook's error, got {:?}", other),
+        }
+        assert!(!pm.is_running());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "pre_start 0\n");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Test that the post-stop hook runs once the process has exited
+    #[tokio::test]
+    async fn test_post_stop_hook_runs_after_exit() {
+        let path = hook_log("post-stop");
+        let hook = Arc::new(FileHook {
+            name: "post_stop",
+            path: path.clone(),
+            fail: true,
+        });
+        let mut pm = IFlowProcessManager::new(8090, false).with_post_stop_hook(hook);
+
+        // Nothing to stop, so the hook does not run
+        pm.stop().await.unwrap();
+        assert!(!path.exists());
+
+        pm.process = Some(
+            tokio::process::Command::new("sleep")
+                .arg("30")
+                .spawn()
+                .unwrap(),
+        );
+        assert!(!path.exists());
+
+        // Errors from the hook are ignored
+        pm.stop().await.unwrap();
+        assert!(!pm.is_running());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "post_stop 0\n");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Test reading the PID of the process and PID files
+    #[tokio::test]
+    async fn test_pid_and_pid_file() {
+        let path = std::env::temp_dir().join(format!("iflow_{}.pid", uuid::Uuid::new_v4()));
+        let mut pm = IFlowProcessManager::new(8090, false);
+
+        // Not started yet
+        assert_eq!(pm.get_pid(), None);
+        assert!(matches!(
+            pm.write_pid_file(&path),
+            Err(IFlowError::ProcessManager(_))
+        ));
+
+        let child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        pm.process = Some(child);
+        assert_eq!(pm.get_pid(), Some(pid));
+
+        pm.write_pid_file(&path).unwrap();
+        assert_eq!(IFlowProcessManager::read_pid_file(&path).unwrap(), pid);
+
+        // Stopping removes the PID file
+        pm.stop().await.unwrap();
+        assert_eq!(pm.get_pid(), None);
+        assert!(!path.exists());
+        assert!(matches!(
+            IFlowProcessManager::read_pid_file(&path),
+            Err(IFlowError::FileNotFound(_))
+        ));
+
+        std::fs::write(&path, "not a pid").unwrap();
+        assert!(matches!(
+            IFlowProcessManager::read_pid_file(&path),
+            Err(IFlowError::ProcessManager(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Test that hooks set on ProcessConfig are kept
+    #[test]
+    fn test_process_config_hooks() {
+        let hook = Arc::new(FileHook {
+            name: "hook",
+            path: hook_log("config"),
+            fail: false,
+        });
+        let config = ProcessConfig::new()
+            .with_pre_start_hook(hook.clone())
+            .with_post_stop_hook(hook);
+        assert!(config.pre_start_hook.is_some());
+        assert!(config.post_stop_hook.is_some());
+    }
 }
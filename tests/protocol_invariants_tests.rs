@@ -0,0 +1,112 @@
+//! Tests for protocol invariant checks
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{MESSAGE_BACKLOG_LIMIT, ProtocolState, Severity};
+
+/// A connected, initialized and authenticated state with a session
+fn valid_state() -> ProtocolState {
+    ProtocolState {
+        connected: true,
+        initialized: true,
+        authenticated: Some(true),
+        session_id: Some("session-1".to_string()),
+        ..ProtocolState::default()
+    }
+}
+
+/// The severity of each violation of `state`
+fn severities(state: ProtocolState) -> Vec<Severity> {
+    state
+        .violations()
+        .into_iter()
+        .map(|violation| violation.severity)
+        .collect()
+}
+
+#[test]
+fn test_valid_state_has_no_violations() {
+    assert!(valid_state().violations().is_empty());
+    assert!(ProtocolState::default().violations().is_empty());
+}
+
+#[test]
+fn test_each_invariant() {
+    let not_initialized = ProtocolState {
+        initialized: false,
+        ..valid_state()
+    };
+    assert_eq!(severities(not_initialized), vec![Severity::Error]);
+
+    let not_authenticated = ProtocolState {
+        authenticated: Some(false),
+        ..valid_state()
+    };
+    assert_eq!(severities(not_authenticated), vec![Severity::Error]);
+
+    // Connections that do not track authentication are not flagged
+    let untracked = ProtocolState {
+        authenticated: None,
+        ..valid_state()
+    };
+    assert!(untracked.violations().is_empty());
+
+    let no_session = ProtocolState {
+        session_id: None,
+        ..valid_state()
+    };
+    let violations = no_session.violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].severity, Severity::Warning);
+    assert_eq!(
+        violations[0].description,
+        "The connection has no session yet"
+    );
+
+    let wrapped = ProtocolState {
+        request_ids_wrapped: true,
+        ..valid_state()
+    };
+    assert_eq!(severities(wrapped), vec![Severity::Warning]);
+
+    let backlog = ProtocolState {
+        queued_messages: MESSAGE_BACKLOG_LIMIT,
+        ..valid_state()
+    };
+    let violations = backlog.violations();
+    assert_eq!(violations.len(), 1);
+    assert!(violations[0].description.contains("1000 received messages"));
+}
+
+#[tokio::test]
+async fn test_client_invariants() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    assert!(client.assert_protocol_invariants().is_empty());
+
+    client.connect().await.unwrap();
+    let violations = client.assert_protocol_invariants();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].severity, Severity::Warning);
+
+    // An attached session is in use before the protocol was initialized
+    client.attach("test-session-1".to_string()).unwrap();
+    let violations = client.assert_protocol_invariants();
+    assert_eq!(violations.len(), 2);
+    assert!(
+        violations
+            .iter()
+            .all(|violation| violation.severity == Severity::Error)
+    );
+
+    // Sending initializes and authenticates the connection
+    client.send_message("hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    assert!(client.assert_protocol_invariants().is_empty());
+
+    client.disconnect().await.unwrap();
+    assert!(client.assert_protocol_invariants().is_empty());
+}
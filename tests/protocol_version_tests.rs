@@ -0,0 +1,114 @@
+//! Tests for selecting the ACP protocol version
+
+mod helpers;
+
+use helpers::server::TestIFlowServer;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions, PROTOCOL_VERSION};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Collects log output in memory
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl LogBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+/// Connect with `version` and return the logged warnings
+async fn connect_with_version(server: &TestIFlowServer, version: u32) -> String {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let options = helpers::websocket_options(server.url()).with_protocol_version(version);
+    let mut client = IFlowClient::new(Some(options));
+    client.warmup().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    logs.contents()
+}
+
+#[test]
+fn test_protocol_version_default() {
+    assert_eq!(IFlowOptions::new().protocol_version, PROTOCOL_VERSION);
+    assert_eq!(
+        IFlowOptions::new()
+            .with_protocol_version(2)
+            .protocol_version,
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_requested_protocol_version_is_sent() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let logs = connect_with_version(&server, 2).await;
+
+    let initialize = server.requests_for("initialize");
+    assert_eq!(initialize[0]["params"]["protocolVersion"], 2);
+
+    // The mock answers with version 1
+    assert!(logs.contains("iFlow downgraded the protocol version from 2 to 1"));
+}
+
+#[tokio::test]
+async fn test_matching_protocol_version_does_not_warn() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let logs = connect_with_version(&server, 1).await;
+
+    assert!(!logs.contains("downgraded"));
+}
+
+#[tokio::test]
+async fn test_out_of_range_protocol_version_is_rejected() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_protocol_version(u32::from(u16::MAX) + 1);
+    let mut client = IFlowClient::new(Some(options));
+    let result = client.warmup().await;
+
+    assert!(matches!(result, Err(IFlowError::Config(_))), "{:?}", result);
+    assert!(server.requests_for("initialize").is_empty());
+    let _ = client.disconnect().await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_out_of_range_protocol_version_is_rejected_over_stdio() {
+    use helpers::stdio_agent;
+
+    stdio_agent::install();
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let options = IFlowOptions::new().with_protocol_version(u32::from(u16::MAX) + 1);
+            let mut client = IFlowClient::new(Some(options));
+            let result = client.warmup().await;
+
+            assert!(matches!(result, Err(IFlowError::Config(_))), "{:?}", result);
+            let _ = client.disconnect().await;
+        })
+        .await;
+}
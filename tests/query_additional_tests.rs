@@ -39,6 +39,7 @@ async fn test_query_with_config_options() {
 
 /// Test IFlowOptions builder pattern
 #[test]
+#[allow(deprecated)]
 fn test_iflow_options_builder_pattern() {
     let options = IFlowOptions::new().with_timeout(30.0).with_auto_start(true);
 
@@ -48,6 +49,7 @@ fn test_iflow_options_builder_pattern() {
 
 /// Test IFlowOptions with different configurations
 #[test]
+#[allow(deprecated)]
 fn test_iflow_options_configurations() {
     // Test default configuration
     let default_options = IFlowOptions::default();
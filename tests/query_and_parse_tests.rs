@@ -0,0 +1,74 @@
+//! Tests for typed query helpers
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{
+    IFlowError, query_and_parse_json_with_config, query_and_parse_with_config,
+    query_and_parse_with_schema_with_config,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[tokio::test]
+async fn test_query_and_parse_i32() {
+    let server = TestIFlowServer::start(vec![agent_chunk(" 4\n")]).await;
+    let answer: i32 =
+        query_and_parse_with_config("What is 2 + 2?", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert_eq!(answer, 4);
+}
+
+#[tokio::test]
+async fn test_query_and_parse_bool() {
+    let server = TestIFlowServer::start(vec![agent_chunk("true")]).await;
+    let answer: bool =
+        query_and_parse_with_config("Is 4 even?", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert!(answer);
+}
+
+#[tokio::test]
+async fn test_query_and_parse_invalid_value() {
+    let server = TestIFlowServer::start(vec![agent_chunk("four")]).await;
+    let result: Result<i32, _> =
+        query_and_parse_with_config("What is 2 + 2?", helpers::websocket_options(server.url()))
+            .await;
+    assert!(matches!(result, Err(IFlowError::Deserialization(_))));
+}
+
+#[tokio::test]
+async fn test_query_and_parse_json_struct() {
+    let server = TestIFlowServer::start(vec![agent_chunk(r#"{"x": 1, "y": 2}"#)]).await;
+    let point: Point =
+        query_and_parse_json_with_config("Give a point", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert_eq!(point, Point { x: 1, y: 2 });
+}
+
+#[tokio::test]
+async fn test_query_and_parse_with_schema_appends_hint() {
+    let server = TestIFlowServer::start(vec![agent_chunk(r#"{"x": 3, "y": 4}"#)]).await;
+    let point: Point = query_and_parse_with_schema_with_config(
+        "Give a point",
+        r#"{"x": int, "y": int}"#,
+        helpers::websocket_options(server.url()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(point, Point { x: 3, y: 4 });
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(
+        prompts[0]["params"]["prompt"][0]["text"],
+        "Give a point\nRespond with valid JSON matching this schema: {\"x\": int, \"y\": int}"
+    );
+}
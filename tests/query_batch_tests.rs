@@ -0,0 +1,61 @@
+//! Tests for sending a batch of prompts over one connection
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowError, IFlowOptions, query_batch_with_config};
+
+#[tokio::test]
+async fn test_batch_reuses_one_session() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("first")
+            .reply_chunks(vec!["One"])
+            .reply_task_finish(),
+        SessionScript::expect_prompt("second")
+            .reply_chunks(vec!["Two"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let responses = query_batch_with_config(
+        &["first", "second"],
+        helpers::websocket_options(server.url()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(responses, vec!["One", "Two"]);
+    assert_eq!(server.requests_for("initialize").len(), 1);
+    assert_eq!(server.requests_for("session/new").len(), 1);
+    assert_eq!(server.requests_for("session/prompt").len(), 2);
+}
+
+#[tokio::test]
+async fn test_empty_batch_does_not_connect() {
+    let responses = query_batch_with_config(&[], IFlowOptions::new())
+        .await
+        .unwrap();
+    assert!(responses.is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_prompt_keeps_partial_results() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![agent_chunk("ok")],
+        vec!["end_turn", "cancelled"],
+    )
+    .await;
+
+    let result = query_batch_with_config(
+        &["first", "second", "third"],
+        helpers::websocket_options(server.url()),
+    )
+    .await;
+    match result {
+        Err(IFlowError::BatchFailed { completed, source }) => {
+            assert_eq!(completed, vec!["ok"]);
+            assert!(source.is_cancelled());
+        }
+        other => panic!("Expected BatchFailed, got {:?}", other),
+    }
+    assert_eq!(server.requests_for("session/prompt").len(), 2);
+}
@@ -46,6 +46,7 @@ async fn test_query_stream_with_timeout_function_exists() {
 
 /// Test IFlowOptions creation with various configurations
 #[test]
+#[allow(deprecated)]
 fn test_iflow_options_creation_variants() {
     // Test default options
     let default_options = IFlowOptions::default();
@@ -197,6 +198,7 @@ async fn test_query_stream_with_config_options() {
 
 /// Test that query function calls query_with_timeout with default timeout
 #[tokio::test]
+#[allow(deprecated)]
 async fn test_query_calls_query_with_timeout() {
     // Verify that the query function exists and has the correct signature
     let _query_fn = query;
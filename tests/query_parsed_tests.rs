@@ -0,0 +1,86 @@
+//! Tests for typed JSON and JSON Lines queries
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{
+    IFlowError, query_stream_jsonl_with_config, query_stream_parsed_with_config,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Item {
+    id: u32,
+    name: String,
+}
+
+#[tokio::test]
+async fn test_query_stream_parsed_reassembles_chunks() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk(r#"{"id": 1, "#),
+        agent_chunk(r#""name": "first"}"#),
+    ])
+    .await;
+
+    let item: Item =
+        query_stream_parsed_with_config("give json", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert_eq!(
+        item,
+        Item {
+            id: 1,
+            name: "first".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_query_stream_parsed_invalid_json() {
+    let server = TestIFlowServer::start(vec![agent_chunk("not json")]).await;
+
+    let result: Result<Item, _> =
+        query_stream_parsed_with_config("give json", helpers::websocket_options(server.url()))
+            .await;
+    assert!(matches!(result, Err(IFlowError::Deserialization(_))));
+}
+
+#[tokio::test]
+async fn test_query_stream_jsonl_splits_lines_across_chunks() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("{\"id\": 1, \"name\": \"a\"}\n{\"id\": 2,"),
+        agent_chunk(" \"name\": \"b\"}\n\n"),
+        agent_chunk("oops\n{\"id\": 3, \"name\": \"c\"}"),
+    ])
+    .await;
+
+    let stream = query_stream_jsonl_with_config::<Item>(
+        "give jsonl",
+        helpers::websocket_options(server.url()),
+    )
+    .await
+    .unwrap();
+    let items: Vec<_> = stream.collect().await;
+
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[0].as_ref().unwrap().id, 1);
+    assert_eq!(items[1].as_ref().unwrap().name, "b");
+    assert!(matches!(items[2], Err(IFlowError::Deserialization(_))));
+    assert_eq!(items[3].as_ref().unwrap().id, 3);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_query_stream_jsonl_over_stdio_without_local_set() {
+    helpers::stdio_agent::install();
+
+    let stream = iflow_cli_sdk_rust::query_stream_jsonl::<Item>("Hi")
+        .await
+        .unwrap();
+    let items: Vec<_> = stream.collect().await;
+
+    // The agent answers with one line of plain text
+    assert_eq!(items.len(), 1);
+    assert!(matches!(items[0], Err(IFlowError::Deserialization(_))));
+}
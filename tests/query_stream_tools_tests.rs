@@ -0,0 +1,98 @@
+//! Tests for streaming agent events
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{TestIFlowServer, agent_chunk, plan, tool_call};
+use iflow_cli_sdk_rust::types::{AgentEvent, PlanEntry, PlanPriority, PlanStatus};
+use iflow_cli_sdk_rust::{Message, query_stream_with_tools_with_config};
+use serde_json::json;
+
+#[test]
+fn test_agent_event_from_message() {
+    let event = AgentEvent::from_message(Message::Assistant {
+        content: "Hi".to_string(),
+    });
+    assert!(matches!(event, Some(AgentEvent::Chunk(text)) if text == "Hi"));
+
+    let event = AgentEvent::from_message(Message::ToolCall {
+        id: "call_1".to_string(),
+        name: "read_file".to_string(),
+        status: "in_progress".to_string(),
+    });
+    assert!(matches!(
+        event,
+        Some(AgentEvent::ToolCallStarted { id, name }) if id == "call_1" && name == "read_file"
+    ));
+
+    for status in ["completed", "Failed"] {
+        let event = AgentEvent::from_message(Message::ToolCall {
+            id: "call_1".to_string(),
+            name: "read_file".to_string(),
+            status: status.to_string(),
+        });
+        assert!(matches!(
+            event,
+            Some(AgentEvent::ToolCallCompleted { id, output: None }) if id == "call_1"
+        ));
+    }
+
+    let entry = PlanEntry {
+        id: Some("1".to_string()),
+        content: "Read".to_string(),
+        priority: PlanPriority::High,
+        status: PlanStatus::Pending,
+    };
+    let event = AgentEvent::from_message(Message::Plan {
+        entries: vec![entry],
+    });
+    assert!(matches!(event, Some(AgentEvent::PlanUpdate(entries)) if entries.len() == 1));
+
+    let event = AgentEvent::from_message(Message::TaskFinish { reason: None });
+    assert!(matches!(event, Some(AgentEvent::Done(reason)) if reason == "completed"));
+
+    let event = AgentEvent::from_message(Message::User {
+        content: "hello".to_string(),
+    });
+    assert!(event.is_none());
+}
+
+#[tokio::test]
+async fn test_query_stream_with_tools_yields_all_events() {
+    let server = TestIFlowServer::start(vec![
+        plan(json!([{ "content": "Read the file", "priority": "high", "status": "pending" }])),
+        tool_call("call_1", "read_file", "pending"),
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let stream =
+        query_stream_with_tools_with_config("read it", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    let events: Vec<AgentEvent> = stream.collect().await;
+
+    assert_eq!(events.len(), 5);
+    assert!(matches!(&events[0], AgentEvent::PlanUpdate(entries) if entries.len() == 1));
+    assert!(matches!(&events[1], AgentEvent::ToolCallStarted { id, .. } if id == "call_1"));
+    assert!(matches!(&events[2], AgentEvent::ToolCallCompleted { id, .. } if id == "call_1"));
+    assert!(matches!(&events[3], AgentEvent::Chunk(text) if text == "Done"));
+    assert!(matches!(&events[4], AgentEvent::Done(reason) if reason == "end_turn"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_query_stream_with_tools_over_stdio_without_local_set() {
+    use helpers::stdio_agent;
+
+    stdio_agent::install();
+    let stream = iflow_cli_sdk_rust::query_stream_with_tools("Hi")
+        .await
+        .unwrap();
+    let events: Vec<AgentEvent> = stream.collect().await;
+
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert!(matches!(&events[0], AgentEvent::Chunk(text) if text == stdio_agent::REPLY));
+    assert!(matches!(&events[1], AgentEvent::Done(_)));
+}
@@ -68,6 +68,7 @@ async fn test_query_stream_with_timeout_function_signature() {
 
 /// Test IFlowOptions creation for query functions
 #[test]
+#[allow(deprecated)]
 fn test_iflow_options_creation() {
     let options = IFlowOptions::new();
     assert_eq!(options.timeout, 120.0); // Default timeout
@@ -80,3 +81,17 @@ fn test_iflow_options_creation() {
     assert_eq!(chained_options.timeout, 30.0);
     assert_eq!(chained_options.process.auto_start, true);
 }
+
+#[test]
+fn test_auto_start_process_alias() {
+    for auto_start in [true, false] {
+        let documented = IFlowOptions::new()
+            .with_auto_start(!auto_start)
+            .with_auto_start_process(auto_start);
+        let alias = IFlowOptions::new()
+            .with_auto_start_process(!auto_start)
+            .with_auto_start(auto_start);
+        assert_eq!(documented.process.auto_start, auto_start);
+        assert_eq!(alias.process.auto_start, auto_start);
+    }
+}
@@ -0,0 +1,60 @@
+//! Tests for limiting the prompt rate
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_rate_limit_spaces_prompts() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client =
+        IFlowClient::new(Some(helpers::websocket_options(server.url()))).with_rate_limit(2.0);
+    client.connect().await.unwrap();
+
+    let start = Instant::now();
+    for _ in 0..5 {
+        client.send_message("Hi", None).await.unwrap();
+        client.collect_response().await.unwrap();
+    }
+
+    assert!(start.elapsed() >= Duration::from_secs_f64(4.0 / 2.0));
+    assert_eq!(server.requests_for("session/prompt").len(), 5);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rate_limit_from_options() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let options = helpers::websocket_options(server.url()).with_rate_limit(10.0);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let start = Instant::now();
+    for _ in 0..3 {
+        client.send_message("Hi", None).await.unwrap();
+        client.collect_response().await.unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_secs(2));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_rate_limit_is_rejected_on_connect() {
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+        let mut client = IFlowClient::new(Some(IFlowOptions::new().with_rate_limit(rate)));
+        let result = client.connect().await;
+        assert!(
+            matches!(result, Err(IFlowError::Config(_))),
+            "rate {} gave {:?}",
+            rate,
+            result
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Tests for observing the raw JSON-RPC messages
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{Direction, RawProtocolObserver, RecordingObserver};
+use serde_json::Value;
+
+#[tokio::test]
+async fn test_records_initialize_exchange() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .observe_raw_protocol(Box::new(RecordingObserver::new()));
+    client.connect().await.unwrap();
+    client.warmup().await.unwrap();
+
+    let log = client.protocol_log();
+    assert!(log.len() >= 4);
+    assert_eq!(log[0].0, Direction::Sent);
+    assert_eq!(log[0].1["method"], "initialize");
+    assert_eq!(log[1].0, Direction::Received);
+    assert_eq!(log[1].1["id"], log[0].1["id"]);
+    assert!(log[1].1["result"]["protocolVersion"].is_number());
+
+    let new_session = log
+        .iter()
+        .position(|(direction, json)| {
+            *direction == Direction::Sent && json["method"] == "session/new"
+        })
+        .unwrap();
+    assert_eq!(log[new_session + 1].0, Direction::Received);
+    assert_eq!(
+        log[new_session + 1].1["result"]["sessionId"],
+        "test-session-1"
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_records_prompt_and_updates() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let observer = RecordingObserver::new();
+    let messages = observer.messages();
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .observe_raw_protocol(Box::new(observer));
+    client.connect().await.unwrap();
+    client.send_message("hi", None).await.unwrap();
+
+    let received: Vec<Value> = messages
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(direction, _)| *direction == Direction::Received)
+        .map(|(_, json)| json.clone())
+        .collect();
+    assert!(
+        received
+            .iter()
+            .any(|json| json["params"]["update"]["content"]["text"] == "Hello")
+    );
+    assert_eq!(client.protocol_log().len(), messages.lock().unwrap().len());
+
+    client.disconnect().await.unwrap();
+}
+
+struct NullObserver;
+
+impl RawProtocolObserver for NullObserver {
+    fn on_send(&self, _json: &Value) {}
+    fn on_receive(&self, _json: &Value) {}
+}
+
+#[tokio::test]
+async fn test_protocol_log_empty_without_recording() {
+    let server = TestIFlowServer::start(vec![]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())))
+        .observe_raw_protocol(Box::new(NullObserver));
+    client.connect().await.unwrap();
+    client.warmup().await.unwrap();
+    assert!(client.protocol_log().is_empty());
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,70 @@
+//! Tests for reconnecting a client
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{ConnectionEvent, IFlowHooks};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_messages_keep_flowing_after_reconnect() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+    let events: Arc<Mutex<Vec<ConnectionEvent>>> = Arc::default();
+    let seen = events.clone();
+    let options = helpers::websocket_options(server.url()).with_hooks(
+        IFlowHooks::new()
+            .on_connection_event(move |event| seen.lock().unwrap().push(event.clone())),
+    );
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    client.send_message("First", None).await.unwrap();
+    let (text, _) = client.collect_response().await.unwrap();
+    assert_eq!(text, "Hello");
+
+    client.reconnect().await.unwrap();
+    assert!(client.snapshot().connected);
+
+    client.send_message("Second", None).await.unwrap();
+    let (text, _) = client.collect_response().await.unwrap();
+    assert_eq!(text, "Hello");
+
+    // The test server does not support session/load, so a new session is created
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![ConnectionEvent::Reconnected {
+            session_id: Some("test-session-1".to_string()),
+            resumed: false,
+        }]
+    );
+    assert_eq!(server.requests_for("initialize").len(), 2);
+    assert_eq!(server.requests_for("session/new").len(), 2);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_resumes_session() {
+    let server = TestIFlowServer::start_with_methods(vec![("session/load", Value::Null)]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("First", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    let session_id = client.session_id().map(str::to_string);
+
+    client.reconnect().await.unwrap();
+
+    assert_eq!(client.session_id().map(str::to_string), session_id);
+    let loads = server.requests_for("session/load");
+    assert_eq!(loads.len(), 1);
+    assert_eq!(loads[0]["params"]["sessionId"], "test-session-1");
+    assert_eq!(server.requests_for("session/new").len(), 1);
+
+    client.send_message("Second", None).await.unwrap();
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts[1]["params"]["sessionId"], "test-session-1");
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,59 @@
+//! Tests for error recovery
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::RecoveryAction;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+
+#[tokio::test]
+async fn test_recover_from_connection_error_reconnects() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("first", None).await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-1"));
+
+    let error = IFlowError::Connection("connection reset".to_string());
+    let action = client.recover_from_error(&error).await.unwrap();
+    assert_eq!(action, RecoveryAction::Reconnected);
+    assert!(client.snapshot().connected);
+    assert!(client.session_id().is_none());
+
+    client.send_message("second", None).await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recover_from_other_errors() {
+    let mut client = IFlowClient::new(None);
+
+    let timeout = IFlowError::Timeout("prompt".to_string());
+    assert_eq!(
+        client.recover_from_error(&timeout).await.unwrap(),
+        RecoveryAction::RetrySend
+    );
+
+    let protocol = IFlowError::Protocol("bad response".to_string());
+    assert_eq!(
+        client.recover_from_error(&protocol).await.unwrap(),
+        RecoveryAction::Abort(protocol.to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_send_message_with_recovery_connects() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+
+    // Not connected yet: the first attempt fails and recovery connects
+    client
+        .send_message_with_recovery("hello", None, 2)
+        .await
+        .unwrap();
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    client.disconnect().await.unwrap();
+    let result = client.send_message_with_recovery("hello", None, 1).await;
+    assert!(matches!(result, Err(IFlowError::NotConnected)));
+}
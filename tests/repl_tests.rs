@@ -0,0 +1,79 @@
+//! Tests for the interactive REPL
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::{IFlowClient, ReplOptions};
+
+async fn run(client: &mut IFlowClient, options: ReplOptions, input: &str) -> String {
+    let mut output = Vec::new();
+    client
+        .run_repl_with_io(options, input.as_bytes(), &mut output)
+        .await
+        .unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[tokio::test]
+async fn test_repl_streams_responses_until_quit() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![SessionScript::new(
+            "hello",
+            vec![
+                agent_chunk("Hi "),
+                tool_call("call_1", "read_file", "completed"),
+                agent_chunk("there"),
+            ],
+        )],
+    )
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    let output = run(
+        &mut client,
+        ReplOptions::default(),
+        "hello\n\n/quit\nnot sent\n",
+    )
+    .await;
+
+    assert_eq!(output, "> Hi there\n> > ");
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_repl_commands_and_tool_calls() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![SessionScript::new(
+            "read",
+            vec![
+                tool_call("call_1", "read_file", "completed"),
+                agent_chunk("Done"),
+            ],
+        )],
+    )
+    .await;
+
+    let options = helpers::websocket_options(server.url()).with_history(true);
+    let mut client = IFlowClient::new(Some(options));
+    let repl_options = ReplOptions::new()
+        .with_prompt_prefix("iflow> ")
+        .with_tool_calls(true);
+    let output = run(
+        &mut client,
+        repl_options,
+        "/help\nread it\n/history\n/stats\n/bogus\n",
+    )
+    .await;
+
+    assert!(output.starts_with("iflow> Commands:\n"));
+    assert!(output.contains("[tool] read_file (completed)\nDone\n"));
+    assert!(output.contains("user: read it\n"));
+    assert!(output.contains("assistant: Done\n"));
+    assert!(output.contains("tool_calls_total: 1"));
+    assert!(output.contains("Unknown command: /bogus (try /help)\n"));
+    assert!(output.ends_with("iflow> "));
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,112 @@
+//! Tests for the preferred response format
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::ResponseFormat;
+use iflow_cli_sdk_rust::{IFlowClient, query_as_json_with_config};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Answer {
+    value: u32,
+}
+
+#[test]
+fn test_response_format_instruction() {
+    assert_eq!(ResponseFormat::default(), ResponseFormat::PlainText);
+    assert_eq!(ResponseFormat::PlainText.instruction(), None);
+    assert_eq!(
+        ResponseFormat::Json { schema_hint: None }.instruction(),
+        Some("Respond with a valid JSON object.".to_string())
+    );
+    assert_eq!(
+        ResponseFormat::Json {
+            schema_hint: Some("{\"type\": \"object\"}".to_string())
+        }
+        .instruction(),
+        Some("Respond with valid JSON matching this schema: {\"type\": \"object\"}".to_string())
+    );
+    assert_eq!(
+        ResponseFormat::Markdown.instruction(),
+        Some("Format your response as Markdown.".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_markdown_instruction_is_sent() {
+    let server = TestIFlowServer::start(vec![agent_chunk("# Title")]).await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_response_format(ResponseFormat::Markdown);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("write a title", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompt = &server.requests_for("session/prompt")[0]["params"]["prompt"];
+    assert_eq!(prompt[0]["text"], "write a title");
+    assert_eq!(prompt[1]["text"], "Format your response as Markdown.");
+}
+
+#[tokio::test]
+async fn test_plain_text_sends_no_instruction() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompt = &server.requests_for("session/prompt")[0]["params"]["prompt"];
+    assert_eq!(prompt.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_query_as_json() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("answer")
+            .reply_chunks(vec!["{\"value\": ", "42}"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let answer: Answer =
+        query_as_json_with_config("answer", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert_eq!(answer, Answer { value: 42 });
+
+    let prompt = &server.requests_for("session/prompt")[0]["params"]["prompt"];
+    assert_eq!(prompt[1]["text"], "Respond with a valid JSON object.");
+}
+
+#[tokio::test]
+async fn test_query_as_json_keeps_schema_hint() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("answer")
+            .reply_chunks(vec!["not json"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_response_format(ResponseFormat::Json {
+            schema_hint: Some("{\"value\": number}".to_string()),
+        });
+    let err = query_as_json_with_config::<Answer>("answer", options)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        iflow_cli_sdk_rust::IFlowError::Deserialization(_)
+    ));
+
+    let prompt = &server.requests_for("session/prompt")[0]["params"]["prompt"];
+    assert_eq!(
+        prompt[1]["text"],
+        "Respond with valid JSON matching this schema: {\"value\": number}"
+    );
+}
@@ -0,0 +1,47 @@
+//! Tests for resuming a session by ID
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, SessionId};
+
+#[tokio::test]
+async fn test_resumed_session_skips_session_new() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hello")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    assert_eq!(client.current_session_id(), None);
+    client.send_message("First", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    let saved = client.current_session_id().unwrap().to_string();
+    client.disconnect().await.unwrap();
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client
+        .resume_session(SessionId(saved.clone().into()))
+        .unwrap();
+    client.send_message("Second", None).await.unwrap();
+    let (text, _) = client.collect_response().await.unwrap();
+    assert_eq!(text, "Hello");
+    assert_eq!(client.current_session_id(), Some(saved.as_str()));
+
+    assert_eq!(server.requests_for("session/new").len(), 1);
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts.len(), 2);
+    assert!(
+        prompts
+            .iter()
+            .all(|prompt| prompt["params"]["sessionId"] == saved.as_str())
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resume_session_requires_connection() {
+    let mut client = IFlowClient::new(None);
+    let result = client.resume_session(SessionId("test-session-1".into()));
+    assert!(matches!(result, Err(IFlowError::NotConnected)));
+}
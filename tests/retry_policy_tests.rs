@@ -0,0 +1,98 @@
+//! Tests for the retry policy of protocol operations
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer};
+use iflow_cli_sdk_rust::types::RetryPolicy;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::time::Duration;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        initial_delay: Duration::from_millis(100),
+        multiplier: 2.0,
+        max_delay: Duration::from_millis(500),
+        jitter: false,
+    }
+}
+
+#[test]
+fn test_delays_grow_exponentially_up_to_the_cap() {
+    let policy = policy();
+    let delays: Vec<_> = (1..=5).map(|attempt| policy.delay(attempt)).collect();
+    assert_eq!(
+        delays,
+        vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(400),
+            Duration::from_millis(500),
+            Duration::from_millis(500),
+        ]
+    );
+    assert_eq!(policy.delay(u32::MAX), Duration::from_millis(500));
+}
+
+#[test]
+fn test_jitter_stays_within_half_and_full_delay() {
+    let policy = RetryPolicy {
+        jitter: true,
+        ..policy()
+    };
+    for _ in 0..100 {
+        let delay = policy.backoff(2);
+        assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn test_retries_stop_at_max_attempts() {
+    let policy = policy();
+    let err = IFlowError::Timeout("slow".to_string());
+    let retries = (1..).take_while(|&attempt| policy.should_retry(&err, attempt));
+    assert_eq!(retries.count(), 4);
+    assert!(!RetryPolicy::none().should_retry(&err, 1));
+}
+
+#[test]
+fn test_only_transient_errors_are_retried() {
+    let policy = policy();
+    assert!(policy.should_retry(&IFlowError::Timeout("slow".to_string()), 1));
+    assert!(policy.should_retry(&IFlowError::Connection("refused".to_string()), 1));
+    assert!(!policy.should_retry(&IFlowError::Authentication("denied".to_string()), 1));
+    assert!(!policy.should_retry(&IFlowError::Protocol("bad response".to_string()), 1));
+}
+
+#[test]
+fn test_options_carry_the_retry_policy() {
+    assert_eq!(IFlowOptions::default().retry_policy, RetryPolicy::default());
+    let options = IFlowOptions::new().with_retry_policy(policy());
+    assert_eq!(options.retry_policy, policy());
+    assert_eq!(IFlowOptions::for_ci_pipeline().retry_policy.max_attempts, 3);
+}
+
+#[tokio::test]
+async fn test_prompt_is_not_resent_after_timeout() {
+    let server =
+        TestIFlowServer::start_scripted(vec![SessionScript::expect_prompt("Hello").reply_never()])
+            .await;
+    let options = helpers::websocket_options(server.url())
+        .with_prompt_timeout(0.2)
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_millis(10),
+            ..policy()
+        });
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    let err = client.send_message("Hello", None).await.unwrap_err();
+    assert!(
+        matches!(err, IFlowError::Timeout(_) | IFlowError::Protocol(_)),
+        "{:?}",
+        err
+    );
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    client.disconnect().await.unwrap();
+}
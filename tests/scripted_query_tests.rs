@@ -0,0 +1,97 @@
+//! End-to-end tests for the query functions against a scripted server
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, tool_call};
+use iflow_cli_sdk_rust::{
+    IFlowError, query_and_parse_json_with_config, query_to_writer_with_config, query_with_config,
+};
+
+#[tokio::test]
+async fn test_query_with_script() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("hello")
+            .reply_chunks(vec!["Hello", " world"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let response = query_with_config("hello", helpers::websocket_options(server.url()))
+        .await
+        .unwrap();
+    assert_eq!(response, "Hello world");
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+}
+
+#[tokio::test]
+async fn test_query_to_writer_with_script() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("count")
+            .reply_update(tool_call("call-1", "counter", "completed"))
+            .reply_chunks(vec!["1", "2", "3"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let mut writer = Vec::new();
+    query_to_writer_with_config(
+        "count",
+        &mut writer,
+        helpers::websocket_options(server.url()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(writer, b"123");
+}
+
+#[tokio::test]
+async fn test_query_and_parse_json_with_script() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("numbers")
+            .reply_chunks(vec!["[1, ", "2, 3]"])
+            .reply_task_finish(),
+    ])
+    .await;
+
+    let numbers: Vec<u32> =
+        query_and_parse_json_with_config("numbers", helpers::websocket_options(server.url()))
+            .await
+            .unwrap();
+    assert_eq!(numbers, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_unexpected_prompt_fails() {
+    let server = TestIFlowServer::start_scripted(vec![
+        SessionScript::expect_prompt("hello").reply_chunks(vec!["Hello"]),
+    ])
+    .await;
+
+    let err = query_with_config("goodbye", helpers::websocket_options(server.url()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Unexpected prompt: goodbye"));
+}
+
+#[tokio::test]
+async fn test_client_with_script() {
+    let (mut client, _server) = helpers::client_with_script(vec![
+        SessionScript::expect_prompt("first").reply_chunks(vec!["one"]),
+        SessionScript::expect_prompt("second")
+            .reply_chunks(vec!["two"])
+            .with_stop_reason("cancelled"),
+    ])
+    .await;
+    client.connect().await.unwrap();
+
+    client.send_message("first", None).await.unwrap();
+    assert_eq!(client.collect_response().await.unwrap().0, "one");
+
+    client.send_message("second", None).await.unwrap();
+    assert!(matches!(
+        client.collect_response().await,
+        Err(IFlowError::Cancelled { .. })
+    ));
+
+    client.disconnect().await.unwrap();
+}
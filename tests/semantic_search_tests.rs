@@ -0,0 +1,97 @@
+//! Tests for IFlowClient::semantic_search
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+
+fn documents() -> Vec<String> {
+    vec![
+        "Rust ownership rules".to_string(),
+        "Borrow checker errors explained".to_string(),
+        "Baking sourdough bread".to_string(),
+    ]
+}
+
+#[tokio::test]
+async fn test_semantic_search_returns_indices() {
+    let (mut client, server) = helpers::client_with_script(vec![
+        SessionScript::expect_prompt("Query: borrowing")
+            .reply_chunks(vec!["{\"indices\": [1, 0]}"]),
+    ])
+    .await;
+    client.connect().await.unwrap();
+
+    let indices = client
+        .semantic_search("borrowing", documents(), 2)
+        .await
+        .unwrap();
+    assert_eq!(indices, vec![1, 0]);
+
+    let prompt = &server.requests_for("session/prompt")[0];
+    let text = prompt["params"]["prompt"][0]["text"].as_str().unwrap();
+    assert!(text.contains("[2] Baking sourdough bread"));
+    assert!(text.contains("2 most relevant"));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_semantic_search_filters_invalid_indices() {
+    let (mut client, _server) = helpers::client_with_script(vec![
+        SessionScript::expect_prompt("Query: bread")
+            .reply_chunks(vec!["{\"indices\": [7, 2, -1, 2, 0, 1]}"]),
+    ])
+    .await;
+    client.connect().await.unwrap();
+
+    let indices = client
+        .semantic_search("bread", documents(), 2)
+        .await
+        .unwrap();
+    assert_eq!(indices, vec![2, 0]);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_semantic_search_rejects_non_json() {
+    let (mut client, _server) = helpers::client_with_script(vec![
+        SessionScript::expect_prompt("Query: rust").reply_chunks(vec!["The first one."]),
+    ])
+    .await;
+    client.connect().await.unwrap();
+
+    let err = client
+        .semantic_search("rust", documents(), 1)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, IFlowError::Deserialization(_)));
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_semantic_search_without_documents() {
+    let server = TestIFlowServer::start_scripted(vec![]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    assert!(
+        client
+            .semantic_search("anything", Vec::new(), 3)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        client
+            .semantic_search("anything", documents(), 0)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(server.requests_for("session/prompt").is_empty());
+
+    client.disconnect().await.unwrap();
+}
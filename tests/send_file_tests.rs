@@ -0,0 +1,74 @@
+//! Tests for IFlowClient::send_file
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::types::FileAccessConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use std::path::{Path, PathBuf};
+
+fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("iflow_{}_{}", uuid::Uuid::new_v4(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+async fn sent_prompt(path: &Path, prompt: Option<&str>) -> String {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_file(path, prompt).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    prompts[0]["params"]["prompt"][0]["text"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_send_text_file_with_prompt() {
+    let path = temp_file("notes.txt", b"fn main() {}");
+    let text = sent_prompt(&path, Some("Review this file")).await;
+
+    assert!(text.starts_with("Review this file"));
+    assert!(text.contains("fn main() {}"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_send_binary_file_is_base64_encoded() {
+    let path = temp_file("image.bin", &[0x00, 0xff, 0x10, 0x80]);
+    let text = sent_prompt(&path, None).await;
+
+    assert!(text.contains("```base64\nAP8QgA==\n```"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_send_file_errors() {
+    let mut client = IFlowClient::new(None);
+    let result = client
+        .send_file(Path::new("/nonexistent/iflow/file.txt"), None)
+        .await;
+    assert!(matches!(result, Err(IFlowError::FileNotFound(_))));
+
+    let path = temp_file("large.txt", &[b'a'; 32]);
+    let options =
+        iflow_cli_sdk_rust::IFlowOptions::new().with_file_access_config(FileAccessConfig {
+            max_size: 16,
+            ..Default::default()
+        });
+    let mut client = IFlowClient::new(Some(options));
+    let result = client.send_file(&path, None).await;
+    assert!(matches!(
+        result,
+        Err(IFlowError::FileTooLarge {
+            size: 32,
+            limit: 16,
+            ..
+        })
+    ));
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,129 @@
+//! Tests for streaming the response to a message
+
+mod helpers;
+
+use futures::StreamExt;
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::client::MessageStream;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions, Message};
+
+/// Read a stream up to and including the message ending the response
+async fn read_response(stream: MessageStream) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut stream = Box::pin(stream);
+    while let Some(msg) = stream.next().await {
+        let done = matches!(msg, Message::TaskFinish { .. } | Message::Error { .. });
+        messages.push(msg);
+        if done {
+            break;
+        }
+    }
+    messages
+}
+
+#[tokio::test]
+async fn test_streaming_yields_response_until_task_finish() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("Hello"),
+        tool_call("call_1", "bash", "pending"),
+        agent_chunk(" world"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    let stream = client.send_message_streaming("Hi", None).await.unwrap();
+
+    let messages = read_response(stream).await;
+    assert_eq!(messages.len(), 4);
+    assert!(matches!(&messages[0], Message::Assistant { content } if content == "Hello"));
+    assert!(matches!(messages[1], Message::ToolCall { .. }));
+    assert!(messages[3].is_task_finish());
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_streaming_embeds_files() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Read it")]).await;
+    let path = std::env::temp_dir().join(format!("iflow_stream_{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "file contents").unwrap();
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    let stream = client
+        .send_message_streaming("Summarize", Some(vec![path.as_path()]))
+        .await
+        .unwrap();
+    read_response(stream).await;
+
+    let prompt = &server.requests_for("session/prompt")[0]["params"]["prompt"];
+    assert_eq!(prompt[0]["text"], "Summarize");
+    assert!(prompt.to_string().contains("file contents"));
+
+    client.disconnect().await.unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_streaming_failed_prompt_returns_error() {
+    let (mut client, _server) = helpers::client_with_script(vec![
+        SessionScript::expect_prompt("expected").reply_task_finish(),
+    ])
+    .await;
+    client.connect().await.unwrap();
+
+    let result = client.send_message_streaming("Something else", None).await;
+    assert!(
+        matches!(&result, Err(error) if error.to_string().contains("Unexpected prompt")),
+        "{:?}",
+        result.err()
+    );
+
+    client.disconnect().await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_streaming_over_stdio_ends_with_task_finish_or_error() {
+    use helpers::stdio_agent;
+
+    stdio_agent::install();
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let mut client = IFlowClient::new(Some(IFlowOptions::new().with_timeout(10.0)));
+            client.connect().await.unwrap();
+
+            let stream = client.send_message_streaming("Hi", None).await.unwrap();
+            let messages = read_response(stream).await;
+            assert!(
+                matches!(&messages[..], [Message::Assistant { content }, Message::TaskFinish { .. }] if content == stdio_agent::REPLY),
+                "{:?}",
+                messages
+            );
+
+            // The prompt runs on a local task, so its failure ends the stream
+            let stream = client
+                .send_message_streaming(stdio_agent::FAILING_PROMPT, None)
+                .await
+                .unwrap();
+            let messages = read_response(stream).await;
+            assert!(
+                matches!(messages.last(), Some(Message::Error { message, .. }) if message.contains("Prompt failed")),
+                "{:?}",
+                messages
+            );
+
+            client.disconnect().await.unwrap();
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn test_streaming_requires_connection() {
+    let mut client = IFlowClient::new(None);
+    let result = client.send_message_streaming("Hi", None).await;
+    assert!(matches!(result, Err(IFlowError::NotConnected)));
+}
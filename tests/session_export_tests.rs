@@ -0,0 +1,90 @@
+//! Tests for exporting and importing sessions
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::types::{OptionsSnapshot, SESSION_FILE_VERSION, SessionFile};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions, Message};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("iflow_{}_{}", uuid::Uuid::new_v4(), name))
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let options = helpers::websocket_options(server.url()).with_history(true);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("hello", None).await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-1"));
+
+    let path = temp_path("session.json");
+    client.export_session(&path).unwrap();
+    client.disconnect().await.unwrap();
+
+    let exported: SessionFile =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(exported.version, SESSION_FILE_VERSION);
+    assert_eq!(exported.session_id.as_deref(), Some("test-session-1"));
+    assert_eq!(exported.tool_call_log.len(), 1);
+    assert!(matches!(&exported.history[0], Message::User { content } if content == "hello"));
+    assert_eq!(exported.options_snapshot.websocket_url, Some(server.url()));
+
+    let mut imported = IFlowClient::import_session(&path).await.unwrap();
+    assert_eq!(imported.session_id(), Some("test-session-1"));
+    assert_eq!(imported.history().len(), exported.history.len());
+
+    // The attached session is reused instead of creating a new one
+    imported.send_message("again", None).await.unwrap();
+    assert_eq!(server.requests_for("session/new").len(), 1);
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompts[1]["params"]["sessionId"], "test-session-1");
+
+    imported.disconnect().await.unwrap();
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_options_snapshot_omits_sensitive_fields() {
+    let options = IFlowOptions::new()
+        .with_auth_method_id("secret-method".to_string())
+        .with_timeout(42.0);
+    let snapshot = OptionsSnapshot::from_options(&options);
+    let json = serde_json::to_string(&snapshot).unwrap();
+
+    assert!(!json.contains("secret-method"));
+    assert_eq!(snapshot.to_options().timeout, 42.0);
+}
+
+#[tokio::test]
+async fn test_import_rejects_unknown_version() {
+    let path = temp_path("future.json");
+    let session = SessionFile {
+        version: SESSION_FILE_VERSION + 1,
+        session_id: None,
+        history: Vec::new(),
+        options_snapshot: OptionsSnapshot::from_options(&IFlowOptions::new()),
+        tool_call_log: Vec::new(),
+    };
+    std::fs::write(&path, serde_json::to_string(&session).unwrap()).unwrap();
+
+    let result = IFlowClient::import_session(&path).await;
+    assert!(matches!(result, Err(IFlowError::InvalidMessage(_))));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_attach_requires_connection() {
+    let mut client = IFlowClient::new(None);
+    let result = client.attach("session".to_string());
+    assert!(matches!(result, Err(IFlowError::NotConnected)));
+    assert!(client.session_id().is_none());
+}
@@ -0,0 +1,40 @@
+//! Tests for summarizing a turn
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, plan, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_drain_and_summarize() {
+    let server = TestIFlowServer::start_with_stop_reasons(
+        vec![
+            plan(json!([{ "content": "Read the file", "status": "in_progress" }])),
+            tool_call("call_1", "read_file", "in_progress"),
+            agent_chunk("The file"),
+            tool_call("call_1", "read_file", "completed"),
+            plan(json!([{ "content": "Read the file", "status": "completed" }])),
+            agent_chunk(" is empty"),
+        ],
+        vec!["max_tokens"],
+    )
+    .await;
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("Read it", None).await.unwrap();
+
+    let summary = client.drain_and_summarize().await.unwrap();
+
+    assert_eq!(summary.full_response, "The file is empty");
+    assert_eq!(summary.total_chunks, 2);
+    assert_eq!(summary.tool_calls.len(), 2);
+    assert_eq!(summary.tool_calls[1].status, "completed");
+    let final_plan = summary.final_plan.unwrap();
+    assert_eq!(final_plan.len(), 1);
+    assert_eq!(final_plan[0].content, "Read the file");
+    assert_eq!(summary.stop_reason.as_deref(), Some("max_tokens"));
+    assert!(summary.total_duration < std::time::Duration::from_secs(5));
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,83 @@
+//! Tests for the raw session update hook
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_session_update_hook_receives_all_updates() {
+    let server = TestIFlowServer::start(vec![
+        json!({
+            "sessionUpdate": "agent_thought_chunk",
+            "content": { "type": "text", "text": "Thinking" },
+        }),
+        tool_call("call_1", "bash", "in_progress"),
+        json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_1",
+            "status": "completed",
+        }),
+        json!({ "sessionUpdate": "available_commands_update", "availableCommands": [] }),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let updates: Arc<Mutex<Vec<(String, Value)>>> = Arc::default();
+    let seen = updates.clone();
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.on_session_update(Arc::new(move |update_type, update| {
+        seen.lock()
+            .unwrap()
+            .push((update_type.to_string(), Value::Object(update.clone())));
+    }));
+    client.connect().await.unwrap();
+    client.send_message("Run it", None).await.unwrap();
+    let (text, tool_calls) = client.collect_response().await.unwrap();
+
+    // Normal message dispatch still happens
+    assert_eq!(text, "Done");
+    assert_eq!(tool_calls.len(), 1);
+
+    let updates = updates.lock().unwrap().clone();
+    let types: Vec<&str> = updates.iter().map(|(t, _)| t.as_str()).collect();
+    assert_eq!(
+        types,
+        vec![
+            "agent_thought_chunk",
+            "tool_call",
+            "tool_call_update",
+            "available_commands_update",
+            "agent_message_chunk",
+        ]
+    );
+    assert_eq!(updates[1].1["toolCall"]["title"], "bash");
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_clear_session_update_hook() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+
+    let count = Arc::new(Mutex::new(0));
+    let counter = count.clone();
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    // Set while connected
+    client.on_session_update(Arc::new(move |_, _| *counter.lock().unwrap() += 1));
+    client.send_message("hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    assert_eq!(*count.lock().unwrap(), 1);
+
+    client.clear_session_update_hook();
+    client.send_message("hello again", None).await.unwrap();
+    let (text, _) = client.collect_response().await.unwrap();
+    assert_eq!(text, "Hi");
+    assert_eq!(*count.lock().unwrap(), 1);
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,43 @@
+//! Tests for IFlowClient::set_cwd
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError};
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn test_set_cwd_starts_new_session() {
+    let server = TestIFlowServer::start(vec![agent_chunk("ok")]).await;
+    let dir = std::env::temp_dir().join(format!("iflow_cwd_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("first", None).await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-1"));
+
+    client.set_cwd(dir.clone()).unwrap();
+    assert_eq!(client.options().cwd, dir);
+    assert!(client.session_id().is_none());
+
+    client.send_message("second", None).await.unwrap();
+    assert_eq!(client.session_id(), Some("test-session-2"));
+
+    let sessions = server.requests_for("session/new");
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[1]["params"]["cwd"], dir.to_string_lossy().as_ref());
+
+    client.disconnect().await.unwrap();
+    let _ = std::fs::remove_dir(dir);
+}
+
+#[test]
+fn test_set_cwd_rejects_missing_directory() {
+    let mut client = IFlowClient::new(None);
+    let original = client.options().cwd.clone();
+
+    let result = client.set_cwd(PathBuf::from("/nonexistent/iflow/dir"));
+    assert!(matches!(result, Err(IFlowError::FileNotFound(_))));
+    assert_eq!(client.options().cwd, original);
+}
@@ -0,0 +1,68 @@
+//! Tests for running shell commands through the client
+
+use iflow_cli_sdk_rust::types::FileAccessConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::time::Duration;
+
+fn client(file_access: FileAccessConfig) -> IFlowClient {
+    let options = IFlowOptions::new()
+        .with_cwd(std::env::temp_dir())
+        .with_file_access_config(file_access);
+    IFlowClient::new(Some(options))
+}
+
+fn enabled() -> FileAccessConfig {
+    FileAccessConfig {
+        enabled: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_execute_shell_echo() {
+    let client = client(enabled());
+
+    let result = client
+        .execute_shell("echo", &["hello"], Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(result.stdout, "hello\n");
+    assert_eq!(result.exit_code, 0);
+
+    let result = client
+        .execute_shell("sh", &["-c", "exit 3"], Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(result.exit_code, 3);
+
+    assert_eq!(client.run_shell("echo hello").await.unwrap(), "hello\n");
+}
+
+#[tokio::test]
+async fn test_execute_shell_respects_file_access() {
+    let result = client(FileAccessConfig::default())
+        .run_shell("echo hello")
+        .await;
+    assert!(matches!(result, Err(IFlowError::Validation(_))));
+
+    let outside = client(FileAccessConfig {
+        allowed_dirs: Some(vec![std::env::current_dir().unwrap()]),
+        ..enabled()
+    });
+    let result = outside.run_shell("echo hello").await;
+    assert!(matches!(result, Err(IFlowError::FilePermissionDenied(_))));
+
+    let inside = client(FileAccessConfig {
+        allowed_dirs: Some(vec![std::env::temp_dir()]),
+        ..enabled()
+    });
+    assert_eq!(inside.run_shell("echo hello").await.unwrap(), "hello\n");
+}
+
+#[tokio::test]
+async fn test_execute_shell_timeout() {
+    let result = client(enabled())
+        .execute_shell("sleep", &["5"], Duration::from_millis(100))
+        .await;
+    assert!(matches!(result, Err(IFlowError::Timeout(_))));
+}
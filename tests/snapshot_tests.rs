@@ -0,0 +1,61 @@
+//! Tests for capturing client snapshots
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::types::ConnectionType;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions};
+
+#[test]
+fn test_snapshot_of_fresh_client() {
+    let client = IFlowClient::new(None);
+    let snapshot = client.snapshot();
+
+    assert!(snapshot.session_id.is_none());
+    assert!(snapshot.connection_type.is_none());
+    assert!(snapshot.last_message_at.is_none());
+    assert!(snapshot.iflow_version.is_none());
+    assert!(!snapshot.connected);
+    assert_eq!(snapshot.message_count, 0);
+    assert_eq!(snapshot.tool_calls_completed, 0);
+    assert!(snapshot.to_string().contains("not connected"));
+}
+
+#[test]
+fn test_snapshot_reports_auth_by_presence_only() {
+    let options = IFlowOptions::new().with_auth_method_id("secret-method".to_string());
+    let snapshot = IFlowClient::new(Some(options)).snapshot();
+
+    assert!(snapshot.options_summary.has_auth_method_id);
+    assert!(!snapshot.to_string().contains("secret-method"));
+    assert!(
+        !serde_json::to_string(&snapshot)
+            .unwrap()
+            .contains("secret-method")
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_of_connected_client() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("hello", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let snapshot = client.snapshot();
+    assert!(snapshot.connected);
+    assert_eq!(snapshot.connection_type, Some(ConnectionType::WebSocket));
+    assert_eq!(snapshot.session_id.as_deref(), Some("test-session-1"));
+    assert_eq!(snapshot.tool_calls_completed, 1);
+    assert!(snapshot.message_count >= 3);
+    assert!(snapshot.last_message_at.is_some());
+    assert!(snapshot.to_string().contains("test-session-1"));
+
+    client.disconnect().await.unwrap();
+}
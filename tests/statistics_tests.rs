@@ -0,0 +1,53 @@
+//! Tests for IFlowClient::statistics
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, permission_request, tool_call};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::PermissionMode;
+
+#[test]
+fn test_statistics_of_fresh_client() {
+    let stats = IFlowClient::new(None).statistics();
+
+    assert_eq!(stats.total_tokens_estimated, 0);
+    assert_eq!(stats.tool_calls_total, 0);
+    assert!(stats.session_duration.is_none());
+    assert!(stats.average_response_time.is_none());
+}
+
+#[tokio::test]
+async fn test_statistics_count_messages() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "completed"),
+        tool_call("call_2", "write_file", "failed"),
+        tool_call("call_3", "list_files", "pending"),
+        permission_request(100, "read_file", "read"),
+        permission_request(101, "write_file", "edit"),
+        agent_chunk("12345678"),
+        agent_chunk("1234"),
+    ])
+    .await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_permission_mode(PermissionMode::Selective);
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("first", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.send_message("second", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let stats = client.statistics();
+    assert_eq!(stats.total_tokens_estimated, 6);
+    assert_eq!(stats.tool_calls_total, 6);
+    assert_eq!(stats.tool_calls_successful, 2);
+    assert_eq!(stats.tool_calls_failed, 2);
+    assert_eq!(stats.permission_requests_auto_approved, 2);
+    assert_eq!(stats.permission_requests_cancelled, 2);
+    assert!(stats.average_response_time.is_some());
+
+    client.disconnect().await.unwrap();
+    let duration = client.statistics().session_duration.unwrap();
+    assert_eq!(client.statistics().session_duration, Some(duration));
+}
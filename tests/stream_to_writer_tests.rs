@@ -0,0 +1,47 @@
+//! Tests for streaming responses to a writer
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::{IFlowClient, query_to_writer_with_config};
+use tokio::io::BufWriter;
+
+#[tokio::test]
+async fn test_stream_to_writer() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("Once "),
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("upon "),
+        agent_chunk("a time"),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+
+    let mut writer = BufWriter::new(Vec::new());
+    client
+        .stream_to_writer("Tell me a story", &mut writer)
+        .await
+        .unwrap();
+    client.disconnect().await.unwrap();
+
+    assert_eq!(writer.into_inner(), b"Once upon a time");
+}
+
+#[tokio::test]
+async fn test_query_to_writer() {
+    let server = TestIFlowServer::start(vec![agent_chunk("4")]).await;
+
+    let mut writer = BufWriter::new(Vec::new());
+    query_to_writer_with_config(
+        "What is 2 + 2?",
+        &mut writer,
+        helpers::websocket_options(server.url()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(writer.into_inner(), b"4");
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+}
@@ -0,0 +1,68 @@
+//! Tests for structured prompts
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{StructuredPrompt, UserMessageChunk};
+
+#[test]
+fn test_structured_prompt_builder() {
+    let prompt = StructuredPrompt::builder()
+        .system("Answer briefly")
+        .example("2 + 2?", "4")
+        .context_file("notes.txt")
+        .user("3 + 3?")
+        .build();
+
+    assert_eq!(prompt.system.as_deref(), Some("Answer briefly"));
+    assert_eq!(
+        prompt.examples,
+        vec![("2 + 2?".to_string(), "4".to_string())]
+    );
+    assert_eq!(prompt.user, "3 + 3?");
+
+    let chunks = prompt.to_user_message().chunks;
+    assert_eq!(chunks.len(), 4);
+    assert!(
+        matches!(&chunks[0], UserMessageChunk::Text { content } if content == "Instructions:\nAnswer briefly")
+    );
+    assert!(
+        matches!(&chunks[1], UserMessageChunk::Text { content } if content == "Example 1:\nUser: 2 + 2?\nAssistant: 4")
+    );
+    assert!(matches!(&chunks[2], UserMessageChunk::Path { path } if path.ends_with("notes.txt")));
+    assert!(matches!(&chunks[3], UserMessageChunk::Text { content } if content == "3 + 3?"));
+}
+
+#[tokio::test]
+async fn test_send_structured_prompt_content_blocks() {
+    let server = TestIFlowServer::start(vec![agent_chunk("6")]).await;
+    let path = std::env::temp_dir().join(format!("iflow_{}_context.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "x = 3").unwrap();
+
+    let prompt = StructuredPrompt::builder()
+        .system("Answer briefly")
+        .example("2 + 2?", "4")
+        .context_file(&path)
+        .user("x + x?")
+        .build();
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_structured_prompt(prompt).await.unwrap();
+    client.disconnect().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    let blocks = prompts[0]["params"]["prompt"].as_array().unwrap();
+    assert_eq!(blocks.len(), 4);
+    assert_eq!(blocks[0]["text"], "Instructions:\nAnswer briefly");
+    assert_eq!(blocks[1]["text"], "Example 1:\nUser: 2 + 2?\nAssistant: 4");
+    assert_eq!(blocks[2]["type"], "resource");
+    assert_eq!(blocks[2]["resource"]["text"], "x = 3");
+    assert_eq!(
+        blocks[3],
+        serde_json::json!({ "type": "text", "text": "x + x?" })
+    );
+
+    let _ = std::fs::remove_file(path);
+}
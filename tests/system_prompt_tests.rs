@@ -0,0 +1,72 @@
+//! Tests for prepending a system prompt to new sessions
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowOptions, SessionId};
+use serde_json::Value;
+
+fn prompt_texts(request: &Value) -> Vec<&str> {
+    request["params"]["prompt"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|block| block["text"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_system_prompt_starts_first_prompt_of_session() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Bonjour")]).await;
+    let options = helpers::websocket_options(server.url()).with_system_prompt("Answer in French");
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+
+    client.send_message("Hi", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.send_message("How are you?", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompt_texts(&prompts[0]), vec!["Answer in French", "Hi"]);
+    assert_eq!(prompt_texts(&prompts[1]), vec!["How are you?"]);
+
+    // A second session created by the client receives it too
+    let session = client
+        .new_session_in_dir(&std::env::temp_dir())
+        .await
+        .unwrap();
+    client.use_session(&session).unwrap();
+    client.send_message("Again", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompt_texts(&prompts[2]), vec!["Answer in French", "Again"]);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resumed_session_does_not_receive_system_prompt() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Bonjour")]).await;
+    let options = helpers::websocket_options(server.url()).with_system_prompt("Answer in French");
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client
+        .resume_session(SessionId("test-session-1".into()))
+        .unwrap();
+
+    client.send_message("Hi", None).await.unwrap();
+    client.collect_response().await.unwrap();
+
+    let prompts = server.requests_for("session/prompt");
+    assert_eq!(prompt_texts(&prompts[0]), vec!["Hi"]);
+
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_with_system_prompt() {
+    assert_eq!(IFlowOptions::new().system_prompt, None);
+    let options = IFlowOptions::new().with_system_prompt("Be brief");
+    assert_eq!(options.system_prompt.as_deref(), Some("Be brief"));
+}
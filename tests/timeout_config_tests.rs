@@ -0,0 +1,61 @@
+//! Tests for the connect, operation and prompt timeouts
+
+use iflow_cli_sdk_rust::types::WebSocketConfig;
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, IFlowOptions};
+use std::time::{Duration, Instant};
+
+#[test]
+#[allow(deprecated)]
+fn test_timeout_defaults_and_builders() {
+    let options = IFlowOptions::new();
+    assert_eq!(options.connect_timeout, 10.0);
+    assert_eq!(options.operation_timeout, 30.0);
+    assert_eq!(options.prompt_timeout, 120.0);
+
+    let options = IFlowOptions::new().with_timeout(45.0);
+    assert_eq!(options.timeout, 45.0);
+    assert_eq!(options.connect_timeout, 45.0);
+    assert_eq!(options.operation_timeout, 45.0);
+    assert_eq!(options.prompt_timeout, 45.0);
+
+    let options = IFlowOptions::new()
+        .with_timeout(45.0)
+        .with_connect_timeout(1.0)
+        .with_operation_timeout(5.0)
+        .with_prompt_timeout(600.0);
+    assert_eq!(options.connect_timeout, 1.0);
+    assert_eq!(options.operation_timeout, 5.0);
+    assert_eq!(options.prompt_timeout, 600.0);
+}
+
+#[tokio::test]
+async fn test_short_connect_timeout_fails_fast() {
+    // Accepts TCP connections but never answers the WebSocket handshake
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let options = IFlowOptions::new()
+        .with_auto_start(false)
+        .with_websocket_config(WebSocketConfig::with_reconnect_settings(
+            format!("ws://127.0.0.1:{}/acp?peer=iflow", port),
+            1,
+            Duration::from_millis(100),
+        ))
+        .with_connect_timeout(0.2)
+        .with_prompt_timeout(600.0);
+    let mut client = IFlowClient::new(Some(options));
+
+    let started = Instant::now();
+    let result = client.connect().await;
+    server.abort();
+
+    assert!(matches!(result, Err(IFlowError::Connection(_))));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(client.options().prompt_timeout, 600.0);
+}
@@ -0,0 +1,67 @@
+//! Tests for counting tokens and the remaining context window
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, no_reply};
+use iflow_cli_sdk_rust::IFlowClient;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_count_tokens_from_server() {
+    let server = TestIFlowServer::start_with_methods(vec![
+        ("session/count_tokens", json!({ "tokens": 42 })),
+        ("session/context_window", json!({ "remainingTokens": 1000 })),
+    ])
+    .await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.warmup().await.unwrap();
+
+    assert_eq!(client.count_tokens("Hello, iFlow!").await.unwrap(), 42);
+    assert_eq!(client.remaining_context_tokens().await.unwrap(), Some(1000));
+
+    let requests = server.requests_for("session/count_tokens");
+    assert_eq!(requests[0]["params"]["text"], "Hello, iFlow!");
+    assert_eq!(requests[0]["params"]["sessionId"], "test-session-1");
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_tokens_falls_back_to_estimate() {
+    let server = TestIFlowServer::start(Vec::new()).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+
+    // Without a connection the count is estimated
+    assert_eq!(client.count_tokens("").await.unwrap(), 1);
+    assert_eq!(client.remaining_context_tokens().await.unwrap(), None);
+
+    // The server does not know the methods
+    client.connect().await.unwrap();
+    client.warmup().await.unwrap();
+    assert_eq!(client.count_tokens(&"a".repeat(400)).await.unwrap(), 100);
+    assert_eq!(client.remaining_context_tokens().await.unwrap(), None);
+    assert_eq!(server.requests_for("session/count_tokens").len(), 1);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_tokens_estimates_when_server_does_not_answer() {
+    let server =
+        TestIFlowServer::start_with_methods(vec![("session/count_tokens", no_reply())]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.warmup().await.unwrap();
+
+    // The options allow 10 seconds, but counting gives up much sooner
+    let started = std::time::Instant::now();
+    assert_eq!(client.count_tokens(&"a".repeat(40)).await.unwrap(), 10);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(server.requests_for("session/count_tokens").len(), 1);
+
+    client.disconnect().await.unwrap();
+}
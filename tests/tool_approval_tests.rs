@@ -0,0 +1,86 @@
+//! Tests for pre-configured tool approvals
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, permission_request};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{PermissionDecision, PermissionMode};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn test_pre_approved_tool_bypasses_manual_mode() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "bash", "execute"),
+        permission_request(101, "write_file", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let options =
+        helpers::websocket_options(server.url()).with_permission_mode(PermissionMode::Manual);
+    let mut client = IFlowClient::new(Some(options));
+    client.set_tool_approvals(HashMap::from([(
+        "bash".to_string(),
+        PermissionDecision::Approve("proceed_once".to_string()),
+    )]));
+    client.connect().await.unwrap();
+    client.send_message("Run it", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+    // Let the server record the permission responses
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(
+        server.permission_outcome(100),
+        json!({ "outcome": "selected", "optionId": "proceed_once" })
+    );
+    assert_eq!(
+        server.permission_outcome(101),
+        json!({ "outcome": "cancelled" })
+    );
+}
+
+#[tokio::test]
+async fn test_pre_denied_tool_overrides_auto_mode() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "rm", "execute"),
+        permission_request(101, "bash", "execute"),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let options = helpers::websocket_options(server.url())
+        .with_permission_mode(PermissionMode::Auto)
+        .with_tool_approvals(HashMap::from([(
+            "rm".to_string(),
+            PermissionDecision::Deny,
+        )]));
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("Clean up", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    // Let the server record the permission responses
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(
+        server.permission_outcome(100),
+        json!({ "outcome": "cancelled" })
+    );
+    assert_eq!(server.permission_outcome(101)["outcome"], "selected");
+
+    // Approvals changed while connected apply to the next permission request
+    client.set_tool_approvals(HashMap::new());
+    client.send_message("Clean up again", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let outcomes: Vec<Value> = server
+        .requests()
+        .into_iter()
+        .filter(|message| message["id"] == 100 && message.get("method").is_none())
+        .map(|message| message["result"]["outcome"]["outcome"].clone())
+        .collect();
+    assert_eq!(outcomes, vec![json!("cancelled"), json!("selected")]);
+}
@@ -0,0 +1,79 @@
+//! Tests for tool call hooks
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, permission_request};
+use iflow_cli_sdk_rust::IFlowClient;
+use iflow_cli_sdk_rust::types::{
+    PermissionMode, ToolCallDecision, ToolCallEvent, ToolCallHook, ToolCallResult,
+};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+/// Denies `write_file` and records finished tool calls
+#[derive(Default)]
+struct DenyWrites {
+    finished: Mutex<Vec<(ToolCallEvent, ToolCallResult)>>,
+}
+
+impl ToolCallHook for DenyWrites {
+    fn before_tool_call(&self, call: &ToolCallEvent) -> ToolCallDecision {
+        if call.name == "write_file" {
+            ToolCallDecision::Deny("writes are not allowed".to_string())
+        } else {
+            ToolCallDecision::Allow
+        }
+    }
+
+    fn after_tool_call(&self, call: &ToolCallEvent, result: &ToolCallResult) {
+        self.finished
+            .lock()
+            .unwrap()
+            .push((call.clone(), result.clone()));
+    }
+}
+
+#[tokio::test]
+async fn test_tool_call_hook_denies_tool() {
+    let server = TestIFlowServer::start(vec![
+        permission_request(100, "write_file", "edit"),
+        permission_request(101, "read_file", "edit"),
+        json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call_101",
+            "title": "read_file",
+            "status": "completed",
+            "rawOutput": { "content": "hello" },
+        }),
+        agent_chunk("Done"),
+    ])
+    .await;
+
+    let hook = Arc::new(DenyWrites::default());
+    let options = helpers::websocket_options(server.url())
+        .with_permission_mode(PermissionMode::Auto)
+        .with_tool_call_hook(hook.clone());
+    let mut client = IFlowClient::new(Some(options));
+    client.connect().await.unwrap();
+    client.send_message("Edit the file", None).await.unwrap();
+    client.collect_response().await.unwrap();
+    client.disconnect().await.unwrap();
+    // Let the server record the permission responses
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(server.permission_outcome(100)["outcome"], "cancelled");
+    assert_eq!(server.permission_outcome(101)["outcome"], "selected");
+    assert_eq!(client.statistics().permission_requests_cancelled, 1);
+
+    let finished = hook.finished.lock().unwrap();
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].0.id, "call_101");
+    assert_eq!(finished[0].0.name, "read_file");
+    assert_eq!(
+        finished[0].1,
+        ToolCallResult {
+            status: "completed".to_string(),
+            output: Some(json!({ "content": "hello" })),
+        }
+    );
+}
@@ -0,0 +1,88 @@
+//! Tests for ToolCallMessage builders and validation
+
+use iflow_cli_sdk_rust::IFlowError;
+use iflow_cli_sdk_rust::types::{
+    Icon, ToolCallConfirmation, ToolCallContent, ToolCallLocation, ToolCallMessage, ToolCallStatus,
+};
+
+fn message(id: &str, label: &str, icon_value: &str) -> ToolCallMessage {
+    ToolCallMessage::new(
+        id.to_string(),
+        label.to_string(),
+        Icon {
+            icon_type: "emoji".to_string(),
+            value: icon_value.to_string(),
+        },
+        ToolCallStatus::Pending,
+    )
+}
+
+fn confirmation(confirmation_type: &str, command: Option<&str>) -> ToolCallConfirmation {
+    ToolCallConfirmation {
+        confirmation_type: confirmation_type.to_string(),
+        description: None,
+        command: command.map(|c| c.to_string()),
+        root_command: None,
+        server_name: None,
+        tool_name: None,
+        tool_display_name: None,
+        urls: None,
+    }
+}
+
+fn location(path: &str) -> ToolCallLocation {
+    ToolCallLocation {
+        path: path.to_string(),
+        line_start: Some(1),
+        line_end: None,
+    }
+}
+
+#[test]
+fn test_builders_populate_fields() {
+    let msg = message("call_1", "Edit file", "✏️")
+        .with_content(ToolCallContent {
+            content_type: "diff".to_string(),
+            markdown: None,
+            path: Some("src/main.rs".to_string()),
+            old_text: Some("a".to_string()),
+            new_text: Some("b".to_string()),
+        })
+        .with_location(location("src/main.rs"))
+        .with_location(location("src/lib.rs"))
+        .with_confirmation(confirmation("edit", None))
+        .with_agent_id("agent_1".to_string())
+        .with_tool_name("edit_file".to_string());
+
+    assert_eq!(msg.content.as_ref().unwrap().content_type, "diff");
+    assert_eq!(msg.locations.as_ref().unwrap().len(), 2);
+    assert_eq!(msg.confirmation.as_ref().unwrap().confirmation_type, "edit");
+    assert_eq!(msg.agent_id.as_deref(), Some("agent_1"));
+    assert_eq!(msg.tool_name.as_deref(), Some("edit_file"));
+    assert!(msg.validate().is_ok());
+}
+
+#[test]
+fn test_validate_accepts_execute_with_command() {
+    let msg = message("call_1", "Run tests", "🔧")
+        .with_confirmation(confirmation("execute", Some("cargo test")));
+    assert!(msg.validate().is_ok());
+}
+
+#[test]
+fn test_validate_rejects_malformed_messages() {
+    let invalid = [
+        message("", "Label", "🔧"),
+        message("call_1", "", "🔧"),
+        message("call_1", "Label", ""),
+        message("call_1", "Label", "🔧").with_confirmation(confirmation("execute", None)),
+    ];
+
+    for msg in invalid {
+        assert!(
+            matches!(msg.validate(), Err(IFlowError::Validation(_))),
+            "expected validation error for {:?}",
+            msg
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Tests for converting UserMessage to ACP content blocks
+
+use iflow_cli_sdk_rust::types::{ContentBlock, FileAccessConfig, UserMessage, UserMessageChunk};
+use iflow_cli_sdk_rust::{IFlowError, IFlowOptions};
+use std::path::PathBuf;
+
+fn temp_file(content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("iflow_{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn mixed_message(path: PathBuf) -> UserMessage {
+    UserMessage::new(vec![
+        UserMessageChunk::Text {
+            content: "Review this file".to_string(),
+        },
+        UserMessageChunk::Path { path },
+    ])
+}
+
+fn assert_mixed_blocks(blocks: &[ContentBlock]) {
+    assert_eq!(blocks.len(), 2);
+    assert!(matches!(&blocks[0], ContentBlock::Text(text) if text.text == "Review this file"));
+    match &blocks[1] {
+        ContentBlock::Resource(resource) => {
+            let json = serde_json::to_value(&resource.resource).unwrap();
+            assert_eq!(json["text"], "fn main() {}");
+            assert!(json["uri"].as_str().unwrap().starts_with("file://"));
+        }
+        other => panic!("Expected resource block, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_try_from_mixed_message() {
+    let path = temp_file("fn main() {}");
+    let blocks = Vec::<ContentBlock>::try_from(mixed_message(path.clone())).unwrap();
+    assert_mixed_blocks(&blocks);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_to_content_blocks_mixed_message() {
+    let path = temp_file("fn main() {}");
+    let blocks = mixed_message(path.clone())
+        .to_content_blocks(&IFlowOptions::new())
+        .await
+        .unwrap();
+    assert_mixed_blocks(&blocks);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_to_content_blocks_enforces_size_limit() {
+    let path = temp_file("fn main() {}");
+    let options = IFlowOptions::new().with_file_access_config(FileAccessConfig {
+        max_size: 4,
+        ..Default::default()
+    });
+    let result = mixed_message(path.clone())
+        .to_content_blocks(&options)
+        .await;
+    assert!(matches!(
+        result,
+        Err(IFlowError::FileTooLarge { limit: 4, .. })
+    ));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_try_from_missing_file() {
+    let message = UserMessage::new_path(PathBuf::from("/nonexistent/iflow/file.txt"));
+    assert!(matches!(
+        Vec::<ContentBlock>::try_from(message),
+        Err(IFlowError::FileNotFound(_))
+    ));
+}
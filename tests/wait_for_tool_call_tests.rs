@@ -0,0 +1,77 @@
+//! Tests for IFlowClient::wait_for_tool_call
+
+mod helpers;
+
+use helpers::server::{TestIFlowServer, agent_chunk, tool_call};
+use iflow_cli_sdk_rust::{IFlowClient, IFlowError, Message};
+use std::time::Duration;
+
+async fn connected_client(server: &TestIFlowServer) -> IFlowClient {
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.connect().await.unwrap();
+    client.send_message("go", None).await.unwrap();
+    client
+}
+
+async fn drain(client: &IFlowClient) -> Vec<Message> {
+    let mut messages = Vec::new();
+    while let Ok(Ok(Some(msg))) =
+        tokio::time::timeout(Duration::from_millis(100), client.receive_message()).await
+    {
+        messages.push(msg);
+    }
+    messages
+}
+
+#[tokio::test]
+async fn test_wait_for_tool_call_keeps_other_messages() {
+    let server = TestIFlowServer::start(vec![
+        agent_chunk("Reading"),
+        tool_call("call_1", "read_file", "completed"),
+        tool_call("call_2", "Write_File", "completed"),
+        agent_chunk("Done"),
+    ])
+    .await;
+    let mut client = connected_client(&server).await;
+
+    let found = client
+        .wait_for_tool_call("write", Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert!(matches!(found, Message::ToolCall { ref id, .. } if id == "call_2"));
+
+    // The skipped messages are still delivered, in order
+    let remaining = drain(&client).await;
+    assert!(matches!(&remaining[0], Message::Assistant { content } if content == "Reading"));
+    assert!(matches!(&remaining[1], Message::ToolCall { id, .. } if id == "call_1"));
+    assert!(matches!(&remaining[2], Message::Assistant { content } if content == "Done"));
+    assert!(matches!(remaining[3], Message::TaskFinish { .. }));
+    assert_eq!(remaining.len(), 4);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wait_for_tool_call_timeout() {
+    let server = TestIFlowServer::start(vec![
+        tool_call("call_1", "read_file", "completed"),
+        agent_chunk("Done"),
+    ])
+    .await;
+    let mut client = connected_client(&server).await;
+
+    let result = client
+        .wait_for_tool_call("shell", Duration::from_millis(200))
+        .await;
+    assert!(matches!(result, Err(IFlowError::Timeout(_))));
+
+    // A later wait finds the tool call that was read ahead
+    let found = client
+        .wait_for_tool_call("READ", Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert!(matches!(found, Message::ToolCall { ref name, .. } if name == "read_file"));
+    assert_eq!(drain(&client).await.len(), 2);
+
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,66 @@
+//! Tests for warming up a session before the first prompt
+
+mod helpers;
+
+use helpers::server::{SessionScript, TestIFlowServer, agent_chunk};
+use iflow_cli_sdk_rust::IFlowClient;
+
+fn methods(server: &TestIFlowServer) -> Vec<String> {
+    server
+        .requests()
+        .iter()
+        .filter_map(|request| request["method"].as_str().map(String::from))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_warmup_creates_session_without_prompt() {
+    let server = TestIFlowServer::start(vec![agent_chunk("Hi")]).await;
+
+    let mut client = IFlowClient::new(Some(helpers::websocket_options(server.url())));
+    client.warmup().await.unwrap();
+
+    assert!(client.snapshot().connected);
+    assert_eq!(client.session_id(), Some("test-session-1"));
+    assert_eq!(methods(&server), vec!["initialize", "session/new"]);
+
+    // The first message only sends the prompt
+    client.send_message("Hello", None).await.unwrap();
+    let (response, _) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Hi");
+    assert_eq!(
+        methods(&server),
+        vec!["initialize", "session/new", "session/prompt"]
+    );
+
+    // Warming up again is a no-op
+    client.warmup().await.unwrap();
+    assert_eq!(methods(&server).len(), 3);
+
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_warmup_sends_initial_message() {
+    let server = TestIFlowServer::new(
+        0,
+        vec![
+            SessionScript::new("You are a reviewer", vec![agent_chunk("Understood")]),
+            SessionScript::new("Review this", vec![agent_chunk("Looks good")]),
+        ],
+    )
+    .await;
+
+    let options = helpers::websocket_options(server.url())
+        .with_initial_message("You are a reviewer".to_string());
+    let mut client = IFlowClient::new(Some(options));
+    client.warmup().await.unwrap();
+    assert_eq!(server.requests_for("session/prompt").len(), 1);
+
+    client.send_message("Review this", None).await.unwrap();
+    let (response, _) = client.collect_response().await.unwrap();
+    assert_eq!(response, "Looks good");
+    assert_eq!(server.requests_for("session/prompt").len(), 2);
+
+    client.disconnect().await.unwrap();
+}
@@ -78,6 +78,15 @@ mod tests {
         // Verify default reconnect settings
         assert_eq!(config.reconnect_attempts, 3);
         assert_eq!(config.reconnect_interval, Duration::from_secs(5));
+
+        // No keepalive pings unless requested
+        assert_eq!(config.ping_interval, None);
+        assert_eq!(
+            config
+                .with_ping_interval(Duration::from_secs(30))
+                .ping_interval,
+            Some(Duration::from_secs(30))
+        );
     }
 
     /// Test IFlowOptions with WebSocketConfig auto-start
@@ -0,0 +1,157 @@
+//! Tests for WebSocketTransport reconnects and keepalive pings
+
+use futures::{SinkExt, StreamExt};
+use iflow_cli_sdk_rust::IFlowError;
+use iflow_cli_sdk_rust::websocket_transport::WebSocketTransport;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+/// Accept a single connection on `listener`, send one message and close it
+async fn serve_once(listener: TcpListener) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut ws = accept_async(stream).await.unwrap();
+    ws.send(Message::Text("//ready".into())).await.unwrap();
+    let _ = ws.close(None).await;
+}
+
+#[tokio::test]
+async fn test_reconnect_after_server_restart() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve_once(listener));
+
+    let mut transport = WebSocketTransport::new(format!("ws://{}/acp", addr), 5.0)
+        .with_reconnect_settings(5, Duration::from_millis(50));
+    transport.connect().await.unwrap();
+    assert_eq!(transport.receive().await.unwrap(), "//ready");
+
+    // The server closes the connection and goes away
+    assert!(transport.receive().await.is_err());
+    assert!(!transport.is_connected());
+    server.await.unwrap();
+
+    // Restart the server on the same address while reconnecting
+    let restart = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(75)).await;
+        serve_once(TcpListener::bind(addr).await.unwrap()).await;
+    });
+
+    transport.reconnect().await.unwrap();
+    assert!(transport.is_connected());
+    assert_eq!(transport.reconnect_count(), 1);
+    assert_eq!(transport.receive().await.unwrap(), "//ready");
+    restart.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_gives_up() {
+    // Reserve a port, then free it so nothing is listening
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut transport = WebSocketTransport::new(format!("ws://{}/acp", addr), 1.0)
+        .with_reconnect_settings(2, Duration::from_millis(10));
+    assert!(transport.reconnect().await.is_err());
+    assert!(!transport.is_connected());
+    assert_eq!(transport.reconnect_count(), 0);
+}
+
+#[tokio::test]
+async fn test_reconnect_jitter() {
+    // Reserve a port, then free it so every attempt fails immediately
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // Start several reconnects at the same time; each waits a random delay first
+    let jitter = Duration::from_millis(100);
+    let attempts = (0..8).map(|_| async move {
+        let mut transport = WebSocketTransport::new(format!("ws://{}/acp", addr), 1.0)
+            .with_reconnect_settings(1, Duration::ZERO)
+            .with_jitter(Some(jitter));
+        let started = std::time::Instant::now();
+        assert!(transport.reconnect().await.is_err());
+        started.elapsed()
+    });
+    let delays = futures::future::join_all(attempts).await;
+
+    let shortest = delays.iter().min().unwrap();
+    let longest = delays.iter().max().unwrap();
+    assert!(*longest < jitter + Duration::from_millis(50));
+    assert!(*longest - *shortest > Duration::from_millis(5));
+}
+
+/// Accept a single connection and greet it; pongs are only sent if `read` is set
+async fn serve_pings(listener: TcpListener, read: bool) -> usize {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut ws = accept_async(stream).await.unwrap();
+    ws.send(Message::Text("//ready".into())).await.unwrap();
+    if !read {
+        // Pings are answered while reading, so never answer them
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        return 0;
+    }
+    let mut pings = 0;
+    while let Some(Ok(msg)) = ws.next().await {
+        if msg.is_ping() {
+            pings += 1;
+        }
+    }
+    pings
+}
+
+#[tokio::test]
+async fn test_keepalive_pings_are_answered() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(serve_pings(listener, true));
+
+    let mut transport = WebSocketTransport::new(format!("ws://{}/acp", addr), 5.0)
+        .with_ping_interval(Some(Duration::from_millis(50)));
+    transport.connect().await.unwrap();
+    assert_eq!(transport.receive().await.unwrap(), "//ready");
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(transport.is_connected());
+    transport.close().await.unwrap();
+    assert!(server.await.unwrap() >= 3);
+}
+
+#[tokio::test]
+async fn test_keepalive_ping_timeout_disconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve_pings(listener, false));
+
+    let mut transport = WebSocketTransport::new(format!("ws://{}/acp", addr), 5.0)
+        .with_ping_interval(Some(Duration::from_millis(50)));
+    transport.connect().await.unwrap();
+    assert_eq!(transport.receive().await.unwrap(), "//ready");
+
+    let err = tokio::time::timeout(Duration::from_secs(1), transport.receive())
+        .await
+        .unwrap()
+        .unwrap_err();
+    assert!(
+        matches!(&err, IFlowError::Connection(message) if message == "ping timeout"),
+        "{:?}",
+        err
+    );
+    assert!(!transport.is_connected());
+}
+
+#[cfg(not(feature = "wss"))]
+#[tokio::test]
+async fn test_wss_requires_feature() {
+    let mut transport = WebSocketTransport::new("wss://127.0.0.1:1/acp".to_string(), 1.0);
+
+    let err = transport.connect().await.unwrap_err();
+
+    assert!(matches!(err, IFlowError::Config(_)), "{:?}", err);
+}
@@ -0,0 +1,114 @@
+//! Tests for connecting over TLS to a wss:// server with a self-signed certificate
+
+#![cfg(feature = "wss")]
+
+use futures::{SinkExt, StreamExt};
+use iflow_cli_sdk_rust::IFlowError;
+use iflow_cli_sdk_rust::websocket_transport::WebSocketTransport;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::Message;
+
+/// A WSS server that greets each client and echoes its messages
+struct WssServer {
+    url: String,
+    ca_bundle: PathBuf,
+}
+
+impl WssServer {
+    async fn start() -> Self {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let ca_bundle = std::env::temp_dir().join(format!("iflow_ca_{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&ca_bundle, certified.cert.pem()).unwrap();
+
+        let key =
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(certified.cert.der().to_vec())],
+                key,
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // Clients that reject the certificate fail the handshake
+                    let Ok(tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(tls).await else {
+                        return;
+                    };
+                    if ws.send(Message::Text("//ready".into())).await.is_err() {
+                        return;
+                    }
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        if ws.send(Message::Text(text)).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        Self {
+            url: format!("wss://localhost:{}/acp", port),
+            ca_bundle,
+        }
+    }
+}
+
+impl Drop for WssServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.ca_bundle);
+    }
+}
+
+#[tokio::test]
+async fn test_wss_with_ca_bundle() {
+    let server = WssServer::start().await;
+    let mut transport = WebSocketTransport::new(server.url.clone(), 5.0)
+        .with_tls_ca_bundle(Some(server.ca_bundle.clone()));
+
+    transport.connect().await.unwrap();
+    assert_eq!(transport.receive().await.unwrap(), "//ready");
+
+    let message = serde_json::json!({ "jsonrpc": "2.0", "method": "ping" });
+    transport.send(&message).await.unwrap();
+    let echoed: serde_json::Value =
+        serde_json::from_str(&transport.receive().await.unwrap()).unwrap();
+    assert_eq!(echoed, message);
+
+    transport.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wss_rejects_untrusted_certificate() {
+    let server = WssServer::start().await;
+    let mut transport = WebSocketTransport::new(server.url.clone(), 5.0);
+
+    let err = transport.connect().await.unwrap_err();
+    assert!(matches!(err, IFlowError::Connection(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn test_wss_with_invalid_ca_bundle() {
+    let server = WssServer::start().await;
+    let bundle = std::env::temp_dir().join(format!("iflow_ca_{}.pem", uuid::Uuid::new_v4()));
+    std::fs::write(&bundle, "not a certificate").unwrap();
+    let mut transport =
+        WebSocketTransport::new(server.url.clone(), 5.0).with_tls_ca_bundle(Some(bundle.clone()));
+
+    let err = transport.connect().await.unwrap_err();
+    assert!(matches!(err, IFlowError::Config(_)), "{:?}", err);
+    let _ = std::fs::remove_file(&bundle);
+}